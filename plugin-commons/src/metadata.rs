@@ -17,6 +17,120 @@ fn to_str(content: &Content) -> Option<String> {
     }
 }
 
+/// The offset in dB between the reference level of R128 gain tags (-23 LUFS)
+/// and the ReplayGain reference level (-18 LUFS).
+const R128_TO_REPLAY_GAIN_OFFSET: f32 = 5.0;
+
+/// Parses the value of a ReplayGain gain tag, such as
+/// `REPLAYGAIN_TRACK_GAIN`, which is given as a decimal number of dB with an
+/// optional `dB` suffix, e.g. `-6.54 dB`.
+///
+/// # Arguments
+///
+/// * `value`: The value of the tag.
+///
+/// # Returns
+///
+/// The parsed gain in dB, or `None` if the value is not a valid gain.
+pub fn parse_replay_gain(value: &str) -> Option<f32> {
+    let value = value.trim().to_ascii_lowercase();
+    let value = value.strip_suffix("db").unwrap_or(&value).trim_end();
+
+    value.parse::<f32>().ok().filter(|gain| gain.is_finite())
+}
+
+/// Parses the value of an R128 gain tag, such as `R128_TRACK_GAIN`, which is
+/// given as a Q7.8 fixed-point number of dB relative to -23 LUFS. The result
+/// is converted to the ReplayGain reference level, so it is comparable to the
+/// output of [parse_replay_gain].
+///
+/// # Arguments
+///
+/// * `value`: The value of the tag.
+///
+/// # Returns
+///
+/// The parsed gain in dB relative to the ReplayGain reference level, or
+/// `None` if the value is not a valid gain.
+pub fn parse_r128_gain(value: &str) -> Option<f32> {
+    value.trim().parse::<i16>().ok()
+        .map(|gain| gain as f32 / 256.0 + R128_TO_REPLAY_GAIN_OFFSET)
+}
+
+/// Parses the value of a ReplayGain peak tag, such as
+/// `REPLAYGAIN_TRACK_PEAK`, which is given as a decimal number where 1 is full
+/// scale, e.g. `0.988525`.
+///
+/// # Arguments
+///
+/// * `value`: The value of the tag.
+///
+/// # Returns
+///
+/// The parsed peak amplitude, or `None` if the value is not a valid peak.
+pub fn parse_replay_gain_peak(value: &str) -> Option<f32> {
+    value.trim().parse::<f32>().ok()
+        .filter(|peak| peak.is_finite() && *peak > 0.0)
+}
+
+/// Interprets a key-value tag, such as a Vorbis comment or an ID3 `TXXX`
+/// frame, as a loudness normalization tag and stores the contained gain or
+/// peak in the given builder, if applicable. ReplayGain
+/// (`REPLAYGAIN_TRACK_GAIN`, `REPLAYGAIN_ALBUM_GAIN`, `REPLAYGAIN_TRACK_PEAK`,
+/// `REPLAYGAIN_ALBUM_PEAK`) and R128 (`R128_TRACK_GAIN`, `R128_ALBUM_GAIN`)
+/// tags are supported. Keys are matched case-insensitively.
+///
+/// # Arguments
+///
+/// * `meta_builder`: The [AudioMetadataBuilder] in which to set the gain.
+/// * `key`: The key of the tag.
+/// * `value`: The value of the tag.
+///
+/// # Returns
+///
+/// `true` if and only if the tag was a valid loudness normalization tag and
+/// its gain or peak was stored in the builder.
+pub fn set_gain_from_tag(meta_builder: &mut AudioMetadataBuilder, key: &str,
+        value: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    let setter: fn(&mut AudioMetadataBuilder, f32) -> &mut AudioMetadataBuilder;
+    let parsed = match key.as_str() {
+        "REPLAYGAIN_TRACK_GAIN" => {
+            setter = AudioMetadataBuilder::set_track_gain;
+            parse_replay_gain(value)
+        },
+        "REPLAYGAIN_ALBUM_GAIN" => {
+            setter = AudioMetadataBuilder::set_album_gain;
+            parse_replay_gain(value)
+        },
+        "REPLAYGAIN_TRACK_PEAK" => {
+            setter = AudioMetadataBuilder::set_track_peak;
+            parse_replay_gain_peak(value)
+        },
+        "REPLAYGAIN_ALBUM_PEAK" => {
+            setter = AudioMetadataBuilder::set_album_peak;
+            parse_replay_gain_peak(value)
+        },
+        "R128_TRACK_GAIN" => {
+            setter = AudioMetadataBuilder::set_track_gain;
+            parse_r128_gain(value)
+        },
+        "R128_ALBUM_GAIN" => {
+            setter = AudioMetadataBuilder::set_album_gain;
+            parse_r128_gain(value)
+        },
+        _ => return false
+    };
+
+    match parsed {
+        Some(parsed) => {
+            setter(meta_builder, parsed);
+            true
+        },
+        None => false
+    }
+}
+
 /// Converts an ID3 [Tag] into [AudioMetadata].
 ///
 /// # Arguments
@@ -36,6 +150,11 @@ pub fn metadata_from_id3_tag(tag: Tag, descriptor: &str) -> AudioMetadata {
     for frame in tag.frames() {
         // See https://docs.puddletag.net/source/id3.html for keys
 
+        if let Content::ExtendedText(text) = frame.content() {
+            set_gain_from_tag(&mut meta_builder, &text.description,
+                &text.value);
+        }
+
         if let Some(content) = to_str(frame.content()) {
             match frame.id() {
                 "TIT1" => { meta_builder.set_super_title(content); },
@@ -114,10 +233,19 @@ pub fn metadata_from_file(opened_file: OpenedFile, descriptor: &str)
 mod tests {
 
     use id3::{Content, Frame, Tag, TagLike};
+    use id3::frame::ExtendedText;
 
     use kernal::prelude::*;
 
-    use crate::metadata_from_id3_tag;
+    use rambot_api::AudioMetadataBuilder;
+
+    use crate::{
+        metadata_from_id3_tag,
+        parse_r128_gain,
+        parse_replay_gain,
+        parse_replay_gain_peak,
+        set_gain_from_tag
+    };
 
     fn make_tag(frames: impl IntoIterator<Item = (&'static str, &'static str)>) -> Tag {
         let mut tag = Tag::new();
@@ -219,4 +347,78 @@ mod tests {
         assert_that!(metadata.track()).is_none();
         assert_that!(metadata.year()).is_none();
     }
+
+    #[test]
+    fn metadata_from_tag_with_replay_gain_frames_sets_gains() {
+        let mut tag = Tag::new();
+        tag.add_frame(ExtendedText {
+            description: "REPLAYGAIN_TRACK_GAIN".to_owned(),
+            value: "-6.54 dB".to_owned()
+        });
+        tag.add_frame(ExtendedText {
+            description: "replaygain_album_gain".to_owned(),
+            value: "+1.25 dB".to_owned()
+        });
+        let metadata = metadata_from_id3_tag(tag, "");
+
+        assert_that!(metadata.track_gain()).contains(-6.54);
+        assert_that!(metadata.album_gain()).contains(1.25);
+    }
+
+    #[test]
+    fn metadata_from_tag_with_replay_gain_peak_frames_sets_peaks() {
+        let mut tag = Tag::new();
+        tag.add_frame(ExtendedText {
+            description: "REPLAYGAIN_TRACK_PEAK".to_owned(),
+            value: "0.5".to_owned()
+        });
+        tag.add_frame(ExtendedText {
+            description: "replaygain_album_peak".to_owned(),
+            value: "0.75".to_owned()
+        });
+        let metadata = metadata_from_id3_tag(tag, "");
+
+        assert_that!(metadata.track_peak()).contains(0.5);
+        assert_that!(metadata.album_peak()).contains(0.75);
+    }
+
+    #[test]
+    fn replay_gain_values_are_parsed_with_and_without_unit() {
+        assert_that!(parse_replay_gain("-6.54 dB")).contains(-6.54);
+        assert_that!(parse_replay_gain("3.5dB")).contains(3.5);
+        assert_that!(parse_replay_gain(" 2 ")).contains(2.0);
+        assert_that!(parse_replay_gain("loud")).is_none();
+        assert_that!(parse_replay_gain("NaN dB")).is_none();
+    }
+
+    #[test]
+    fn replay_gain_peaks_are_parsed() {
+        assert_that!(parse_replay_gain_peak("0.988525")).contains(0.988525);
+        assert_that!(parse_replay_gain_peak(" 1.2 ")).contains(1.2);
+        assert_that!(parse_replay_gain_peak("0")).is_none();
+        assert_that!(parse_replay_gain_peak("-0.5")).is_none();
+        assert_that!(parse_replay_gain_peak("inf")).is_none();
+    }
+
+    #[test]
+    fn r128_gain_values_are_converted_to_replay_gain_reference() {
+        assert_that!(parse_r128_gain("0")).contains(5.0);
+        assert_that!(parse_r128_gain("-512")).contains(3.0);
+        assert_that!(parse_r128_gain("1.5")).is_none();
+    }
+
+    #[test]
+    fn unrelated_tags_do_not_set_gains() {
+        let mut meta_builder = AudioMetadataBuilder::new();
+
+        assert_that!(set_gain_from_tag(&mut meta_builder, "TITLE", "-1 dB"))
+            .is_false();
+        assert_that!(set_gain_from_tag(&mut meta_builder,
+            "R128_TRACK_GAIN", "-256")).is_true();
+
+        let metadata = meta_builder.build();
+
+        assert_that!(metadata.track_gain()).contains(4.0);
+        assert_that!(metadata.album_gain()).is_none();
+    }
 }
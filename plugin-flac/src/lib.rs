@@ -1,3 +1,7 @@
+// The accessors generated by ouroboros for the mutably borrowed reader below
+// trigger this lint.
+#![allow(clippy::mut_from_ref)]
+
 use claxon::FlacReader;
 use claxon::frame::{Block, FrameReader};
 use claxon::input::BufferedReader;
@@ -20,7 +24,7 @@ use rambot_api::{
     Sample
 };
 
-use std::io::{self, ErrorKind, Read};
+use std::io::{self, Read};
 use std::mem;
 
 #[ouroboros::self_referencing]
@@ -74,8 +78,7 @@ impl<R: Read> AudioSource for FlacAudioSource<R> {
             match next_frame_res {
                 Ok(Some(block)) => self.block = block,
                 Ok(None) => return Ok(0),
-                Err(e) => return
-                    Err(io::Error::new(ErrorKind::Other, e.to_string()))
+                Err(e) => return Err(io::Error::other(e.to_string()))
             }
         }
 
@@ -168,6 +171,10 @@ impl FlacAudioSourceResolver {
             }
         }
 
+        for (key, value) in reader.tags() {
+            plugin_commons::set_gain_from_tag(&mut meta_builder, key, value);
+        }
+
         let metadata = meta_builder.build();
         let sampling_rate = reader.streaminfo().sample_rate;
 
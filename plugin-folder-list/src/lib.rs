@@ -10,7 +10,7 @@ use rambot_api::{
 };
 
 use std::fs::{self, ReadDir};
-use std::io::{self, ErrorKind};
+use std::io;
 use std::path::{Path, PathBuf};
 
 struct FolderList {
@@ -26,8 +26,7 @@ impl AudioSourceList for FolderList {
             let result = self.path.as_os_str().to_owned();
             self.path.pop();
             let result = result.into_string()
-                .map_err(|_| io::Error::new(
-                    ErrorKind::Other, "file name is not utf-8"))?;
+                .map_err(|_| io::Error::other("file name is not utf-8"))?;
 
             Ok(Some(result))
         }
@@ -15,7 +15,7 @@ use rambot_api::{
     Sample
 };
 
-use std::io::{self, ErrorKind, Read};
+use std::io::{self, Read};
 
 struct FrameIterator<R> {
     decoder: Decoder<R>
@@ -52,7 +52,7 @@ impl<R: Read> AudioSource for Mp3AudioSource<R> {
             if let Some(next_frame) = self.frames.next() {
                 self.current_frame = next_frame
                     .map_err(|e|
-                        io::Error::new(ErrorKind::Other, format!("{}", e)))?;
+                        io::Error::other(format!("{}", e)))?;
                 self.current_frame_idx = 0;
             }
             else {
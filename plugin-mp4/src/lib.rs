@@ -68,7 +68,7 @@ impl<D: Decoder> Mp4TrackDecoder<D> {
 fn to_io_err(e: SymphoniaError) -> io::Error {
     match e {
         SymphoniaError::IoError(e) => e,
-        e => io::Error::new(ErrorKind::Other, format!("{}", e))
+        e => io::Error::other(format!("{}", e))
     }
 }
 
@@ -331,6 +331,30 @@ where
                             meta_builder.set_year(timestamp.year);
                         }
                     },
+                    StandardTagKey::ReplayGainAlbumGain => {
+                        if let Some(gain) =
+                                plugin_commons::parse_replay_gain(&value) {
+                            meta_builder.set_album_gain(gain);
+                        }
+                    },
+                    StandardTagKey::ReplayGainTrackGain => {
+                        if let Some(gain) =
+                                plugin_commons::parse_replay_gain(&value) {
+                            meta_builder.set_track_gain(gain);
+                        }
+                    },
+                    StandardTagKey::ReplayGainAlbumPeak => {
+                        if let Some(peak) =
+                                plugin_commons::parse_replay_gain_peak(&value) {
+                            meta_builder.set_album_peak(peak);
+                        }
+                    },
+                    StandardTagKey::ReplayGainTrackPeak => {
+                        if let Some(peak) =
+                                plugin_commons::parse_replay_gain_peak(&value) {
+                            meta_builder.set_track_peak(peak);
+                        }
+                    },
                     StandardTagKey::Performer => {
                         meta_builder.set_lead_performer(value);
                     },
@@ -351,6 +375,16 @@ where
                     _ => { }
                 }
             }
+            else {
+                // Freeform iTunes tags have keys such as
+                // `----:com.apple.iTunes:replaygain_track_gain`.
+
+                let key = tag.key.rsplit(':').next().unwrap_or(&tag.key);
+                let value = format!("{}", tag.value);
+
+                plugin_commons::set_gain_from_tag(
+                    &mut meta_builder, key, &value);
+            }
         }
     }
 
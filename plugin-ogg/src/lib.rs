@@ -18,7 +18,7 @@ use rambot_api::{
     Sample
 };
 
-use std::io::{self, ErrorKind, Read, Seek};
+use std::io::{self, Read, Seek};
 
 struct OggAudioSource<R: Read + Seek> {
     reader: OggStreamReader<R>,
@@ -72,7 +72,7 @@ impl<R: Read + Seek> AudioSource for OggAudioSource<R> {
             else {
                 let packet = self.reader.read_dec_packet_generic::<Vec<Vec<f32>>>()
                     .map_err(|e|
-                        io::Error::new(ErrorKind::Other, format!("{}", e)))?;
+                        io::Error::other(format!("{}", e)))?;
 
                 match packet {
                     Some(packet) => self.extend_remaining(packet),
@@ -114,7 +114,10 @@ impl<R: Read + Seek> AudioSource for OggAudioSource<R> {
                     }
                 },
                 "GENRE" => { meta_builder.set_genre(value); },
-                _ => { }
+                _ => {
+                    plugin_commons::set_gain_from_tag(
+                        &mut meta_builder, key, value);
+                }
             }
         }
 
@@ -17,7 +17,7 @@ use rambot_api::{
     Sample
 };
 
-use std::io::{ErrorKind, Read, self};
+use std::io::{Read, self};
 
 trait FloatSamples {
     fn next(&mut self);
@@ -65,7 +65,7 @@ where
     for (i, buf_sample) in buf.iter_mut().enumerate() {
         if let Some(sample) = f.next_sample() {
             let sample = sample.map_err(|e|
-                io::Error::new(ErrorKind::Other, format!("{}", e)))?;
+                io::Error::other(format!("{}", e)))?;
             *buf_sample = sample;
         }
         else {
@@ -219,7 +219,7 @@ impl Div<f32> for &Sample {
 /// has to be generated by plugins and provided to the bot in
 /// [AudioSource::metadata]. The bot can then report it to the user. To
 /// construct an instance, use the [AudioMetadataBuilder].
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct AudioMetadata {
     title: Option<String>,
     sub_title: Option<String>,
@@ -235,9 +235,46 @@ pub struct AudioMetadata {
     album: Option<String>,
     track: Option<i32>,
     year: Option<i32>,
-    genre: Option<String>
+    genre: Option<String>,
+    track_gain: Option<f32>,
+    album_gain: Option<f32>,
+    track_peak: Option<f32>,
+    album_peak: Option<f32>
+}
+
+fn bits(value: Option<f32>) -> Option<u32> {
+    value.map(f32::to_bits)
 }
 
+impl PartialEq for AudioMetadata {
+    fn eq(&self, other: &AudioMetadata) -> bool {
+        // Gains and peaks are compared by their bits, so equality is
+        // reflexive even for NaN.
+
+        self.title == other.title &&
+            self.sub_title == other.sub_title &&
+            self.super_title == other.super_title &&
+            self.artist == other.artist &&
+            self.composer == other.composer &&
+            self.lead_performer == other.lead_performer &&
+            self.group_name == other.group_name &&
+            self.conductor == other.conductor &&
+            self.lyricist == other.lyricist &&
+            self.interpreter == other.interpreter &&
+            self.publisher == other.publisher &&
+            self.album == other.album &&
+            self.track == other.track &&
+            self.year == other.year &&
+            self.genre == other.genre &&
+            bits(self.track_gain) == bits(other.track_gain) &&
+            bits(self.album_gain) == bits(other.album_gain) &&
+            bits(self.track_peak) == bits(other.track_peak) &&
+            bits(self.album_peak) == bits(other.album_peak)
+    }
+}
+
+impl Eq for AudioMetadata { }
+
 impl AudioMetadata {
 
     /// Gets the title of the track, if it could be determined.
@@ -325,6 +362,36 @@ impl AudioMetadata {
     pub fn genre(&self) -> Option<&str> {
         self.genre.as_deref()
     }
+
+    /// Gets the gain in dB which has to be applied to the track to normalize
+    /// its loudness to the ReplayGain reference level, if it could be
+    /// determined.
+    pub fn track_gain(&self) -> Option<f32> {
+        self.track_gain
+    }
+
+    /// Gets the gain in dB which has to be applied to the album of the track
+    /// to normalize its loudness to the ReplayGain reference level, if it
+    /// could be determined. In contrast to [AudioMetadata::track_gain], this
+    /// gain is equal for all tracks of the album, preserving their relative
+    /// loudness.
+    pub fn album_gain(&self) -> Option<f32> {
+        self.album_gain
+    }
+
+    /// Gets the peak amplitude of the track, where 1 is full scale, if it
+    /// could be determined. This limits how much the track can be amplified
+    /// without clipping.
+    pub fn track_peak(&self) -> Option<f32> {
+        self.track_peak
+    }
+
+    /// Gets the peak amplitude of the album of the track, where 1 is full
+    /// scale, if it could be determined. This limits how much the album can be
+    /// amplified without clipping.
+    pub fn album_peak(&self) -> Option<f32> {
+        self.album_peak
+    }
 }
 
 /// A builder for [AudioMetadata] instances. Construct a new builder with
@@ -372,7 +439,11 @@ impl AudioMetadataBuilder {
                 album: None,
                 track: None,
                 year: None,
-                genre: None
+                genre: None,
+                track_gain: None,
+                album_gain: None,
+                track_peak: None,
+                album_peak: None
             }
         }
     }
@@ -931,6 +1002,142 @@ impl AudioMetadataBuilder {
         self
     }
 
+    /// Specifies the gain in dB which has to be applied to the track to
+    /// normalize its loudness to the ReplayGain reference level of -18 LUFS.
+    /// That is, the value provided here will be returned in
+    /// [AudioMetadata::track_gain].
+    ///
+    /// # Arguments
+    ///
+    /// * `track_gain`: The track gain in dB.
+    ///
+    /// # Returns
+    ///
+    /// A mutable reference to this builder for chaining.
+    pub fn set_track_gain(&mut self, track_gain: f32)
+            -> &mut AudioMetadataBuilder {
+        self.audio_metadata.track_gain = Some(track_gain);
+        self
+    }
+
+    /// Specifies the gain in dB which has to be applied to the track to
+    /// normalize its loudness to the ReplayGain reference level of -18 LUFS.
+    /// That is, the value provided here will be returned in
+    /// [AudioMetadata::track_gain].
+    ///
+    /// # Arguments
+    ///
+    /// * `track_gain`: The track gain in dB.
+    ///
+    /// # Returns
+    ///
+    /// This builder for chaining.
+    pub fn with_track_gain(mut self, track_gain: f32) -> AudioMetadataBuilder {
+        self.set_track_gain(track_gain);
+        self
+    }
+
+    /// Specifies the gain in dB which has to be applied to the album of the
+    /// track to normalize its loudness to the ReplayGain reference level of
+    /// -18 LUFS. That is, the value provided here will be returned in
+    /// [AudioMetadata::album_gain].
+    ///
+    /// # Arguments
+    ///
+    /// * `album_gain`: The album gain in dB.
+    ///
+    /// # Returns
+    ///
+    /// A mutable reference to this builder for chaining.
+    pub fn set_album_gain(&mut self, album_gain: f32)
+            -> &mut AudioMetadataBuilder {
+        self.audio_metadata.album_gain = Some(album_gain);
+        self
+    }
+
+    /// Specifies the gain in dB which has to be applied to the album of the
+    /// track to normalize its loudness to the ReplayGain reference level of
+    /// -18 LUFS. That is, the value provided here will be returned in
+    /// [AudioMetadata::album_gain].
+    ///
+    /// # Arguments
+    ///
+    /// * `album_gain`: The album gain in dB.
+    ///
+    /// # Returns
+    ///
+    /// This builder for chaining.
+    pub fn with_album_gain(mut self, album_gain: f32) -> AudioMetadataBuilder {
+        self.set_album_gain(album_gain);
+        self
+    }
+
+    /// Specifies the peak amplitude of the track, where 1 is full scale. That
+    /// is, the value provided here will be returned in
+    /// [AudioMetadata::track_peak].
+    ///
+    /// # Arguments
+    ///
+    /// * `track_peak`: The peak amplitude of the track.
+    ///
+    /// # Returns
+    ///
+    /// A mutable reference to this builder for chaining.
+    pub fn set_track_peak(&mut self, track_peak: f32)
+            -> &mut AudioMetadataBuilder {
+        self.audio_metadata.track_peak = Some(track_peak);
+        self
+    }
+
+    /// Specifies the peak amplitude of the track, where 1 is full scale. That
+    /// is, the value provided here will be returned in
+    /// [AudioMetadata::track_peak].
+    ///
+    /// # Arguments
+    ///
+    /// * `track_peak`: The peak amplitude of the track.
+    ///
+    /// # Returns
+    ///
+    /// This builder for chaining.
+    pub fn with_track_peak(mut self, track_peak: f32) -> AudioMetadataBuilder {
+        self.set_track_peak(track_peak);
+        self
+    }
+
+    /// Specifies the peak amplitude of the album of the track, where 1 is full
+    /// scale. That is, the value provided here will be returned in
+    /// [AudioMetadata::album_peak].
+    ///
+    /// # Arguments
+    ///
+    /// * `album_peak`: The peak amplitude of the album.
+    ///
+    /// # Returns
+    ///
+    /// A mutable reference to this builder for chaining.
+    pub fn set_album_peak(&mut self, album_peak: f32)
+            -> &mut AudioMetadataBuilder {
+        self.audio_metadata.album_peak = Some(album_peak);
+        self
+    }
+
+    /// Specifies the peak amplitude of the album of the track, where 1 is full
+    /// scale. That is, the value provided here will be returned in
+    /// [AudioMetadata::album_peak].
+    ///
+    /// # Arguments
+    ///
+    /// * `album_peak`: The peak amplitude of the album.
+    ///
+    /// # Returns
+    ///
+    /// This builder for chaining.
+    pub fn with_album_peak(mut self, album_peak: f32) -> AudioMetadataBuilder {
+        self.set_album_peak(album_peak);
+        self
+    }
+
     /// Constructs the [AudioMetadata] instance from the information provided
     /// in the previous `with_*` calls.
     pub fn build(self) -> AudioMetadata {
@@ -1140,4 +1347,20 @@ mod tests {
             right: 0.8
         }.abs_amplitude());
     }
+    #[test]
+    fn metadata_is_compared_by_gain_bits() {
+        let metadata = AudioMetadataBuilder::new()
+            .with_title("title")
+            .with_track_gain(f32::NAN)
+            .with_track_peak(0.5)
+            .build();
+        let other = AudioMetadataBuilder::new()
+            .with_title("title")
+            .with_track_gain(f32::NAN)
+            .with_track_peak(0.25)
+            .build();
+
+        assert_eq!(metadata, metadata.clone());
+        assert_ne!(metadata, other);
+    }
 }
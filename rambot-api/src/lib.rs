@@ -90,6 +90,16 @@ pub use time::{
     SAMPLES_PER_SECOND
 };
 
+use std::collections::HashSet;
+
+/// The version of the interface between the bot and its plugins. It is
+/// increased whenever a type shared with plugins changes its layout, such as
+/// [AudioMetadata] in version 2, which added gains and peaks. The bot refuses
+/// to load plugins built against a different version, as it could not safely
+/// exchange data with them. Plugins export the version they were built
+/// against with [export_plugin].
+pub const API_VERSION: u32 = 2;
+
 /// Configuration information that is potentially relevant to a specific
 /// plugin, but not the bot itself. It is passed to the plugin during
 /// initialization. It is the plugin's responsibility to act according to this
@@ -152,7 +162,10 @@ impl PluginConfig {
 /// Guild-specific configuration provided to a plugin's resolvers.
 #[derive(Clone, Debug, Default)]
 pub struct PluginGuildConfig {
-    root_directory: Option<String>
+    root_directory: Option<String>,
+    disabled_plugins: HashSet<String>,
+    disabled_effects: HashSet<String>,
+    disabled_adapters: HashSet<String>
 }
 
 impl PluginGuildConfig {
@@ -168,16 +181,76 @@ impl PluginGuildConfig {
         S: Into<String>
     {
         PluginGuildConfig {
-            root_directory: root_directory.map(|s| s.into())
+            root_directory: root_directory.map(|s| s.into()),
+            disabled_plugins: HashSet::new(),
+            disabled_effects: HashSet::new(),
+            disabled_adapters: HashSet::new()
         }
     }
 
+    /// Returns this config with the plugins with the given file names
+    /// disabled. The bot does not use resolvers of disabled plugins, so
+    /// plugins do not need to check this themselves.
+    pub fn with_disabled_plugins<I, S>(mut self, plugins: I)
+        -> PluginGuildConfig
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        self.disabled_plugins.extend(plugins.into_iter().map(|s| s.into()));
+        self
+    }
+
+    /// Returns this config with the effects with the given names disabled.
+    /// The bot does not resolve disabled effects, so plugins do not need to
+    /// check this themselves.
+    pub fn with_disabled_effects<I, S>(mut self, effects: I)
+        -> PluginGuildConfig
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        self.disabled_effects.extend(effects.into_iter().map(|s| s.into()));
+        self
+    }
+
+    /// Returns this config with the adapters with the given names disabled.
+    /// The bot does not resolve disabled adapters, so plugins do not need to
+    /// check this themselves.
+    pub fn with_disabled_adapters<I, S>(mut self, adapters: I)
+        -> PluginGuildConfig
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        self.disabled_adapters.extend(adapters.into_iter().map(|s| s.into()));
+        self
+    }
+
     /// Gets the guild-specific root directory to use for file system accesses.
     /// If present, this overrides the global root directory, which should be
     /// used if this method returns `None`.
     pub fn root_directory(&self) -> Option<&String> {
         self.root_directory.as_ref()
     }
+
+    /// Indicates whether the plugin with the given file name is disabled in
+    /// the guild.
+    pub fn is_plugin_disabled(&self, file_name: &str) -> bool {
+        self.disabled_plugins.contains(file_name)
+    }
+
+    /// Indicates whether the effect with the given name is disabled in the
+    /// guild.
+    pub fn is_effect_disabled(&self, name: &str) -> bool {
+        self.disabled_effects.contains(name)
+    }
+
+    /// Indicates whether the adapter with the given name is disabled in the
+    /// guild.
+    pub fn is_adapter_disabled(&self, name: &str) -> bool {
+        self.disabled_adapters.contains(name)
+    }
 }
 
 /// The main trait for Rambot plugins. This handles all initialization and
@@ -211,7 +284,9 @@ pub trait Plugin : Send + Sync {
 /// libraries that returns a pointer to a [Plugin] trait object. As an
 /// argument, this macro requires the path to a function which can be called
 /// without arguments and returns an instance of any type implementing
-/// [Plugin].
+/// [Plugin]. The version of the crate which invokes this macro is exported
+/// as well, so the bot can display it, and so is the [API_VERSION], so the
+/// bot can refuse plugins built against an incompatible API.
 ///
 /// # Example
 ///
@@ -239,5 +314,15 @@ macro_rules! export_plugin {
 
             Box::into_raw(boxed)
         }
+
+        #[no_mangle]
+        pub extern "Rust" fn _plugin_version() -> &'static str {
+            env!("CARGO_PKG_VERSION")
+        }
+
+        #[no_mangle]
+        pub extern "Rust" fn _api_version() -> u32 {
+            $crate::API_VERSION
+        }
     }
 }
@@ -0,0 +1,229 @@
+use std::fmt::{self, Display, Formatter};
+use std::mem;
+
+/// The character which starts a parameter in the command of an alias.
+const PARAMETER_CHAR: char = '$';
+
+/// An enumeration of the errors that may occur while expanding an alias.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AliasError {
+
+    /// Indicates that the command of the alias refers to a positional
+    /// parameter for which no argument was given. Contains the position of
+    /// the parameter. Positions start at 1, so there is never an argument for
+    /// position 0.
+    MissingArgument(usize)
+}
+
+impl Display for AliasError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AliasError::MissingArgument(position) =>
+                write!(f, "No argument was given for parameter ${}.",
+                    position)
+        }
+    }
+}
+
+/// Splits the arguments given to an alias at whitespace. Arguments which
+/// contain whitespace can be enclosed in double quotes, which are removed.
+pub fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut in_argument = false;
+    let mut quoted = false;
+
+    for c in arguments.chars() {
+        if c == '"' {
+            quoted = !quoted;
+            in_argument = true;
+        }
+        else if c.is_whitespace() && !quoted {
+            if in_argument {
+                result.push(mem::take(&mut current));
+                in_argument = false;
+            }
+        }
+        else {
+            current.push(c);
+            in_argument = true;
+        }
+    }
+
+    if in_argument {
+        result.push(current);
+    }
+
+    result
+}
+
+/// Expands the command of an alias by substituting its parameters with the
+/// given arguments. `$1`, `$2`, and so on are replaced by the argument at
+/// that position, `$*` by all arguments separated by spaces, and `$$` by a
+/// single `$`. Arguments are inserted as they are, so parameters which may
+/// receive arguments containing whitespace should be enclosed in quotes in
+/// the command if the command requires that.
+///
+/// # Errors
+///
+/// [AliasError::MissingArgument] if the command refers to a position for
+/// which no argument was given.
+pub fn expand(command: &str, arguments: &[String])
+        -> Result<String, AliasError> {
+    let mut result = String::with_capacity(command.len());
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != PARAMETER_CHAR {
+            result.push(c);
+            continue;
+        }
+
+        match chars.peek() {
+            Some(&PARAMETER_CHAR) => {
+                chars.next();
+                result.push(PARAMETER_CHAR);
+            },
+            Some('*') => {
+                chars.next();
+                result.push_str(&arguments.join(" "));
+            },
+            Some(d) if d.is_ascii_digit() => {
+                let mut position = 0usize;
+
+                while let Some(digit) =
+                        chars.peek().and_then(|d| d.to_digit(10)) {
+                    chars.next();
+                    position = position.saturating_mul(10)
+                        .saturating_add(digit as usize);
+                }
+
+                let argument = position.checked_sub(1)
+                    .and_then(|index| arguments.get(index))
+                    .ok_or(AliasError::MissingArgument(position))?;
+
+                result.push_str(argument);
+            },
+            _ => result.push(PARAMETER_CHAR)
+        }
+    }
+
+    Ok(result)
+}
+
+/// Gets the rest of the given text after skipping the given number of words
+/// separated by whitespace.
+fn skip_words(text: &str, count: usize) -> &str {
+    let mut rest = text.trim_start();
+
+    for _ in 0..count {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        rest = rest[end..].trim_start();
+    }
+
+    rest
+}
+
+/// Indicates whether the given command runs the command with the given name,
+/// either directly or nested within commands which run other commands, that
+/// is, `do` and `schedule in` or `schedule at`. This is used to prevent
+/// aliases from running each other, which could otherwise go on forever.
+///
+/// # Arguments
+///
+/// * `command`: The command to check, as it would be written in a message.
+/// * `name`: The name of the command to look for.
+/// * `prefixes`: The prefixes with which commands may start, which are
+///   ignored.
+pub fn runs_command(command: &str, name: &str, prefixes: &[&str]) -> bool {
+    let command = command.trim_start();
+    let command = prefixes.iter()
+        .filter(|prefix| !prefix.is_empty())
+        .find_map(|prefix| command.strip_prefix(prefix))
+        .unwrap_or(command);
+    let mut words = command.split_whitespace();
+    let first = match words.next() {
+        Some(first) => first.to_lowercase(),
+        None => return false
+    };
+
+    if first == name.to_lowercase() {
+        true
+    }
+    else if first == "do" {
+        split_arguments(skip_words(command, 1)).iter()
+            .any(|command| runs_command(command, name, prefixes))
+    }
+    else if first == "schedule" {
+        match words.next().map(str::to_lowercase).as_deref() {
+            Some("in") | Some("at") =>
+                runs_command(skip_words(command, 3), name, prefixes),
+            _ => false
+        }
+    }
+    else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn arguments(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|&a| a.to_owned()).collect()
+    }
+
+    #[test]
+    fn arguments_are_split_at_whitespace_outside_quotes() {
+        assert_eq!(arguments(&["music", "rain forest.ogg", ""]),
+            split_arguments(r#" music  "rain forest.ogg" "" "#));
+    }
+
+    #[test]
+    fn positional_parameters_are_substituted() {
+        let expanded = expand("effect add $1 echo(delay=$2)",
+            &arguments(&["music", "0.5"]));
+
+        assert_eq!(Ok("effect add music echo(delay=0.5)".to_owned()),
+            expanded);
+    }
+
+    #[test]
+    fn special_parameters_are_substituted() {
+        let expanded = expand("play $1 $* costs $$5 or $",
+            &arguments(&["music", "a.ogg"]));
+
+        assert_eq!(Ok("play music music a.ogg costs $5 or $".to_owned()),
+            expanded);
+    }
+
+    #[test]
+    fn directly_run_command_is_found() {
+        assert!(runs_command("alias run x", "alias", &[]));
+        assert!(runs_command("!Alias run x", "alias", &["!"]));
+        assert!(!runs_command("play alias.ogg", "alias", &["!"]));
+        assert!(!runs_command("", "alias", &["!"]));
+    }
+
+    #[test]
+    fn nested_command_is_found() {
+        assert!(runs_command("schedule in 1s alias run x", "alias", &["!"]));
+        assert!(runs_command(r#"do "stop" "!alias run x""#, "alias", &["!"]));
+        assert!(runs_command(
+            r#"schedule at 20:00 do "schedule in 1m ?alias run x""#,
+            "alias", &["!", "?"]));
+        assert!(!runs_command(r#"do "play alias.ogg" "stop""#, "alias",
+            &["!"]));
+        assert!(!runs_command("schedule list", "alias", &["!"]));
+    }
+
+    #[test]
+    fn missing_argument_is_reported() {
+        assert_eq!(Err(AliasError::MissingArgument(2)),
+            expand("play $1 $2", &arguments(&["music"])));
+        assert_eq!(Err(AliasError::MissingArgument(0)),
+            expand("play $0", &arguments(&["music"])));
+    }
+}
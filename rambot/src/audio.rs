@@ -1,16 +1,20 @@
 use rambot_api::{
     AudioMetadata,
+    AudioMetadataBuilder,
     AudioSource,
     AudioSourceList,
     PluginGuildConfig,
     Sample, SampleDuration, SeekError
 };
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
 use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
-use std::sync::{Arc, RwLock};
+use std::mem;
+use std::sync::{Arc, Mutex, RwLock};
 
 #[cfg(feature = "bench")]
 use std::time::{Duration, Instant};
@@ -18,6 +22,10 @@ use songbird::input::core::io::MediaSource;
 use vmcircbuffer::double_mapped_buffer::DoubleMappedBuffer;
 
 use crate::key_value::KeyValueDescriptor;
+use crate::limiter::Limiter;
+use crate::loudness::{NormalizationGain, NormalizationSettings, Normalizer};
+use crate::meter::{LevelMeter, Levels};
+use crate::recording::Recorder;
 use crate::plugin::{PluginManager, AudioDescriptorList, ResolveError};
 
 struct AudioBuffer {
@@ -96,8 +104,118 @@ impl AudioSourceList for SingleAudioSourceList {
     }
 }
 
+/// The highest gain a layer may have, which amplifies it by 20 dB.
+pub const MAX_LAYER_GAIN: f32 = 10.0;
+
+/// Indicates whether the given gain may be set for a layer, that is, whether
+/// it is a number between 0 and [MAX_LAYER_GAIN].
+pub fn is_valid_layer_gain(gain: f32) -> bool {
+    (0.0..=MAX_LAYER_GAIN).contains(&gain)
+}
+
+/// The number of samples over which a change of the effective gain of a layer
+/// is spread in order to avoid audible clicks. A full change from 0 to 1 (or
+/// vice versa) takes this many samples, i.e. 10 ms.
+const GAIN_RAMP_SAMPLES: f32 = 480.0;
+const GAIN_RAMP_STEP: f32 = 1.0 / GAIN_RAMP_SAMPLES;
+
+/// Computes the change of gain per sample with which the gain of a layer is
+/// faded from or to the given gain over the given number of samples. The fade
+/// is never faster than the ramp used for ordinary changes of gain.
+fn fade_step(gain: f32, samples: usize) -> f32 {
+    if gain > 0.0 && samples > 0 {
+        (gain / samples as f32).min(GAIN_RAMP_STEP)
+    }
+    else {
+        GAIN_RAMP_STEP
+    }
+}
+
+fn mix_into(buf: &mut [Sample], layer_samples: &[Sample], gain: &mut f32,
+        target_gain: f32, step: f32) {
+    if *gain == target_gain {
+        let gain = *gain;
+
+        for (out, sample) in buf.iter_mut().zip(layer_samples) {
+            *out += sample * gain;
+        }
+
+        return;
+    }
+
+    for (out, sample) in buf.iter_mut().zip(layer_samples) {
+        let diff = target_gain - *gain;
+
+        if diff.abs() <= step {
+            *gain = target_gain;
+        }
+        else {
+            *gain += step.copysign(diff);
+        }
+
+        *out += sample * *gain;
+    }
+}
+
 type ErrorCallback = Box<dyn Fn(String, io::Error) + Send + Sync>;
 
+/// The maximum number of upcoming entries of a playlist which are included in
+/// a [LayerPlayback]. Lists may be infinite, for example if they are looped,
+/// so only this many entries are taken from them in advance. A restored
+/// playlist therefore ends after these entries, and adapters such as loops
+/// are not applied to it again.
+const MAX_SAVED_UPCOMING: usize = 256;
+
+/// A snapshot of what a single [Layer] is playing, which is sufficient to
+/// continue playback later, for example after the bot was restarted. It can be
+/// obtained by [Mixer::playback] and restored by [Mixer::restore_playback].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LayerPlayback {
+    layer: String,
+    descriptor: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    upcoming: Vec<String>,
+
+    #[serde(default)]
+    position: u64,
+
+    #[serde(default)]
+    paused: bool
+}
+
+impl LayerPlayback {
+
+    /// Gets the name of the layer on which the audio was played.
+    pub fn layer(&self) -> &str {
+        &self.layer
+    }
+
+    /// Gets the descriptor of the audio source which was played, i.e. the
+    /// current entry of the playlist if a list was played.
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Gets the descriptors of the entries of the playlist which were still
+    /// to be played after the current one, in order. At most
+    /// [MAX_SAVED_UPCOMING] entries are retained.
+    pub fn upcoming(&self) -> &[String] {
+        &self.upcoming
+    }
+
+    /// Gets the [SampleDuration] of audio of the current audio source which
+    /// was already played.
+    pub fn position(&self) -> SampleDuration {
+        SampleDuration::from_samples(self.position as i64)
+    }
+
+    /// Indicates whether the layer was paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
 fn no_callback() -> ErrorCallback {
     Box::new(|_, _| { })
 }
@@ -109,11 +227,22 @@ pub struct Layer {
     name: String,
     source: Option<Box<dyn AudioSource + Send + Sync>>,
     list: Option<Box<dyn AudioSourceList + Send + Sync>>,
+    track: Option<String>,
+    position: u64,
+    upcoming: VecDeque<String>,
     error_callback: ErrorCallback,
     buffer: AudioBuffer,
     effects: Vec<KeyValueDescriptor>,
     adapters: Vec<KeyValueDescriptor>,
-    plugin_guild_config: PluginGuildConfig
+    plugin_guild_config: PluginGuildConfig,
+    paused: bool,
+    gain: f32,
+    muted: bool,
+    solo: bool,
+    current_gain: f32,
+    gain_step: f32,
+    normalizer: Normalizer,
+    meter: LevelMeter
 }
 
 impl Layer {
@@ -123,11 +252,33 @@ impl Layer {
             name: name.into(),
             source: None,
             list: None,
+            track: None,
+            position: 0,
+            upcoming: VecDeque::new(),
             error_callback: no_callback(),
             buffer: AudioBuffer::new(),
             effects: Vec::new(),
             adapters: Vec::new(),
-            plugin_guild_config: PluginGuildConfig::default()
+            plugin_guild_config: PluginGuildConfig::default(),
+            paused: false,
+            gain: 1.0,
+            muted: false,
+            solo: false,
+            current_gain: 1.0,
+            gain_step: GAIN_RAMP_STEP,
+            normalizer: Normalizer::new(),
+            meter: LevelMeter::new()
+        }
+    }
+
+    /// Computes the gain which this layer should have in the output, given
+    /// whether any layer of the mixer is currently soloed.
+    fn target_gain(&self, solo_active: bool) -> f32 {
+        if self.muted || (solo_active && !self.solo) {
+            0.0
+        }
+        else {
+            self.gain
         }
     }
 
@@ -135,28 +286,144 @@ impl Layer {
         self.buffer.len() > 0 || self.source.is_some()
     }
 
+    /// Like `active`, but additionally requires the layer not to be paused,
+    /// i.e. it actually contributes audio to the mixer output.
+    fn playing(&self) -> bool {
+        self.active() && !self.paused
+    }
+
     fn set_source(&mut self, source: Box<dyn AudioSource + Send + Sync>) {
+        self.normalizer.start_track(&source.metadata());
         self.source = Some(source);
     }
 
     fn stop(&mut self) -> bool {
         self.error_callback = no_callback();
         self.buffer.clear();
+        self.paused = false;
+        self.track = None;
+        self.upcoming.clear();
         self.list.take().is_some() | self.source.take().is_some()
     }
 
+    /// Pauses this layer, retaining its source, list, and buffer. Returns
+    /// `true` if and only if the layer was playing before.
+    fn pause(&mut self) -> bool {
+        let was_playing = self.playing();
+
+        if was_playing {
+            self.paused = true;
+        }
+
+        was_playing
+    }
+
+    /// Resumes this layer if it was paused. Returns `true` if and only if the
+    /// layer was paused before.
+    fn resume(&mut self) -> bool {
+        let was_paused = self.paused;
+        self.paused = false;
+        was_paused
+    }
+
     /// Like `stop`, but does not clear the buffer.
     fn soft_stop(&mut self) {
         self.list = None;
         self.source = None;
+        self.track = None;
+        self.upcoming.clear();
         self.error_callback = no_callback();
     }
 
+    /// Gets the descriptor of the next audio source to play, which is the
+    /// first entry taken from the list in advance, if any, and otherwise the
+    /// next entry of the list. Returns `None` if nothing is left to play.
+    fn next_descriptor(&mut self) -> Result<Option<String>, io::Error> {
+        if let Some(next) = self.upcoming.pop_front() {
+            return Ok(Some(next));
+        }
+
+        match &mut self.list {
+            Some(list) => list.next(),
+            None => Ok(None)
+        }
+    }
+
+    /// Takes a snapshot of what this layer is playing, taking up to
+    /// [MAX_SAVED_UPCOMING] entries from the list in advance. They are still
+    /// played afterwards. Returns `None` if the layer is not playing anything.
+    fn playback(&mut self) -> Option<LayerPlayback> {
+        let descriptor = self.track.clone()?;
+
+        while self.upcoming.len() < MAX_SAVED_UPCOMING {
+            let list = match &mut self.list {
+                Some(list) => list,
+                None => break
+            };
+
+            match list.next() {
+                Ok(Some(next)) => self.upcoming.push_back(next),
+                Ok(None) => self.list = None,
+                Err(e) => {
+                    log::warn!("Could not get upcoming entries on layer {}: \
+                        {}", self.name, e);
+                    break;
+                }
+            }
+        }
+
+        Some(LayerPlayback {
+            layer: self.name.clone(),
+            descriptor,
+            upcoming: self.upcoming.iter().cloned().collect(),
+            position: self.position.saturating_sub(self.buffer.len() as u64),
+            paused: self.paused
+        })
+    }
+
     /// Gets the name of this layer.
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Indicates whether this layer is currently paused. A paused layer keeps
+    /// the audio it is playing, but does not contribute to the output of the
+    /// [Mixer] until it is resumed.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Gets the gain of this layer, i.e. the factor by which its audio is
+    /// multiplied before it is added to the mixer output. A gain of 1
+    /// represents the original volume.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Indicates whether this layer is muted. A muted layer continues playing,
+    /// but does not contribute to the mixer output.
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Indicates whether this layer is soloed. As long as at least one layer
+    /// of a mixer is soloed, only soloed layers contribute to its output.
+    pub fn is_solo(&self) -> bool {
+        self.solo
+    }
+
+    /// Gets the [NormalizationGain] which is currently applied to the audio of
+    /// this layer by loudness normalization.
+    pub fn normalization_gain(&self) -> NormalizationGain {
+        self.normalizer.gain()
+    }
+
+    /// Gets the [Levels] which this layer most recently contributed to the
+    /// mixer output, i.e. after applying its gain.
+    pub fn levels(&self) -> Levels {
+        self.meter.levels()
+    }
+
     /// Gets a slice of [KeyValueDescriptor]s representing the audio effects
     /// that are active on this layer. The order in the slice is equal to the
     /// order in which they are applied to the audio.
@@ -171,8 +438,8 @@ impl Layer {
         &self.adapters
     }
 
-    fn read_from_source<P>(&mut self, capacity: usize, plugin_manager: &P)
-        -> Result<(), io::Error>
+    fn read_from_source<P>(&mut self, capacity: usize, plugin_manager: &P,
+        normalization: NormalizationSettings) -> Result<(), io::Error>
     where
         P: AsRef<PluginManager>
     {
@@ -182,32 +449,30 @@ impl Layer {
             let sample_count = unsafe {
                 let inactive_slice = self.buffer.inactive_slice_mut();
                 let count = source.read(inactive_slice)?;
+                self.normalizer.process(&mut inactive_slice[..count],
+                    normalization);
                 self.buffer.advance_tail(count);
                 count
             };
 
+            self.position += sample_count as u64;
+
             if sample_count == 0 {
-                if let Some(list) = &mut self.list {
-                    if let Some(next) = list.next()? {
-                        // Audio source ran out but list continues
-
-                        let res = play_on_layer::<false, _>(
-                            self, &next, plugin_manager);
-
-                        if let Err(e) = res {
-                            (self.error_callback)(self.name.clone(), e);
-                            self.soft_stop();
-                            break;
-                        }
-                    }
-                    else {
-                        // Audio source ran out and list is finished
+                if let Some(next) = self.next_descriptor()? {
+                    // Audio source ran out but list continues
+
+                    let res = play_on_layer::<false, _>(
+                        self, &next, plugin_manager);
 
+                    if let Err(e) = res {
+                        (self.error_callback)(self.name.clone(), e);
                         self.soft_stop();
+                        break;
                     }
                 }
                 else {
-                    // Audio source ran out and there is no list
+                    // Audio source ran out and list is finished or there is
+                    // no list
 
                     self.soft_stop();
                 }
@@ -254,19 +519,15 @@ impl Layers {
         self.layers.push(layer);
     }
 
-    fn remove(&mut self, layer: &str) -> bool {
-        if let Some(index) = self.names.remove(layer) {
-            self.layers.swap_remove(index);
-
-            if let Some(moved_layer) = self.layers.get(index) {
-                *self.names.get_mut(&moved_layer.name).unwrap() = index;
-            }
+    fn remove(&mut self, layer: &str) -> Option<Layer> {
+        let index = self.names.remove(layer)?;
+        let removed_layer = self.layers.swap_remove(index);
 
-            true
-        }
-        else {
-            false
+        if let Some(moved_layer) = self.layers.get(index) {
+            *self.names.get_mut(&moved_layer.name).unwrap() = index;
         }
+
+        Some(removed_layer)
     }
 
     fn iter(&self) -> impl Iterator<Item = &Layer> {
@@ -340,14 +601,56 @@ impl From<SeekError> for SeekOnLayerError {
     }
 }
 
+/// The [AudioSource] at the root of the master effect chain of a [Mixer]. It
+/// provides the summed audio of all layers, which the mixer pushes into a
+/// shared queue before reading from the master effect chain.
+struct MasterInput {
+    queue: Arc<Mutex<VecDeque<Sample>>>
+}
+
+impl AudioSource for MasterInput {
+    fn read(&mut self, buf: &mut [Sample]) -> Result<usize, io::Error> {
+        let mut queue = self.queue.lock().unwrap();
+        let count = buf.len().min(queue.len());
+
+        for (target, sample) in buf.iter_mut().zip(queue.drain(..count)) {
+            *target = sample;
+        }
+
+        Ok(count)
+    }
+
+    fn has_child(&self) -> bool {
+        false
+    }
+
+    fn take_child(&mut self) -> Box<dyn AudioSource + Send + Sync> {
+        panic!("master input has no child")
+    }
+
+    fn metadata(&self) -> AudioMetadata {
+        AudioMetadataBuilder::new().build()
+    }
+}
+
 /// A mixer manages multiple [AudioSource]s and adds their outputs.
 pub struct Mixer {
     layers: Layers,
-    plugin_manager: Arc<PluginManager>
+    fading_layers: Vec<Layer>,
+    plugin_manager: Arc<PluginManager>,
+    master_effects: Vec<KeyValueDescriptor>,
+    master_chain: Box<dyn AudioSource + Send + Sync>,
+    master_queue: Arc<Mutex<VecDeque<Sample>>>,
+    master_buf: Vec<Sample>,
+    limiter: Limiter,
+    normalization: NormalizationSettings,
+    output_meter: LevelMeter,
+    recorder: Option<Recorder>,
+    plugin_guild_config: PluginGuildConfig
 }
 
 fn to_io_err<T, E: Display>(r: Result<T, E>) -> Result<T, io::Error> {
-    r.map_err(|e| io::Error::new(ErrorKind::Other, format!("{}", e)))
+    r.map_err(|e| io::Error::other(format!("{}", e)))
 }
 
 fn play_source_on_layer<const CLEAR_BUF: bool, P>(layer: &mut Layer,
@@ -380,7 +683,10 @@ where
         plugin_manager.as_ref().resolve_audio_source(
             descriptor, &layer.plugin_guild_config))?;
 
-    play_source_on_layer::<CLEAR_BUF, _>(layer, source, plugin_manager)
+    play_source_on_layer::<CLEAR_BUF, _>(layer, source, plugin_manager)?;
+    layer.track = Some(descriptor.to_owned());
+    layer.position = 0;
+    Ok(())
 }
 
 fn play_list_on_layer<const CLEAR_BUF: bool, P>(layer: &mut Layer,
@@ -403,48 +709,97 @@ where
     Ok(())
 }
 
-fn reapply_effects_after_removal<P>(layer: &mut Layer,
-    first_removed_idx: usize, total_removed: usize, plugin_manager: &P)
+/// Rebuilds the effect chain of the audio currently played on the given layer
+/// after effects were removed. Since disabled effects and effects which could
+/// not be resolved are not part of the chain, it is unwrapped completely
+/// instead of removing one wrapper per configured effect.
+fn reapply_effects_after_removal<P>(layer: &mut Layer, plugin_manager: &P)
     -> Result<(), ResolveError>
 where
     P: AsRef<PluginManager>
 {
     let mut result = Ok(());
 
-    if let Some(mut source) = layer.source.take() {
-        let old_len = layer.effects.len() + total_removed;
+    if let Some(source) = layer.source.take() {
+        let (source, reapply_result) = rebuild_effect_chain(source,
+            &layer.effects, plugin_manager.as_ref(),
+            &layer.plugin_guild_config);
 
-        for _ in 0..(old_len - first_removed_idx) {
-            source = source.take_child();
-        }
+        layer.source = Some(source);
+        result = reapply_result;
+    }
 
-        for old_effect in &layer.effects[first_removed_idx..] {
-            let effect_res = plugin_manager.as_ref().resolve_effect(
-                &old_effect.name, &old_effect.key_values, source,
-                &layer.plugin_guild_config);
+    result
+}
 
-            match effect_res {
-                Ok(effect) => source = effect,
-                Err((err, child)) => {
+/// Removes all effects from the given source and resolves the given effects
+/// anew. Effects which cannot be resolved are skipped, and the first error is
+/// returned alongside the new source.
+fn rebuild_effect_chain(mut source: Box<dyn AudioSource + Send + Sync>,
+    effects: &[KeyValueDescriptor], plugin_manager: &PluginManager,
+    plugin_guild_config: &PluginGuildConfig)
+    -> (Box<dyn AudioSource + Send + Sync>, Result<(), ResolveError>)
+{
+    let mut result = Ok(());
+
+    while source.has_child() {
+        source = source.take_child();
+    }
+
+    for effect in effects {
+        let effect_res = plugin_manager.resolve_effect(
+            &effect.name, &effect.key_values, source, plugin_guild_config);
+
+        match effect_res {
+            Ok(effect) => source = effect,
+            Err((err, child)) => {
+                if result.is_ok() {
                     result = Err(err);
-                    source = child;
                 }
+
+                source = child;
             }
         }
-
-        layer.source = Some(source);
     }
 
-    result
+    (source, result)
+}
+
+/// Removes all effects that do not match the given `predicate` from the given
+/// list of `effects`. Returns the number of removed effects.
+fn retain_descriptors<P>(effects: &mut Vec<KeyValueDescriptor>, predicate: P)
+    -> usize
+where
+    P: FnMut(&KeyValueDescriptor) -> bool
+{
+    let old_len = effects.len();
+
+    effects.retain(predicate);
+    old_len - effects.len()
 }
 
 impl Mixer {
 
     /// Creates a new mixer without layers.
     pub fn new(plugin_manager: Arc<PluginManager>) -> Mixer {
+        let master_queue = Arc::new(Mutex::new(VecDeque::new()));
+        let master_input = MasterInput {
+            queue: Arc::clone(&master_queue)
+        };
+
         Mixer {
             layers: Layers::new(),
-            plugin_manager
+            fading_layers: Vec::new(),
+            plugin_manager,
+            master_effects: Vec::new(),
+            master_chain: Box::new(master_input),
+            master_queue,
+            master_buf: Vec::new(),
+            limiter: Limiter::new(),
+            normalization: NormalizationSettings::default(),
+            output_meter: LevelMeter::new(),
+            recorder: None,
+            plugin_guild_config: PluginGuildConfig::default()
         }
     }
 
@@ -470,7 +825,9 @@ impl Mixer {
             return false;
         }
 
-        self.layers.push(Layer::new(name));
+        let mut layer = Layer::new(name);
+        layer.plugin_guild_config = self.plugin_guild_config.clone();
+        self.layers.push(layer);
 
         true
     }
@@ -478,13 +835,46 @@ impl Mixer {
     /// Removes the layer with the given name and returns whether a layer was
     /// removed, i.e. there was one with the given name.
     pub fn remove_layer(&mut self, name: &str) -> bool {
-        self.layers.remove(name)
+        self.layers.remove(name).is_some()
+    }
+
+    /// Removes the layer with the given name, but lets the audio playing on
+    /// it fade out over the given number of samples instead of stopping it
+    /// immediately. A new layer of the same name can be added right away.
+    /// Returns whether a layer was removed, i.e. there was one with the given
+    /// name.
+    pub fn fade_out_layer(&mut self, name: &str, samples: usize) -> bool {
+        let mut layer = match self.layers.remove(name) {
+            Some(layer) => layer,
+            None => return false
+        };
+
+        if layer.playing() && layer.current_gain > 0.0 {
+            layer.gain_step = fade_step(layer.current_gain, samples);
+            self.fading_layers.push(layer);
+        }
+
+        true
+    }
+
+    /// Lets the audio on the `layer` with the given name fade in from silence
+    /// to the gain of the layer over the given number of samples. This is
+    /// intended to be called right after playback was started on the layer.
+    /// Panics if the layer does not exist.
+    pub fn fade_in_layer(&mut self, layer: &str, samples: usize) {
+        let layer = self.layers.get_mut(layer);
+
+        layer.current_gain = 0.0;
+        layer.gain_step = fade_step(layer.gain, samples);
     }
 
     /// Indicates whether this mixer is currently active, i.e. there is an
-    /// active layer.
+    /// active layer which is not paused. Layers which are fading out after
+    /// they were removed count as well.
     pub fn active(&self) -> bool {
-        self.layers.iter().map(|l| &l.source).any(Option::is_some)
+        self.layers.iter()
+            .chain(self.fading_layers.iter())
+            .any(|l| l.source.is_some() && !l.paused)
     }
 
     /// Adds an effect to the layer with the given name. If the effect is
@@ -514,8 +904,7 @@ impl Mixer {
 
             if let Some(idx) = removed_idx {
                 layer.effects.remove(idx);
-                reapply_effects_after_removal(
-                    layer, idx, 1, &self.plugin_manager)?;
+                reapply_effects_after_removal(layer, &self.plugin_manager)?;
             }
         }
 
@@ -580,39 +969,248 @@ impl Mixer {
     ///
     /// # Errors
     ///
-    /// If a lower-level effect was removed while a higher-level one was
-    /// retained, the higher-level effect needs to be re-resolved with audio
-    /// that does not have the lower-level effect. This can cause a
-    /// [ResolveError].
-    pub fn retain_effects<P>(&mut self, layer: &str, mut predicate: P)
+    /// If any effect was removed while audio is being played, the retained
+    /// effects need to be re-resolved. This can cause a [ResolveError], in
+    /// which case the effect is skipped, but remains configured.
+    pub fn retain_effects<P>(&mut self, layer: &str, predicate: P)
         -> Result<usize, ResolveError>
     where
         P: FnMut(&KeyValueDescriptor) -> bool
     {
         let layer = self.layers.get_mut(layer);
-        let mut index = 0;
-        let mut first_removed_idx = None;
-        let old_len = layer.effects.len();
+        let total_removed = retain_descriptors(&mut layer.effects, predicate);
+
+        if total_removed > 0 {
+            reapply_effects_after_removal(layer, &self.plugin_manager)?;
+        }
+
+        Ok(total_removed)
+    }
+
+    fn master_input(&self) -> Box<dyn AudioSource + Send + Sync> {
+        Box::new(MasterInput {
+            queue: Arc::clone(&self.master_queue)
+        })
+    }
+
+    /// Gets a slice of [KeyValueDescriptor]s representing the audio effects
+    /// on the master bus, i.e. which are applied to the summed audio of all
+    /// layers. The order in the slice is equal to the order in which they are
+    /// applied to the audio.
+    pub fn master_effects(&self) -> &[KeyValueDescriptor] {
+        &self.master_effects
+    }
+
+    /// Adds an effect to the master bus, which processes the summed audio of
+    /// all layers. If the effect is unique, any old version of it will be
+    /// removed before.
+    ///
+    /// # Arguments
+    ///
+    /// * `descriptor`: A [KeyValueDescriptor] describing the effect to add.
+    /// * `plugin_guild_config`: The [PluginGuildConfig] with which to resolve
+    ///   the effect.
+    ///
+    /// # Errors
+    ///
+    /// If the effect, or any effect which needs to be re-resolved due to the
+    /// removal of an older version, cannot be resolved, a [ResolveError] is
+    /// raised.
+    pub fn add_master_effect(&mut self, descriptor: KeyValueDescriptor,
+            plugin_guild_config: &PluginGuildConfig)
+            -> Result<(), ResolveError> {
+        if self.plugin_manager.is_effect_unique(&descriptor.name) {
+            self.retain_master_effects(|e| e.name != descriptor.name,
+                plugin_guild_config)?;
+        }
 
-        layer.effects.retain(|descriptor| {
-            if predicate(descriptor) {
-                index += 1;
-                true
+        let master_input = self.master_input();
+        let chain = mem::replace(&mut self.master_chain, master_input);
+        let effect_res = self.plugin_manager.resolve_effect(
+            &descriptor.name, &descriptor.key_values, chain,
+            plugin_guild_config);
+
+        match effect_res {
+            Ok(effect) => {
+                self.master_chain = effect;
+                self.master_effects.push(descriptor);
+                Ok(())
+            },
+            Err((err, child)) => {
+                self.master_chain = child;
+                Err(err)
             }
-            else {
-                first_removed_idx.get_or_insert(index);
-                false
+        }
+    }
+
+    /// Removes all effects from the master bus that do not match the given
+    /// `predicate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate`: A function which takes as input a reference to a
+    ///   [KeyValueDescriptor] representing an effect and decides whether this
+    ///   effect should be retained (`true`) or not (`false`).
+    /// * `plugin_guild_config`: The [PluginGuildConfig] with which to
+    ///   re-resolve retained effects, if necessary.
+    ///
+    /// # Returns
+    ///
+    /// The number of effects that were removed.
+    ///
+    /// # Errors
+    ///
+    /// If any effect was removed, the retained effects need to be
+    /// re-resolved. This can cause a [ResolveError], in which case the effect
+    /// is skipped, but remains configured.
+    pub fn retain_master_effects<P>(&mut self, predicate: P,
+        plugin_guild_config: &PluginGuildConfig)
+        -> Result<usize, ResolveError>
+    where
+        P: FnMut(&KeyValueDescriptor) -> bool
+    {
+        let total_removed =
+            retain_descriptors(&mut self.master_effects, predicate);
+
+        if total_removed > 0 {
+            let master_input = self.master_input();
+            let chain = mem::replace(&mut self.master_chain, master_input);
+            let (chain, result) = rebuild_effect_chain(chain,
+                &self.master_effects, &self.plugin_manager,
+                plugin_guild_config);
+
+            self.master_chain = chain;
+            result?;
+        }
+
+        Ok(total_removed)
+    }
+
+    /// Clears all effects from the master bus.
+    ///
+    /// # Returns
+    ///
+    /// The number of effects that were removed.
+    pub fn clear_master_effects(&mut self) -> usize {
+        while self.master_chain.has_child() {
+            self.master_chain = self.master_chain.take_child();
+        }
+
+        let old_len = self.master_effects.len();
+        self.master_effects.clear();
+        old_len
+    }
+
+    /// Resolves all effects on active layers and the master bus anew. This is
+    /// required after plugins were reloaded in order for existing audio to
+    /// use the new versions of the effects. Adapters are resolved whenever a
+    /// playlist is started, so playlists which are currently being played
+    /// keep their old adapters.
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin_guild_config`: The [PluginGuildConfig] with which to resolve
+    ///   the master effects. Layer effects are resolved with the config of
+    ///   their layer.
+    ///
+    /// # Errors
+    ///
+    /// The first [ResolveError] raised by an effect which could not be
+    /// resolved, for example because its plugin was unloaded. Such effects
+    /// are skipped, but remain configured, so they are applied again once
+    /// they can be resolved.
+    pub fn reapply_effects(&mut self, plugin_guild_config: &PluginGuildConfig)
+            -> Result<(), ResolveError> {
+        let mut result = Ok(());
+
+        for layer in self.layers.iter_mut() {
+            if let Some(source) = layer.source.take() {
+                let (source, layer_result) = rebuild_effect_chain(source,
+                    &layer.effects, &self.plugin_manager,
+                    &layer.plugin_guild_config);
+
+                layer.source = Some(source);
+                result = result.and(layer_result);
             }
-        });
+        }
 
-        let total_removed = old_len - layer.effects.len();
+        let master_input = self.master_input();
+        let chain = mem::replace(&mut self.master_chain, master_input);
+        let (chain, master_result) = rebuild_effect_chain(chain,
+            &self.master_effects, &self.plugin_manager, plugin_guild_config);
 
-        if let Some(first_removed_idx) = first_removed_idx {
-            reapply_effects_after_removal(layer, first_removed_idx,
-                total_removed, &self.plugin_manager)?;
+        self.master_chain = chain;
+        result.and(master_result)
+    }
+
+    /// Sets the [PluginGuildConfig] with which effects and adapters on all
+    /// layers are resolved, including layers added later, and re-applies all
+    /// effects, so changes such as disabled effects take effect immediately.
+    ///
+    /// # Errors
+    ///
+    /// The first [ResolveError] raised by an effect which could not be
+    /// resolved with the new config. See [Mixer::reapply_effects].
+    pub fn set_plugin_guild_config(&mut self,
+            plugin_guild_config: PluginGuildConfig)
+            -> Result<(), ResolveError> {
+        for layer in self.layers.iter_mut() {
+            layer.plugin_guild_config = plugin_guild_config.clone();
         }
 
-        Ok(total_removed)
+        let result = self.reapply_effects(&plugin_guild_config);
+        self.plugin_guild_config = plugin_guild_config;
+        result
+    }
+
+    /// Gets a reference to the [Limiter] which is applied to the output of
+    /// this mixer after all master effects.
+    pub fn limiter(&self) -> &Limiter {
+        &self.limiter
+    }
+
+    /// Gets a mutable reference to the [Limiter] which is applied to the
+    /// output of this mixer after all master effects.
+    pub fn limiter_mut(&mut self) -> &mut Limiter {
+        &mut self.limiter
+    }
+
+    /// Gets the [NormalizationSettings] which control the loudness
+    /// normalization of all layers of this mixer.
+    pub fn normalization(&self) -> NormalizationSettings {
+        self.normalization
+    }
+
+    /// Sets the [NormalizationSettings] which control the loudness
+    /// normalization of all layers of this mixer. Audio which has already
+    /// been buffered is not affected.
+    pub fn set_normalization(&mut self, normalization: NormalizationSettings) {
+        self.normalization = normalization;
+    }
+
+    /// Gets the [Levels] of the most recent output of this mixer, i.e. after
+    /// master effects and the limiter.
+    pub fn output_levels(&self) -> Levels {
+        self.output_meter.levels()
+    }
+
+    /// Starts recording the output of this mixer, i.e. after master effects
+    /// and the limiter, with the given [Recorder]. Any previous recorder is
+    /// replaced and returned, so it can be stopped by the caller.
+    pub fn start_recording(&mut self, recorder: Recorder) -> Option<Recorder> {
+        self.recorder.replace(recorder)
+    }
+
+    /// Removes the current [Recorder], if any, and returns it, so it can be
+    /// stopped by the caller.
+    pub fn stop_recording(&mut self) -> Option<Recorder> {
+        self.recorder.take()
+    }
+
+    /// Gets the [Recorder] which currently records the output of this mixer,
+    /// if there is one.
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
     }
 
     /// Adds an adapter to the layer with the given name. If a playlist is
@@ -685,12 +1283,18 @@ impl Mixer {
     where
         E: Fn(String, io::Error) + Send + Sync + 'static
     {
+        let solo_active = self.solo_active();
         let layer = self.layers.get_mut(layer);
         let audio = to_io_err(
             self.plugin_manager.resolve_audio_descriptor_list(descriptor,
                 &layer.plugin_guild_config))?;
 
         layer.stop();
+
+        // The layer is silent now, so we can jump to the target gain without
+        // causing a click.
+
+        layer.current_gain = layer.target_gain(solo_active);
         layer.plugin_guild_config = plugin_guild_config;
         layer.error_callback = Box::new(error_callback);
 
@@ -715,13 +1319,13 @@ impl Mixer {
     pub fn skip_on_layer(&mut self, layer: &str) -> Result<(), io::Error> {
         let layer = self.layers.get_mut(layer);
 
-        match layer.list.as_mut().map(|l| l.next()) {
-            Some(Ok(Some(next))) => {
+        match layer.next_descriptor() {
+            Ok(Some(next)) => {
                 play_on_layer::<true, _>(layer, &next, &self.plugin_manager)?;
                 Ok(())
             },
-            Some(Err(e)) => Err(e),
-            Some(Ok(None)) | None => {
+            Err(e) => Err(e),
+            Ok(None) => {
                 layer.stop();
                 Ok(())
             }
@@ -763,7 +1367,10 @@ impl Mixer {
             }
 
             if let Some(source) = layer.source.as_mut() {
-                Ok(source.seek(delta)?)
+                source.seek(delta)?;
+                layer.position =
+                    (layer.position as i64 + delta.samples()).max(0) as u64;
+                Ok(())
             }
             else {
                 Ok(())
@@ -774,17 +1381,77 @@ impl Mixer {
         }
     }
 
-    /// Stops the audio source currently played on the `layer` with the given
-    /// name. Returns true if and only if there was something playing on the
-    /// layer before. Panics if the layer does not exist.
-    pub fn stop_layer(&mut self, layer: &str) -> bool {
-        let layer = self.layers.get_mut(layer);
-        layer.stop()
+    /// Takes a snapshot of what every layer of this mixer is playing, which
+    /// can later be restored with [Mixer::restore_playback]. Layers which are
+    /// not playing anything are omitted. Upcoming entries of playlists are
+    /// taken from them in advance, which does not change what is played.
+    pub fn playback(&mut self) -> Vec<LayerPlayback> {
+        self.layers.iter_mut()
+            .filter_map(Layer::playback)
+            .collect()
     }
 
-    /// Stops audio on all layers. Returns true if and only if at there was
-    /// audio playing before on at least one layer.
+    /// Continues playback on a layer as it was when the given [LayerPlayback]
+    /// was taken. The audio source is resolved again and seeked to the saved
+    /// position. If seeking is not supported by the audio source, it is
+    /// played from the start. Panics if the layer does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `playback`: The [LayerPlayback] to restore. It determines the layer
+    ///   on which the audio is played.
+    /// * `plugin_guild_config`: The [PluginGuildConfig] with which the audio
+    ///   is resolved.
+    /// * `error_callback`: Called if an error occurs while playing a later
+    ///   entry of the playlist.
+    ///
+    /// # Errors
+    ///
+    /// If the audio source cannot be resolved.
+    pub fn restore_playback<E>(&mut self, playback: &LayerPlayback,
+        plugin_guild_config: PluginGuildConfig, error_callback: E)
+        -> Result<(), io::Error>
+    where
+        E: Fn(String, io::Error) + Send + Sync + 'static
+    {
+        let solo_active = self.solo_active();
+        let layer = self.layers.get_mut(&playback.layer);
+
+        layer.stop();
+        layer.current_gain = layer.target_gain(solo_active);
+        layer.plugin_guild_config = plugin_guild_config;
+        play_on_layer::<true, _>(
+            layer, &playback.descriptor, &self.plugin_manager)?;
+        layer.error_callback = Box::new(error_callback);
+        layer.upcoming = playback.upcoming.iter().cloned().collect();
+        layer.paused = playback.paused;
+
+        if playback.position > 0 {
+            let source = layer.source.as_mut().unwrap();
+
+            match source.seek(playback.position()) {
+                Ok(()) => layer.position = playback.position,
+                Err(e) => log::warn!(
+                    "Could not seek to saved position on layer {}: {}",
+                    playback.layer, e)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stops the audio source currently played on the `layer` with the given
+    /// name. Returns true if and only if there was something playing on the
+    /// layer before. Panics if the layer does not exist.
+    pub fn stop_layer(&mut self, layer: &str) -> bool {
+        let layer = self.layers.get_mut(layer);
+        layer.stop()
+    }
+
+    /// Stops audio on all layers. Returns true if and only if at there was
+    /// audio playing before on at least one layer.
     pub fn stop_all(&mut self) -> bool {
+        self.fading_layers.clear();
         self.layers.iter_mut()
             .map(Layer::stop)
             .collect::<Vec<_>>() // Avoid short circuiting
@@ -792,6 +1459,79 @@ impl Mixer {
             .any(|x| x)
     }
 
+    /// Pauses the audio currently played on the `layer` with the given name.
+    /// The layer retains its audio source, playlist, and buffered audio, so it
+    /// can later be continued from the same position using
+    /// [Mixer::resume_layer]. Returns true if and only if the layer was
+    /// playing before. Panics if the layer does not exist.
+    pub fn pause_layer(&mut self, layer: &str) -> bool {
+        let layer = self.layers.get_mut(layer);
+        layer.pause()
+    }
+
+    /// Pauses audio on all layers. Returns true if and only if there was
+    /// audio playing before on at least one layer.
+    pub fn pause_all(&mut self) -> bool {
+        self.layers.iter_mut()
+            .map(Layer::pause)
+            .collect::<Vec<_>>() // Avoid short circuiting
+            .into_iter()
+            .any(|x| x)
+    }
+
+    /// Resumes the audio on the `layer` with the given name, which was
+    /// previously paused with [Mixer::pause_layer] or [Mixer::pause_all].
+    /// Returns true if and only if the layer was paused before. Panics if the
+    /// layer does not exist.
+    pub fn resume_layer(&mut self, layer: &str) -> bool {
+        let layer = self.layers.get_mut(layer);
+        layer.resume()
+    }
+
+    /// Resumes audio on all paused layers. Returns true if and only if at
+    /// least one layer was paused before.
+    pub fn resume_all(&mut self) -> bool {
+        self.layers.iter_mut()
+            .map(Layer::resume)
+            .collect::<Vec<_>>() // Avoid short circuiting
+            .into_iter()
+            .any(|x| x)
+    }
+
+    fn solo_active(&self) -> bool {
+        self.layers.iter().any(|l| l.solo)
+    }
+
+    /// Sets the gain of the `layer` with the given name, i.e. the factor by
+    /// which its audio is multiplied before it is added to the output. The
+    /// change is applied gradually to avoid audible clicks. Panics if the
+    /// layer does not exist.
+    pub fn set_layer_gain(&mut self, layer: &str, gain: f32) {
+        self.layers.get_mut(layer).gain = gain;
+    }
+
+    /// Mutes or unmutes the `layer` with the given name. A muted layer
+    /// continues playing, but does not contribute to the output. Returns
+    /// `true` if and only if the mute state of the layer changed. Panics if
+    /// the layer does not exist.
+    pub fn set_layer_muted(&mut self, layer: &str, muted: bool) -> bool {
+        let layer = self.layers.get_mut(layer);
+        let changed = layer.muted != muted;
+        layer.muted = muted;
+        changed
+    }
+
+    /// Solos or unsolos the `layer` with the given name. As long as at least
+    /// one layer is soloed, all other layers do not contribute to the output.
+    /// Returns `true` if and only if the solo state of the layer changed.
+    /// Panics if the layer does not exist.
+    pub fn set_layer_solo(&mut self, layer: &str, solo: bool) -> bool {
+        let layer = self.layers.get_mut(layer);
+        let changed = layer.solo != solo;
+        layer.solo = solo;
+        changed
+    }
+
     /// Returns a slice of all layers in this mixer.
     pub fn layers(&self) -> &[Layer] {
         &self.layers.layers
@@ -830,52 +1570,135 @@ impl Mixer {
     }
 }
 
-impl AudioSource for Mixer {
-    fn read(&mut self, buf: &mut [Sample]) -> Result<usize, io::Error> {
+impl Mixer {
+    fn read_layers(&mut self, buf: &mut [Sample]) -> Result<usize, io::Error> {
         let mut size = usize::MAX;
         let mut active_layers = Vec::new();
+        let mut silent_layers = Vec::new();
+        let solo_active = self.solo_active();
+
+        // Layers which were faded out are dropped once they are silent.
+
+        self.fading_layers.retain(|l| l.playing() && l.current_gain > 0.0);
+
+        let layers = self.layers.iter_mut().map(|l| (l, false))
+            .chain(self.fading_layers.iter_mut().map(|l| (l, true)));
+
+        for (layer, fading) in layers {
+            if layer.paused {
+                silent_layers.push(layer);
+                continue;
+            }
 
-        for layer in self.layers.iter_mut() {
             if layer.active() && layer.buffer.len() < buf.len() {
-                layer.read_from_source(buf.len(), &self.plugin_manager)?;
+                layer.read_from_source(buf.len(), &self.plugin_manager,
+                    self.normalization)?;
             }
 
             // The layer may have been deactivated just now, so we check again
 
             if !layer.active() {
+                silent_layers.push(layer);
                 continue;
             }
 
             size = size.min(layer.buffer.len());
-            active_layers.push(layer);
+            active_layers.push((layer, fading));
         }
 
         if size == usize::MAX {
+            for layer in silent_layers {
+                layer.meter.reset();
+            }
+
             return Ok(0);
         }
 
         size = size.min(buf.len());
+        buf[..size].fill(Sample::ZERO);
 
-        let mut active_layers = active_layers.into_iter();
-        let first_layer = active_layers.next().unwrap();
-
-        unsafe {
-            buf[..size].copy_from_slice(first_layer.buffer.get_slice(size));
-            first_layer.buffer.advance_head(size);
+        for layer in silent_layers {
+            layer.meter.process_silence(size);
         }
 
-        for layer in active_layers {
+        for (layer, fading) in active_layers {
+            let target_gain = if fading {
+                0.0
+            }
+            else {
+                layer.target_gain(solo_active)
+            };
             let slice = unsafe { layer.buffer.get_slice(size) };
 
-            for (i, sample) in slice.iter().enumerate() {
-                buf[i] += sample;
+            mix_into(&mut buf[..size], slice, &mut layer.current_gain,
+                target_gain, layer.gain_step);
+
+            if layer.current_gain == target_gain {
+                layer.gain_step = GAIN_RAMP_STEP;
             }
 
+            layer.meter.process(slice, layer.current_gain);
             layer.buffer.advance_head(size);
         }
 
         Ok(size)
     }
+}
+
+impl Mixer {
+    fn read_master(&mut self, buf: &mut [Sample]) -> Result<usize, io::Error> {
+        if self.master_effects.is_empty() {
+            return self.read_layers(buf);
+        }
+
+        let queued = self.master_queue.lock().unwrap().len();
+        let missing = buf.len().saturating_sub(queued);
+
+        if missing > 0 {
+            if self.master_buf.len() < missing {
+                self.master_buf = vec![Sample::ZERO; missing];
+            }
+
+            let mut master_buf = mem::take(&mut self.master_buf);
+            let count = self.read_layers(&mut master_buf[..missing]);
+
+            if let Ok(count) = &count {
+                self.master_queue.lock().unwrap()
+                    .extend(master_buf[..*count].iter().cloned());
+            }
+
+            self.master_buf = master_buf;
+            count?;
+        }
+
+        self.master_chain.read(buf)
+    }
+}
+
+impl AudioSource for Mixer {
+    fn read(&mut self, buf: &mut [Sample]) -> Result<usize, io::Error> {
+        let mut count = self.read_master(buf)?;
+        self.limiter.process(&mut buf[..count]);
+
+        if count == 0 {
+            // The limiter still holds the end of the audio in its delay line.
+
+            count = self.limiter.flush(buf);
+        }
+
+        if count == 0 {
+            self.output_meter.reset();
+        }
+        else {
+            self.output_meter.process(&buf[..count], 1.0);
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.push(&buf[..count]);
+        }
+
+        Ok(count)
+    }
 
     fn has_child(&self) -> bool {
         false
@@ -997,14 +1820,19 @@ mod tests {
 
     use rambot_api::{
         AudioDocumentation,
-        AudioMetadataBuilder,
         AudioSourceListResolver,
         AudioSourceResolver,
-        PluginGuildConfig
+        EffectResolver,
+        ModifierDocumentation,
+        PluginGuildConfig,
+        ResolveEffectError
     };
 
     use rambot_test_util::{MockAudioSource, MockAudioSourceList};
 
+    use crate::limiter::LOOKAHEAD_SAMPLES;
+    use crate::loudness::NormalizationMode;
+
     use std::sync::Mutex;
 
     fn pcm_read_to_end<S>(mut buf: &mut [u8], read: &mut PCMRead<S>) -> usize
@@ -1023,7 +1851,7 @@ mod tests {
             buf = &mut buf[count..];
             total += count;
 
-            if buf.len() == 0 {
+            if buf.is_empty() {
                 return total;
             }
         }
@@ -1058,6 +1886,7 @@ mod tests {
 
     const TEST_1_LEN: usize = 48000;
     const TEST_2_LEN: usize = 48000;
+    const EPS: f32 = 0.0001;
 
     fn test_audio_1() -> Vec<Sample> {
         rambot_test_util::test_data(TEST_1_LEN, 60.0, 120.0)
@@ -1093,7 +1922,9 @@ mod tests {
     }
 
     fn mock_mixer() -> Mixer {
-        Mixer::new(Arc::new(PluginManager::empty()))
+        let mut mixer = Mixer::new(Arc::new(PluginManager::empty()));
+        mixer.limiter_mut().set_enabled(false);
+        mixer
     }
 
     #[test]
@@ -1173,16 +2004,80 @@ mod tests {
         }
     }
 
+    struct MockScaleEffect {
+        child: Option<Box<dyn AudioSource + Send + Sync>>,
+        factor: f32
+    }
+
+    impl AudioSource for MockScaleEffect {
+        fn read(&mut self, buf: &mut [Sample]) -> Result<usize, io::Error> {
+            let count = self.child.as_mut().unwrap().read(buf)?;
+
+            for sample in &mut buf[..count] {
+                *sample *= self.factor;
+            }
+
+            Ok(count)
+        }
+
+        fn has_child(&self) -> bool {
+            true
+        }
+
+        fn take_child(&mut self) -> Box<dyn AudioSource + Send + Sync> {
+            self.child.take().unwrap()
+        }
+
+        fn metadata(&self) -> AudioMetadata {
+            self.child.as_ref().unwrap().metadata()
+        }
+    }
+
+    struct MockScaleEffectResolver;
+
+    impl EffectResolver for MockScaleEffectResolver {
+        fn name(&self) -> &str {
+            "scale"
+        }
+
+        fn unique(&self) -> bool {
+            false
+        }
+
+        fn documentation(&self) -> ModifierDocumentation {
+            panic!("mock effect resolver asked for documentation")
+        }
+
+        fn resolve(&self, key_values: &HashMap<String, String>,
+                child: Box<dyn AudioSource + Send + Sync>, _: PluginGuildConfig)
+                -> Result<Box<dyn AudioSource + Send + Sync>, ResolveEffectError> {
+            match key_values.get("scale").map(|v| v.parse::<f32>()) {
+                Some(Ok(factor)) => Ok(Box::new(MockScaleEffect {
+                    child: Some(child),
+                    factor
+                })),
+                _ => Err(ResolveEffectError::new("invalid scale", child))
+            }
+        }
+    }
+
+    fn scale_effect(factor: &str) -> KeyValueDescriptor {
+        format!("scale={}", factor).parse().unwrap()
+    }
+
     fn registered_mixer() -> Mixer {
         let mut plugin_manager = PluginManager::empty();
-        let mut registry = plugin_manager.mock_registry();
+        let mut registry = plugin_manager.mock_registry("mock");
 
         registry.register_audio_source_resolver(MockAudioSourceResolver);
         registry.register_audio_source_list_resolver(
             MockAudioSourceListResolver);
+        registry.register_effect_resolver(MockScaleEffectResolver);
         drop(registry);
 
-        Mixer::new(Arc::new(plugin_manager))
+        let mut mixer = Mixer::new(Arc::new(plugin_manager));
+        mixer.limiter_mut().set_enabled(false);
+        mixer
     }
 
     fn play(mixer: &mut Mixer, layer: &str, descriptor: &str)
@@ -1305,6 +2200,71 @@ mod tests {
         }
     }
 
+    fn playlist_audio_after(count: usize) -> Vec<Sample> {
+        let mut expected = test_audio_1()[count..].to_vec();
+        expected.append(&mut test_audio_2());
+        expected.append(&mut test_audio_1());
+        expected
+    }
+
+    #[test]
+    fn playback_snapshot_does_not_change_audio() {
+        for _ in 0..RANDOM_TEST_ITERATORS {
+            let mut mixer = registered_mixer();
+            mixer.add_layer("l");
+            mixer.add_layer("silent");
+            play(&mut mixer, "l", "1,2,1").unwrap();
+
+            let count = mixer.read(&mut [Sample::ZERO; 100]).unwrap();
+            let playback = mixer.playback();
+
+            assert_eq!(1, playback.len());
+            assert_eq!("l", playback[0].layer());
+            assert_eq!("1", playback[0].descriptor());
+            assert_eq!(&["2".to_owned(), "1".to_owned()],
+                playback[0].upcoming());
+            assert_eq!(SampleDuration::from_samples(count as i64),
+                playback[0].position());
+
+            let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+            rambot_test_util::assert_approximately_equal(
+                playlist_audio_after(count), audio);
+        }
+    }
+
+    #[test]
+    fn restored_playback_continues_at_saved_position() {
+        for _ in 0..RANDOM_TEST_ITERATORS {
+            let mut mixer = registered_mixer();
+            mixer.add_layer("l");
+            play(&mut mixer, "l", "1,2,1").unwrap();
+
+            let count = mixer.read(&mut [Sample::ZERO; 100]).unwrap();
+            let playback = mixer.playback().remove(0);
+            let json = serde_json::to_string(&playback).unwrap();
+            let playback: LayerPlayback = serde_json::from_str(&json).unwrap();
+            let mut mixer = registered_mixer();
+            mixer.add_layer("l");
+            mixer.restore_playback(&playback, Default::default(), no_callback())
+                .unwrap();
+            let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+            rambot_test_util::assert_approximately_equal(
+                playlist_audio_after(count), audio);
+        }
+    }
+
+    #[test]
+    fn stopped_layer_has_no_playback() {
+        let mut mixer = registered_mixer();
+        mixer.add_layer("l");
+        play(&mut mixer, "l", "1,2").unwrap();
+        mixer.stop_layer("l");
+
+        assert!(mixer.playback().is_empty());
+    }
+
     #[test]
     fn stop_layer() {
         let mut mixer = registered_mixer();
@@ -1325,6 +2285,426 @@ mod tests {
         assert!(!mixer.active());
     }
 
+    #[test]
+    fn paused_layer_is_skipped() {
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test1", test_audio_1(), None);
+        add_layer(&mut mixer, "test2", test_audio_2(), None);
+
+        assert!(mixer.pause_layer("test2"));
+
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(test_audio_1(), result);
+        assert!(!mixer.active());
+        assert!(mixer.layer("test2").is_paused());
+    }
+
+    #[test]
+    fn resume_continues_at_paused_position() {
+        for _ in 0..RANDOM_TEST_ITERATORS {
+            let mut mixer = registered_mixer();
+            mixer.add_layer("l");
+            play(&mut mixer, "l", "1,2").unwrap();
+
+            let mut buf = [Sample::ZERO; TEST_1_LEN / 3];
+            let count = mixer.read(&mut buf).unwrap();
+
+            assert!(mixer.pause_all());
+            assert!(!mixer.active());
+            assert_eq!(0, mixer.read(&mut [Sample::ZERO; 10]).unwrap());
+            assert!(mixer.resume_layer("l"));
+            assert!(mixer.active());
+
+            let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+            let mut expected = test_audio_1()[count..].to_vec();
+            expected.append(&mut test_audio_2());
+
+            rambot_test_util::assert_approximately_equal(expected, audio);
+        }
+    }
+
+    #[test]
+    fn stop_resets_pause() {
+        let mut mixer = registered_mixer();
+        mixer.add_layer("l");
+        play(&mut mixer, "l", "1").unwrap();
+        mixer.pause_layer("l");
+        mixer.stop_layer("l");
+
+        assert!(!mixer.layer("l").is_paused());
+        assert!(!mixer.resume_layer("l"));
+    }
+
+    const RAMP_LEN: usize = GAIN_RAMP_SAMPLES as usize;
+
+    fn scale_audio(audio: &[Sample], factor: f32) -> Vec<Sample> {
+        audio.iter().map(|s| s * factor).collect()
+    }
+
+    #[test]
+    fn layer_gain_is_applied() {
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test1", test_audio_1(), None);
+        add_layer(&mut mixer, "test2", test_audio_2(), None);
+        mixer.set_layer_gain("test2", 0.5);
+
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+        let expected = rambot_test_util::sum_audio(
+            &test_audio_1(), &scale_audio(&test_audio_2(), 0.5));
+
+        rambot_test_util::assert_approximately_equal(
+            &expected[RAMP_LEN..], &result[RAMP_LEN..]);
+    }
+
+    #[test]
+    fn muted_layer_is_silent_but_continues() {
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test1", test_audio_1(), None);
+        add_layer(&mut mixer, "test2", test_audio_2(), None);
+
+        assert!(mixer.set_layer_muted("test2", true));
+        assert!(!mixer.set_layer_muted("test2", true));
+
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(
+            &test_audio_1()[RAMP_LEN..], &result[RAMP_LEN..]);
+        assert!(!mixer.active());
+    }
+
+    #[test]
+    fn solo_silences_other_layers() {
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test1", test_audio_1(), None);
+        add_layer(&mut mixer, "test2", test_audio_2(), None);
+        mixer.set_layer_solo("test1", true);
+
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(
+            &test_audio_1()[RAMP_LEN..], &result[RAMP_LEN..]);
+    }
+
+    #[test]
+    fn gain_change_is_ramped() {
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test", vec![Sample::mono(1.0); RAMP_LEN * 2],
+            None);
+        mixer.set_layer_muted("test", true);
+
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        for window in result[..RAMP_LEN].windows(2) {
+            assert!(window[1].left < window[0].left);
+            assert!(window[0].left - window[1].left <= GAIN_RAMP_STEP * 1.01);
+        }
+
+        assert!(result[RAMP_LEN..].iter().all(|s| s.abs_amplitude() < EPS));
+    }
+
+    #[test]
+    fn removed_layer_fades_out() {
+        let fade_len = RAMP_LEN * 4;
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test", vec![Sample::mono(1.0); fade_len * 2],
+            None);
+
+        assert!(mixer.fade_out_layer("test", fade_len));
+        assert!(mixer.add_layer("test"));
+
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        for window in result[..fade_len].windows(2) {
+            assert!(window[1].left < window[0].left);
+            assert!(window[0].left - window[1].left < GAIN_RAMP_STEP);
+        }
+
+        assert!(result[fade_len..].iter().all(|s| s.abs_amplitude() < EPS));
+        assert!(!mixer.active());
+    }
+
+    #[test]
+    fn layer_fades_in() {
+        let fade_len = RAMP_LEN * 4;
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test", vec![Sample::mono(1.0); fade_len * 2],
+            None);
+        mixer.fade_in_layer("test", fade_len);
+
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        for window in result[..fade_len].windows(2) {
+            assert!(window[1].left > window[0].left);
+            assert!(window[1].left - window[0].left < GAIN_RAMP_STEP);
+        }
+
+        rambot_test_util::assert_approximately_equal(
+            vec![Sample::mono(1.0); fade_len], &result[fade_len..]);
+    }
+
+    #[test]
+    fn master_effect_is_applied_to_sum() {
+        for _ in 0..RANDOM_TEST_ITERATORS {
+            let mut mixer = registered_mixer();
+            mixer.add_layer("a");
+            mixer.add_layer("b");
+            mixer.add_master_effect(scale_effect("2"), &Default::default())
+                .unwrap();
+            play(&mut mixer, "a", "1").unwrap();
+            play(&mut mixer, "b", "2").unwrap();
+
+            let audio = rambot_test_util::read_to_end_segmented(&mut mixer, 100)
+                .unwrap();
+
+            rambot_test_util::assert_approximately_equal(
+                scale_audio(&test_audio_sum(), 2.0), audio);
+        }
+    }
+
+    #[test]
+    fn master_effects_are_chained_and_retained() {
+        let mut mixer = registered_mixer();
+        let config = PluginGuildConfig::default();
+        mixer.add_layer("l");
+        mixer.add_master_effect(scale_effect("2"), &config).unwrap();
+        mixer.add_master_effect(scale_effect("3"), &config).unwrap();
+
+        assert_eq!(1, mixer.retain_master_effects(
+            |e| e.key_values.get("scale").unwrap() != "2", &config).unwrap());
+        assert_eq!(&[scale_effect("3")], mixer.master_effects());
+
+        play(&mut mixer, "l", "1").unwrap();
+
+        let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(
+            scale_audio(&test_audio_1(), 3.0), audio);
+    }
+
+    #[test]
+    fn clear_master_effects() {
+        let mut mixer = registered_mixer();
+        mixer.add_layer("l");
+        mixer.add_master_effect(scale_effect("2"), &Default::default())
+            .unwrap();
+
+        assert_eq!(1, mixer.clear_master_effects());
+
+        play(&mut mixer, "l", "1").unwrap();
+
+        let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(test_audio_1(), audio);
+    }
+
+    #[test]
+    fn invalid_master_effect_is_rejected() {
+        let mut mixer = registered_mixer();
+
+        assert!(mixer.add_master_effect(scale_effect("x"), &Default::default())
+            .is_err());
+        assert!(mixer.master_effects().is_empty());
+    }
+
+    #[test]
+    fn reapplied_effects_are_retained() {
+        let mut mixer = registered_mixer();
+        let config = PluginGuildConfig::default();
+        mixer.add_layer("l");
+        play(&mut mixer, "l", "1").unwrap();
+        mixer.add_effect("l", scale_effect("2")).unwrap();
+        mixer.add_master_effect(scale_effect("3"), &config).unwrap();
+        mixer.reapply_effects(&config).unwrap();
+
+        let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(
+            scale_audio(&test_audio_1(), 6.0), audio);
+    }
+
+    #[test]
+    fn disabled_effects_are_skipped() {
+        let mut mixer = registered_mixer();
+        let disabled_config = PluginGuildConfig::default()
+            .with_disabled_effects(["scale"]);
+        mixer.add_layer("l");
+        play(&mut mixer, "l", "1").unwrap();
+        mixer.add_effect("l", scale_effect("2")).unwrap();
+        mixer.set_plugin_guild_config(disabled_config).unwrap();
+
+        assert_eq!(1, mixer.layer("l").effects().len());
+
+        let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(test_audio_1(), audio);
+    }
+
+    #[test]
+    fn effects_of_unloaded_plugins_are_skipped() {
+        let mut mixer = registered_mixer();
+        let config = PluginGuildConfig::default();
+        mixer.add_layer("l");
+        play(&mut mixer, "l", "1").unwrap();
+        mixer.add_effect("l", scale_effect("2")).unwrap();
+        mixer.add_master_effect(scale_effect("3"), &config).unwrap();
+        mixer.plugin_manager.unload_plugin("mock").unwrap();
+
+        assert!(mixer.reapply_effects(&config).is_err());
+        assert_eq!(1, mixer.layer("l").effects().len());
+        assert_eq!(1, mixer.master_effects().len());
+
+        let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(test_audio_1(), audio);
+    }
+
+    #[test]
+    fn effects_can_be_removed_while_disabled() {
+        let mut mixer = registered_mixer();
+        let disabled_config = PluginGuildConfig::default()
+            .with_disabled_effects(["scale"]);
+        mixer.set_plugin_guild_config(disabled_config).unwrap();
+        mixer.add_layer("l");
+        mixer.add_effect("l", scale_effect("2")).unwrap();
+        mixer.add_effect("l", scale_effect("3")).unwrap();
+        play(&mut mixer, "l", "1").unwrap();
+
+        assert_eq!(1, mixer.retain_effects("l", |e| e != &scale_effect("2"))
+            .unwrap());
+
+        mixer.set_plugin_guild_config(PluginGuildConfig::default()).unwrap();
+
+        let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(
+            scale_audio(&test_audio_1(), 3.0), audio);
+    }
+
+    #[test]
+    fn effects_of_unloaded_plugins_can_be_removed() {
+        let mut mixer = registered_mixer();
+        let config = PluginGuildConfig::default();
+        mixer.add_layer("l");
+        play(&mut mixer, "l", "1").unwrap();
+        mixer.add_effect("l", scale_effect("2")).unwrap();
+        mixer.add_effect("l", scale_effect("3")).unwrap();
+        mixer.add_master_effect(scale_effect("5"), &config).unwrap();
+        mixer.add_master_effect(scale_effect("7"), &config).unwrap();
+        mixer.plugin_manager.unload_plugin("mock").unwrap();
+
+        assert!(mixer.reapply_effects(&config).is_err());
+        assert!(mixer.retain_effects("l", |e| e != &scale_effect("2"))
+            .is_err());
+        assert!(mixer.retain_master_effects(|e| e != &scale_effect("5"),
+            &config).is_err());
+        assert_eq!(1, mixer.layer("l").effects().len());
+        assert_eq!(1, mixer.master_effects().len());
+
+        let audio = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(test_audio_1(), audio);
+    }
+
+    #[test]
+    fn limiter_protects_summed_output() {
+        let mut mixer = mock_mixer();
+        mixer.limiter_mut().set_enabled(true);
+        add_layer(&mut mixer, "test1", test_audio_1(), None);
+        add_layer(&mut mixer, "test2", test_audio_2(), None);
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+        let ceiling = mixer.limiter().ceiling();
+
+        assert_eq!(TEST_1_LEN + LOOKAHEAD_SAMPLES, result.len());
+        assert!(result.iter().all(|s| s.abs_amplitude() <= ceiling + EPS));
+        assert!(mixer.limiter().statistics().limited_samples > 0);
+    }
+
+    #[test]
+    fn track_gain_is_applied_to_layer() {
+        let mut mixer = mock_mixer();
+        let metadata = AudioMetadataBuilder::new()
+            .with_track_gain(-20.0)
+            .build();
+        let source = MockAudioSource::with_metadata(
+            vec![Sample::mono(1.0); TEST_1_LEN], metadata);
+        mixer.set_normalization(NormalizationSettings {
+            mode: NormalizationMode::Track,
+            analyze: false
+        });
+        mixer.add_layer("test");
+        mixer.layers.get_mut("test").set_source(Box::new(source));
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        assert_eq!(TEST_1_LEN, result.len());
+        assert!((result.last().unwrap().left - 0.1).abs() < EPS);
+        assert_eq!(NormalizationGain::TrackTag(-20.0),
+            mixer.layer("test").normalization_gain());
+    }
+
+    #[test]
+    fn track_gain_is_ignored_by_default() {
+        let mut mixer = mock_mixer();
+        let metadata = AudioMetadataBuilder::new()
+            .with_track_gain(-20.0)
+            .build();
+        let source =
+            MockAudioSource::with_metadata(test_audio_1(), metadata);
+        mixer.add_layer("test");
+        mixer.layers.get_mut("test").set_source(Box::new(source));
+        let result = rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        rambot_test_util::assert_approximately_equal(test_audio_1(), result);
+    }
+
+    #[test]
+    fn layer_and_output_levels_are_measured() {
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "loud", vec![Sample::mono(0.5); TEST_1_LEN],
+            None);
+        add_layer(&mut mixer, "quiet", vec![Sample::mono(0.25); TEST_1_LEN],
+            None);
+        mixer.set_layer_gain("quiet", 0.5);
+        let mut buf = vec![Sample::ZERO; TEST_1_LEN / 4];
+
+        // The first measurement window still contains the gain ramp.
+
+        for _ in 0..3 {
+            assert_eq!(buf.len(), mixer.read(&mut buf).unwrap());
+        }
+
+        let loud = mixer.layer("loud").levels();
+        let quiet = mixer.layer("quiet").levels();
+        let output = mixer.output_levels();
+
+        assert!((loud.peak - 0.5).abs() < EPS);
+        assert!((quiet.rms - 0.125).abs() < EPS);
+        assert!((output.peak - 0.625).abs() < EPS);
+
+        rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        assert_eq!(Levels::default(), mixer.layer("loud").levels());
+        assert_eq!(Levels::default(), mixer.output_levels());
+    }
+
+    #[test]
+    fn recorder_receives_mixer_output() {
+        let path = std::env::temp_dir()
+            .join(format!("rambot-mixer-recording-{}.wav", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut mixer = mock_mixer();
+        add_layer(&mut mixer, "test", test_audio_1(), None);
+        mixer.start_recording(
+            Recorder::start(&path, 1 << 24).unwrap());
+        rambot_test_util::read_to_end(&mut mixer).unwrap();
+
+        let summary = mixer.stop_recording().unwrap().stop().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(TEST_1_LEN as u64, summary.samples);
+        assert_eq!(0, summary.dropped_samples);
+    }
+
     #[test]
     fn mid_playlist_resolution_fail() {
         for _ in 0..RANDOM_TEST_ITERATORS {
@@ -0,0 +1,239 @@
+//! Renders audio offline to a WAV file, using the same plugins and mixer as
+//! the bot, but without connecting to Discord. This is intended for checking
+//! decoders and effect chains as well as generating golden files for
+//! regression tests.
+//!
+//! Usage: `rambot-render [options] -o <output.wav> <descriptor>`
+
+use rambot::audio::Mixer;
+use rambot::config::{
+    self,
+    DEFAULT_PLUGIN_CONFIG_DIRECTORY,
+    DEFAULT_PLUGIN_DIRECTORY
+};
+use rambot::key_value::KeyValueDescriptor;
+use rambot::loudness::{NormalizationMode, NormalizationSettings};
+use rambot::plugin::PluginManager;
+use rambot::recording::WavWriter;
+use rambot::render;
+
+use rambot_api::{PluginGuildConfig, SAMPLES_PER_SECOND};
+
+use simplelog::{ColorChoice, LevelFilter, TerminalMode, TermLogger};
+
+use std::env;
+use std::fs::File;
+use std::process;
+use std::sync::{Arc, Mutex};
+
+const LAYER_NAME: &str = "render";
+
+const USAGE: &str = "\
+Usage: rambot-render [options] -o <output.wav> <descriptor>
+
+Options:
+  -o, --output <file>          The WAV file to write (overwritten if present).
+  -e, --effect <descriptor>    Adds an effect to the rendered layer. May be
+                               given multiple times, effects are applied in
+                               order.
+  -a, --adapter <descriptor>   Adds an adapter to the rendered layer. May be
+                               given multiple times.
+  -d, --duration <seconds>     Stops rendering after the given duration.
+      --plugins <dir>          The plugin directory (default: plugins).
+      --plugin-config <dir>    The plugin config directory
+                               (default: plugins/config).
+      --root <dir>             The root directory for file system based
+                               plugins (default: the working directory).
+      --no-web                 Forbids plugins to access the internet.
+      --limiter                Applies the output limiter of the mixer.
+      --normalize <mode>       Applies loudness normalization (off, track, or
+                               album; default: off).
+  -v, --verbose                Prints informational log messages.
+  -h, --help                   Prints this help.";
+
+struct Arguments {
+    descriptor: String,
+    output: String,
+    effects: Vec<KeyValueDescriptor>,
+    adapters: Vec<KeyValueDescriptor>,
+    duration: Option<f64>,
+    plugin_directory: String,
+    plugin_config_directory: String,
+    root_directory: Option<String>,
+    allow_web_access: bool,
+    limiter: bool,
+    normalization: NormalizationMode,
+    verbose: bool
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str)
+        -> Result<String, String> {
+    args.next().ok_or_else(|| format!("Missing value for `{}`.", option))
+}
+
+fn parse_descriptor(code: &str) -> Result<KeyValueDescriptor, String> {
+    code.parse()
+        .map_err(|e| format!("Invalid descriptor `{}`: {}", code, e))
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>)
+        -> Result<Option<Arguments>, String> {
+    let mut descriptor = None;
+    let mut output = None;
+    let mut arguments = Arguments {
+        descriptor: String::new(),
+        output: String::new(),
+        effects: Vec::new(),
+        adapters: Vec::new(),
+        duration: None,
+        plugin_directory: DEFAULT_PLUGIN_DIRECTORY.to_owned(),
+        plugin_config_directory: DEFAULT_PLUGIN_CONFIG_DIRECTORY.to_owned(),
+        root_directory: None,
+        allow_web_access: true,
+        limiter: false,
+        normalization: NormalizationMode::Off,
+        verbose: false
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => output = Some(next_value(&mut args, &arg)?),
+            "-e" | "--effect" => arguments.effects.push(
+                parse_descriptor(&next_value(&mut args, &arg)?)?),
+            "-a" | "--adapter" => arguments.adapters.push(
+                parse_descriptor(&next_value(&mut args, &arg)?)?),
+            "-d" | "--duration" => {
+                let value = next_value(&mut args, &arg)?;
+                let duration = value.parse::<f64>().ok()
+                    .filter(|d| d.is_finite() && *d > 0.0)
+                    .ok_or_else(|| format!("Invalid duration: {}", value))?;
+
+                arguments.duration = Some(duration);
+            },
+            "--plugins" =>
+                arguments.plugin_directory = next_value(&mut args, &arg)?,
+            "--plugin-config" => arguments.plugin_config_directory =
+                next_value(&mut args, &arg)?,
+            "--root" =>
+                arguments.root_directory = Some(next_value(&mut args, &arg)?),
+            "--no-web" => arguments.allow_web_access = false,
+            "--limiter" => arguments.limiter = true,
+            "--normalize" => {
+                let value = next_value(&mut args, &arg)?;
+
+                arguments.normalization = value.parse()
+                    .map_err(|e| format!("{}", e))?;
+            },
+            "-v" | "--verbose" => arguments.verbose = true,
+            _ if arg.starts_with('-') && arg.len() > 1 =>
+                return Err(format!("Unknown option: {}", arg)),
+            _ if descriptor.is_none() => descriptor = Some(arg),
+            _ => return Err(format!("Unexpected argument: {}", arg))
+        }
+    }
+
+    arguments.descriptor = descriptor.ok_or("No descriptor specified.")?;
+    arguments.output = output.ok_or("No output file specified.")?;
+
+    Ok(Some(arguments))
+}
+
+fn run(arguments: Arguments) -> Result<u64, String> {
+    let root_directory = match arguments.root_directory {
+        Some(root_directory) => root_directory,
+        None => env::current_dir()
+            .map_err(|e| format!("Could not get working directory: {}", e))?
+            .to_string_lossy()
+            .into_owned()
+    };
+    let plugin_config_directory = arguments.plugin_config_directory.clone();
+    let allow_web_access = arguments.allow_web_access;
+    let plugin_manager = PluginManager::load(&arguments.plugin_directory,
+        &arguments.plugin_config_directory,
+        move |library| config::plugin_config_for_library(library,
+            &plugin_config_directory, &root_directory, allow_web_access))
+        .map_err(|e| format!("Error loading plugins: {}", e))?;
+    let mut mixer = Mixer::new(Arc::new(plugin_manager));
+
+    mixer.limiter_mut().set_enabled(arguments.limiter);
+    mixer.set_normalization(NormalizationSettings {
+        mode: arguments.normalization,
+        analyze: false
+    });
+    mixer.add_layer(LAYER_NAME);
+
+    for adapter in arguments.adapters {
+        mixer.add_adapter(LAYER_NAME, adapter);
+    }
+
+    for effect in arguments.effects {
+        mixer.add_effect(LAYER_NAME, effect)
+            .map_err(|e| format!("Error adding effect: {}", e))?;
+    }
+
+    let playback_error = Arc::new(Mutex::new(None));
+    let playback_error_clone = Arc::clone(&playback_error);
+
+    mixer.play_on_layer(LAYER_NAME, &arguments.descriptor,
+        PluginGuildConfig::default(), move |layer, e| {
+            *playback_error_clone.lock().unwrap() =
+                Some(format!("Error playing on layer {}: {}", layer, e));
+        })
+        .map_err(|e| format!("Error playing audio: {}", e))?;
+
+    let file = File::create(&arguments.output)
+        .map_err(|e| format!("Could not create output file: {}", e))?;
+    let mut writer = WavWriter::new(file, u64::MAX)
+        .map_err(|e| format!("Error writing output file: {}", e))?;
+    let max_samples = arguments.duration
+        .map(|d| (d * SAMPLES_PER_SECOND as f64).round() as u64)
+        .unwrap_or(u64::MAX);
+    let samples = render::render(&mut mixer, &mut writer, max_samples)
+        .map_err(|e| format!("Error rendering audio: {}", e))?;
+
+    writer.finish()
+        .map_err(|e| format!("Error writing output file: {}", e))?;
+
+    if let Some(e) = playback_error.lock().unwrap().take() {
+        return Err(e);
+    }
+
+    Ok(samples)
+}
+
+fn main() {
+    let arguments = match parse_arguments(env::args().skip(1)) {
+        Ok(Some(arguments)) => arguments,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!();
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let level_filter = if arguments.verbose {
+        LevelFilter::Info
+    }
+    else {
+        LevelFilter::Warn
+    };
+    let logger_config = simplelog::ConfigBuilder::new().build();
+    let _ = TermLogger::init(level_filter, logger_config, TerminalMode::Stderr,
+        ColorChoice::Auto);
+
+    let output = arguments.output.clone();
+
+    match run(arguments) {
+        Ok(samples) => println!("Rendered {:.2} seconds to {}.",
+            samples as f64 / SAMPLES_PER_SECOND as f64, output),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -1,17 +1,46 @@
-use crate::audio::Layer;
-use crate::command::{configure_layer, display_help, help_modifiers, list_layer_key_value_descriptors, respond, CommandResponse, CommandResult, Context};
+use crate::audio::{Layer, Mixer};
+use crate::command::{
+    configure_layer,
+    display_help,
+    get_guild_state_mut,
+    layer_key_value_descriptor_list,
+    modifier_help,
+    respond,
+    with_mixer,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::command::plugin::{is_disabled, set_disabled_do, set_disabled_local};
 use crate::key_value::KeyValueDescriptor;
 use crate::plugin::PluginManager;
+use crate::state::{GuildState, PluginFeature};
+
+use std::sync::Arc;
 
 /// Collection of commands related to adapters.
 ///
 /// Adapters are modifiers put on layers that control in which sequence elements of a playlist are
 /// played.
-#[poise::command(slash_command, prefix_command, subcommands("add", "clear", "list", "help"))]
+#[poise::command(slash_command, prefix_command,
+    subcommands("add", "clear", "disable", "enable", "list", "help"))]
 pub async fn adapter(ctx: Context<'_>) -> CommandResult {
     display_help(ctx, Some("adapter")).await
 }
 
+fn add_adapter(guild_state: &GuildState, plugin_manager: &PluginManager, layer: &str,
+        adapter: KeyValueDescriptor) -> CommandResponse {
+    if is_disabled(guild_state, plugin_manager, PluginFeature::Adapter, &adapter.name) {
+        return format!("The adapter `{}` is disabled in this guild.", adapter.name).into();
+    }
+
+    configure_layer(guild_state, layer, |mixer| {
+        mixer.add_adapter(layer, adapter);
+        CommandResponse::Confirm
+    })
+}
+
 /// Adds an adapter to the layer with the given name.
 ///
 /// Adapters are given in the format `name(key1=value1,key2=value2,...)`, where the set of available
@@ -19,21 +48,43 @@ pub async fn adapter(ctx: Context<'_>) -> CommandResult {
 /// can use the shortcuts `name` for `name()` and `name=value` for `name(name=value)`.
 ///
 /// Usage: `adapter add <layer> <adapter>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(add_local))]
 async fn add(ctx: Context<'_>, layer: String, #[rest] adapter: KeyValueDescriptor)
         -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let success = configure_layer(ctx, guild_id, &layer,
-        |mut mixer| mixer.add_adapter(&layer, adapter)).await.is_some();
+    let response = add_adapter(&*get_guild_state_mut(ctx.data(), guild_id).await,
+        ctx.data().plugin_manager(), &layer, adapter);
 
-    let response = if !success {
-        CommandResponse::Reply("Layer not found.")
+    respond(ctx, response).await
+}
+
+fn add_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let adapter = arguments.parse_rest::<KeyValueDescriptor>()?;
+    let plugin_manager = Arc::clone(ctx.plugin_manager());
+
+    Ok(add_adapter(&ctx.guild_state_mut(), &plugin_manager, &layer, adapter))
+}
+
+fn clear_adapters(mixer: &mut Mixer, layer: &str, name: Option<String>) -> CommandResponse {
+    let count = if let Some(name) = &name {
+        mixer.retain_adapters(layer, |descriptor| &descriptor.name != name)
     }
     else {
-        CommandResponse::Confirm
+        mixer.clear_adapters(layer)
     };
 
-    respond(ctx, response).await
+    if count == 0 {
+        if let Some(name) = name {
+            format!("Found no adapter with name {} on layer {}.", name, layer).into()
+        }
+        else {
+            format!("Found no adapter on layer {}.", layer).into()
+        }
+    }
+    else {
+        CommandResponse::Confirm
+    }
 }
 
 /// Clears all adapters from the layer with the given name.
@@ -42,46 +93,75 @@ async fn add(ctx: Context<'_>, layer: String, #[rest] adapter: KeyValueDescripto
 /// adapters of that name are removed.
 ///
 /// Usage: `adapter clear <layer> [adapter-type]`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(clear_local))]
 async fn clear(ctx: Context<'_>, layer: String, name: Option<String>) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let removed = configure_layer(ctx, guild_id, &layer, |mut mixer|
-        if let Some(name) = &name {
-            mixer.retain_adapters(&layer,
-                |descriptor| &descriptor.name != name)
-        }
-        else {
-            mixer.clear_adapters(&layer)
-        }).await;
-
-    let response = match removed {
-        Some(count) => {
-            if count == 0 {
-                if let Some(name) = name {
-                    format!("Found no adapter with name {} on layer {}.", name, layer).into()
-                }
-                else {
-                    format!("Found no adapter on layer {}.", layer).into()
-                }
-            }
-            else {
-                CommandResponse::Confirm
-            }
-        },
-        None => {
-            "Layer not found.".into()
-        }
-    };
+    let response = configure_layer(&*get_guild_state_mut(ctx.data(), guild_id).await, &layer,
+        |mixer| clear_adapters(mixer, &layer, name));
 
     respond(ctx, response).await
 }
 
+fn clear_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let name = arguments.next();
+
+    Ok(configure_layer(&ctx.guild_state_mut(), &layer,
+        |mixer| clear_adapters(mixer, &layer, name)))
+}
+
+/// Disables the adapter with the given name in this guild.
+///
+/// Disabled adapters leave playlists unchanged. Adapters of that name which are already on layers
+/// remain there and take effect again once the adapter is enabled.
+///
+/// Usage: `adapter disable <adapter-type>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    custom_data = LocalAction(disable_local))]
+async fn disable(ctx: Context<'_>, name: String) -> CommandResult {
+    set_disabled_do(ctx, PluginFeature::Adapter, name, true).await
+}
+
+fn disable_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    set_disabled_local(ctx, arguments, PluginFeature::Adapter, true)
+}
+
+/// Enables the adapter with the given name in this guild again after it was disabled.
+///
+/// Usage: `adapter enable <adapter-type>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    custom_data = LocalAction(enable_local))]
+async fn enable(ctx: Context<'_>, name: String) -> CommandResult {
+    set_disabled_do(ctx, PluginFeature::Adapter, name, false).await
+}
+
+fn enable_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    set_disabled_local(ctx, arguments, PluginFeature::Adapter, false)
+}
+
 /// Prints a list of all adapters on the layer with the given name.
 ///
 /// Usage: `adapter list <layer>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(list_local))]
 async fn list(ctx: Context<'_>, layer: String) -> CommandResult {
-    list_layer_key_value_descriptors(ctx, layer, "Adapters", Layer::adapters).await
+    let reply = with_mixer(ctx,
+        |mixer| layer_key_value_descriptor_list(mixer, &layer, "Adapters", Layer::adapters)).await;
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn list_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let mixer = ctx.guild_state().mixer_blocking();
+
+    Ok(layer_key_value_descriptor_list(&mixer, &layer, "Adapters", Layer::adapters).into())
+}
+
+fn adapter_help(plugin_manager: &PluginManager, adapter: Option<String>) -> String {
+    modifier_help(plugin_manager, adapter, "Adapters", "adapter",
+        PluginManager::get_adapter_documentation, PluginManager::adapter_names)
 }
 
 /// Lists all available adapters with a short description.
@@ -90,9 +170,16 @@ async fn list(ctx: Context<'_>, layer: String) -> CommandResult {
 /// given.
 ///
 /// Usage: `adapter help [adapter]`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(help_local))]
 async fn help(ctx: Context<'_>, adapter: Option<String>) -> CommandResult {
-    help_modifiers(ctx, adapter, "Adapters", "adapter",
-        PluginManager::get_adapter_documentation, PluginManager::adapter_names)
-        .await
+    let reply = adapter_help(ctx.data().plugin_manager(), adapter);
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn help_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let adapter = arguments.next();
+
+    Ok(adapter_help(ctx.plugin_manager(), adapter).into())
 }
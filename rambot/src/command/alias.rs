@@ -0,0 +1,210 @@
+use crate::alias::{expand, runs_command, split_arguments};
+use crate::command::{
+    dispatch_command_as_message,
+    display_help,
+    get_guild_state,
+    get_guild_state_mut,
+    guild_settings,
+    prepare_command_for_dispatch,
+    respond,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::state::GuildState;
+
+use std::fmt::Write;
+
+/// The name of this command group. Aliases may not run it in order to prevent them from running
+/// each other endlessly.
+const ALIAS_COMMAND: &str = "alias";
+
+/// Indicates whether the given command of an alias runs an alias at any level, considering both
+/// the global prefix, if there is one, and the prefix of the guild.
+fn runs_alias(command: &str, global_prefix: Option<&str>, guild_prefix: Option<&str>) -> bool {
+    let prefixes = [global_prefix, guild_prefix].into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    runs_command(command, ALIAS_COMMAND, &prefixes)
+}
+
+/// Expands the alias with the given name with the given arguments into the command it runs.
+fn expand_alias(guild_state: Option<&GuildState>, name: &str, arguments: Option<&str>,
+        global_prefix: Option<&str>) -> Result<String, String> {
+    let command = guild_state
+        .and_then(|guild_state| guild_state.aliases().get(name))
+        .ok_or_else(|| format!("I found no alias with name `{}`.", name))?;
+    let arguments = split_arguments(arguments.unwrap_or(""));
+    let command = expand(command, &arguments).map_err(|e| format!("{}", e))?;
+    let guild_prefix = guild_state.and_then(|guild_state| guild_state.settings().prefix());
+
+    if runs_alias(&command, global_prefix, guild_prefix) {
+        // The arguments may have smuggled in a command which runs an alias.
+
+        Err("An alias may not run another alias.".to_owned())
+    }
+    else {
+        Ok(command)
+    }
+}
+
+fn add_alias(guild_state: &mut GuildState, name: String, command: &str,
+        global_prefix: Option<&str>) -> CommandResponse {
+    let command = command.trim();
+
+    if runs_alias(command, global_prefix, guild_state.settings().prefix()) {
+        CommandResponse::Reply("An alias may not run another alias.".to_owned())
+    }
+    else {
+        guild_state.aliases_mut().insert(name, command.to_owned());
+        CommandResponse::Confirm
+    }
+}
+
+fn alias_list(guild_state: &GuildState) -> String {
+    let mut reply = String::new();
+
+    for (name, command) in guild_state.aliases() {
+        write!(reply, "\n- {}: `{}`", name, command).unwrap();
+    }
+
+    if reply.is_empty() {
+        "There are no aliases.".to_owned()
+    }
+    else {
+        format!("Aliases:{}", reply)
+    }
+}
+
+fn remove_alias(guild_state: &mut GuildState, name: &str) -> CommandResponse {
+    if guild_state.aliases_mut().remove(name).is_some() {
+        CommandResponse::Confirm
+    }
+    else {
+        CommandResponse::Reply(format!("I found no alias with name `{}`.", name))
+    }
+}
+
+/// Collection of commands for defining aliases, i.e. names for commands with parameters.
+///
+/// Aliases are run with `alias run <name> [arguments]`, which can also be used as the command of
+/// a board button.
+#[poise::command(slash_command, prefix_command, guild_only,
+    subcommands("add", "list", "remove", "run"))]
+pub async fn alias(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("alias")).await
+}
+
+/// Defines an alias with the given name for the given command.
+///
+/// In the command, `$1`, `$2`, and so on are replaced by the arguments given to `alias run`, `$*`
+/// by all arguments, and `$$` by a single `$`. To run multiple commands, use `do`. An alias may not
+/// run another alias, not even nested in `do` or `schedule`. An alias with the same name is
+/// replaced.
+///
+/// Usage: `alias add <name> <command>`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(add_local))]
+async fn add(ctx: Context<'_>, name: String, #[rest] command: String) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = add_alias(&mut *get_guild_state_mut(ctx.data(), guild_id).await, name,
+        &command, ctx.data().config().prefix());
+
+    respond(ctx, response).await
+}
+
+fn add_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let name = arguments.required()?;
+    let command = arguments.parse_rest::<String>()?;
+
+    Ok(add_alias(&mut ctx.guild_state_mut(), name, &command, None))
+}
+
+/// Lists all aliases defined in this guild together with their commands.
+///
+/// Usage: `alias list`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(list_local))]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let reply = get_guild_state(ctx.data(), guild_id).await
+        .map(|guild_state| alias_list(&guild_state))
+        .unwrap_or_else(|| "There are no aliases.".to_owned());
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn list_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(alias_list(ctx.guild_state()).into())
+}
+
+/// Removes the alias with the given name.
+///
+/// Usage: `alias remove <name>`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(remove_local))]
+async fn remove(ctx: Context<'_>, name: String) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = remove_alias(&mut *get_guild_state_mut(ctx.data(), guild_id).await, &name);
+
+    respond(ctx, response).await
+}
+
+fn remove_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let name = arguments.required()?;
+
+    Ok(remove_alias(&mut ctx.guild_state_mut(), &name))
+}
+
+/// Runs the alias with the given name, replacing its parameters by the given arguments.
+///
+/// Arguments are separated by spaces. Arguments which contain spaces can be enclosed in double
+/// quotes.
+///
+/// Usage: `alias run <name> [arguments]`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(run_local))]
+async fn run(ctx: Context<'_>, name: String, #[rest] arguments: Option<String>)
+        -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let command = {
+        let guild_state = get_guild_state(ctx.data(), guild_id).await;
+
+        expand_alias(guild_state.as_deref(), &name, arguments.as_deref(),
+            ctx.data().config().prefix())
+    };
+    let command = match command {
+        Ok(command) => command,
+        Err(message) => {
+            ctx.reply(message).await?;
+            return Ok(());
+        }
+    };
+    let guild_settings = guild_settings(ctx).await;
+    let mut msg = match ctx {
+        Context::Prefix(ctx) => ctx.msg.clone(),
+        Context::Application(_) => {
+            // Slash commands have no message as which the command could be dispatched, so we
+            // respond with one and attribute it to the author, like a board button does.
+
+            let reply = ctx.reply(format!("Running alias `{}`.", name)).await?;
+            let mut msg = reply.message().await?.into_owned();
+            msg.author = ctx.author().clone();
+            msg.guild_id = Some(guild_id);
+            msg
+        }
+    };
+
+    msg.content = prepare_command_for_dispatch(
+        &command, guild_settings.prefix(), ctx.data().config());
+    dispatch_command_as_message(ctx.framework(), ctx.serenity_context(), &msg).await
+}
+
+fn run_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let name = arguments.required()?;
+    let alias_arguments = arguments.rest();
+    let command =
+        expand_alias(Some(ctx.guild_state()), &name, alias_arguments.as_deref(), None)?;
+
+    Ok(ctx.execute(&command))
+}
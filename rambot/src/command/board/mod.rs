@@ -59,13 +59,13 @@ pub async fn board(ctx: Context<'_>) -> CommandResult {
 #[poise::command(slash_command, prefix_command, guild_only)]
 async fn add(ctx: Context<'_>, name: String) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let mut board_mgr = get_board_manager_mut(ctx.data(), guild_id).await;
     let board = Board {
         name,
         buttons: Vec::new()
     };
+    let added = get_board_manager_mut(ctx.data(), guild_id).await.add_board(board);
 
-    let response = if board_mgr.add_board(board) {
+    let response = if added {
         CommandResponse::Confirm
     }
     else {
@@ -81,10 +81,13 @@ async fn add(ctx: Context<'_>, name: String) -> CommandResult {
 #[poise::command(slash_command, prefix_command, guild_only)]
 async fn remove(ctx: Context<'_>, name: String) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let mut board_mgr = get_board_manager_mut(ctx.data(), guild_id).await;
-    board_mgr.deactivate_board(ctx, &name).await?;
+    let removed = {
+        let mut board_mgr = get_board_manager_mut(ctx.data(), guild_id).await;
+        board_mgr.deactivate_board(ctx, &name).await?;
+        board_mgr.boards.remove(&name).is_some()
+    };
 
-    let response = if board_mgr.boards.remove(&name).is_some() {
+    let response = if removed {
         CommandResponse::Confirm
     }
     else {
@@ -162,6 +165,24 @@ pub struct Button {
 }
 
 impl Button {
+
+    /// Gets the label displayed on this button.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Indicates whether this button is a toggle button, i.e. whether it has
+    /// a command to execute when it is pressed while active.
+    pub fn is_toggle(&self) -> bool {
+        self.deactivate_command.is_some()
+    }
+
+    /// Indicates whether this button is a toggle button that is currently
+    /// active.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
     fn component_button(&self, index: usize) -> CreateButton {
         let style = if self.active {
             ButtonStyle::Primary
@@ -198,6 +219,18 @@ const MAX_BUTTONS_PER_MESSAGE: usize =
     MAX_ROWS_PER_MESSAGE * MAX_BUTTONS_PER_ROW;
 
 impl Board {
+
+    /// Gets the name of this board.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the buttons on this board in the order in which they are
+    /// displayed.
+    pub fn buttons(&self) -> &[Button] {
+        &self.buttons
+    }
+
     fn page_count(&self) -> usize {
         self.buttons.len().div_ceil(MAX_BUTTONS_PER_MESSAGE)
     }
@@ -276,6 +309,29 @@ impl BoardManager {
         }
     }
 
+    /// Presses the button with the given label on the board with the given
+    /// name without a message in Discord, switching its state if it is a
+    /// toggle button. Displayed instances of the board are not updated.
+    ///
+    /// # Returns
+    ///
+    /// The command to execute in response to the button press, or `None` if
+    /// there is no such button.
+    pub fn press_button(&mut self, board: &str, label: &str) -> Option<String> {
+        let button = self.boards.get_mut(board)?.buttons.iter_mut()
+            .find(|b| b.label == label)?;
+        let command = match &button.deactivate_command {
+            Some(deactivate_command) if button.active => deactivate_command.clone(),
+            _ => button.command.clone()
+        };
+
+        if button.is_toggle() {
+            button.active = !button.active;
+        }
+
+        Some(command)
+    }
+
     /// Removes all messages associated with any active instance of the board
     /// with the given name. Returns `true` if there was an active instance.
     /// Raises an error if deleting any message failed.
@@ -504,7 +560,11 @@ impl FrameworkEventHandler for BoardButtonEventHandler {
 
             // Execute the command
 
-            msg.content = prepare_command_for_dispatch(command, user_data.config());
+            let guild_prefix = get_guild_state(user_data, guild_id).await
+                .and_then(|guild_state| guild_state.settings().prefix().map(str::to_owned));
+
+            msg.content = prepare_command_for_dispatch(
+                command, guild_prefix.as_deref(), user_data.config());
             msg.author = message_component.user.clone();
             msg.webhook_id = None;
 
@@ -1,25 +1,45 @@
-use crate::audio::Layer;
+use crate::audio::{Layer, Mixer};
 use crate::command::{
     configure_layer,
     display_help,
-    help_modifiers,
-    list_layer_key_value_descriptors,
+    get_guild_state_mut,
+    layer_key_value_descriptor_list,
+    modifier_help,
     respond,
+    with_mixer,
     CommandResponse,
     CommandResult,
     Context
 };
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::command::plugin::{is_disabled, set_disabled_do, set_disabled_local};
 use crate::key_value::KeyValueDescriptor;
 use crate::plugin::PluginManager;
+use crate::state::{GuildState, PluginFeature};
+
+use std::sync::Arc;
 
 /// Collection of commands related to effects.
 ///
 /// Effects are modifiers put on layers that alter the audio in some way, such as volume or filters.
-#[poise::command(slash_command, prefix_command, subcommands("add", "clear", "list", "help"))]
+#[poise::command(slash_command, prefix_command,
+    subcommands("add", "clear", "disable", "enable", "list", "help"))]
 pub async fn effect(ctx: Context<'_>) -> CommandResult {
     display_help(ctx, Some("effect")).await
 }
 
+fn add_effect(guild_state: &GuildState, plugin_manager: &PluginManager, layer: &str,
+        effect: KeyValueDescriptor) -> CommandResponse {
+    if is_disabled(guild_state, plugin_manager, PluginFeature::Effect, &effect.name) {
+        return format!("The effect `{}` is disabled in this guild.", effect.name).into();
+    }
+
+    configure_layer(guild_state, layer, |mixer| match mixer.add_effect(layer, effect) {
+        Ok(()) => CommandResponse::Confirm,
+        Err(e) => format!("{}", e).into()
+    })
+}
+
 /// Adds an effect to the layer with the given name.
 ///
 /// Effects are given in the format `name(key1=value1,key2=value2,...)`, where the set of available
@@ -27,25 +47,43 @@ pub async fn effect(ctx: Context<'_>) -> CommandResult {
 /// can use the shortcuts `name` for `name()` and `name=value` for `name(name=value)`.
 ///
 /// Usage: `effect add <layer> <effect>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(add_local))]
 async fn add(ctx: Context<'_>, layer: String, #[rest] effect: KeyValueDescriptor) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let res = configure_layer(ctx, guild_id, &layer,
-        |mut mixer| mixer.add_effect(&layer, effect)).await;
+    let response = add_effect(&*get_guild_state_mut(ctx.data(), guild_id).await,
+        ctx.data().plugin_manager(), &layer, effect);
 
-    let response = match res {
-        Some(Ok(())) => {
-            CommandResponse::Confirm
-        },
-        Some(Err(e)) => {
-            format!("{}", e).into()
-        },
-        None => {
-            "Layer not found.".into()
-        }
+    respond(ctx, response).await
+}
+
+fn add_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let effect = arguments.parse_rest::<KeyValueDescriptor>()?;
+    let plugin_manager = Arc::clone(ctx.plugin_manager());
+
+    Ok(add_effect(&ctx.guild_state_mut(), &plugin_manager, &layer, effect))
+}
+
+fn clear_effects(mixer: &mut Mixer, layer: &str, name: Option<String>) -> CommandResponse {
+    let res = if let Some(name) = &name {
+        mixer.retain_effects(layer, |descriptor| &descriptor.name != name)
+    }
+    else {
+        Ok(mixer.clear_effects(layer))
     };
 
-    respond(ctx, response).await
+    match res {
+        Ok(0) => {
+            if let Some(name) = name {
+                format!("Found no effect with name {} on layer {}.", name, layer).into()
+            }
+            else {
+                format!("Found no effect on layer {}.", layer).into()
+            }
+        },
+        Ok(_) => CommandResponse::Confirm,
+        Err(e) => format!("{}", e).into()
+    }
 }
 
 /// Clears all effects from the layer with the given name.
@@ -54,49 +92,74 @@ async fn add(ctx: Context<'_>, layer: String, #[rest] effect: KeyValueDescriptor
 /// effects of that name are removed.
 /// 
 /// Usage: `effect clear <layer> [effect-type]`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(clear_local))]
 async fn clear(ctx: Context<'_>, layer: String, name: Option<String>) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let res = configure_layer(ctx, guild_id, &layer, |mut mixer|
-        if let Some(name) = &name {
-            mixer.retain_effects(&layer,
-                |descriptor| &descriptor.name != name)
-        }
-        else {
-            Ok(mixer.clear_effects(&layer))
-        }).await;
-
-    let response = match res {
-        Some(Ok(count)) => {
-            if count == 0 {
-                if let Some(name) = name {
-                    format!("Found no effect with name {} on layer {}.", name, layer).into()
-                }
-                else {
-                    format!("Found no effect on layer {}.", layer).into()
-                }
-            }
-            else {
-                CommandResponse::Confirm
-            }
-        },
-        Some(Err(e)) => {
-            format!("{}", e).into()
-        },
-        None => {
-            "Layer not found.".into()
-        }
-    };
+    let response = configure_layer(&*get_guild_state_mut(ctx.data(), guild_id).await, &layer,
+        |mixer| clear_effects(mixer, &layer, name));
 
     respond(ctx, response).await
 }
 
+fn clear_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let name = arguments.next();
+
+    Ok(configure_layer(&ctx.guild_state_mut(), &layer,
+        |mixer| clear_effects(mixer, &layer, name)))
+}
+
+/// Disables the effect with the given name in this guild.
+///
+/// Disabled effects leave the audio unchanged. Effects of that name which are already on layers
+/// remain there and take effect again once the effect is enabled.
+///
+/// Usage: `effect disable <effect-type>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    custom_data = LocalAction(disable_local))]
+async fn disable(ctx: Context<'_>, name: String) -> CommandResult {
+    set_disabled_do(ctx, PluginFeature::Effect, name, true).await
+}
+
+fn disable_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    set_disabled_local(ctx, arguments, PluginFeature::Effect, true)
+}
+
+/// Enables the effect with the given name in this guild again after it was disabled.
+///
+/// Usage: `effect enable <effect-type>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    custom_data = LocalAction(enable_local))]
+async fn enable(ctx: Context<'_>, name: String) -> CommandResult {
+    set_disabled_do(ctx, PluginFeature::Effect, name, false).await
+}
+
+fn enable_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    set_disabled_local(ctx, arguments, PluginFeature::Effect, false)
+}
+
 /// Prints a list of all effects on the layer with the given name.
 /// 
 /// Usage: `effect list <layer>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(list_local))]
 async fn list(ctx: Context<'_>, layer: String) -> CommandResult {
-    list_layer_key_value_descriptors(ctx, layer, "Effects", Layer::effects).await
+    let reply = with_mixer(ctx,
+        |mixer| layer_key_value_descriptor_list(mixer, &layer, "Effects", Layer::effects)).await;
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn list_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let mixer = ctx.guild_state().mixer_blocking();
+
+    Ok(layer_key_value_descriptor_list(&mixer, &layer, "Effects", Layer::effects).into())
+}
+
+pub(crate) fn effect_help(plugin_manager: &PluginManager, effect: Option<String>) -> String {
+    modifier_help(plugin_manager, effect, "Effects", "effect",
+        PluginManager::get_effect_documentation, PluginManager::effect_names)
 }
 
 /// Lists all available effects with a short description.
@@ -104,9 +167,16 @@ async fn list(ctx: Context<'_>, layer: String) -> CommandResult {
 /// If an effect name is provided, a detailed description of the effect and its parameters is given.
 /// 
 /// Usage: `effect help [effect]`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(help_local))]
 async fn help(ctx: Context<'_>, effect: Option<String>) -> CommandResult {
-    help_modifiers(ctx, effect, "Effects", "effect",
-        PluginManager::get_effect_documentation, PluginManager::effect_names)
-        .await
+    let reply = effect_help(ctx.data().plugin_manager(), effect);
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn help_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let effect = arguments.next();
+
+    Ok(effect_help(ctx.plugin_manager(), effect).into())
 }
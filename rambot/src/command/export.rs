@@ -0,0 +1,93 @@
+use crate::command::{
+    confirm,
+    get_guild_state,
+    get_guild_state_mut,
+    unwrap_or_reply,
+    CommandResult,
+    Context
+};
+use crate::state::GuildExport;
+
+use poise::CreateReply;
+
+use serenity::all::{Attachment, CreateAttachment};
+
+/// The maximum size of an attachment accepted by `import` in bytes.
+const MAX_IMPORT_SIZE: u32 = 1024 * 1024;
+
+/// Exports the configuration of this guild as a JSON file.
+///
+/// The file contains all layers with their effects and adapters, the master effects, the limiter
+/// and normalization settings, all sound boards, scenes, and aliases, and the guild settings.
+/// Permissions, disabled plugins, and the root directory are not exported. The file can be imported into another guild
+/// with `import`.
+///
+/// Usage: `export`
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn export(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let data = {
+        let guild_state = unwrap_or_reply!(get_guild_state(ctx.data(), guild_id).await, ctx,
+            "There is no configuration to export.");
+
+        guild_state.export().to_json()
+    };
+    let attachment = CreateAttachment::bytes(data, format!("rambot-{}.json", guild_id));
+
+    ctx.send(CreateReply::default().attachment(attachment)).await?;
+    Ok(())
+}
+
+/// Imports a configuration created by `export` from a JSON file attached to the command.
+///
+/// The layers, master effects, limiter and normalization settings, sound boards, scenes, aliases,
+/// and guild settings of this guild are replaced by those in the file. Audio that is currently playing is
+/// stopped. Before anything is changed, the file is checked against the installed plugins, so it
+/// is only imported if all of its effects and adapters are available.
+///
+/// Usage: `import <file>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn import(ctx: Context<'_>,
+        #[description = "A configuration file created by `export`."] file: Attachment)
+        -> CommandResult {
+    if file.size > MAX_IMPORT_SIZE {
+        ctx.reply(format!("The file may be at most {} KiB large.", MAX_IMPORT_SIZE / 1024))
+            .await?;
+        return Ok(());
+    }
+
+    let export = match GuildExport::from_json(&file.download().await?) {
+        Ok(export) => export,
+        Err(e) => {
+            ctx.reply(format!("The file does not contain a valid configuration: {}", e)).await?;
+            return Ok(());
+        }
+    };
+    let guild_id = ctx.guild_id().unwrap();
+    let problems = {
+        let mut guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
+        let plugin_guild_config = guild_state.build_plugin_guild_config();
+        let problems = export.validate(ctx.data().plugin_manager(), &plugin_guild_config);
+
+        if problems.is_empty() {
+            guild_state.import(export);
+        }
+
+        problems
+    };
+
+    if problems.is_empty() {
+        confirm(ctx).await
+    }
+    else {
+        let mut reply = "The configuration cannot be imported:".to_owned();
+
+        for problem in problems {
+            reply.push_str("\n- ");
+            reply.push_str(&problem);
+        }
+
+        ctx.reply(reply).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::command::{
+    confirm,
+    get_guild_state,
+    get_guild_state_mut,
+    respond,
+    unwrap_or_return,
+    CommandData,
+    CommandError,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::event::FrameworkEventHandler;
+
+use poise::FrameworkContext;
+
+use serenity::all::{FullEvent, User};
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::prelude::Context as SerenityContext;
+
+/// Moves the bot to the given voice channel if it is connected to a different one in the given
+/// guild. If it is not connected, nothing happens.
+async fn move_to(serenity_ctx: &SerenityContext, guild_id: GuildId, channel_id: ChannelId)
+        -> CommandResult {
+    let songbird = unwrap_or_return!(songbird::get(serenity_ctx).await, Ok(()));
+    let call = unwrap_or_return!(songbird.get(guild_id), Ok(()));
+    let current_channel = call.lock().await.current_channel();
+
+    match current_channel {
+        Some(current_channel) if current_channel.0.get() != channel_id.get() => {
+            log::debug!("Following to channel {} on guild {}.", channel_id, guild_id);
+            songbird.join(guild_id, channel_id).await?;
+            Ok(())
+        },
+        _ => Ok(())
+    }
+}
+
+/// Makes the bot follow a user between voice channels.
+///
+/// Whenever the given user, or the sender of the command if no user is given, switches voice
+/// channels while the bot is connected, the bot moves along. Only one user is followed in each
+/// guild. If the bot is connected to another voice channel than the user, it moves to the user's
+/// channel right away. Use `unfollow` to stop following.
+///
+/// Usage: `follow [user]`
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn follow(ctx: Context<'_>, user: Option<User>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let user_id = user.map(|user| user.id).unwrap_or(ctx.author().id);
+
+    get_guild_state_mut(ctx.data(), guild_id).await.set_followed_user(Some(user_id));
+
+    let channel_id = ctx.guild().unwrap().voice_states
+        .get(&user_id)
+        .and_then(|voice_state| voice_state.channel_id);
+
+    if let Some(channel_id) = channel_id {
+        move_to(ctx.serenity_context(), guild_id, channel_id).await?;
+    }
+
+    confirm(ctx).await
+}
+
+/// Stops following the user set with `follow`, so the bot stays in its voice channel.
+///
+/// Usage: `unfollow`
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn unfollow(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = {
+        let mut guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
+
+        if guild_state.followed_user().is_some() {
+            guild_state.set_followed_user(None);
+            CommandResponse::Confirm
+        }
+        else {
+            CommandResponse::Reply("I am not following anyone.")
+        }
+    };
+
+    respond(ctx, response).await
+}
+
+/// A [FrameworkEventHandler] which moves the bot to the voice channel of the user set with
+/// `follow` whenever that user switches voice channels.
+pub struct FollowEventHandler;
+
+impl FrameworkEventHandler for FollowEventHandler {
+    async fn handle_event(&self, serenity_ctx: &SerenityContext, event: &FullEvent,
+            framework_ctx: FrameworkContext<'_, CommandData, CommandError>)
+            -> CommandResult {
+        if let FullEvent::VoiceStateUpdate { new, .. } = event {
+            let (guild_id, channel_id) = match (new.guild_id, new.channel_id) {
+                (Some(guild_id), Some(channel_id)) => (guild_id, channel_id),
+                _ => return Ok(())
+            };
+            let followed_user = get_guild_state(framework_ctx.user_data, guild_id).await
+                .and_then(|guild_state| guild_state.followed_user());
+
+            if followed_user == Some(new.user_id) {
+                move_to(serenity_ctx, guild_id, channel_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,15 +1,20 @@
+use crate::audio::{is_valid_layer_gain, Layer, Mixer, MAX_LAYER_GAIN};
 use crate::command::{
+    configure_layer,
     display_help,
-    get_guild_state,
     get_guild_state_mut,
     respond,
+    with_mixer,
     CommandResponse,
     CommandResult,
     Context
 };
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::state::GuildState;
 
 /// Collection of commands for managing audio layers.
-#[poise::command(slash_command, prefix_command, subcommands("add", "remove", "list"))]
+#[poise::command(slash_command, prefix_command,
+    subcommands("add", "remove", "list", "gain", "mute", "unmute", "solo", "unsolo"))]
 pub async fn layer(ctx: Context<'_>) -> CommandResult {
     display_help(ctx, Some("layer")).await
 }
@@ -17,59 +22,236 @@ pub async fn layer(ctx: Context<'_>) -> CommandResult {
 /// Adds a layer with the given name to the mixer in this guild.
 ///
 /// Usage: `layer add <name>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(add_local))]
 async fn add(ctx: Context<'_>, layer: String) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
-    let added = guild_state.mixer_mut().add_layer(layer);
+    let response = add_layer(&*get_guild_state_mut(ctx.data(), guild_id).await, layer);
 
-    let response = if added {
+    respond(ctx, response).await
+}
+
+fn add_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+
+    Ok(add_layer(&ctx.guild_state_mut(), layer))
+}
+
+fn add_layer(guild_state: &GuildState, layer: String) -> CommandResponse {
+    if guild_state.mixer_mut().add_layer(layer) {
         CommandResponse::Confirm
     }
     else {
-        CommandResponse::Reply("A layer with the same name already exists.")
-    };
-
-    respond(ctx, response).await
+        "A layer with the same name already exists.".into()
+    }
 }
 
 /// Removes a layer with the given name from the mixer in this guild.
 ///
 /// Usage: `layer remove <name>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(remove_local))]
 async fn remove(ctx: Context<'_>, layer: String) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
-    let removed = guild_state.mixer_mut().remove_layer(&layer);
+    let response = remove_layer(&*get_guild_state_mut(ctx.data(), guild_id).await, &layer);
+
+    respond(ctx, response).await
+}
 
-    let response = if removed {
+fn remove_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+
+    Ok(remove_layer(&ctx.guild_state_mut(), &layer))
+}
+
+fn remove_layer(guild_state: &GuildState, layer: &str) -> CommandResponse {
+    if guild_state.mixer_mut().remove_layer(layer) {
         CommandResponse::Confirm
     }
     else {
-        CommandResponse::Reply("Layer not found.")
-    };
-
-    respond(ctx, response).await
+        "Layer not found.".into()
+    }
 }
 
 /// Prints a list of the names of all layers of the mixer in this guild.
 ///
 /// Usage: `layer list`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(list_local))]
 async fn list(ctx: Context<'_>) -> CommandResult {
-    let guild_id = ctx.guild_id().unwrap();
-    let layers = get_guild_state(ctx.data(), guild_id).await
-        .map(|gs| gs.mixer_blocking().layers().iter()
-            .map(|l| l.name().to_owned())
-            .collect::<Vec<_>>()
-        ).unwrap_or_default();
+    let reply = with_mixer(ctx, |mixer| layer_list(mixer)).await;
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn list_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(layer_list(&ctx.guild_state().mixer_blocking()).into())
+}
+
+fn layer_list(mixer: &Mixer) -> String {
+    let layers = mixer.layers().iter()
+        .map(layer_list_entry)
+        .collect::<Vec<_>>();
 
-    let response = if layers.is_empty() {
-        "No layers registered in this guild.".into()
+    if layers.is_empty() {
+        "No layers registered in this guild.".to_owned()
     }
     else {
-        format!("Layer list:\n- {}", layers.join("\n- ")).into()
-    };
+        format!("Layer list:\n- {}", layers.join("\n- "))
+    }
+}
+
+fn layer_list_entry(layer: &Layer) -> String {
+    let mut entry = layer.name().to_owned();
+    let mut properties = Vec::new();
+
+    if layer.gain() != 1.0 {
+        properties.push(format!("gain {}", layer.gain()));
+    }
+
+    if layer.is_muted() {
+        properties.push("muted".to_owned());
+    }
+
+    if layer.is_solo() {
+        properties.push("solo".to_owned());
+    }
+
+    if !properties.is_empty() {
+        entry.push_str(&format!(" ({})", properties.join(", ")));
+    }
+
+    entry
+}
+
+async fn configure_layer_response<F>(ctx: Context<'_>, layer: String, f: F) -> CommandResult
+where
+    F: FnOnce(&mut Mixer, &str) -> CommandResponse
+{
+    let guild_id = ctx.guild_id().unwrap();
+    let response = configure_layer(&*get_guild_state_mut(ctx.data(), guild_id).await, &layer,
+        |mixer| f(mixer, &layer));
 
     respond(ctx, response).await
 }
+
+fn configure_layer_local<F>(ctx: &mut LocalContext<'_>, layer: String, f: F) -> LocalResult
+where
+    F: FnOnce(&mut Mixer, &str) -> CommandResponse
+{
+    Ok(configure_layer(&ctx.guild_state_mut(), &layer, |mixer| f(mixer, &layer)))
+}
+
+fn set_gain(mixer: &mut Mixer, layer: &str, gain: f32) -> CommandResponse {
+    if is_valid_layer_gain(gain) {
+        mixer.set_layer_gain(layer, gain);
+        CommandResponse::Confirm
+    }
+    else {
+        format!("The gain must be a number between 0 and {}.", MAX_LAYER_GAIN).into()
+    }
+}
+
+fn set_muted(mixer: &mut Mixer, layer: &str, muted: bool) -> CommandResponse {
+    if mixer.set_layer_muted(layer, muted) {
+        CommandResponse::Confirm
+    }
+    else if muted {
+        "The layer is already muted.".into()
+    }
+    else {
+        "The layer is not muted.".into()
+    }
+}
+
+fn set_solo(mixer: &mut Mixer, layer: &str, solo: bool) -> CommandResponse {
+    if mixer.set_layer_solo(layer, solo) {
+        CommandResponse::Confirm
+    }
+    else if solo {
+        "The layer is already soloed.".into()
+    }
+    else {
+        "The layer is not soloed.".into()
+    }
+}
+
+/// Sets the gain of the layer with the given name.
+///
+/// The gain is the factor by which the audio of the layer is multiplied before it is mixed with
+/// the other layers, where 1 represents the original volume and 0 is absolutely quiet. It can be at
+/// most 10.
+///
+/// Usage: `layer gain <name> <gain>`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(gain_local))]
+async fn gain(ctx: Context<'_>, layer: String, gain: f32) -> CommandResult {
+    configure_layer_response(ctx, layer, |mixer, layer| set_gain(mixer, layer, gain)).await
+}
+
+fn gain_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let gain = arguments.parse::<f32>()?;
+
+    configure_layer_local(ctx, layer, |mixer, layer| set_gain(mixer, layer, gain))
+}
+
+/// Mutes the layer with the given name.
+///
+/// A muted layer continues playing, but cannot be heard until it is unmuted.
+///
+/// Usage: `layer mute <name>`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(mute_local))]
+async fn mute(ctx: Context<'_>, layer: String) -> CommandResult {
+    configure_layer_response(ctx, layer, |mixer, layer| set_muted(mixer, layer, true)).await
+}
+
+fn mute_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+
+    configure_layer_local(ctx, layer, |mixer, layer| set_muted(mixer, layer, true))
+}
+
+/// Unmutes the layer with the given name.
+///
+/// Usage: `layer unmute <name>`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(unmute_local))]
+async fn unmute(ctx: Context<'_>, layer: String) -> CommandResult {
+    configure_layer_response(ctx, layer, |mixer, layer| set_muted(mixer, layer, false)).await
+}
+
+fn unmute_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+
+    configure_layer_local(ctx, layer, |mixer, layer| set_muted(mixer, layer, false))
+}
+
+/// Solos the layer with the given name.
+///
+/// As long as at least one layer is soloed, only soloed layers can be heard.
+///
+/// Usage: `layer solo <name>`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(solo_local))]
+async fn solo(ctx: Context<'_>, layer: String) -> CommandResult {
+    configure_layer_response(ctx, layer, |mixer, layer| set_solo(mixer, layer, true)).await
+}
+
+fn solo_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+
+    configure_layer_local(ctx, layer, |mixer, layer| set_solo(mixer, layer, true))
+}
+
+/// Removes the solo from the layer with the given name.
+///
+/// Usage: `layer unsolo <name>`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(unsolo_local))]
+async fn unsolo(ctx: Context<'_>, layer: String) -> CommandResult {
+    configure_layer_response(ctx, layer, |mixer, layer| set_solo(mixer, layer, false)).await
+}
+
+fn unsolo_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+
+    configure_layer_local(ctx, layer, |mixer, layer| set_solo(mixer, layer, false))
+}
@@ -0,0 +1,127 @@
+use crate::audio::Mixer;
+use crate::command::{
+    display_help,
+    get_guild_state_mut,
+    respond,
+    with_mixer,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::state::GuildState;
+
+/// Collection of commands for configuring the limiter on the mixer output.
+///
+/// The limiter keeps the summed audio of all layers below a configurable ceiling so that loud
+/// layers playing at the same time do not clip. It is enabled by default. While it is enabled, it
+/// delays the audio by 5 ms in order to react to peaks before they are played.
+#[poise::command(slash_command, prefix_command,
+    subcommands("enable", "disable", "ceiling", "status"))]
+pub async fn limiter(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("limiter")).await
+}
+
+fn set_limiter_enabled(guild_state: &GuildState, enabled: bool) -> CommandResponse {
+    guild_state.mixer_mut().limiter_mut().set_enabled(enabled);
+    CommandResponse::Confirm
+}
+
+async fn set_limiter_enabled_response(ctx: Context<'_>, enabled: bool) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response =
+        set_limiter_enabled(&*get_guild_state_mut(ctx.data(), guild_id).await, enabled);
+
+    respond(ctx, response).await
+}
+
+/// Enables the limiter on the mixer output.
+///
+/// Usage: `limiter enable`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(enable_local))]
+async fn enable(ctx: Context<'_>) -> CommandResult {
+    set_limiter_enabled_response(ctx, true).await
+}
+
+fn enable_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(set_limiter_enabled(&ctx.guild_state_mut(), true))
+}
+
+/// Disables the limiter on the mixer output.
+///
+/// Without the limiter, the summed audio of all layers may clip.
+///
+/// Usage: `limiter disable`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(disable_local))]
+async fn disable(ctx: Context<'_>) -> CommandResult {
+    set_limiter_enabled_response(ctx, false).await
+}
+
+fn disable_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(set_limiter_enabled(&ctx.guild_state_mut(), false))
+}
+
+fn set_ceiling(guild_state: &GuildState, ceiling: f32) -> CommandResponse {
+    if ceiling > 0.0 && ceiling <= 1.0 {
+        guild_state.mixer_mut().limiter_mut().set_ceiling(ceiling);
+        CommandResponse::Confirm
+    }
+    else {
+        "Ceiling must be greater than 0 and at most 1.".into()
+    }
+}
+
+/// Sets the ceiling of the limiter, i.e. the maximum amplitude of the mixer output.
+///
+/// The ceiling must be greater than 0 and at most 1, where 1 is full scale.
+///
+/// Usage: `limiter ceiling <ceiling>`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(ceiling_local))]
+async fn ceiling(ctx: Context<'_>, ceiling: f32) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = set_ceiling(&*get_guild_state_mut(ctx.data(), guild_id).await, ceiling);
+
+    respond(ctx, response).await
+}
+
+fn ceiling_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let ceiling = arguments.parse::<f32>()?;
+
+    Ok(set_ceiling(&ctx.guild_state_mut(), ceiling))
+}
+
+fn limiter_status(mixer: &Mixer) -> String {
+    let limiter = mixer.limiter();
+
+    if limiter.enabled() {
+        let statistics = limiter.statistics();
+
+        format!("Limiter is enabled with a ceiling of {}.\nLimited {:.2}% of samples, activated \
+                {} times.",
+            limiter.ceiling(), statistics.limited_fraction() * 100.0, statistics.activations)
+    }
+    else {
+        format!("Limiter is disabled (ceiling {}).", limiter.ceiling())
+    }
+}
+
+/// Shows the configuration of the limiter and how often it had to limit the mixer output.
+///
+/// The statistics are counted since the limiter was last reconfigured or the bot was started.
+///
+/// Usage: `limiter status`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(status_local))]
+async fn status(ctx: Context<'_>) -> CommandResult {
+    let reply = with_mixer(ctx, |mixer| limiter_status(mixer)).await;
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn status_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(limiter_status(&ctx.guild_state().mixer_blocking()).into())
+}
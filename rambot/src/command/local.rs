@@ -0,0 +1,427 @@
+use crate::command::{commands, CommandData, CommandError, CommandResponse};
+use crate::plugin::PluginManager;
+use crate::settings::StatusText;
+use crate::state::{GuildState, GuildStateGuard, State};
+
+use poise::Command;
+
+use serenity::model::id::GuildId;
+
+use std::fmt::{Display, Write};
+use std::io;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// The result of a [LocalAction]. An error is replied just like a [CommandResponse::Reply].
+pub(crate) type LocalResult = Result<CommandResponse, String>;
+
+/// The action of a command when it is executed outside Discord, for example on the console. It is
+/// attached to the definition of the command as its `custom_data`, so commands have the same names,
+/// aliases, and subcommands everywhere. Commands without a local action are only available on
+/// Discord.
+pub(crate) struct LocalAction(
+    pub(crate) fn(&mut LocalContext<'_>, &mut LocalArguments<'_>) -> LocalResult);
+
+/// Who executes a command outside Discord, which determines the commands they may use.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Invoker {
+
+    /// The operator of the bot on the console, who may use all commands like an owner of the bot.
+    Owner,
+
+    /// A remote control interface such as the web API or OSC. It is treated like a member without
+    /// any roles, so it may not use commands reserved to the owners of the bot, commands which
+    /// require permissions in the guild, or commands restricted in the guild.
+    Remote
+}
+
+impl Invoker {
+
+    /// Indicates whether this invoker may use the given command in the given guild.
+    fn may_use(self, command: &Command<CommandData, CommandError>, guild_state: &GuildState)
+            -> bool {
+        match self {
+            Invoker::Owner => true,
+            Invoker::Remote => !command.owners_only && command.required_permissions.is_empty() &&
+                guild_state.permissions().is_allowed(&command.qualified_name, &[])
+        }
+    }
+}
+
+/// Gets all commands with their qualified names filled in, which poise otherwise only does when
+/// the framework is built. Permissions are checked by qualified name. The commands are built once
+/// and shared by all local executions.
+fn local_commands() -> &'static [Command<CommandData, CommandError>] {
+    static COMMANDS: OnceLock<Vec<Command<CommandData, CommandError>>> = OnceLock::new();
+
+    COMMANDS.get_or_init(|| {
+        let mut commands = commands();
+        poise::framework::set_qualified_names(&mut commands);
+        commands
+    })
+}
+
+/// Indicates whether the given invoker may use the command with the given qualified name in the
+/// given guild, just as if they executed it. Unknown commands may not be used.
+pub(crate) fn may_use(invoker: Invoker, guild_state: &GuildState, qualified_name: &str) -> bool {
+    let commands = local_commands();
+    let mut names = qualified_name.split(' ');
+    let mut command = names.next().and_then(|name| find_command(commands, name));
+
+    for name in names {
+        command = command.and_then(|command| find_command(&command.subcommands, name));
+    }
+
+    command.is_some_and(|command| invoker.may_use(command, guild_state))
+}
+
+/// The context in which a [LocalAction] is executed, which provides access to the state of the
+/// guild in which the command is executed.
+pub(crate) struct LocalContext<'a> {
+    state: &'a mut State,
+    plugin_manager: &'a Arc<PluginManager>,
+    guild_id: GuildId,
+    invoker: Invoker,
+    commands: &'a [Command<CommandData, CommandError>]
+}
+
+impl LocalContext<'_> {
+
+    /// Gets the state of the guild in which the command is executed. Changes to the configuration
+    /// made through it are not saved.
+    pub(crate) fn guild_state(&self) -> &GuildState {
+        self.state.guild_state(self.guild_id).unwrap()
+    }
+
+    /// Gets a guard to the state of the guild in which the command is executed, which saves the
+    /// configuration once it is dropped.
+    pub(crate) fn guild_state_mut(&mut self) -> GuildStateGuard<'_> {
+        self.state.guild_state_mut(self.guild_id, self.plugin_manager)
+    }
+
+    /// Gets the state of all guilds, for commands which affect every guild.
+    pub(crate) fn state(&self) -> &State {
+        self.state
+    }
+
+    pub(crate) fn plugin_manager(&self) -> &Arc<PluginManager> {
+        self.plugin_manager
+    }
+
+    /// Executes another command in the same guild, as `do` and `alias run` do. If the command
+    /// starts with the prefix of the guild, the prefix is removed.
+    pub(crate) fn execute(&mut self, line: &str) -> CommandResponse {
+        let guild_prefix = self.guild_state().settings().prefix().map(str::to_owned);
+        let line = guild_prefix.as_deref()
+            .and_then(|prefix| line.trim_start().strip_prefix(prefix))
+            .unwrap_or(line);
+        let mut arguments = LocalArguments::new(line);
+        let commands = self.commands;
+        let name = match arguments.next() {
+            Some(name) => name,
+            None => return CommandResponse::Reply(String::new())
+        };
+        let mut command = match find_command(commands, &name) {
+            Some(command) => command,
+            None => return format!("Unknown command: {}. Type `help` for a list of commands.",
+                name).into()
+        };
+
+        loop {
+            let mut lookahead = arguments.clone();
+            let subcommand = lookahead.next()
+                .and_then(|name| find_command(&command.subcommands, &name));
+
+            match subcommand {
+                Some(subcommand) => {
+                    command = subcommand;
+                    arguments = lookahead;
+                },
+                None => break
+            }
+        }
+
+        if !self.invoker.may_use(command, self.guild_state()) {
+            let language = self.guild_state().settings().status_language();
+
+            return language.status_text(StatusText::NotAllowed).into();
+        }
+
+        arguments.usage = command.help_text.as_deref().and_then(usage);
+
+        match command.custom_data.downcast_ref::<LocalAction>() {
+            Some(LocalAction(action)) => match action(self, &mut arguments) {
+                Ok(response) => response,
+                Err(message) => CommandResponse::Reply(message)
+            },
+            None if !command.subcommands.is_empty() => command_help(command).into(),
+            None => format!("The command `{}` is only available on Discord.",
+                command.qualified_name).into()
+        }
+    }
+}
+
+/// Executes a command against the state of the guild with the given ID, creating the state if it
+/// does not exist yet. Commands are resolved by the same names as on Discord and run the
+/// [LocalAction] attached to their definition.
+///
+/// # Arguments
+///
+/// * `state`: The [State] containing the guild's state. Changes to the configuration are saved just
+///   as they are on Discord.
+/// * `plugin_manager`: The [PluginManager] used to resolve audio.
+/// * `guild_id`: The ID of the guild whose state to use.
+/// * `invoker`: The [Invoker] who executes the command. Commands they may not use are rejected
+///   with the same message as on Discord.
+/// * `line`: The command to execute, in the same format as a prefix command on Discord, but without
+///   the prefix.
+pub(crate) fn execute_local(state: &mut State, plugin_manager: &Arc<PluginManager>,
+        guild_id: GuildId, invoker: Invoker, line: &str) -> CommandResponse {
+    let commands = local_commands();
+
+    state.ensure_guild_state_exists(guild_id, plugin_manager);
+
+    LocalContext {
+        state,
+        plugin_manager,
+        guild_id,
+        invoker,
+        commands
+    }.execute(line)
+}
+
+/// The arguments of a command executed by a [LocalAction]. They are taken in order, like poise
+/// parses the arguments of prefix commands.
+#[derive(Clone)]
+pub(crate) struct LocalArguments<'a> {
+    rest: &'a str,
+    usage: Option<&'a str>
+}
+
+impl<'a> LocalArguments<'a> {
+
+    fn new(line: &'a str) -> LocalArguments<'a> {
+        LocalArguments {
+            rest: line,
+            usage: None
+        }
+    }
+
+    /// Takes the next argument, which is either a word or text in double quotes. Returns `None` if
+    /// there are no arguments left.
+    pub(crate) fn next(&mut self) -> Option<String> {
+        let rest = self.rest.trim_start();
+
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+
+        let (argument, rest) = match rest.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[(end + 1)..]),
+                None => (quoted, "")
+            },
+            None => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+
+                (&rest[..end], &rest[end..])
+            }
+        };
+
+        self.rest = rest;
+        Some(argument.to_owned())
+    }
+
+    /// Takes the next argument. If there is none, the usage of the command is returned as an
+    /// error.
+    pub(crate) fn required(&mut self) -> Result<String, String> {
+        self.next().ok_or_else(|| self.missing_argument())
+    }
+
+    /// Takes the next argument and parses it. If there is none or it cannot be parsed, an error
+    /// message is returned.
+    pub(crate) fn parse<T>(&mut self) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: Display
+    {
+        self.required()?.parse().map_err(|e| format!("{}", e))
+    }
+
+    /// Takes the next argument if there is one and parses it.
+    pub(crate) fn parse_optional<T>(&mut self) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: Display
+    {
+        self.next()
+            .map(|argument| argument.parse().map_err(|e| format!("{}", e)))
+            .transpose()
+    }
+
+    /// Takes all remaining text and parses it, like a required `#[rest]` argument. If there is none
+    /// or it cannot be parsed, an error message is returned.
+    pub(crate) fn parse_rest<T>(&mut self) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: Display
+    {
+        match self.rest() {
+            Some(rest) => rest.parse().map_err(|e| format!("{}", e)),
+            None => Err(self.missing_argument())
+        }
+    }
+
+    fn missing_argument(&self) -> String {
+        match self.usage {
+            Some(usage) => format!("Missing argument. {}", usage),
+            None => "Missing argument.".to_owned()
+        }
+    }
+
+    /// Takes all remaining text, like a `#[rest]` argument. Returns `None` if it is empty.
+    pub(crate) fn rest(&mut self) -> Option<String> {
+        let rest = self.rest.trim();
+
+        self.rest = "";
+
+        if rest.is_empty() {
+            None
+        }
+        else {
+            Some(rest.to_owned())
+        }
+    }
+}
+
+/// Reports errors that occur while playing later entries of a playlist outside Discord, where there
+/// is no channel to report them to.
+pub(crate) fn log_layer_error(layer: String, e: io::Error) {
+    log::warn!("Error on layer {}: {}", layer, e);
+}
+
+fn find_command<'c>(commands: &'c [Command<CommandData, CommandError>], name: &str)
+        -> Option<&'c Command<CommandData, CommandError>> {
+    commands.iter().find(|command| command.name.eq_ignore_ascii_case(name) ||
+        command.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name)))
+}
+
+/// Gets the line of the help text of a command which explains its usage.
+fn usage(help_text: &str) -> Option<&str> {
+    help_text.lines().find(|line| line.starts_with("Usage:"))
+}
+
+fn is_available_locally(command: &Command<CommandData, CommandError>) -> bool {
+    command.custom_data.is::<LocalAction>() ||
+        command.subcommands.iter().any(is_available_locally)
+}
+
+fn command_help(command: &Command<CommandData, CommandError>) -> String {
+    let mut help = command.description.clone().unwrap_or_default();
+
+    if let Some(help_text) = &command.help_text {
+        write!(help, "\n\n{}", help_text).unwrap();
+    }
+
+    if !command.subcommands.is_empty() {
+        help.push_str("\n\nSubcommands:");
+
+        for subcommand in &command.subcommands {
+            write!(help, "\n- {}", subcommand.name).unwrap();
+
+            if !is_available_locally(subcommand) {
+                help.push_str(" (Discord only)");
+            }
+        }
+    }
+
+    help
+}
+
+/// Displays help about all commands or a specific one, as generated from the command definitions.
+pub(crate) fn help_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>)
+        -> LocalResult {
+    let mut commands = ctx.commands;
+    let mut command = None;
+
+    while let Some(name) = arguments.next() {
+        match find_command(commands, &name) {
+            Some(found) => {
+                commands = &found.subcommands;
+                command = Some(found);
+            },
+            None => return Err(format!("Found no command `{}`.", name))
+        }
+    }
+
+    if let Some(command) = command {
+        return Ok(command_help(command).into());
+    }
+
+    let mut help = "Commands:".to_owned();
+
+    for command in commands {
+        write!(help, "\n- {}", command.name).unwrap();
+
+        if let Some(description) = &command.description {
+            write!(help, ": {}", description).unwrap();
+        }
+
+        if !is_available_locally(command) {
+            help.push_str(" (Discord only)");
+        }
+    }
+
+    help.push_str("\n\nType `help <command>` for more information on a command.");
+
+    Ok(help.into())
+}
+
+/// Executes commands provided as quoted strings, like `do` on Discord. The replies of all commands
+/// which do not just confirm are combined.
+pub(crate) fn do_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>)
+        -> LocalResult {
+    let mut replies = Vec::new();
+
+    while let Some(command) = arguments.next() {
+        if let CommandResponse::Reply(reply) = ctx.execute(&command) {
+            replies.push(reply);
+        }
+    }
+
+    if replies.is_empty() {
+        Ok(CommandResponse::Confirm)
+    }
+    else {
+        Ok(replies.join("\n").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn arguments_are_taken_in_order() {
+        let mut arguments = LocalArguments::new("  play \"my layer\" some  audio ");
+
+        assert_eq!(Some("play".to_owned()), arguments.next());
+        assert_eq!(Some("my layer".to_owned()), arguments.next());
+        assert_eq!(Some("some  audio".to_owned()), arguments.rest());
+        assert_eq!(None, arguments.next());
+        assert_eq!(None, arguments.rest());
+    }
+
+    #[test]
+    fn missing_arguments_report_usage() {
+        let mut arguments = LocalArguments::new("");
+        arguments.usage = usage("Some text.\n\nUsage: `layer add <name>`");
+
+        assert_eq!(Err("Missing argument. Usage: `layer add <name>`".to_owned()),
+            arguments.required());
+        assert!(arguments.parse::<f32>().is_err());
+        assert_eq!(Ok(None), arguments.parse_optional::<f32>());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::audio::Mixer;
+use crate::command::{
+    display_help,
+    get_guild_state_mut,
+    respond,
+    with_mixer,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command::effect::effect_help;
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::command::plugin::is_disabled;
+use crate::key_value::KeyValueDescriptor;
+use crate::plugin::PluginManager;
+use crate::state::{GuildState, PluginFeature};
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Collection of commands related to the master bus.
+///
+/// The master bus processes the summed audio of all layers before it is sent to the voice channel.
+/// Effects put on the master bus, such as a limiter or an equalizer, apply to all layers at once.
+#[poise::command(slash_command, prefix_command, subcommands("add", "clear", "list", "help"))]
+pub async fn master(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("master")).await
+}
+
+fn add_master_effect(guild_state: &GuildState, plugin_manager: &PluginManager,
+        effect: KeyValueDescriptor) -> CommandResponse {
+    if is_disabled(guild_state, plugin_manager, PluginFeature::Effect, &effect.name) {
+        return format!("The effect `{}` is disabled in this guild.", effect.name).into();
+    }
+
+    let plugin_guild_config = guild_state.build_plugin_guild_config();
+    let res = guild_state.mixer_mut().add_master_effect(effect, &plugin_guild_config);
+
+    match res {
+        Ok(()) => CommandResponse::Confirm,
+        Err(e) => format!("{}", e).into()
+    }
+}
+
+/// Adds an effect to the master bus.
+///
+/// Effects are given in the same format as for the `effect add` command.
+///
+/// Usage: `master add <effect>`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(add_local))]
+async fn add(ctx: Context<'_>, #[rest] effect: KeyValueDescriptor) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = add_master_effect(&*get_guild_state_mut(ctx.data(), guild_id).await,
+        ctx.data().plugin_manager(), effect);
+
+    respond(ctx, response).await
+}
+
+fn add_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let effect = arguments.parse_rest::<KeyValueDescriptor>()?;
+    let plugin_manager = Arc::clone(ctx.plugin_manager());
+
+    Ok(add_master_effect(&ctx.guild_state_mut(), &plugin_manager, effect))
+}
+
+fn clear_master_effects(guild_state: &GuildState, name: Option<String>) -> CommandResponse {
+    let plugin_guild_config = guild_state.build_plugin_guild_config();
+    let res = {
+        let mut mixer = guild_state.mixer_mut();
+
+        if let Some(name) = &name {
+            mixer.retain_master_effects(|descriptor| &descriptor.name != name,
+                &plugin_guild_config)
+        }
+        else {
+            Ok(mixer.clear_master_effects())
+        }
+    };
+
+    match res {
+        Ok(0) => {
+            if let Some(name) = name {
+                format!("Found no effect with name {} on the master bus.", name).into()
+            }
+            else {
+                "Found no effect on the master bus.".into()
+            }
+        },
+        Ok(_) => CommandResponse::Confirm,
+        Err(e) => format!("{}", e).into()
+    }
+}
+
+/// Clears all effects from the master bus.
+///
+/// As an optional argument, this command takes an effect name. If that is provided, only effects of
+/// that name are removed.
+///
+/// Usage: `master clear [effect-type]`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(clear_local))]
+async fn clear(ctx: Context<'_>, name: Option<String>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response =
+        clear_master_effects(&*get_guild_state_mut(ctx.data(), guild_id).await, name);
+
+    respond(ctx, response).await
+}
+
+fn clear_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let name = arguments.next();
+
+    Ok(clear_master_effects(&ctx.guild_state_mut(), name))
+}
+
+fn master_effect_list(mixer: &Mixer) -> String {
+    let mut reply = "Effects on the master bus:".to_owned();
+
+    for (i, descriptor) in mixer.master_effects().iter().enumerate() {
+        write!(reply, "\n{}. {}", i + 1, descriptor).unwrap();
+    }
+
+    reply
+}
+
+/// Prints a list of all effects on the master bus.
+///
+/// Usage: `master list`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(list_local))]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    let reply = with_mixer(ctx, |mixer| master_effect_list(mixer)).await;
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn list_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(master_effect_list(&ctx.guild_state().mixer_blocking()).into())
+}
+
+/// Lists all available effects with a short description.
+///
+/// If an effect name is provided, a detailed description of the effect and its parameters is given.
+///
+/// Usage: `master help [effect]`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(help_local))]
+async fn help(ctx: Context<'_>, effect: Option<String>) -> CommandResult {
+    let reply = effect_help(ctx.data().plugin_manager(), effect);
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn help_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let effect = arguments.next();
+
+    Ok(effect_help(ctx.plugin_manager(), effect).into())
+}
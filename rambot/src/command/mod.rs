@@ -1,9 +1,12 @@
 use crate::audio::{PCMRead, Layer, Mixer};
 use crate::key_value::KeyValueDescriptor;
+use crate::meter::Levels;
 use crate::plugin::PluginManager;
+use crate::settings::{ConfirmationStyle, GuildSettings, StatusText};
 use crate::state::{State, GuildState};
 
 use rambot_api::{
+    AudioMetadata,
     AudioSource,
     ModifierDocumentation,
     PluginGuildConfig,
@@ -31,17 +34,35 @@ use tokio::sync::RwLockWriteGuard as TokioRwLockWriteGuard;
 
 use std::any::Any;
 use std::clone::Clone;
-use std::collections::hash_map::Keys;
 use std::fmt::{Display, Write};
+use std::io;
 use std::ops::{Deref, DerefMut};
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::sync::{Arc, RwLock};
 
 mod adapter;
+mod alias;
 pub mod board;
 mod effect;
+mod export;
+mod follow;
 mod layer;
+mod limiter;
+mod local;
+mod master;
+mod normalization;
+mod permissions;
+mod plugin;
+mod record;
+mod scene;
+mod schedule;
+mod settings;
 
 pub use board::BoardButtonEventHandler;
+pub use follow::FollowEventHandler;
+pub use permissions::check_permissions;
+pub use schedule::run_scheduler;
+pub use settings::guild_prefix;
+pub(crate) use local::{execute_local, may_use, Invoker};
 
 pub type CommandError = Box<dyn std::error::Error + Send + Sync>;
 pub type CommandResult<T = ()> = Result<T, CommandError>;
@@ -62,6 +83,7 @@ pub(crate) use unwrap_or_return;
 pub fn commands() -> Vec<Command<CommandData, CommandError>> {
     vec![
         adapter::adapter(),
+        alias::alias(),
         audio(),
         board::board(),
         connect(),
@@ -69,13 +91,30 @@ pub fn commands() -> Vec<Command<CommandData, CommandError>> {
         disconnect(),
         cmd_do(),
         effect::effect(),
+        export::export(),
+        follow::follow(),
         help(),
+        export::import(),
         info(),
         layer::layer(),
+        limiter::limiter(),
+        master::master(),
+        meters(),
+        normalization::normalization(),
+        pause(),
+        permissions::permissions(),
         play(),
+        plugin::plugin(),
+        plugin::plugins(),
+        record::record(),
+        resume(),
+        scene::scene(),
+        schedule::schedule(),
         seek(),
+        settings::settings(),
         skip(),
-        stop()
+        stop(),
+        follow::unfollow()
     ]
 }
 
@@ -93,6 +132,15 @@ macro_rules! unwrap_or_reply {
 
 pub(crate) use unwrap_or_reply;
 use crate::command_data::CommandData;
+use crate::command::local::{
+    do_local,
+    help_local,
+    log_layer_error,
+    LocalAction,
+    LocalArguments,
+    LocalContext,
+    LocalResult
+};
 use crate::config::Config;
 
 /// Connects to the channel of the command author. Returns `true` if and only if the bot is
@@ -105,13 +153,13 @@ async fn connect_do(ctx: Context<'_>, call: TokioMutexGuard<'_, Call>) -> (bool,
     let channel_id = match channel_id_opt{
         Some(id) => id,
         None => {
-            return (false, "I cannot see your voice channel. Are you connected?".into());
+            return (false, status_text(ctx, StatusText::AuthorNotConnected).await.into());
         }
     };
 
     if let Some(channel) = call.current_channel() {
         if channel.0.get() == channel_id.get() {
-            return (true, "I am already connected to your voice channel.".into());
+            return (true, status_text(ctx, StatusText::AlreadyConnected).await.into());
         }
     }
 
@@ -140,8 +188,6 @@ async fn get_songbird_call(ctx: Context<'_>) -> Arc<TokioMutex<Call>> {
     songbird::get(ctx.serenity_context()).await.unwrap().get_or_insert(guild_id)
 }
 
-const NOT_CONNECTED: &str = "I am not connected to a voice channel";
-
 /// Disconnects the bot from the voice channel to which it is currently connected.
 ///
 /// Usage: `disconnect`
@@ -152,11 +198,11 @@ async fn disconnect(ctx: Context<'_>) -> CommandResult {
 
         match songbird.remove(guild_id).await {
             Ok(_) => {
-                stop_all(ctx).await; // drop audio sources
+                with_mixer(ctx, Mixer::stop_all).await; // drop audio sources
                 log::debug!("Left voice on guild {}.", guild_id);
                 CommandResponse::Confirm
             },
-            Err(JoinError::NoCall) => CommandResponse::Reply(NOT_CONNECTED),
+            Err(JoinError::NoCall) => CommandResponse::Reply(status_text(ctx, StatusText::NotConnected).await),
             Err(e) => return Err(e.into())
         }
     }
@@ -168,7 +214,7 @@ async fn disconnect(ctx: Context<'_>) -> CommandResult {
     respond(ctx, response).await
 }
 
-fn to_input<S>(source: Arc<RwLock<S>>) -> Input
+pub(crate) fn to_input<S>(source: Arc<RwLock<S>>) -> Input
 where
     S: AudioSource + Send + Sync + 'static
 {
@@ -288,23 +334,14 @@ async fn get_guild_state_mut(data: &CommandData, guild_id: GuildId)
     }
 }
 
-fn play_mixer(
-    ctx: Context<'_>,
-    mixer: Arc<RwLock<Mixer>>,
-    layer: &str,
-    audio: &str,
-    plugin_guild_config: PluginGuildConfig
-) -> Result<bool, String> {
-    let mut mixer_guard = mixer.write().unwrap();
-
-    if !mixer_guard.contains_layer(layer) {
-        return Err(format!("No layer of name {}.", &layer));
-    }
-
-    let active_before = mixer_guard.active();
+/// Creates a callback which reports errors that occur while playing later entries of a playlist
+/// to the channel in which the command of the given context was sent.
+fn layer_error_callback(ctx: Context<'_>)
+        -> impl Fn(String, io::Error) + Clone + Send + Sync + 'static {
     let serenity_ctx = ctx.serenity_context().clone();
     let channel_id = ctx.channel_id();
-    let error_callback = move |layer, e| {
+
+    move |layer, e| {
         // TODO this is just asking for trouble.
 
         let content = format!("Error on layer {}: {}", layer, e);
@@ -317,7 +354,26 @@ fn play_mixer(
             let runtime = Runtime::new().unwrap();
             runtime.block_on(future).unwrap();
         }
-    };
+    }
+}
+
+fn play_mixer<C>(
+    mixer: &RwLock<Mixer>,
+    layer: &str,
+    audio: &str,
+    plugin_guild_config: PluginGuildConfig,
+    error_callback: C
+) -> Result<bool, String>
+where
+    C: Fn(String, io::Error) + Clone + Send + Sync + 'static
+{
+    let mut mixer_guard = mixer.write().unwrap();
+
+    if !mixer_guard.contains_layer(layer) {
+        return Err(format!("No layer of name {}.", &layer));
+    }
+
+    let active_before = mixer_guard.active();
     let play_res = mixer_guard.play_on_layer(layer, audio, plugin_guild_config, error_callback);
 
     if let Err(e) = play_res {
@@ -328,17 +384,59 @@ fn play_mixer(
     }
 }
 
-async fn play_do(ctx: Context<'_>, layer: String, audio: String) -> CommandResult<CommandResponse> {
-    let guild_id = ctx.guild_id().unwrap();
-    let guild_state = unwrap_or_return!(get_guild_state(ctx.data(), guild_id).await,
-        Ok(CommandResponse::Reply(format!("No layer of name {}.", &layer))));
-    let plugin_guild_config = guild_state.build_plugin_guild_config();
+/// Plays the given audio on the given layer of the mixer of the given guild, or on the default
+/// layer if no audio is given. Returns the mixer and whether it was active before, so the caller
+/// can make sure the audio is sent where it can be heard.
+fn play_on_guild<C>(guild_state: &GuildState, layer: String, audio: Option<String>,
+    error_callback: C) -> Result<(Arc<RwLock<Mixer>>, bool), String>
+where
+    C: Fn(String, io::Error) + Clone + Send + Sync + 'static
+{
     let mixer = guild_state.mixer_arc();
-    let play_res = play_mixer(ctx, Arc::clone(&mixer), &layer, &audio, plugin_guild_config);
-    let active_before = match play_res {
-        Ok(active_before) => active_before,
+    let arguments = guild_state.settings().play_arguments(layer, audio,
+        |layer| mixer.read().unwrap().contains_layer(layer));
+    let (layer, audio) = arguments.ok_or(
+        "Please provide a layer and the audio to play or set a default layer.")?;
+    let plugin_guild_config = guild_state.build_plugin_guild_config();
+    let active_before =
+        play_mixer(&mixer, &layer, &audio, plugin_guild_config, error_callback)?;
+
+    Ok((mixer, active_before))
+}
+
+async fn play_do(ctx: Context<'_>, layer: String, audio: Option<String>)
+        -> CommandResult<CommandResponse> {
+    let guild_id = ctx.guild_id().unwrap();
+    let play_res = {
+        let guild_state = unwrap_or_return!(get_guild_state(ctx.data(), guild_id).await,
+            Ok(CommandResponse::Reply(format!("No layer of name {}.", &layer))));
+
+        play_on_guild(&guild_state, layer, audio, layer_error_callback(ctx))
+    };
+
+    // Connecting reads the guild state again, so it must not remain locked.
+
+    let (mixer, active_before) = match play_res {
+        Ok(played) => played,
         Err(message) => return Ok(CommandResponse::Reply(message))
     };
+
+    Ok(start_playback(ctx, mixer, active_before).await)
+}
+
+fn play_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let audio = arguments.rest();
+
+    play_on_guild(ctx.guild_state(), layer, audio, log_layer_error)?;
+    Ok(CommandResponse::Confirm)
+}
+
+/// Ensures that the audio of the given mixer is sent to the voice channel after it has been
+/// (re-)activated, connecting to the author's voice channel if necessary. If the connection fails,
+/// all audio on the mixer is stopped.
+async fn start_playback(ctx: Context<'_>, mixer: Arc<RwLock<Mixer>>, active_before: bool)
+        -> CommandResponse {
     let call = get_songbird_call(ctx).await;
     let mut call_guard = call.lock().await;
 
@@ -353,34 +451,49 @@ async fn play_do(ctx: Context<'_>, layer: String, audio: String) -> CommandResul
             mixer.write().unwrap().stop_all();
         }
 
-        Ok(response)
+        response
     }
     else {
-        Ok(CommandResponse::Confirm)
+        CommandResponse::Confirm
     }
 }
 
 /// Plays the given audio on the given layer.
 ///
-/// Possible formats for the input depend on the installed plugins.
+/// Possible formats for the input depend on the installed plugins. If a default layer is set with
+/// `settings layer`, the layer can be omitted to play the audio on the default layer. In that case,
+/// the first word is only considered a layer if a layer of that name exists. If the bot is not
+/// connected to a voice channel, it joins the one of the sender, which also applies to board
+/// buttons.
 ///
-/// Usage: `play <layer> <audio>`
-#[poise::command(slash_command, prefix_command, guild_only)]
-async fn play(ctx: Context<'_>, layer: String, #[rest] audio: String) -> CommandResult {
+/// Usage: `play [layer] <audio>`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(play_local))]
+async fn play(ctx: Context<'_>, layer: String, #[rest] audio: Option<String>) -> CommandResult {
+    let audio = audio.filter(|audio| !audio.is_empty());
     let response = play_do(ctx, layer, audio).await?;
     respond(ctx, response).await
 }
 
-async fn with_layer_mut<F, E>(ctx: Context<'_>, layer: &str, f: F) -> CommandResponse
+/// Runs the given function on the mixer of the guild in which the command was invoked. If the
+/// guild has no state yet, the function runs on an empty mixer, so it responds as if there were no
+/// layers.
+async fn with_mixer<F, T>(ctx: Context<'_>, f: F) -> T
 where
-    F: FnOnce(RwLockWriteGuard<Mixer>, &str) -> Result<(), E>,
-    E: Display
+    F: FnOnce(&mut Mixer) -> T
 {
     let guild_id = ctx.guild_id().unwrap();
-    let guild_state = unwrap_or_return!(get_guild_state(ctx.data(), guild_id).await,
-        format!("Found no layer with name {}.", layer).into());
-    let mixer = guild_state.mixer_mut();
 
+    match get_guild_state(ctx.data(), guild_id).await {
+        Some(guild_state) => f(&mut guild_state.mixer_mut()),
+        None => f(&mut Mixer::new(ctx.data().plugin_manager_arc()))
+    }
+}
+
+fn on_layer<F, E>(mixer: &mut Mixer, layer: &str, f: F) -> CommandResponse
+where
+    F: FnOnce(&mut Mixer, &str) -> Result<(), E>,
+    E: Display
+{
     if mixer.contains_layer(layer) {
         if let Err(e) = f(mixer, layer) {
             CommandResponse::Reply(format!("{}", e))
@@ -399,37 +512,34 @@ where
 /// If the last piece of the list is active, this stops audio on the layer.
 ///
 /// Usage: `skip <layer>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(skip_local))]
 async fn skip(ctx: Context<'_>, layer: String)
         -> CommandResult<()> {
-    let response = with_layer_mut(ctx, &layer, |mut mixer, layer| mixer.skip_on_layer(layer)).await;
+    let response = with_mixer(ctx,
+        |mixer| on_layer(mixer, &layer, Mixer::skip_on_layer)).await;
 
     respond(ctx, response).await
 }
 
-async fn stop_layer(ctx: Context<'_>, layer: &str) -> CommandResponse {
-    let guild_id = ctx.guild_id().unwrap();
-    let guild_state = unwrap_or_return!(get_guild_state(ctx.data(), guild_id).await,
-        format!("No layer of name {}.", &layer).into());
-    let mut mixer = guild_state.mixer_mut();
+fn skip_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
 
-    if !mixer.contains_layer(layer) {
-        format!("No layer of name {}.", layer).into()
-    }
-    else if !mixer.stop_layer(layer) {
-        "No audio to stop.".into()
-    }
-    else {
-        CommandResponse::Confirm
-    }
+    Ok(on_layer(&mut ctx.guild_state().mixer_mut(), &layer, Mixer::skip_on_layer))
 }
 
-async fn stop_all(ctx: Context<'_>) -> CommandResponse {
-    let guild_id = ctx.guild_id().unwrap();
-    let guild_state =
-        unwrap_or_return!(get_guild_state(ctx.data(), guild_id).await, "No audio to stop.".into());
-
-    if guild_state.mixer_mut().stop_all() {
+fn stop_mixer(mixer: &mut Mixer, layer: Option<&str>) -> CommandResponse {
+    if let Some(layer) = layer {
+        if !mixer.contains_layer(layer) {
+            format!("No layer of name {}.", layer).into()
+        }
+        else if !mixer.stop_layer(layer) {
+            "No audio to stop.".into()
+        }
+        else {
+            CommandResponse::Confirm
+        }
+    }
+    else if mixer.stop_all() {
         CommandResponse::Confirm
     }
     else {
@@ -442,18 +552,111 @@ async fn stop_all(ctx: Context<'_>) -> CommandResponse {
 /// If no layer is given, all audio is stopped.
 ///
 /// Usage: `stop [layer]`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(stop_local))]
 async fn stop(ctx: Context<'_>, layer: Option<String>) -> CommandResult {
-    let response = if let Some(layer) = layer {
-        stop_layer(ctx, &layer).await
+    let response = with_mixer(ctx, |mixer| stop_mixer(mixer, layer.as_deref())).await;
+
+    respond(ctx, response).await
+}
+
+fn stop_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.next();
+
+    Ok(stop_mixer(&mut ctx.guild_state().mixer_mut(), layer.as_deref()))
+}
+
+fn pause_mixer(mixer: &mut Mixer, layer: Option<&str>) -> CommandResponse {
+    if let Some(layer) = layer {
+        if !mixer.contains_layer(layer) {
+            format!("No layer of name {}.", layer).into()
+        }
+        else if !mixer.pause_layer(layer) {
+            "No audio to pause.".into()
+        }
+        else {
+            CommandResponse::Confirm
+        }
+    }
+    else if mixer.pause_all() {
+        CommandResponse::Confirm
     }
     else {
-        stop_all(ctx).await
+        "No audio to pause.".into()
+    }
+}
+
+/// Pauses the audio currently playing on the given layer or all layers.
+///
+/// If no layer is given, all audio is paused. Paused audio keeps its position and can be continued
+/// with the `resume` command.
+///
+/// Usage: `pause [layer]`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(pause_local))]
+async fn pause(ctx: Context<'_>, layer: Option<String>) -> CommandResult {
+    let response = with_mixer(ctx, |mixer| pause_mixer(mixer, layer.as_deref())).await;
+
+    respond(ctx, response).await
+}
+
+fn pause_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.next();
+
+    Ok(pause_mixer(&mut ctx.guild_state().mixer_mut(), layer.as_deref()))
+}
+
+/// Resumes the audio on the given layer or all layers. Returns whether the mixer was active before
+/// if any audio was resumed and otherwise a message explaining why not.
+fn resume_mixer(mixer: &mut Mixer, layer: Option<&str>) -> Result<bool, String> {
+    let active_before = mixer.active();
+    let resumed = if let Some(layer) = layer {
+        if !mixer.contains_layer(layer) {
+            return Err(format!("No layer of name {}.", layer));
+        }
+
+        mixer.resume_layer(layer)
+    }
+    else {
+        mixer.resume_all()
+    };
+
+    if resumed {
+        Ok(active_before)
+    }
+    else {
+        Err("No audio to resume.".to_owned())
+    }
+}
+
+/// Resumes the audio on the given layer or all layers that was previously paused.
+///
+/// If no layer is given, all paused audio is resumed.
+///
+/// Usage: `resume [layer]`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(resume_local))]
+async fn resume(ctx: Context<'_>, layer: Option<String>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild_state = unwrap_or_reply!(get_guild_state(ctx.data(), guild_id).await, ctx,
+        "No audio to resume.");
+    let mixer = guild_state.mixer_arc();
+    drop(guild_state);
+
+    let resume_res = resume_mixer(&mut mixer.write().unwrap(), layer.as_deref());
+    let response = match resume_res {
+        Ok(active_before) => start_playback(ctx, mixer, active_before).await,
+        Err(message) => message.into()
     };
 
     respond(ctx, response).await
 }
 
+fn resume_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.next();
+
+    resume_mixer(&mut ctx.guild_state().mixer_mut(), layer.as_deref())?;
+    Ok(CommandResponse::Confirm)
+}
+
 /// Moves the current position in the audio of the layer with the given by the given amount of time.
 ///
 /// The `delta` is of the format `AhBmCsDmsEsam`, representing `A` hours, `B` minutes, `C` seconds,
@@ -461,28 +664,39 @@ async fn stop(ctx: Context<'_>, layer: Option<String>) -> CommandResult {
 /// Negative deltas are used to seek backwards in time.
 ///
 /// Usage: `seek <layer> <delta>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(seek_local))]
 async fn seek(ctx: Context<'_>, layer: String, delta: SampleDuration) -> CommandResult {
-    let response = with_layer_mut(ctx, &layer,
-        |mut mixer, layer| mixer.seek_on_layer(layer, delta)).await;
+    let response = with_mixer(ctx,
+        |mixer| on_layer(mixer, &layer, |mixer, layer| mixer.seek_on_layer(layer, delta))).await;
 
     respond(ctx, response).await
 }
 
+fn seek_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
+    let delta = arguments.parse::<SampleDuration>()?;
+
+    Ok(on_layer(&mut ctx.guild_state().mixer_mut(), &layer,
+        |mixer, layer| mixer.seek_on_layer(layer, delta)))
+}
+
 /// Executes commands provided as quoted strings.
 ///
 /// Takes as input a list of quoted strings separated by spaces. These are then executed as commands
 /// in order.
 ///
 /// Usage: `do [command] [command] ...`
-#[poise::command(prefix_command, guild_only, rename = "do")]
+#[poise::command(prefix_command, guild_only, rename = "do", custom_data = LocalAction(do_local))]
 async fn cmd_do(ctx: Context<'_>, commands: Vec<String>) -> CommandResult {
+    let guild_settings = guild_settings(ctx).await;
+
     for command in commands {
         match ctx {
             Context::Application(_) => unreachable!(), // prefix-only command
             Context::Prefix(ctx) => {
                 let mut msg = ctx.msg.clone();
-                msg.content = prepare_command_for_dispatch(&command, ctx.data.config());
+                msg.content = prepare_command_for_dispatch(
+                    &command, guild_settings.prefix(), ctx.data.config());
                 dispatch_command_as_message(ctx.framework(), ctx.serenity_context(), &msg).await?;
             }
         }
@@ -496,13 +710,23 @@ async fn cmd_do(ctx: Context<'_>, commands: Vec<String>) -> CommandResult {
 /// If an audio name is provided, a more detailed documentation page for that audio is displayed.
 ///
 /// Usage: `audio [audio]`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(audio_local))]
 async fn audio(ctx: Context<'_>, audio: Option<String>) -> CommandResult {
-    let plugin_manager = ctx.data().plugin_manager();
+    let reply = audio_message(ctx.data().plugin_manager(), audio);
 
-    let reply = if let Some(audio) = audio {
+    respond(ctx, reply.into()).await
+}
+
+fn audio_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let audio = arguments.next();
+
+    Ok(audio_message(ctx.plugin_manager(), audio).into())
+}
+
+fn audio_message(plugin_manager: &PluginManager, audio: Option<String>) -> String {
+    if let Some(audio) = audio {
         let audio_lower = audio.to_lowercase();
-        let doc = plugin_manager.get_audio_documentations()
+        let doc = plugin_manager.get_audio_documentations().into_iter()
             .find(|d| d.name().to_lowercase() == audio_lower);
 
         if let Some(doc) = doc {
@@ -526,9 +750,7 @@ async fn audio(ctx: Context<'_>, audio: Option<String>) -> CommandResult {
         }
 
         message
-    };
-
-    respond(ctx, reply.into()).await
+    }
 }
 
 fn add_line(message: &mut String, name: &str, entry: Option<impl Display>) {
@@ -540,52 +762,162 @@ fn add_line(message: &mut String, name: &str, entry: Option<impl Display>) {
 /// Prints information about the audio currently played on the layer with the given name.
 ///
 /// Usage: `info <layer>`
-#[poise::command(slash_command, prefix_command, guild_only)]
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(info_local))]
 async fn info(ctx: Context<'_>, layer: String) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
     let guild_state = unwrap_or_reply!(get_guild_state(ctx.data(), guild_id).await, ctx,
         format!("No layer of name `{}`.", layer));
-    let metadata = guild_state.mixer_blocking().layer_metadata(&layer);
+    let reply = info_message(&guild_state.mixer_blocking(), &layer);
 
-    let reply = match metadata {
-        Ok(metadata) => {
-            let mut message = String::new();
+    respond(ctx, reply.into()).await
+}
 
-            if let (Some(title), Some(sub_title)) =
-                    (metadata.title(), metadata.sub_title()) {
-                add_line(&mut message, "Title",
-                    Some(format!("{} - {}", title, sub_title)));
-            }
-            else {
-                add_line(&mut message, "Title", metadata.title());
-            }
+fn info_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let layer = arguments.required()?;
 
-            add_line(&mut message, "From", metadata.super_title());
-            add_line(&mut message, "Artist", metadata.artist());
-            add_line(&mut message, "Composer", metadata.composer());
-            add_line(&mut message, "Lead Performer", metadata.lead_performer());
-            add_line(&mut message, "Band/Orchestra", metadata.group_name());
-            add_line(&mut message, "Conductor", metadata.conductor());
-            add_line(&mut message, "Lyricist", metadata.lyricist());
-            add_line(&mut message, "Interpreter", metadata.interpreter());
-            add_line(&mut message, "Publisher", metadata.publisher());
-            add_line(&mut message, "Album", metadata.album());
-            add_line(&mut message, "Track Number", metadata.track());
-            add_line(&mut message, "Year", metadata.year());
-            add_line(&mut message, "Genre", metadata.genre());
-
-            let mut message = message.trim_end().to_owned();
-
-            if message.is_empty() {
-                message = "No information available.".to_owned();
-            }
+    Ok(info_message(&ctx.guild_state().mixer_blocking(), &layer).into())
+}
 
-            message
-        },
+fn info_message(mixer: &Mixer, layer: &str) -> String {
+    match mixer.layer_metadata(layer) {
+        Ok(metadata) => metadata_message(&metadata),
         Err(e) => format!("{}", e)
-    };
+    }
+}
 
-    respond(ctx, reply.into()).await
+fn metadata_message(metadata: &AudioMetadata) -> String {
+    let mut message = String::new();
+
+    if let (Some(title), Some(sub_title)) =
+            (metadata.title(), metadata.sub_title()) {
+        add_line(&mut message, "Title",
+            Some(format!("{} - {}", title, sub_title)));
+    }
+    else {
+        add_line(&mut message, "Title", metadata.title());
+    }
+
+    add_line(&mut message, "From", metadata.super_title());
+    add_line(&mut message, "Artist", metadata.artist());
+    add_line(&mut message, "Composer", metadata.composer());
+    add_line(&mut message, "Lead Performer", metadata.lead_performer());
+    add_line(&mut message, "Band/Orchestra", metadata.group_name());
+    add_line(&mut message, "Conductor", metadata.conductor());
+    add_line(&mut message, "Lyricist", metadata.lyricist());
+    add_line(&mut message, "Interpreter", metadata.interpreter());
+    add_line(&mut message, "Publisher", metadata.publisher());
+    add_line(&mut message, "Album", metadata.album());
+    add_line(&mut message, "Track Number", metadata.track());
+    add_line(&mut message, "Year", metadata.year());
+    add_line(&mut message, "Genre", metadata.genre());
+
+    let mut message = message.trim_end().to_owned();
+
+    if message.is_empty() {
+        message = "No information available.".to_owned();
+    }
+
+    message
+}
+
+/// The number of characters of a level bar displayed by the `meters` command.
+const LEVEL_BAR_WIDTH: usize = 24;
+
+/// The level in dBFS displayed at the left end of a level bar.
+const LEVEL_BAR_MIN_DB: f32 = -48.0;
+
+fn level_bar_position(db: f32) -> usize {
+    let fraction = (db - LEVEL_BAR_MIN_DB) / -LEVEL_BAR_MIN_DB;
+
+    (fraction * LEVEL_BAR_WIDTH as f32).round().clamp(0.0, LEVEL_BAR_WIDTH as f32) as usize
+}
+
+/// Renders a text bar in which the filled part represents the RMS level and a `|` marks the peak
+/// level, both on a dBFS scale.
+fn level_bar(levels: Levels) -> String {
+    let rms = level_bar_position(levels.rms_db());
+    let peak = level_bar_position(levels.peak_db());
+    let mut bar = String::with_capacity(LEVEL_BAR_WIDTH * 3);
+
+    for i in 0..LEVEL_BAR_WIDTH {
+        if peak > 0 && i == peak - 1 {
+            bar.push('|');
+        }
+        else if i < rms {
+            bar.push('█');
+        }
+        else {
+            bar.push('░');
+        }
+    }
+
+    bar
+}
+
+fn format_level(amplitude: f32, db: f32) -> String {
+    if amplitude == 0.0 {
+        "  -inf".to_owned()
+    }
+    else {
+        format!("{:>6.1}", db)
+    }
+}
+
+fn meter_line(name: &str, levels: Levels, name_width: usize) -> String {
+    format!("{:<width$} {} peak {} dB, RMS {} dB", name, level_bar(levels),
+        format_level(levels.peak, levels.peak_db()), format_level(levels.rms, levels.rms_db()),
+        width = name_width)
+}
+
+/// Shows the current peak and RMS levels of every layer and of the mixer output as text bars.
+///
+/// The filled part of a bar shows the RMS level and `|` marks the peak level. Levels are measured
+/// over the last 300 ms of audio sent to the voice channel, after applying layer gains. The output
+/// level is measured after master effects and the limiter.
+///
+/// Usage: `meters`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(meters_local))]
+async fn meters(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let (layers, output) = get_guild_state(ctx.data(), guild_id).await
+        .map(|gs| mixer_levels(&gs.mixer_blocking()))
+        .unwrap_or_default();
+    let reply = format!("```\n{}```", meter_lines(layers, output));
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn meters_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    let (layers, output) = mixer_levels(&ctx.guild_state().mixer_blocking());
+
+    Ok(meter_lines(layers, output).trim_end().into())
+}
+
+fn mixer_levels(mixer: &Mixer) -> (Vec<(String, Levels)>, Levels) {
+    let layers = mixer.layers().iter()
+        .map(|l| (l.name().to_owned(), l.levels()))
+        .collect::<Vec<_>>();
+
+    (layers, mixer.output_levels())
+}
+
+/// Renders one line with a level bar for every given layer as well as the mixer output.
+fn meter_lines(layers: Vec<(String, Levels)>, output: Levels) -> String {
+    let name_width = layers.iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Output".len());
+    let mut lines = String::new();
+
+    for (name, levels) in layers {
+        writeln!(lines, "{}", meter_line(&name, levels, name_width)).unwrap();
+    }
+
+    writeln!(lines, "{}", meter_line("Output", output, name_width)).unwrap();
+    lines
 }
 
 /// Specify or reset a guild-specific root directory for file system based plugins.
@@ -595,23 +927,34 @@ async fn info(ctx: Context<'_>, layer: String) -> CommandResult {
 /// root directory.
 ///
 /// Usage: `directory [directory]`
-#[poise::command(slash_command, prefix_command, guild_only, owners_only)]
+#[poise::command(slash_command, prefix_command, guild_only, owners_only,
+    custom_data = LocalAction(directory_local))]
 async fn directory(ctx: Context<'_>, #[rest] directory: String) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    let mut guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
 
+    set_directory(&mut *get_guild_state_mut(ctx.data(), guild_id).await, directory);
+    confirm(ctx).await
+}
+
+fn directory_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>)
+        -> LocalResult {
+    let directory = arguments.rest().unwrap_or_default();
+
+    set_directory(&mut ctx.guild_state_mut(), directory);
+    Ok(CommandResponse::Confirm)
+}
+
+fn set_directory(guild_state: &mut GuildState, directory: String) {
     if directory.is_empty() {
         guild_state.unset_root_directory()
     }
     else {
         guild_state.set_root_directory(directory)
     }
-
-    confirm(ctx).await
 }
 
 /// Display help about all or a specific command.
-#[poise::command(prefix_command, slash_command)]
+#[poise::command(prefix_command, slash_command, custom_data = LocalAction(help_local))]
 pub async fn help(ctx: Context<'_>,
         #[description = "Specific command to show help about"] command: Option<String>)
         -> CommandResult {
@@ -624,94 +967,76 @@ async fn display_help(ctx: Context<'_>, command: Option<&str>) -> CommandResult
     Ok(())
 }
 
-async fn configure_layer<F, T>(ctx: Context<'_>, guild_id: GuildId, layer: &str,
-    f: F) -> Option<T>
+/// Runs the given function on the mixer of the given guild state if it contains a layer with the
+/// given name. Otherwise, replies that the layer was not found.
+fn configure_layer<F>(guild_state: &GuildState, layer: &str, f: F) -> CommandResponse
 where
-    F: FnOnce(RwLockWriteGuard<Mixer>) -> T
+    F: FnOnce(&mut Mixer) -> CommandResponse
 {
-    let guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
-    let mixer = guild_state.mixer_mut();
+    let mut mixer = guild_state.mixer_mut();
 
     if mixer.contains_layer(layer) {
-        Some(f(mixer))
+        f(&mut mixer)
     }
     else {
-        None
+        "Layer not found.".into()
     }
 }
 
-async fn list_layer_key_value_descriptors<F>(ctx: Context<'_>,
-    layer: String, name_plural_capital: &str, get: F) -> CommandResult
+/// Builds a numbered list of the effects or adapters on the layer with the given name, as returned
+/// by `get`.
+fn layer_key_value_descriptor_list<F>(mixer: &Mixer, layer: &str, name_plural_capital: &str,
+    get: F) -> String
 where
     F: FnOnce(&Layer) -> &[KeyValueDescriptor]
 {
-    let guild_id = ctx.guild_id().unwrap();
-    let descriptors = get_guild_state(ctx.data(), guild_id).await
-        .and_then(|gs| {
-            let mixer = gs.mixer_blocking();
-
-            if mixer.contains_layer(&layer) {
-                Some(get(mixer.layer(&layer)).iter()
-                    .map(|e| format!("{}", e))
-                    .collect::<Vec<_>>())
-            }
-            else {
-                None
-            }
-        });
-
-    if let Some(descriptors) = descriptors {
-        let mut reply =
-            format!("{} on layer `{}`:", name_plural_capital, &layer);
+    if !mixer.contains_layer(layer) {
+        return "Layer not found.".to_owned();
+    }
 
-        for (i, descriptor) in descriptors.iter().enumerate() {
-            write!(reply, "\n{}. {}", i + 1, descriptor).unwrap();
-        }
+    let mut reply = format!("{} on layer `{}`:", name_plural_capital, layer);
 
-        ctx.reply(reply).await?;
-    }
-    else {
-        ctx.reply("Layer not found.").await?;
+    for (i, descriptor) in get(mixer.layer(layer)).iter().enumerate() {
+        write!(reply, "\n{}. {}", i + 1, descriptor).unwrap();
     }
 
-    Ok(())
+    reply
 }
 
-async fn help_modifiers<D, N, R>(ctx: Context<'_>, modifier: Option<String>,
+/// Builds an overview of all effects or adapters provided by plugins or, if a modifier name is
+/// given, the documentation of that modifier.
+fn modifier_help<D, N>(plugin_manager: &PluginManager, modifier: Option<String>,
     name_plural_upper: &str, name_singular_lower: &str, mut get_documentation: D, get_names: N)
-    -> CommandResult
+    -> String
 where
     D: FnMut(&PluginManager, &str) -> Option<ModifierDocumentation>,
-    N: FnOnce(&PluginManager) -> Keys<'_, String, R>
+    N: FnOnce(&PluginManager) -> Vec<String>
 {
-    let plugin_manager = ctx.data().plugin_manager();
-
     if let Some(name) = modifier {
         if let Some(documentation) = get_documentation(plugin_manager, &name) {
-            ctx.reply(format!("**{}**\n\n{}", name, documentation)).await?;
+            format!("**{}**\n\n{}", name, documentation)
         }
         else {
-            ctx.reply(format!("No {} of name {}.", name_singular_lower, name)).await?;
+            format!("No {} of name {}.", name_singular_lower, name)
         }
     }
     else {
         let mut response = format!("{} provided by plugins:", name_plural_upper);
 
         for name in get_names(plugin_manager) {
-            let doc = get_documentation(plugin_manager, name.as_str()).unwrap();
-
-            write!(&mut response, "\n- **{}**: {}", name, doc.short_summary())
-                .unwrap();
+            if let Some(doc) = get_documentation(plugin_manager, &name) {
+                write!(&mut response, "\n- **{}**: {}", name, doc.short_summary())
+                    .unwrap();
+            }
         }
 
-        ctx.reply(response).await?;
+        response
     }
-
-    Ok(())
 }
 
 /// Indicates that the message which caused a command to be executed is not a real message but
-/// synthetically created as part of some bot-internal command dispatch (sound board or do-command).
+/// synthetically created as part of some bot-internal command dispatch (sound board, alias,
+/// schedule, or do-command).
 /// This will be supplied as `invocation_data`.
 struct SyntheticMessageMarker;
 
@@ -736,11 +1061,35 @@ async fn dispatch_command_as_message(
         &mut vec![]).await.map_err(|err| format!("{}", err).into())
 }
 
+/// Gets the [GuildSettings] of the guild in which the command was invoked, or the default ones
+/// outside of guilds or if the guild has no state yet.
+async fn guild_settings(ctx: Context<'_>) -> GuildSettings {
+    match ctx.guild_id() {
+        Some(guild_id) => get_guild_state(ctx.data(), guild_id).await
+            .map(|guild_state| guild_state.settings().clone())
+            .unwrap_or_default(),
+        None => GuildSettings::default()
+    }
+}
+
+/// Translates the given [StatusText] to the status language of the guild in which the command was
+/// invoked.
+async fn status_text(ctx: Context<'_>, text: StatusText) -> &'static str {
+    guild_settings(ctx).await.status_language().status_text(text)
+}
+
 async fn confirm(ctx: Context<'_>) -> CommandResult {
+    let settings = guild_settings(ctx).await;
+    let confirmation = settings.status_language().status_text(StatusText::Confirmation);
+
     match ctx {
         Context::Application(ctx) => {
+            let content = match settings.confirmation_style() {
+                ConfirmationStyle::Message => confirmation,
+                _ => "\u{1f44c}"
+            };
             let response = CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new().ephemeral(true).content("\u{1f44c}")
+                CreateInteractionResponseMessage::new().ephemeral(true).content(content)
             );
 
             ctx.interaction.create_response(ctx, response).await?;
@@ -748,8 +1097,18 @@ async fn confirm(ctx: Context<'_>) -> CommandResult {
         Context::Prefix(ctx) => {
             let invocation_data = ctx.invocation_data.lock().await;
 
-            if !invocation_data.is::<SyntheticMessageMarker>() {
-                ctx.msg.react(ctx, '\u{1f44c}').await?;
+            if invocation_data.is::<SyntheticMessageMarker>() {
+                return Ok(());
+            }
+
+            match settings.confirmation_style() {
+                ConfirmationStyle::Reaction => {
+                    ctx.msg.react(ctx, '\u{1f44c}').await?;
+                },
+                ConfirmationStyle::Message => {
+                    ctx.msg.reply(ctx, confirmation).await?;
+                },
+                ConfirmationStyle::Silent => { }
             }
         }
     }
@@ -757,7 +1116,7 @@ async fn confirm(ctx: Context<'_>) -> CommandResult {
     Ok(())
 }
 
-enum CommandResponse<R: Into<String> = String> {
+pub(crate) enum CommandResponse<R: Into<String> = String> {
     Confirm,
     Reply(R)
 }
@@ -796,7 +1155,15 @@ async fn respond<R: Into<String>>(ctx: Context<'_>, response: CommandResponse<R>
     Ok(())
 }
 
-fn prepare_command_for_dispatch(command: &str, config: &Config) -> String {
+/// Prepares a command, for example of a sound board button, to be dispatched with the programmatic
+/// command framework options, which use the globally configured prefix. If the command starts with
+/// the prefix of the guild, that prefix is replaced.
+fn prepare_command_for_dispatch(command: &str, guild_prefix: Option<&str>, config: &Config)
+        -> String {
+    let command = guild_prefix
+        .and_then(|prefix| command.strip_prefix(prefix))
+        .unwrap_or(command);
+
     if let Some(prefix) = config.prefix() {
         if !command.starts_with(prefix) {
             return format!("{}{}", prefix, command);
@@ -827,24 +1194,38 @@ mod tests {
                 \"allow_web_access\": true,
                 \"log_level_filter\": \"info\"
             }}
-        ", prefix.map(|prefix| format!("\"prefix\": \"{}\",", prefix)).unwrap_or_else(String::new));
+        ", prefix.map(|prefix| format!("\"prefix\": \"{}\",", prefix)).unwrap_or_default());
 
         serde_json::from_str(&json).unwrap()
     }
 
     #[rstest]
-    #[case::without_configured_prefix(None, "test command", "test command")]
-    #[case::command_has_prefix(Some("!"), "!test command", "!test command")]
-    #[case::command_does_not_have_prefix(Some("!"), "test command", "!test command")]
+    #[case::without_configured_prefix(None, None, "test command", "test command")]
+    #[case::command_has_prefix(Some("!"), None, "!test command", "!test command")]
+    #[case::command_does_not_have_prefix(Some("!"), None, "test command", "!test command")]
+    #[case::command_has_guild_prefix(Some("!"), Some("?"), "?test command", "!test command")]
+    #[case::command_has_global_prefix(Some("!"), Some("?"), "!test command", "!test command")]
+    #[case::only_guild_prefix(None, Some("?"), "?test command", "test command")]
     fn prepare_command_for_dispatch_test(
         #[case] prefix: Option<&str>,
+        #[case] guild_prefix: Option<&str>,
         #[case] command: &str,
         #[case] expected_prepared_command: &str
     ) {
         let config = config_with_prefix(prefix);
 
-        let prepared_command = prepare_command_for_dispatch(command, &config);
+        let prepared_command = prepare_command_for_dispatch(command, guild_prefix, &config);
 
         assert_that!(prepared_command).is_equal_to(expected_prepared_command.to_owned());
     }
+
+    #[rstest]
+    #[case::silence(0.0, 0.0, "░░░░░░░░░░░░░░░░░░░░░░░░")]
+    #[case::full_scale(1.0, 1.0, "███████████████████████|")]
+    #[case::half_scale(0.5, 0.0625, "████████████░░░░░░░░|░░░")]
+    fn level_bar_test(#[case] peak: f32, #[case] rms: f32, #[case] expected_bar: &str) {
+        let bar = level_bar(Levels { peak, rms });
+
+        assert_that!(bar).is_equal_to(expected_bar.to_owned());
+    }
 }
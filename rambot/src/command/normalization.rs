@@ -0,0 +1,127 @@
+use crate::audio::Mixer;
+use crate::command::{
+    display_help,
+    get_guild_state_mut,
+    respond,
+    with_mixer,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::loudness::{NormalizationMode, NormalizationSettings};
+use crate::state::GuildState;
+
+use std::fmt::Write;
+
+/// Collection of commands for configuring loudness normalization.
+///
+/// Loudness normalization adjusts the volume of every track on every layer such that tracks play at
+/// a similar loudness. The gain is read from ReplayGain or R128 tags of the played files. For files
+/// without such tags, the loudness can optionally be measured while they are played. Loudness
+/// normalization is off by default. Tags never amplify a track beyond its tagged peak.
+#[poise::command(slash_command, prefix_command,
+    subcommands("mode", "analyze", "status"))]
+pub async fn normalization(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("normalization")).await
+}
+
+fn update_settings<F>(guild_state: &GuildState, update: F) -> CommandResponse
+where
+    F: FnOnce(&mut NormalizationSettings)
+{
+    let mut mixer = guild_state.mixer_mut();
+    let mut settings = mixer.normalization();
+
+    update(&mut settings);
+    mixer.set_normalization(settings);
+    CommandResponse::Confirm
+}
+
+fn set_mode(guild_state: &GuildState, mode: &str) -> CommandResponse {
+    match mode.parse::<NormalizationMode>() {
+        Ok(mode) => update_settings(guild_state, |settings| settings.mode = mode),
+        Err(e) => format!("{}", e).into()
+    }
+}
+
+/// Sets the normalization mode.
+///
+/// In `track` mode, every track is normalized individually. In `album` mode, the album gain is used
+/// where available, which preserves the loudness differences between tracks on the same album.
+/// `off` disables loudness normalization.
+///
+/// Usage: `normalization mode <off|track|album>`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(mode_local))]
+async fn mode(ctx: Context<'_>, mode: String) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = set_mode(&*get_guild_state_mut(ctx.data(), guild_id).await, &mode);
+
+    respond(ctx, response).await
+}
+
+fn mode_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let mode = arguments.required()?;
+
+    Ok(set_mode(&ctx.guild_state_mut(), &mode))
+}
+
+fn set_analyze(guild_state: &GuildState, state: &str) -> CommandResponse {
+    let analyze = match state.to_ascii_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => return "Expected `on` or `off`.".into()
+    };
+
+    update_settings(guild_state, |settings| settings.analyze = analyze)
+}
+
+/// Enables or disables measuring the loudness of tracks without loudness tags.
+///
+/// If enabled, the loudness of such tracks is measured according to EBU R128 while they are played.
+/// After a few seconds, the measured loudness is used to normalize the track.
+///
+/// Usage: `normalization analyze <on|off>`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(analyze_local))]
+async fn analyze(ctx: Context<'_>, state: String) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = set_analyze(&*get_guild_state_mut(ctx.data(), guild_id).await, &state);
+
+    respond(ctx, response).await
+}
+
+fn analyze_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let state = arguments.required()?;
+
+    Ok(set_analyze(&ctx.guild_state_mut(), &state))
+}
+
+fn normalization_status(mixer: &Mixer) -> String {
+    let settings = mixer.normalization();
+    let analyze = if settings.analyze { "on" } else { "off" };
+    let mut reply = format!("Normalization mode: {}, analysis: {}",
+        settings.mode, analyze);
+
+    for layer in mixer.layers() {
+        write!(reply, "\n- {}: {}", layer.name(), layer.normalization_gain()).unwrap();
+    }
+
+    reply
+}
+
+/// Shows the normalization settings and the gain currently applied on every layer.
+///
+/// Usage: `normalization status`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(status_local))]
+async fn status(ctx: Context<'_>) -> CommandResult {
+    let reply = with_mixer(ctx, |mixer| normalization_status(mixer)).await;
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn status_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(normalization_status(&ctx.guild_state().mixer_blocking()).into())
+}
@@ -0,0 +1,173 @@
+use crate::command::{
+    confirm,
+    display_help,
+    get_guild_state,
+    get_guild_state_mut,
+    respond,
+    status_text,
+    CommandError,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command_data::CommandData;
+use crate::settings::StatusText;
+
+use poise::Command;
+
+use serenity::model::guild::{Member, Role};
+use serenity::model::id::RoleId;
+
+/// Collection of commands for restricting who may use which commands in this guild.
+///
+/// Restricted commands may only be used by members with the DJ role, members with one of the roles
+/// allowed for the command, and members who can manage this guild. Restricting a command also
+/// restricts all of its subcommands, unless they are restricted themselves.
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    subcommands("dj", "list", "restrict", "unrestrict"))]
+pub async fn permissions(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("permissions")).await
+}
+
+fn is_guild_manager(ctx: Context<'_>, member: &Member) -> bool {
+    ctx.guild()
+        .map(|guild| guild.owner_id == member.user.id ||
+            guild.member_permissions(member).manage_guild())
+        .unwrap_or(false)
+}
+
+/// A command check which enforces the permissions configured for the guild in which the command
+/// was invoked. Bot owners and members who can manage the guild may use all commands. If the
+/// author is not allowed to use the command, they are told so.
+pub async fn check_permissions(ctx: Context<'_>) -> Result<bool, CommandError> {
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return Ok(true)
+    };
+
+    if ctx.framework().options().owners.contains(&ctx.author().id) {
+        return Ok(true);
+    }
+
+    let permissions = match get_guild_state(ctx.data(), guild_id).await {
+        Some(guild_state) => guild_state.permissions().clone(),
+        None => return Ok(true)
+    };
+    let command = &ctx.command().qualified_name;
+
+    if permissions.is_allowed(command, &[]) {
+        return Ok(true);
+    }
+
+    let allowed = match ctx.author_member().await {
+        Some(member) =>
+            is_guild_manager(ctx, &member) || permissions.is_allowed(command, &member.roles),
+        None => false
+    };
+
+    if !allowed {
+        ctx.reply(status_text(ctx, StatusText::NotAllowed).await).await?;
+    }
+
+    Ok(allowed)
+}
+
+fn command_exists(commands: &[Command<CommandData, CommandError>], qualified_name: &str) -> bool {
+    commands.iter().any(|command| command.qualified_name == qualified_name ||
+        command_exists(&command.subcommands, qualified_name))
+}
+
+fn role_name(ctx: Context<'_>, role_id: RoleId) -> String {
+    ctx.guild()
+        .and_then(|guild| guild.roles.get(&role_id).map(|role| role.name.clone()))
+        .unwrap_or_else(|| role_id.to_string())
+}
+
+/// Sets the DJ role, whose members may use all restricted commands.
+///
+/// Omit the role to remove the DJ role.
+///
+/// Usage: `permissions dj [role]`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn dj(ctx: Context<'_>, role: Option<Role>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+
+    get_guild_state_mut(ctx.data(), guild_id).await
+        .permissions_mut()
+        .set_dj_role(role.map(|role| role.id));
+
+    confirm(ctx).await
+}
+
+/// Prints the DJ role and all restricted commands together with the roles that may use them.
+///
+/// Usage: `permissions list`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let permissions = get_guild_state(ctx.data(), guild_id).await
+        .map(|guild_state| guild_state.permissions().clone())
+        .unwrap_or_default();
+    let dj_role = match permissions.dj_role() {
+        Some(role_id) => role_name(ctx, role_id),
+        None => "none".to_owned()
+    };
+    let mut response = format!("DJ role: {}", dj_role);
+
+    for (command, roles) in permissions.restrictions() {
+        let mut roles = roles.iter()
+            .map(|&role_id| role_name(ctx, role_id))
+            .collect::<Vec<_>>();
+
+        roles.insert(0, "DJ".to_owned());
+        response.push_str(&format!("\n- `{}`: {}", command, roles.join(", ")));
+    }
+
+    ctx.reply(response).await?;
+    Ok(())
+}
+
+/// Restricts a command, so only members with the DJ role or one of the allowed roles may use it.
+///
+/// Commands are given by their full name, for example `stop` or `"board button add"` (in quotes if
+/// it contains spaces). Optionally, a role can be provided which is then allowed to use the command
+/// in addition to the roles allowed before.
+///
+/// Usage: `permissions restrict <command> [role]`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn restrict(ctx: Context<'_>, command: String, role: Option<Role>) -> CommandResult {
+    if !command_exists(&ctx.framework().options().commands, &command) {
+        return respond(ctx, format!("Found no command `{}`.", command).into()).await;
+    }
+
+    let guild_id = ctx.guild_id().unwrap();
+
+    get_guild_state_mut(ctx.data(), guild_id).await
+        .permissions_mut()
+        .restrict(&command, role.map(|role| role.id));
+
+    confirm(ctx).await
+}
+
+/// Lifts the restriction of a command.
+///
+/// If a role is provided, only that role is no longer allowed to use the command, but the command
+/// remains restricted. Otherwise, everybody may use the command again.
+///
+/// Usage: `permissions unrestrict <command> [role]`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn unrestrict(ctx: Context<'_>, command: String, role: Option<Role>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let changed = get_guild_state_mut(ctx.data(), guild_id).await
+        .permissions_mut()
+        .unrestrict(&command, role.map(|role| role.id));
+
+    let response = if changed {
+        CommandResponse::Confirm
+    }
+    else {
+        format!("Found no such restriction of `{}`.", command).into()
+    };
+
+    respond(ctx, response).await
+}
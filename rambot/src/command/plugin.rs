@@ -0,0 +1,345 @@
+use crate::command::{
+    display_help,
+    get_guild_state,
+    get_guild_state_mut,
+    respond,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command::local::{LocalAction, LocalArguments, LocalContext, LocalResult};
+use crate::plugin::{LoadPluginsError, PluginInfo, PluginManager, ResolverInfo};
+use crate::state::{GuildState, PluginFeature, State};
+
+use rambot_api::PluginGuildConfig;
+
+use std::sync::Arc;
+
+/// Collection of commands for managing plugins while the bot is running.
+///
+/// Plugins are identified by the file name of their library in the plugin directory. Audio which
+/// is currently being played continues with the old version of a plugin, but effects are switched
+/// to the new version immediately. Playlists which are currently being played keep the adapters of
+/// the old version until they are played again. Effects whose plugin was unloaded are skipped, but
+/// remain configured, so they are applied again once a plugin providing them is loaded. Loading,
+/// reloading, and unloading is reserved to the owners of the bot, while the owners of a guild can
+/// disable and enable plugins in their guild.
+#[poise::command(slash_command, prefix_command,
+    subcommands("disable", "enable", "load", "reload", "unload"))]
+pub async fn plugin(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("plugin")).await
+}
+
+/// Resolves the effects in all guilds anew, so they use the currently loaded plugins. Returns the
+/// number of guilds in which some effect could not be resolved.
+fn reapply_effects(state: &State) -> usize {
+    let mut failed_guilds = 0;
+
+    for guild_id in state.guild_ids() {
+        let guild_state = state.guild_state(guild_id).unwrap();
+        let plugin_guild_config = guild_state.build_plugin_guild_config();
+
+        if let Err(e) = guild_state.mixer_mut().reapply_effects(&plugin_guild_config) {
+            log::warn!("Error re-applying effects in guild {}: {}", guild_id, e);
+            failed_guilds += 1;
+        }
+    }
+
+    failed_guilds
+}
+
+fn reapplied_effects_message(state: &State, result: Result<String, LoadPluginsError>) -> String {
+    let mut reply = match result {
+        Ok(reply) => reply,
+        Err(e) => return format!("Error: {}.", e)
+    };
+    let failed_guilds = reapply_effects(state);
+
+    if failed_guilds > 0 {
+        reply.push_str(&format!(" Some effects could not be re-applied in {} guilds, they remain \
+            inactive until their plugin is loaded again.", failed_guilds));
+    }
+
+    reply
+}
+
+async fn reply_with_reapplied_effects(ctx: Context<'_>, result: Result<String, LoadPluginsError>)
+        -> CommandResult {
+    let reply = reapplied_effects_message(&*ctx.data().state().await, result);
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+/// Gets the [PluginGuildConfig] of the guild in which the command was invoked, or the default one
+/// outside of guilds or if the guild has no state yet.
+async fn plugin_guild_config(ctx: Context<'_>) -> PluginGuildConfig {
+    match ctx.guild_id() {
+        Some(guild_id) => get_guild_state(ctx.data(), guild_id).await
+            .map(|guild_state| guild_state.build_plugin_guild_config())
+            .unwrap_or_default(),
+        None => PluginGuildConfig::default()
+    }
+}
+
+/// Indicates whether the plugin, effect, or adapter with the given name is disabled in the given
+/// guild. Effects and adapters are also disabled if all plugins which provide them are disabled.
+pub(crate) fn is_disabled(guild_state: &GuildState, plugin_manager: &PluginManager,
+        feature: PluginFeature, name: &str) -> bool {
+    let plugin_guild_config = guild_state.build_plugin_guild_config();
+
+    match feature {
+        PluginFeature::Plugin => plugin_guild_config.is_plugin_disabled(name),
+        PluginFeature::Effect => plugin_manager.is_effect_disabled(name, &plugin_guild_config),
+        PluginFeature::Adapter => plugin_manager.is_adapter_disabled(name, &plugin_guild_config)
+    }
+}
+
+/// Disables or enables the plugin, effect, or adapter with the given name in the given guild. Only
+/// currently loaded plugins and existing effects and adapters can be disabled, but anything can be
+/// enabled again.
+pub(crate) fn set_disabled(guild_state: &mut GuildState, plugin_manager: &PluginManager,
+        feature: PluginFeature, name: String, disabled: bool) -> CommandResponse {
+    let exists = match feature {
+        PluginFeature::Plugin =>
+            plugin_manager.plugin_infos().iter().any(|info| info.file_name() == name),
+        PluginFeature::Effect => plugin_manager.effect_names().contains(&name),
+        PluginFeature::Adapter => plugin_manager.adapter_names().contains(&name)
+    };
+
+    if disabled && !exists {
+        format!("Found no {} named `{}`.", feature, name).into()
+    }
+    else if guild_state.set_disabled(feature, &name, disabled) {
+        CommandResponse::Confirm
+    }
+    else if disabled {
+        format!("The {} `{}` is already disabled.", feature, name).into()
+    }
+    else {
+        format!("The {} `{}` is not disabled.", feature, name).into()
+    }
+}
+
+/// Disables or enables the plugin, effect, or adapter with the given name in the guild in which
+/// the command was invoked.
+pub(crate) async fn set_disabled_do(ctx: Context<'_>, feature: PluginFeature, name: String,
+        disabled: bool) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = set_disabled(&mut *get_guild_state_mut(ctx.data(), guild_id).await,
+        ctx.data().plugin_manager(), feature, name, disabled);
+
+    respond(ctx, response).await
+}
+
+/// Disables or enables the plugin, effect, or adapter named by the next argument, as done by the
+/// `disable` and `enable` subcommands outside Discord.
+pub(crate) fn set_disabled_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>,
+        feature: PluginFeature, disabled: bool) -> LocalResult {
+    let name = arguments.required()?;
+    let plugin_manager = Arc::clone(ctx.plugin_manager());
+
+    Ok(set_disabled(&mut ctx.guild_state_mut(), &plugin_manager, feature, name, disabled))
+}
+
+/// Disables the plugin with the given file name in this guild.
+///
+/// Audio, effects, and adapters of a disabled plugin are no longer available in this guild. If
+/// another plugin offers an effect or adapter of the same name, that one is used instead.
+///
+/// Usage: `plugin disable <file>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    custom_data = LocalAction(disable_local))]
+async fn disable(ctx: Context<'_>, file: String) -> CommandResult {
+    set_disabled_do(ctx, PluginFeature::Plugin, file, true).await
+}
+
+fn disable_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    set_disabled_local(ctx, arguments, PluginFeature::Plugin, true)
+}
+
+/// Enables the plugin with the given file name in this guild again after it was disabled.
+///
+/// Usage: `plugin enable <file>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    custom_data = LocalAction(enable_local))]
+async fn enable(ctx: Context<'_>, file: String) -> CommandResult {
+    set_disabled_do(ctx, PluginFeature::Plugin, file, false).await
+}
+
+fn enable_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    set_disabled_local(ctx, arguments, PluginFeature::Plugin, false)
+}
+
+fn load_plugin(plugin_manager: &PluginManager, file: &str) -> Result<String, LoadPluginsError> {
+    plugin_manager.load_plugin(file).map(|_| format!("Loaded plugin `{}`.", file))
+}
+
+fn reload_plugins(plugin_manager: &PluginManager, file: Option<&str>)
+        -> Result<String, LoadPluginsError> {
+    plugin_manager.reload(file).map(|count| match file {
+        Some(file) => format!("Reloaded plugin `{}`.", file),
+        None => format!("Reloaded {} plugins.", count)
+    })
+}
+
+fn unload_plugin(plugin_manager: &PluginManager, file: &str) -> Result<String, LoadPluginsError> {
+    plugin_manager.unload_plugin(file).map(|_| format!("Unloaded plugin `{}`.", file))
+}
+
+/// Loads a plugin from the plugin directory which is not loaded yet.
+///
+/// Usage: `plugin load <file>`
+#[poise::command(slash_command, prefix_command, owners_only, custom_data = LocalAction(load_local))]
+async fn load(ctx: Context<'_>, file: String) -> CommandResult {
+    let plugin_manager = ctx.data().plugin_manager_arc();
+
+    // Loading libraries and notifying plugins may block, so it is done outside the async runtime.
+
+    let result = tokio::task::spawn_blocking(move || load_plugin(&plugin_manager, &file)).await?;
+
+    reply_with_reapplied_effects(ctx, result).await
+}
+
+fn load_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let file = arguments.required()?;
+    let result = load_plugin(ctx.plugin_manager(), &file);
+
+    Ok(reapplied_effects_message(ctx.state(), result).into())
+}
+
+/// Reloads the plugin with the given file name from the plugin directory.
+///
+/// If no file name is provided, all plugins are replaced by the ones currently in the plugin
+/// directory. If loading fails, the old versions remain in use.
+///
+/// Usage: `plugin reload [file]`
+#[poise::command(slash_command, prefix_command, owners_only,
+    custom_data = LocalAction(reload_local))]
+async fn reload(ctx: Context<'_>, file: Option<String>) -> CommandResult {
+    let plugin_manager = ctx.data().plugin_manager_arc();
+    let result = tokio::task::spawn_blocking(
+            move || reload_plugins(&plugin_manager, file.as_deref()))
+        .await?;
+
+    reply_with_reapplied_effects(ctx, result).await
+}
+
+fn reload_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let file = arguments.next();
+    let result = reload_plugins(ctx.plugin_manager(), file.as_deref());
+
+    Ok(reapplied_effects_message(ctx.state(), result).into())
+}
+
+/// Unloads the plugin with the given file name.
+///
+/// Audio which is currently being played by the plugin continues, but its effects are removed.
+///
+/// Usage: `plugin unload <file>`
+#[poise::command(slash_command, prefix_command, owners_only,
+    custom_data = LocalAction(unload_local))]
+async fn unload(ctx: Context<'_>, file: String) -> CommandResult {
+    let plugin_manager = ctx.data().plugin_manager_arc();
+    let result =
+        tokio::task::spawn_blocking(move || unload_plugin(&plugin_manager, &file)).await?;
+
+    reply_with_reapplied_effects(ctx, result).await
+}
+
+fn unload_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let file = arguments.required()?;
+    let result = unload_plugin(ctx.plugin_manager(), &file);
+
+    Ok(reapplied_effects_message(ctx.state(), result).into())
+}
+
+fn resolver_list<D>(kind: &str, resolvers: &[ResolverInfo], is_disabled: D) -> Option<String>
+where
+    D: Fn(&str) -> bool
+{
+    if resolvers.is_empty() {
+        return None;
+    }
+
+    let names = resolvers.iter()
+        .map(|resolver| {
+            let mut name = resolver.name().to_owned();
+
+            if let Some(file_name) = resolver.overridden_by() {
+                name.push_str(&format!(" (overridden by `{}`)", file_name));
+            }
+
+            if is_disabled(resolver.name()) {
+                name.push_str(" (disabled)");
+            }
+
+            name
+        })
+        .collect::<Vec<_>>();
+
+    Some(format!("{}: {}", kind, names.join(", ")))
+}
+
+fn plugin_entry(info: &PluginInfo, plugin_guild_config: &PluginGuildConfig) -> String {
+    let mut entry = format!("`{}` {}", info.file_name(), info.version().unwrap_or("(no version)"));
+
+    if plugin_guild_config.is_plugin_disabled(info.file_name()) {
+        entry.push_str(" (disabled)");
+    }
+
+    let resolver_lists = [
+        resolver_list("Audio sources", info.audio_sources(), |_| false),
+        resolver_list("Lists", info.audio_source_lists(), |_| false),
+        resolver_list("Effects", info.effects(),
+            |name| plugin_guild_config.is_effect_disabled(name)),
+        resolver_list("Adapters", info.adapters(),
+            |name| plugin_guild_config.is_adapter_disabled(name))
+    ];
+
+    for resolver_list in resolver_lists.into_iter().flatten() {
+        entry.push_str("\n  - ");
+        entry.push_str(&resolver_list);
+    }
+
+    entry
+}
+
+/// Prints a list of all loaded plugins and what they provide.
+///
+/// Plugins are listed in the order in which they were loaded, together with their version and the
+/// audio sources, lists, effects, and adapters they provide.
+///
+/// If multiple plugins provide something of the same name, the ones which are not used are marked
+/// as overridden. Plugins, effects, and adapters which are disabled in this guild are marked as
+/// well.
+///
+/// Usage: `plugins`
+#[poise::command(slash_command, prefix_command, custom_data = LocalAction(plugins_local))]
+pub async fn plugins(ctx: Context<'_>) -> CommandResult {
+    let plugin_guild_config = plugin_guild_config(ctx).await;
+    let reply = plugin_list(ctx.data().plugin_manager(), &plugin_guild_config);
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn plugins_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    let plugin_guild_config = ctx.guild_state().build_plugin_guild_config();
+
+    Ok(plugin_list(ctx.plugin_manager(), &plugin_guild_config).into())
+}
+
+fn plugin_list(plugin_manager: &PluginManager, plugin_guild_config: &PluginGuildConfig)
+        -> String {
+    let entries = plugin_manager.plugin_infos().iter()
+        .map(|info| plugin_entry(info, plugin_guild_config))
+        .collect::<Vec<_>>();
+
+    if entries.is_empty() {
+        "No plugins loaded.".to_owned()
+    }
+    else {
+        format!("Plugin list:\n- {}", entries.join("\n- "))
+    }
+}
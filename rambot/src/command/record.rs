@@ -0,0 +1,199 @@
+use crate::command::{
+    display_help,
+    get_guild_state,
+    get_guild_state_mut,
+    CommandResult,
+    Context
+};
+use crate::recording::Recorder;
+
+use serenity::model::id::GuildId;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Collection of commands for recording the audio sent to the voice channel.
+///
+/// Recordings contain the mixer output after master effects and the limiter, including silence
+/// while nothing is played. They are stored as 48 kHz stereo WAV files in the recordings directory
+/// specified in the config. Once a recording reaches the maximum size specified in the
+/// config, it stops automatically.
+#[poise::command(slash_command, prefix_command, owners_only,
+    subcommands("start", "stop", "status"))]
+pub async fn record(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("record")).await
+}
+
+fn default_file_name(guild_id: GuildId) -> String {
+    format!("{}-{}.wav", guild_id, chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+fn validate_file_name(file: &str) -> Result<String, String> {
+    let is_valid = !file.is_empty() && !file.starts_with('.') &&
+        !file.contains(['/', '\\', ':']);
+
+    if !is_valid {
+        return Err(format!("Invalid file name `{}`. The file name may not contain path separators.",
+            file));
+    }
+
+    if file.to_ascii_lowercase().ends_with(".wav") {
+        Ok(file.to_owned())
+    }
+    else {
+        Ok(format!("{}.wav", file))
+    }
+}
+
+async fn recording_path(ctx: Context<'_>, guild_id: GuildId) -> Option<PathBuf> {
+    get_guild_state(ctx.data(), guild_id).await
+        .and_then(|gs| gs.mixer_blocking().recorder().map(|r| r.path().to_owned()))
+}
+
+fn already_recording(path: &Path) -> String {
+    format!("Already recording to `{}`. Use `record stop` first.", path.display())
+}
+
+/// Starts recording the audio sent to the voice channel into a file.
+///
+/// Optionally, a file name can be provided. Otherwise, a name is generated from the guild ID and the
+/// current time. The `.wav` extension is added if needed. Existing files are never overwritten.
+///
+/// Usage: `record start [file]`
+#[poise::command(slash_command, prefix_command, guild_only, owners_only)]
+async fn start(ctx: Context<'_>, file: Option<String>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let file = match file {
+        Some(file) => validate_file_name(&file),
+        None => Ok(default_file_name(guild_id))
+    };
+    let file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            ctx.reply(e).await?;
+            return Ok(());
+        }
+    };
+    if let Some(path) = recording_path(ctx, guild_id).await {
+        ctx.reply(already_recording(&path)).await?;
+        return Ok(());
+    }
+
+    // The file is created and the writer thread spawned before the mixer is locked, so the audio
+    // is not held up in the meantime.
+
+    let config = ctx.data().config();
+    let directory = Path::new(config.recordings_directory()).to_owned();
+    let path = directory.join(&file);
+    let max_size = config.max_recording_size();
+    let recorder = tokio::task::spawn_blocking(move || fs::create_dir_all(&directory)
+        .and_then(|_| Recorder::start(path, max_size))).await?;
+    let recorder = match recorder {
+        Ok(recorder) => recorder,
+        Err(e) => {
+            ctx.reply(format!("Could not start recording: {}", e)).await?;
+            return Ok(());
+        }
+    };
+    let rejected = {
+        let guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
+        let mut mixer = guild_state.mixer_mut();
+
+        if mixer.recorder().is_some() {
+            Some(recorder)
+        }
+        else {
+            mixer.start_recording(recorder);
+            None
+        }
+    };
+
+    // Another recording may have been started while the file was created.
+
+    let reply = match rejected {
+        Some(recorder) => {
+            let path = recorder.path().to_owned();
+            let reply = already_recording(&path);
+
+            tokio::task::spawn_blocking(move || {
+                let _ = recorder.stop();
+                let _ = fs::remove_file(path);
+            }).await?;
+
+            reply
+        },
+        None => format!("Started recording to `{}`.", file)
+    };
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+/// Stops the current recording and finalizes the file.
+///
+/// Usage: `record stop`
+#[poise::command(slash_command, prefix_command, guild_only, owners_only)]
+async fn stop(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let recorder = {
+        let guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
+        let mut mixer = guild_state.mixer_mut();
+
+        mixer.stop_recording()
+    };
+    let recorder = match recorder {
+        Some(recorder) => recorder,
+        None => {
+            ctx.reply("Not recording.").await?;
+            return Ok(());
+        }
+    };
+    let summary = tokio::task::spawn_blocking(move || recorder.stop()).await?;
+
+    let reply = match summary {
+        Ok(summary) => {
+            let mut reply = format!("Recorded {:.1} seconds to `{}`.", summary.seconds(),
+                summary.path.display());
+
+            if summary.limit_reached {
+                reply.push_str(" The recording was cut off at the size limit.");
+            }
+
+            if summary.dropped_samples > 0 {
+                reply.push_str(&format!(" {:.1} seconds of audio were dropped because writing \
+                    did not keep up.",
+                    summary.dropped_samples as f64 / rambot_api::SAMPLES_PER_SECOND as f64));
+            }
+
+            reply
+        },
+        Err(e) => format!("Error while writing the recording: {}", e)
+    };
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+/// Shows whether a recording is currently running.
+///
+/// Usage: `record status`
+#[poise::command(slash_command, prefix_command, guild_only, owners_only)]
+async fn status(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let recording = get_guild_state(ctx.data(), guild_id).await
+        .and_then(|gs| {
+            let mixer = gs.mixer_blocking();
+
+            mixer.recorder().map(|r| (r.path().display().to_string(), r.is_finished()))
+        });
+
+    let reply = match recording {
+        Some((path, false)) => format!("Recording to `{}`.", path),
+        Some((path, true)) => format!("Recording to `{}` stopped writing, either because the \
+            size limit was reached or because of an error. Use `record stop` to finalize it.", path),
+        None => "Not recording.".to_owned()
+    };
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
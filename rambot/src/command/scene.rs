@@ -0,0 +1,274 @@
+use crate::audio::Mixer;
+use crate::command::{
+    display_help,
+    get_guild_state,
+    get_guild_state_mut,
+    layer_error_callback,
+    respond,
+    start_playback,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command::local::{
+    log_layer_error,
+    LocalAction,
+    LocalArguments,
+    LocalContext,
+    LocalResult
+};
+use crate::scene::Scene;
+use crate::state::GuildState;
+
+use rambot_api::SAMPLES_PER_SECOND;
+
+use std::fmt::Write;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+/// Collection of commands for saving the configuration of the layers as scenes and recalling them.
+///
+/// A scene contains either all layers or a single one with their effects, adapters, and gain, and
+/// optionally the audio that was playing on them. To recall a scene with a board button, use
+/// `scene load <name>` as the command of the button.
+#[poise::command(slash_command, prefix_command, guild_only,
+    subcommands("capture", "list", "load", "remove", "save"))]
+pub async fn scene(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("scene")).await
+}
+
+fn save_scene(guild_state: &mut GuildState, name: String, layer: Option<String>,
+        include_playback: bool) -> CommandResponse {
+    let scene = {
+        let mut mixer = guild_state.mixer_mut();
+
+        match &layer {
+            Some(layer) if !mixer.contains_layer(layer) => None,
+            Some(layer) => Some(Scene::of_layer(&mut mixer, layer, include_playback)),
+            None => Some(Scene::of_mixer(&mut mixer, include_playback))
+        }
+    };
+
+    match scene {
+        Some(scene) => {
+            guild_state.scenes_mut().insert(name, scene);
+            CommandResponse::Confirm
+        },
+        None => CommandResponse::Reply(format!("No layer of name {}.", layer.unwrap()))
+    }
+}
+
+async fn save_do(ctx: Context<'_>, name: String, layer: Option<String>, include_playback: bool)
+        -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = save_scene(&mut *get_guild_state_mut(ctx.data(), guild_id).await, name, layer,
+        include_playback);
+
+    respond(ctx, response).await
+}
+
+fn save_scene_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>,
+        include_playback: bool) -> LocalResult {
+    let name = arguments.required()?;
+    let layer = arguments.next();
+
+    Ok(save_scene(&mut ctx.guild_state_mut(), name, layer, include_playback))
+}
+
+/// Saves the configuration of all layers or the given layer as a scene with the given name.
+///
+/// The scene contains the effects, adapters, and gain of the layers, but not the audio playing on
+/// them. Recalling it leaves the audio unchanged. A scene with the same name is replaced.
+///
+/// Usage: `scene save <name> [layer]`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(save_local))]
+async fn save(ctx: Context<'_>, name: String, layer: Option<String>) -> CommandResult {
+    save_do(ctx, name, layer, false).await
+}
+
+fn save_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    save_scene_local(ctx, arguments, false)
+}
+
+/// Saves the layers together with the audio playing on them as a scene with the given name.
+///
+/// Like `scene save`, this saves all layers or only the given one. Recalling the scene plays the
+/// audio again from the position it had when the scene was saved. A scene with the same name is
+/// replaced.
+///
+/// Usage: `scene capture <name> [layer]`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(capture_local))]
+async fn capture(ctx: Context<'_>, name: String, layer: Option<String>) -> CommandResult {
+    save_do(ctx, name, layer, true).await
+}
+
+fn capture_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    save_scene_local(ctx, arguments, true)
+}
+
+/// The result of recalling a scene: the mixer, whether it was active before and after, and the
+/// errors that occurred while playing the audio of the scene.
+type Recalled = (Arc<RwLock<Mixer>>, bool, bool, Vec<String>);
+
+/// Recalls the scene with the given name on the mixer of the given guild state, fading over the
+/// given crossfade duration in seconds.
+fn recall_scene<C>(guild_state: &GuildState, name: &str, crossfade: Option<f32>,
+    error_callback: C) -> Result<Recalled, String>
+where
+    C: Fn(String, io::Error) + Clone + Send + Sync + 'static
+{
+    let crossfade = crossfade.unwrap_or(0.0);
+
+    if !crossfade.is_finite() || crossfade < 0.0 {
+        return Err("The crossfade must be a non-negative number of seconds.".to_owned());
+    }
+
+    let crossfade = (crossfade * SAMPLES_PER_SECOND as f32) as usize;
+    let scene = guild_state.scenes().get(name)
+        .ok_or_else(|| format!("I found no scene with name `{}`.", name))?;
+    let plugin_guild_config = guild_state.build_plugin_guild_config();
+    let mixer = guild_state.mixer_arc();
+    let mut mixer_guard = mixer.write().unwrap();
+    let active_before = mixer_guard.active();
+    let errors = scene.recall(&mut mixer_guard, &plugin_guild_config, crossfade, error_callback);
+    let active_after = mixer_guard.active();
+    drop(mixer_guard);
+
+    Ok((mixer, active_before, active_after, errors))
+}
+
+/// Recalls the scene with the given name.
+///
+/// If the scene contains all layers, layers which are not part of it are removed. If a crossfade
+/// duration in seconds is given, audio that is removed or replaced fades out while the audio of
+/// the scene fades in over that duration.
+///
+/// Usage: `scene load <name> [crossfade]`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(load_local))]
+async fn load(ctx: Context<'_>, name: String, crossfade: Option<f32>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let error_callback = layer_error_callback(ctx);
+    let recalled = recall_scene(&*get_guild_state_mut(ctx.data(), guild_id).await, &name,
+        crossfade, error_callback);
+    let (mixer, active_before, active_after, mut errors) = match recalled {
+        Ok(recalled) => recalled,
+        Err(message) => {
+            ctx.reply(message).await?;
+            return Ok(());
+        }
+    };
+
+    // Connecting reads the guild state again, so it must not remain locked.
+
+    let response = if active_after {
+        start_playback(ctx, mixer, active_before).await
+    }
+    else {
+        CommandResponse::Confirm
+    };
+
+    if errors.is_empty() {
+        respond(ctx, response).await
+    }
+    else {
+        if let CommandResponse::Reply(reply) = response {
+            errors.push(reply);
+        }
+
+        ctx.reply(errors.join("\n")).await?;
+        Ok(())
+    }
+}
+
+fn load_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let name = arguments.required()?;
+    let crossfade = arguments.parse_optional::<f32>()?;
+    let (_, _, _, errors) =
+        recall_scene(&ctx.guild_state_mut(), &name, crossfade, log_layer_error)?;
+
+    if errors.is_empty() {
+        Ok(CommandResponse::Confirm)
+    }
+    else {
+        Ok(errors.join("\n").into())
+    }
+}
+
+fn scene_list(guild_state: &GuildState) -> String {
+    let mut reply = String::new();
+
+    for (name, scene) in guild_state.scenes() {
+        let mut properties = Vec::new();
+
+        if scene.is_partial() {
+            let layers = scene.layers().iter()
+                .map(|layer| layer.name())
+                .collect::<Vec<_>>();
+
+            properties.push(format!("layer {}", layers.join(", ")));
+        }
+
+        if scene.has_playback() {
+            properties.push("with audio".to_owned());
+        }
+
+        write!(reply, "\n- {}", name).unwrap();
+
+        if !properties.is_empty() {
+            write!(reply, " ({})", properties.join(", ")).unwrap();
+        }
+    }
+
+    if reply.is_empty() {
+        "There are no scenes.".to_owned()
+    }
+    else {
+        format!("Scenes:{}", reply)
+    }
+}
+
+/// Lists all scenes saved in this guild.
+///
+/// Usage: `scene list`
+#[poise::command(slash_command, prefix_command, guild_only, custom_data = LocalAction(list_local))]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let reply = get_guild_state(ctx.data(), guild_id).await
+        .map(|guild_state| scene_list(&guild_state))
+        .unwrap_or_else(|| "There are no scenes.".to_owned());
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+fn list_local(ctx: &mut LocalContext<'_>, _: &mut LocalArguments<'_>) -> LocalResult {
+    Ok(scene_list(ctx.guild_state()).into())
+}
+
+fn remove_scene(guild_state: &mut GuildState, name: &str) -> CommandResponse {
+    if guild_state.scenes_mut().remove(name).is_some() {
+        CommandResponse::Confirm
+    }
+    else {
+        CommandResponse::Reply(format!("I found no scene with name `{}`.", name))
+    }
+}
+
+/// Removes the scene with the given name.
+///
+/// Usage: `scene remove <name>`
+#[poise::command(slash_command, prefix_command, guild_only,
+    custom_data = LocalAction(remove_local))]
+async fn remove(ctx: Context<'_>, name: String) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let response = remove_scene(&mut *get_guild_state_mut(ctx.data(), guild_id).await, &name);
+
+    respond(ctx, response).await
+}
+
+fn remove_local(ctx: &mut LocalContext<'_>, arguments: &mut LocalArguments<'_>) -> LocalResult {
+    let name = arguments.required()?;
+
+    Ok(remove_scene(&mut ctx.guild_state_mut(), &name))
+}
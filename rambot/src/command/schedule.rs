@@ -0,0 +1,241 @@
+use crate::command::{
+    dispatch_command_as_message,
+    display_help,
+    get_guild_state,
+    get_guild_state_mut,
+    prepare_command_for_dispatch,
+    respond,
+    CommandData,
+    CommandError,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::schedule::{self, ScheduledCommand};
+
+use chrono::{Local, Utc};
+
+use poise::FrameworkContext;
+
+use serenity::gateway::ShardManager;
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::Context as SerenityContext;
+
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The interval in which the scheduler checks for due commands.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of seconds by which a scheduled command may be late, for example because the bot was
+/// offline, before it is dropped instead of executed.
+const MAX_LATENESS: i64 = 5 * 60;
+
+/// Collection of commands for executing commands later.
+///
+/// Scheduled commands are executed in the channel in which they were scheduled as if the user who
+/// scheduled them had sent them. They are kept when the bot restarts, but commands which are more
+/// than five minutes overdue when it is back online are dropped.
+#[poise::command(slash_command, prefix_command, guild_only,
+    subcommands("schedule_in", "at", "list", "cancel"))]
+pub async fn schedule(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("schedule")).await
+}
+
+async fn schedule_do(ctx: Context<'_>, time: i64, command: String) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let command = command.trim();
+    let id = get_guild_state_mut(ctx.data(), guild_id).await
+        .schedule_mut()
+        .add(time, command, ctx.channel_id(), ctx.author().id);
+
+    ctx.reply(format!("Scheduled `{}` as #{} for <t:{}:f>.", command, id, time)).await?;
+    Ok(())
+}
+
+/// Executes the given command after the given delay.
+///
+/// The delay consists of numbers followed by a unit, `s` for seconds, `m` for minutes, `h` for
+/// hours, or `d` for days, for example `10m` or `1h30m`.
+///
+/// Usage: `schedule in <delay> <command>`
+#[poise::command(slash_command, prefix_command, guild_only, rename = "in")]
+async fn schedule_in(ctx: Context<'_>, delay: String, #[rest] command: String) -> CommandResult {
+    let delay = match schedule::parse_delay(&delay) {
+        Some(delay) => delay,
+        None => {
+            ctx.reply(format!("`{}` is not a valid delay, such as `10m` or `1h30m`.", delay))
+                .await?;
+            return Ok(());
+        }
+    };
+    let time = i64::try_from(delay.as_secs()).ok()
+        .and_then(|delay| Utc::now().timestamp().checked_add(delay));
+    let time = match time {
+        Some(time) => time,
+        None => {
+            ctx.reply("The delay is too long.").await?;
+            return Ok(());
+        }
+    };
+
+    schedule_do(ctx, time, command).await
+}
+
+/// Executes the given command at the next time the clock shows the given time of day.
+///
+/// The time is given as `HH:MM` in the time zone of the bot. If it has already passed today, the
+/// command is executed tomorrow.
+///
+/// Usage: `schedule at <time> <command>`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn at(ctx: Context<'_>, time: String, #[rest] command: String) -> CommandResult {
+    let time = match schedule::next_time_of_day(&time, &Local::now()) {
+        Some(time) => time.timestamp(),
+        None => {
+            ctx.reply(format!("`{}` is not a valid time, such as `20:00`.", time)).await?;
+            return Ok(());
+        }
+    };
+
+    schedule_do(ctx, time, command).await
+}
+
+/// Lists all commands scheduled in this guild with their numbers and times.
+///
+/// Usage: `schedule list`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let reply = {
+        let mut reply = String::new();
+
+        if let Some(guild_state) = get_guild_state(ctx.data(), guild_id).await {
+            for command in guild_state.schedule().commands() {
+                write!(reply, "\n- #{} <t:{}:f> (<t:{}:R>): `{}`", command.id(), command.time(),
+                    command.time(), command.command()).unwrap();
+            }
+        }
+
+        if reply.is_empty() {
+            "There are no scheduled commands.".to_owned()
+        }
+        else {
+            format!("Scheduled commands:{}", reply)
+        }
+    };
+
+    ctx.reply(reply).await?;
+    Ok(())
+}
+
+/// Cancels the scheduled command with the given number, as shown by `schedule list`.
+///
+/// Usage: `schedule cancel <number>`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn cancel(ctx: Context<'_>, number: u64) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let cancelled = get_guild_state_mut(ctx.data(), guild_id).await
+        .schedule_mut()
+        .cancel(number)
+        .is_some();
+    let response = if cancelled {
+        CommandResponse::Confirm
+    }
+    else {
+        CommandResponse::Reply(format!("I found no scheduled command with number {}.", number))
+    };
+
+    respond(ctx, response).await
+}
+
+/// Removes all commands which are due from the schedules of all guilds.
+async fn take_due_commands(data: &CommandData, now: i64) -> Vec<(GuildId, ScheduledCommand)> {
+    let guild_ids = {
+        let state = data.state().await;
+
+        state.guild_ids()
+            .filter(|&guild_id| state.guild_state(guild_id).unwrap().schedule().has_due(now))
+            .collect::<Vec<_>>()
+    };
+
+    if guild_ids.is_empty() {
+        return Vec::new();
+    }
+
+    let plugin_manager = data.plugin_manager_arc();
+    let mut state = data.state_mut().await;
+    let mut due = Vec::new();
+
+    for guild_id in guild_ids {
+        let mut guild_state = state.guild_state_mut(guild_id, &plugin_manager);
+
+        due.extend(guild_state.schedule_mut().take_due(now).into_iter()
+            .map(|command| (guild_id, command)));
+    }
+
+    due
+}
+
+/// Executes a scheduled command by announcing it in its channel and dispatching it as that
+/// announcement, attributed to the user who scheduled it, just like a board button does.
+async fn execute(serenity_ctx: &SerenityContext,
+        framework_ctx: FrameworkContext<'_, CommandData, CommandError>, guild_id: GuildId,
+        command: ScheduledCommand) -> CommandResult {
+    let user_data = framework_ctx.user_data;
+    let author = command.author().to_user(serenity_ctx).await?;
+    let mut msg = command.channel()
+        .say(serenity_ctx, format!("Running scheduled command `{}`.", command.command()))
+        .await?;
+    let guild_prefix = get_guild_state(user_data, guild_id).await
+        .and_then(|guild_state| guild_state.settings().prefix().map(str::to_owned));
+
+    msg.content = prepare_command_for_dispatch(
+        command.command(), guild_prefix.as_deref(), user_data.config());
+    msg.author = author;
+    msg.webhook_id = None;
+    msg.guild_id = Some(guild_id);
+
+    dispatch_command_as_message(framework_ctx, serenity_ctx, &msg).await
+}
+
+/// Runs the scheduler, which executes scheduled commands when they are due, until the bot stops.
+/// This is meant to be spawned as a task once the bot is ready.
+///
+/// # Arguments
+///
+/// * `serenity_ctx`: The serenity context with which commands are executed.
+/// * `data`: The [CommandData] on which commands operate.
+/// * `bot_id`: The user ID of the bot.
+/// * `shard_manager`: The shard manager of the framework.
+pub async fn run_scheduler(serenity_ctx: SerenityContext, data: CommandData, bot_id: UserId,
+        shard_manager: Arc<ShardManager>) {
+    let framework_ctx = FrameworkContext {
+        bot_id,
+        options: data.programmatic_command_framework_options(),
+        user_data: &data,
+        shard_manager: &shard_manager
+    };
+    let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now().timestamp();
+
+        for (guild_id, command) in take_due_commands(&data, now).await {
+            if now - command.time() > MAX_LATENESS {
+                log::warn!("Dropped scheduled command `{}` in guild {}, which is overdue.",
+                    command.command(), guild_id);
+                continue;
+            }
+
+            let description = format!("`{}` in guild {}", command.command(), guild_id);
+
+            if let Err(e) = execute(&serenity_ctx, framework_ctx, guild_id, command).await {
+                log::warn!("Error executing scheduled command {}: {}", description, e);
+            }
+        }
+    }
+}
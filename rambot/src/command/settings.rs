@@ -0,0 +1,215 @@
+use crate::command::{
+    confirm,
+    display_help,
+    get_guild_state,
+    get_guild_state_mut,
+    guild_settings,
+    respond,
+    CommandError,
+    CommandResponse,
+    CommandResult,
+    Context
+};
+use crate::command_data::CommandData;
+use crate::settings::{ConfirmationStyle, Language};
+
+use poise::PartialContext;
+
+/// Collection of commands for customizing how the bot behaves in this guild.
+///
+/// The settings comprise a command prefix which overrides the global one, a default layer for
+/// `play`, the language of status messages, the way in which successful commands are confirmed, and
+/// when the bot leaves the voice channel on its own.
+#[poise::command(slash_command, prefix_command, guild_only,
+    subcommands("alone", "confirmation", "idle", "layer", "list", "prefix", "status_language"))]
+pub async fn settings(ctx: Context<'_>) -> CommandResult {
+    display_help(ctx, Some("settings")).await
+}
+
+/// The dynamic prefix of the framework, which is the prefix set in the settings of the guild in
+/// which a message was sent or the globally configured prefix if there is none.
+pub async fn guild_prefix(ctx: PartialContext<'_, CommandData, CommandError>)
+        -> Result<Option<String>, CommandError> {
+    let guild_prefix = match ctx.guild_id {
+        Some(guild_id) => get_guild_state(ctx.data, guild_id).await
+            .and_then(|guild_state| guild_state.settings().prefix().map(str::to_owned)),
+        None => None
+    };
+
+    Ok(guild_prefix.or_else(|| ctx.data.config().prefix().map(str::to_owned)))
+}
+
+/// Sets the prefix of commands in this guild, which replaces the globally configured prefix.
+///
+/// Omit the prefix to use the global prefix again. The prefix may not contain spaces.
+///
+/// Usage: `settings prefix [prefix]`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn prefix(ctx: Context<'_>, prefix: Option<String>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let result = get_guild_state_mut(ctx.data(), guild_id).await
+        .settings_mut()
+        .set_prefix(prefix);
+
+    let response = match result {
+        Ok(()) => CommandResponse::Confirm,
+        Err(message) => CommandResponse::Reply(message)
+    };
+
+    respond(ctx, response).await
+}
+
+/// Sets the default layer, on which `play` plays audio if no layer is given.
+///
+/// Omit the layer to require a layer for `play` again.
+///
+/// Usage: `settings layer [layer]`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn layer(ctx: Context<'_>, layer: Option<String>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let found = {
+        let mut guild_state = get_guild_state_mut(ctx.data(), guild_id).await;
+        let found = layer.as_ref()
+            .map(|layer| guild_state.mixer_blocking().contains_layer(layer))
+            .unwrap_or(true);
+
+        if found {
+            guild_state.settings_mut().set_default_layer(layer.clone());
+        }
+
+        found
+    };
+
+    if found {
+        confirm(ctx).await
+    }
+    else {
+        respond(ctx, format!("Found no layer with name {}.", layer.unwrap()).into()).await
+    }
+}
+
+/// Sets the language of the status messages of the bot in this guild.
+///
+/// Supported languages are `english` (the default) and `german`. Only the confirmation message,
+/// the rejection of commands which may not be used, and the messages about connecting to voice
+/// channels are translated. All other replies are in English.
+///
+/// Usage: `settings status-language <english|german>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD",
+    rename = "status-language")]
+async fn status_language(ctx: Context<'_>, language: String) -> CommandResult {
+    let language = match language.parse::<Language>() {
+        Ok(language) => language,
+        Err(e) => {
+            ctx.reply(format!("{}", e)).await?;
+            return Ok(());
+        }
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    get_guild_state_mut(ctx.data(), guild_id).await
+        .settings_mut()
+        .set_status_language(language);
+
+    confirm(ctx).await
+}
+
+/// Sets the way in which the bot confirms successful commands in this guild.
+///
+/// With `reaction` (the default), the bot reacts to the command with an emoji. With `message`, it
+/// replies with a short text. With `silent`, it does not confirm commands at all.
+///
+/// Usage: `settings confirmation <reaction|message|silent>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn confirmation(ctx: Context<'_>, style: String) -> CommandResult {
+    let style = match style.parse::<ConfirmationStyle>() {
+        Ok(style) => style,
+        Err(e) => {
+            ctx.reply(format!("{}", e)).await?;
+            return Ok(());
+        }
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    get_guild_state_mut(ctx.data(), guild_id).await
+        .settings_mut()
+        .set_confirmation_style(style);
+
+    confirm(ctx).await
+}
+
+/// Sets the number of minutes after which the bot leaves the voice channel if no audio is playing.
+///
+/// Omit the number to stay connected until `disconnect` is used.
+///
+/// Usage: `settings idle [minutes]`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn idle(ctx: Context<'_>, minutes: Option<u32>) -> CommandResult {
+    let guild_id = ctx.guild_id().unwrap();
+    let result = get_guild_state_mut(ctx.data(), guild_id).await
+        .settings_mut()
+        .set_idle_timeout(minutes);
+
+    let response = match result {
+        Ok(()) => CommandResponse::Confirm,
+        Err(message) => CommandResponse::Reply(message)
+    };
+
+    respond(ctx, response).await
+}
+
+/// Sets whether the bot leaves the voice channel once no one but bots remains in it.
+///
+/// With `leave`, the bot leaves a voice channel without listeners. With `stay` (the default), it
+/// stays connected until `disconnect` is used or the idle timeout is reached.
+///
+/// Usage: `settings alone <leave|stay>`
+#[poise::command(slash_command, prefix_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn alone(ctx: Context<'_>, behavior: String) -> CommandResult {
+    let leave_when_alone = match behavior.to_ascii_lowercase().as_str() {
+        "leave" => true,
+        "stay" => false,
+        _ => {
+            ctx.reply(format!("Unknown behavior `{}`. Expected `leave` or `stay`.", behavior))
+                .await?;
+            return Ok(());
+        }
+    };
+    let guild_id = ctx.guild_id().unwrap();
+
+    get_guild_state_mut(ctx.data(), guild_id).await
+        .settings_mut()
+        .set_leave_when_alone(leave_when_alone);
+
+    confirm(ctx).await
+}
+
+/// Prints the current settings of this guild.
+///
+/// Usage: `settings list`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn list(ctx: Context<'_>) -> CommandResult {
+    let settings = guild_settings(ctx).await;
+    let prefix = match settings.effective_prefix(ctx.data().config().prefix()) {
+        Some(prefix) if settings.prefix().is_some() => format!("`{}`", prefix),
+        Some(prefix) => format!("`{}` (global)", prefix),
+        None => "none".to_owned()
+    };
+    let default_layer = settings.default_layer().unwrap_or("none");
+    let idle_timeout = match settings.idle_timeout() {
+        Some(minutes) => format!("{} min", minutes),
+        None => "none".to_owned()
+    };
+    let alone = if settings.leave_when_alone() {
+        "leave"
+    }
+    else {
+        "stay"
+    };
+    let response = format!("Prefix: {}\nDefault layer: {}\nStatus language: {}\nConfirmation style: {}\n\
+        Idle timeout: {}\nWhen alone: {}", prefix, default_layer, settings.status_language(),
+        settings.confirmation_style(), idle_timeout, alone);
+
+    ctx.reply(response).await?;
+    Ok(())
+}
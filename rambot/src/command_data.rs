@@ -6,12 +6,14 @@ use crate::config::Config;
 use crate::plugin::PluginManager;
 use crate::state::State;
 
-/// Manages access of commands to shared data.
+/// Manages access of commands to shared data. Clones are cheap and share all data, which allows
+/// background tasks, such as the scheduler, to own their access.
+#[derive(Clone)]
 pub struct CommandData {
-    config: Config,
+    config: Arc<Config>,
     plugin_manager: Arc<PluginManager>,
-    state: RwLock<State>,
-    programmatic_command_framework_options: FrameworkOptions<CommandData, CommandError>
+    state: Arc<RwLock<State>>,
+    programmatic_command_framework_options: Arc<FrameworkOptions<CommandData, CommandError>>
 }
 
 impl CommandData {
@@ -34,10 +36,10 @@ impl CommandData {
         programmatic_command_framework_options: FrameworkOptions<CommandData, CommandError>
     ) -> CommandData {
         CommandData {
-            config,
+            config: Arc::new(config),
             plugin_manager: plugin_mgr,
-            state: RwLock::new(state),
-            programmatic_command_framework_options
+            state: Arc::new(RwLock::new(state)),
+            programmatic_command_framework_options: Arc::new(programmatic_command_framework_options)
         }
     }
 
@@ -66,6 +68,12 @@ impl CommandData {
         self.state.write().await
     }
 
+    /// Clones the arc to the lock of the mutable state shared by commands. This
+    /// allows other front-ends, such as the web API, to access the state.
+    pub fn state_arc(&self) -> Arc<RwLock<State>> {
+        Arc::clone(&self.state)
+    }
+
     /// Gets the framework options to use for programmatic command execution. This is different to
     /// the ordinary framework options as commands have to be invokable by their prefix action, even
     /// if prefix commands are disabled.
@@ -7,33 +7,85 @@ use serenity::model::prelude::UserId;
 use simplelog::LevelFilter;
 
 use std::env;
+use std::ffi::OsStr;
 use std::fmt::{self, Display, Formatter};
-use std::fs::{self, File};
-use std::io::{self, BufRead};
-use std::path::Path;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 const CONFIG_FILE_NAME: &str = "config.json";
+const TOML_CONFIG_FILE_NAME: &str = "config.toml";
 const DEFAULT_PREFIX: &str = "!";
 const DEFAULT_ALLOW_SLASH_COMMANDS: bool = true;
-const DEFAULT_PLUGIN_DIRECTORY: &str = "plugins";
-const DEFAULT_PLUGIN_CONFIG_DIRECTORY: &str = "plugins/config";
+
+/// The plugin directory used if none is specified.
+pub const DEFAULT_PLUGIN_DIRECTORY: &str = "plugins";
+
+/// The plugin config directory used if none is specified.
+pub const DEFAULT_PLUGIN_CONFIG_DIRECTORY: &str = "plugins/config";
+
 const DEFAULT_STATE_DIRECTORY: &str = "state";
 const DEFAULT_ALLOW_WEB_ACCESS: bool = true;
 const DEFAULT_LOG_LEVEL_FILTER: LevelFilter = LevelFilter::Info;
+const DEFAULT_RECORDINGS_DIRECTORY: &str = "recordings";
+const DEFAULT_MAX_RECORDING_SIZE_MB: u64 = 1024;
+const DEFAULT_WEB_API_ADDRESS: &str = "127.0.0.1:8080";
+const DEFAULT_OSC_ADDRESS: &str = "127.0.0.1:9000";
+const DEFAULT_RESUME_PLAYBACK: bool = false;
+
+/// The environment variable which specifies the path of the config file if
+/// none is given on the command line.
+pub const CONFIG_PATH_VARIABLE: &str = "RAMBOT_CONFIG";
+
+const PREFIX_VARIABLE: &str = "RAMBOT_PREFIX";
+const ALLOW_SLASH_COMMANDS_VARIABLE: &str = "RAMBOT_ALLOW_SLASH_COMMANDS";
+const TOKEN_VARIABLE: &str = "RAMBOT_TOKEN";
+const OWNERS_VARIABLE: &str = "RAMBOT_OWNERS";
+const PLUGIN_DIRECTORY_VARIABLE: &str = "RAMBOT_PLUGIN_DIRECTORY";
+const PLUGIN_CONFIG_DIRECTORY_VARIABLE: &str =
+    "RAMBOT_PLUGIN_CONFIG_DIRECTORY";
+const STATE_DIRECTORY_VARIABLE: &str = "RAMBOT_STATE_DIRECTORY";
+const ROOT_DIRECTORY_VARIABLE: &str = "RAMBOT_ROOT_DIRECTORY";
+const ALLOW_WEB_ACCESS_VARIABLE: &str = "RAMBOT_ALLOW_WEB_ACCESS";
+const LOG_LEVEL_FILTER_VARIABLE: &str = "RAMBOT_LOG_LEVEL_FILTER";
+const RECORDINGS_DIRECTORY_VARIABLE: &str = "RAMBOT_RECORDINGS_DIRECTORY";
+const MAX_RECORDING_SIZE_MB_VARIABLE: &str = "RAMBOT_MAX_RECORDING_SIZE_MB";
+const WEB_API_ADDRESS_VARIABLE: &str = "RAMBOT_WEB_API_ADDRESS";
+const WEB_API_TOKEN_VARIABLE: &str = "RAMBOT_WEB_API_TOKEN";
+const OSC_ADDRESS_VARIABLE: &str = "RAMBOT_OSC_ADDRESS";
+const RESUME_PLAYBACK_VARIABLE: &str = "RAMBOT_RESUME_PLAYBACK";
 
 /// An enumeration of the different errors that can occur when loading the configuration.
+#[derive(Debug)]
 pub enum ConfigError {
 
-    /// Indicates that the path of the config file is currently occupied by a directory of the same
-    /// name.
-    OccupiedByDirectory,
+    /// Indicates that the path of the config file, which is provided, is currently occupied by a
+    /// directory of the same name.
+    OccupiedByDirectory(PathBuf),
 
     /// Wraps an [IO error](io::Error) that occurred while loading or saving the file.
     IOError(io::Error),
 
     /// Wraps a [JSON error](serde_json::Error) that occurred during serialization or
     /// deserialization of the configuration file.
-    JSONError(serde_json::Error)
+    JSONError(serde_json::Error),
+
+    /// Wraps a [TOML error](toml::de::Error) that occurred during deserialization of the
+    /// configuration file.
+    TOMLError(toml::de::Error),
+
+    /// Wraps a [TOML error](toml::ser::Error) that occurred during serialization of the
+    /// configuration file.
+    TOMLSerializationError(toml::ser::Error),
+
+    /// Indicates that the environment variable with the provided name has a value which cannot be
+    /// parsed. The value is provided as well.
+    InvalidEnvironmentVariable(&'static str, String),
+
+    /// Indicates that the configuration could be loaded, but some values are invalid. Contains a
+    /// description of every problem that was found.
+    Invalid(Vec<String>)
 }
 
 impl From<io::Error> for ConfigError {
@@ -48,16 +100,44 @@ impl From<serde_json::Error> for ConfigError {
     }
 }
 
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> ConfigError {
+        ConfigError::TOMLError(e)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(e: toml::ser::Error) -> ConfigError {
+        ConfigError::TOMLSerializationError(e)
+    }
+}
+
 impl Display for ConfigError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            ConfigError::OccupiedByDirectory =>
+            ConfigError::OccupiedByDirectory(path) =>
                 write!(f,
                     "The config file name ({}) is occupied by a directory.",
-                    CONFIG_FILE_NAME),
+                    path.display()),
             ConfigError::IOError(e) => write!(f, "{}", e),
             ConfigError::JSONError(e) =>
-                write!(f, "Error while parsing the configuration file: {}", e)
+                write!(f, "Error while parsing the configuration file: {}", e),
+            ConfigError::TOMLError(e) =>
+                write!(f, "Error while parsing the configuration file: {}", e),
+            ConfigError::TOMLSerializationError(e) =>
+                write!(f, "Error while writing the configuration file: {}", e),
+            ConfigError::InvalidEnvironmentVariable(name, value) =>
+                write!(f, "Invalid value of environment variable {}: {}",
+                    name, value),
+            ConfigError::Invalid(problems) => {
+                write!(f, "The configuration is invalid:")?;
+
+                for problem in problems {
+                    write!(f, "\n- {}", problem)?;
+                }
+
+                Ok(())
+            }
         }
     }
 }
@@ -115,11 +195,169 @@ where
     Ok(ConfigLevelFilter::deserialize(deserializer)?.into())
 }
 
+/// Generates the [PluginConfig] to pass to a plugin loaded from a library
+/// with the given name.
+///
+/// # Arguments
+///
+/// * `library`: The file name (without preceding directories, but with
+///   extension) of the library that contains the plugin.
+/// * `plugin_config_directory`: The directory in which plugins put their
+///   specific config files.
+/// * `root_directory`: The root directory for file system based plugins.
+/// * `allow_web_access`: Whether plugins may access the internet.
+pub fn plugin_config_for_library(library: &str, plugin_config_directory: &str,
+        root_directory: &str, allow_web_access: bool) -> PluginConfig {
+    let config_path = format!("{}/{}.config", plugin_config_directory, library);
+
+    PluginConfig::new(root_directory, allow_web_access, config_path)
+}
+
+fn default_recordings_directory() -> String {
+    DEFAULT_RECORDINGS_DIRECTORY.to_owned()
+}
+
+fn default_max_recording_size_mb() -> u64 {
+    DEFAULT_MAX_RECORDING_SIZE_MB
+}
+
+fn default_web_api_address() -> String {
+    DEFAULT_WEB_API_ADDRESS.to_owned()
+}
+
+/// The configuration of the HTTP/WebSocket control API.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebApiConfig {
+    #[serde(default = "default_web_api_address")]
+    address: String,
+    token: String
+}
+
+impl WebApiConfig {
+
+    /// Gets the socket address to which the web API is bound. By default, it
+    /// is only reachable from localhost.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Gets the token which clients must provide to use the web API.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+fn default_osc_address() -> String {
+    DEFAULT_OSC_ADDRESS.to_owned()
+}
+
+/// The configuration of the OSC (Open Sound Control) listener.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OscConfig {
+    #[serde(default = "default_osc_address")]
+    address: String
+}
+
+impl OscConfig {
+
+    /// Gets the socket address on which the OSC listener receives UDP
+    /// packets. OSC has no authentication, so by default it is only reachable
+    /// from localhost.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|extension| extension.eq_ignore_ascii_case("toml"))
+        .unwrap_or(false)
+}
+
+/// Determines the path of the config file. If no path is given, this is
+/// `config.json`, unless only `config.toml` exists.
+fn resolve_path(path: Option<&str>) -> PathBuf {
+    match path {
+        Some(path) => PathBuf::from(path),
+        None if !Path::new(CONFIG_FILE_NAME).exists() &&
+                Path::new(TOML_CONFIG_FILE_NAME).is_file() =>
+            PathBuf::from(TOML_CONFIG_FILE_NAME),
+        None => PathBuf::from(CONFIG_FILE_NAME)
+    }
+}
+
+fn parse_variable<T, F>(name: &'static str, value: String, parse: F)
+    -> Result<T, ConfigError>
+where
+    F: FnOnce(&str) -> Option<T>
+{
+    match parse(value.trim()) {
+        Some(parsed) => Ok(parsed),
+        None => Err(ConfigError::InvalidEnvironmentVariable(name, value))
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None
+    }
+}
+
+fn parse_owners(value: &str) -> Option<Vec<UserId>> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse::<u64>().ok()
+            .filter(|&id| id != 0)
+            .map(UserId::new))
+        .collect()
+}
+
+fn check_directory(problems: &mut Vec<String>, name: &str, directory: &str,
+        must_exist: bool) {
+    let path = Path::new(directory);
+
+    if directory.is_empty() {
+        problems.push(format!("The {} must not be empty.", name));
+    }
+    else if path.exists() && !path.is_dir() {
+        problems.push(
+            format!("The {} `{}` is not a directory.", name, directory));
+    }
+    else if must_exist && !path.exists() {
+        problems.push(
+            format!("The {} `{}` does not exist.", name, directory));
+    }
+}
+
+fn check_address(problems: &mut Vec<String>, name: &str, address: &str) {
+    if address.parse::<SocketAddr>().is_err() {
+        problems.push(format!(
+            "The {} `{}` is not a valid socket address, such as \
+                `127.0.0.1:8080`.", name, address));
+    }
+}
+
 /// The configuration data of the bot.
+///
+/// It is read from a JSON file or, if the file name ends in `.toml`, a TOML
+/// file. Fields which are missing in the file take their default value,
+/// except for a missing `prefix`, which disables prefix commands. Every field
+/// can be overridden by an environment variable, which is named like the
+/// field in upper case with the prefix `RAMBOT_`, for example `RAMBOT_TOKEN`
+/// or `RAMBOT_STATE_DIRECTORY`. Owners are given as a
+/// comma-separated list of user IDs and an empty `RAMBOT_PREFIX` disables
+/// prefix commands. The web API and OSC listener are configured with
+/// `RAMBOT_WEB_API_TOKEN`, `RAMBOT_WEB_API_ADDRESS`, and
+/// `RAMBOT_OSC_ADDRESS`.
 #[derive(Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     prefix: Option<String>,
     allow_slash_commands: bool,
     token: String,
@@ -132,56 +370,322 @@ pub struct Config {
 
     #[serde(serialize_with = "serialize_level_filter")]
     #[serde(deserialize_with = "deserialize_level_filter")]
-    log_level_filter: LevelFilter
+    log_level_filter: LevelFilter,
+
+    #[serde(default = "default_recordings_directory")]
+    recordings_directory: String,
+
+    #[serde(default = "default_max_recording_size_mb")]
+    max_recording_size_mb: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    web_api: Option<WebApiConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    osc: Option<OscConfig>,
+
+    resume_playback: bool
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let root_directory = env::current_dir().ok()
+            .and_then(|directory| directory.to_str().map(str::to_owned))
+            .unwrap_or_else(|| ".".to_owned());
+
+        Config {
+            prefix: Some(DEFAULT_PREFIX.to_owned()),
+            allow_slash_commands: DEFAULT_ALLOW_SLASH_COMMANDS,
+            token: String::new(),
+            owners: Vec::new(),
+            plugin_directory: DEFAULT_PLUGIN_DIRECTORY.to_owned(),
+            plugin_config_directory: DEFAULT_PLUGIN_CONFIG_DIRECTORY.to_owned(),
+            state_directory: DEFAULT_STATE_DIRECTORY.to_owned(),
+            root_directory,
+            allow_web_access: DEFAULT_ALLOW_WEB_ACCESS,
+            log_level_filter: DEFAULT_LOG_LEVEL_FILTER,
+            recordings_directory: default_recordings_directory(),
+            max_recording_size_mb: default_max_recording_size_mb(),
+            web_api: None,
+            osc: None,
+            resume_playback: DEFAULT_RESUME_PLAYBACK
+        }
+    }
 }
 
 impl Config {
 
-    /// Loads the config file or, if it is not present, creates a default
-    /// config and stores it to the file.
-    pub fn load() -> Result<Config, ConfigError> {
-        let path = Path::new(CONFIG_FILE_NAME);
+    /// Loads the config file, applies the overrides from environment
+    /// variables, and validates the result.
+    ///
+    /// If the config file is not present, the default config is used. In
+    /// that case, if no token is given by the `RAMBOT_TOKEN` environment
+    /// variable and the bot is run in a terminal, the user is asked for the
+    /// token and a new config file is created.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the config file. If `None`, `config.json` is
+    ///   used, or `config.toml` if only that one exists.
+    ///
+    /// # Errors
+    ///
+    /// Any [ConfigError] according to their respective documentation. In
+    /// particular, [ConfigError::Invalid] if no token is configured.
+    pub fn load(path: Option<&str>) -> Result<Config, ConfigError> {
+        let path = resolve_path(path);
+        let config = match Config::read(&path)? {
+            Some(config) => config,
+            None if env::var_os(TOKEN_VARIABLE).is_none() &&
+                    io::stdin().is_terminal() =>
+                Config::create_interactively(&path)?,
+            None => {
+                log::info!("No config file was found. Using the default \
+                    config.");
+
+                Config::default()
+            }
+        };
+
+        config.finish(|name| env::var(name).ok(), true)
+    }
+
+    /// Loads the config file for running the bot locally, without a
+    /// connection to Discord. If the file is not present, the default config
+    /// is used. In contrast to [Config::load], this never prompts for a token,
+    /// never creates a config file, and does not require a token.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the config file. If `None`, `config.json` is
+    ///   used, or `config.toml` if only that one exists.
+    ///
+    /// # Errors
+    ///
+    /// Any [ConfigError] according to their respective documentation.
+    pub fn load_local(path: Option<&str>) -> Result<Config, ConfigError> {
+        let path = resolve_path(path);
+        let config = match Config::read(&path)? {
+            Some(config) => config,
+            None => {
+                log::info!("No config file was found. Using the default \
+                    config.");
+
+                Config::default()
+            }
+        };
+
+        config.finish(|name| env::var(name).ok(), false)
+    }
 
+    /// Reads the config file at the given path, or returns `None` if it does
+    /// not exist.
+    fn read(path: &Path) -> Result<Option<Config>, ConfigError> {
         if path.is_dir() {
-            Err(ConfigError::OccupiedByDirectory)
+            Err(ConfigError::OccupiedByDirectory(path.to_owned()))
         }
         else if path.is_file() {
-            let json = fs::read_to_string(path)?;
-            Ok(serde_json::from_str(&json)?)
+            let content = fs::read_to_string(path)?;
+
+            if is_toml(path) {
+                Ok(Some(toml::from_str(&content)?))
+            }
+            else {
+                Ok(Some(serde_json::from_str(&content)?))
+            }
         }
         else {
-            log::info!("No config file was found. A new one will be created.");
-            println!("Please specify the Discord API token below.");
-
-            let stdin = io::stdin();
-            let token = stdin.lock().lines().next().unwrap()?;
-            let root_directory = env::current_dir()?
-                .as_os_str()
-                .to_str()
-                .unwrap()
-                .to_owned();
-            let config = Config {
-                prefix: Some(DEFAULT_PREFIX.to_owned()),
-                allow_slash_commands: DEFAULT_ALLOW_SLASH_COMMANDS,
-                token,
-                owners: Vec::new(),
-                plugin_directory: DEFAULT_PLUGIN_DIRECTORY.to_owned(),
-                plugin_config_directory:
-                    DEFAULT_PLUGIN_CONFIG_DIRECTORY.to_owned(),
-                state_directory: DEFAULT_STATE_DIRECTORY.to_owned(),
-                root_directory,
-                allow_web_access: DEFAULT_ALLOW_WEB_ACCESS,
-                log_level_filter: DEFAULT_LOG_LEVEL_FILTER
-            };
-            let file = File::create(path)?;
-            serde_json::to_writer(file, &config)?;
-
-            log::info!("New config file successfully created.");
-            log::info!("If you want to use owner-only commands, specify the \
-                owners' user IDs in the config file.");
-
-            Ok(config)
+            Ok(None)
+        }
+    }
+
+    fn create_interactively(path: &Path) -> Result<Config, ConfigError> {
+        log::info!("No config file was found. A new one will be created.");
+        println!("Please specify the Discord API token below.");
+
+        let stdin = io::stdin();
+        let token = stdin.lock().lines().next().unwrap()?;
+        let config = Config {
+            token,
+            ..Config::default()
+        };
+        let content = if is_toml(path) {
+            toml::to_string(&config)?
         }
+        else {
+            serde_json::to_string(&config)?
+        };
+
+        fs::write(path, content)?;
+
+        log::info!("New config file successfully created.");
+        log::info!("If you want to use owner-only commands, specify the \
+            owners' user IDs in the config file.");
+
+        Ok(config)
+    }
+
+    fn finish<V>(mut self, variable: V, require_token: bool)
+        -> Result<Config, ConfigError>
+    where
+        V: Fn(&str) -> Option<String>
+    {
+        self.apply_environment(variable)?;
+
+        let problems = self.problems(require_token);
+
+        if problems.is_empty() {
+            Ok(self)
+        }
+        else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Overrides the fields of this config for which an environment variable
+    /// is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `variable`: Gets the value of the environment variable with the
+    ///   given name, or `None` if it is not set.
+    ///
+    /// # Errors
+    ///
+    /// [ConfigError::InvalidEnvironmentVariable] if the value of a variable
+    /// cannot be parsed.
+    fn apply_environment<V>(&mut self, variable: V) -> Result<(), ConfigError>
+    where
+        V: Fn(&str) -> Option<String>
+    {
+        if let Some(prefix) = variable(PREFIX_VARIABLE) {
+            self.prefix = Some(prefix).filter(|prefix| !prefix.is_empty());
+        }
+
+        if let Some(value) = variable(ALLOW_SLASH_COMMANDS_VARIABLE) {
+            self.allow_slash_commands = parse_variable(
+                ALLOW_SLASH_COMMANDS_VARIABLE, value, parse_bool)?;
+        }
+
+        if let Some(token) = variable(TOKEN_VARIABLE) {
+            self.token = token;
+        }
+
+        if let Some(value) = variable(OWNERS_VARIABLE) {
+            self.owners = parse_variable(OWNERS_VARIABLE, value, parse_owners)?;
+        }
+
+        let directories = [
+            (PLUGIN_DIRECTORY_VARIABLE, &mut self.plugin_directory),
+            (PLUGIN_CONFIG_DIRECTORY_VARIABLE,
+                &mut self.plugin_config_directory),
+            (STATE_DIRECTORY_VARIABLE, &mut self.state_directory),
+            (ROOT_DIRECTORY_VARIABLE, &mut self.root_directory),
+            (RECORDINGS_DIRECTORY_VARIABLE, &mut self.recordings_directory)
+        ];
+
+        for (name, directory) in directories {
+            if let Some(value) = variable(name) {
+                *directory = value;
+            }
+        }
+
+        if let Some(value) = variable(ALLOW_WEB_ACCESS_VARIABLE) {
+            self.allow_web_access =
+                parse_variable(ALLOW_WEB_ACCESS_VARIABLE, value, parse_bool)?;
+        }
+
+        if let Some(value) = variable(LOG_LEVEL_FILTER_VARIABLE) {
+            self.log_level_filter = parse_variable(LOG_LEVEL_FILTER_VARIABLE,
+                value, |value| value.parse().ok())?;
+        }
+
+        if let Some(value) = variable(MAX_RECORDING_SIZE_MB_VARIABLE) {
+            self.max_recording_size_mb = parse_variable(
+                MAX_RECORDING_SIZE_MB_VARIABLE, value,
+                |value| value.parse().ok())?;
+        }
+
+        let web_api_token = variable(WEB_API_TOKEN_VARIABLE);
+        let web_api_address = variable(WEB_API_ADDRESS_VARIABLE);
+
+        if web_api_token.is_some() || web_api_address.is_some() {
+            let web_api = self.web_api.get_or_insert_with(|| WebApiConfig {
+                address: default_web_api_address(),
+                token: String::new()
+            });
+
+            if let Some(token) = web_api_token {
+                web_api.token = token;
+            }
+
+            if let Some(address) = web_api_address {
+                web_api.address = address;
+            }
+        }
+
+        if let Some(address) = variable(OSC_ADDRESS_VARIABLE) {
+            self.osc = Some(OscConfig {
+                address
+            });
+        }
+
+        if let Some(value) = variable(RESUME_PLAYBACK_VARIABLE) {
+            self.resume_playback =
+                parse_variable(RESUME_PLAYBACK_VARIABLE, value, parse_bool)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks this config for invalid values and returns a description of
+    /// every problem that was found.
+    fn problems(&self, require_token: bool) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if require_token && self.token.trim().is_empty() {
+            problems.push(format!("No Discord token is configured. Set \
+                `token` in the config file or the {} environment variable.",
+                TOKEN_VARIABLE));
+        }
+
+        let prefix = self.prefix.as_deref().unwrap_or("");
+
+        if prefix.contains(char::is_whitespace) {
+            problems.push("The prefix must not contain spaces.".to_owned());
+        }
+
+        check_directory(&mut problems, "plugin directory",
+            &self.plugin_directory, false);
+        check_directory(&mut problems, "plugin config directory",
+            &self.plugin_config_directory, false);
+        check_directory(&mut problems, "state directory",
+            &self.state_directory, false);
+        check_directory(&mut problems, "root directory",
+            &self.root_directory, true);
+        check_directory(&mut problems, "recordings directory",
+            &self.recordings_directory, false);
+
+        if self.max_recording_size_mb == 0 {
+            problems.push(
+                "The maximum recording size must be positive.".to_owned());
+        }
+
+        if let Some(web_api) = &self.web_api {
+            if web_api.token.trim().is_empty() {
+                problems.push(format!("The web API requires a token. Set it \
+                    in the config file or the {} environment variable.",
+                    WEB_API_TOKEN_VARIABLE));
+            }
+
+            check_address(&mut problems, "web API address", &web_api.address);
+        }
+
+        if let Some(osc) = &self.osc {
+            check_address(&mut problems, "OSC address", &osc.address);
+        }
+
+        problems
     }
 
     /// The prefix for commands to be recognized by the bot. If `None`, prefix commands are not
@@ -222,24 +726,186 @@ impl Config {
         &self.state_directory
     }
 
-    /// Gets the [PluginConfig] to pass to a plugin loaded from a file with the
-    /// given name.
-    ///
-    /// # Arguments
-    ///
-    /// * `library`: The file name (without preceding directories, but with
-    ///   extension) of the library that contains the plugin for which to
-    ///   generate a config.
-    pub fn generate_plugin_config(&self, library: &str) -> PluginConfig {
-        let config_path =
-            format!("{}/{}.config", &self.plugin_config_directory, library);
-
-        PluginConfig::new(
-            &self.root_directory, self.allow_web_access, config_path)
+    /// Gets a function which generates the [PluginConfig] to pass to a plugin
+    /// given the file name (without preceding directories, but with
+    /// extension) of the library that contains it. The function does not
+    /// borrow this config, so it can be retained to load plugins at runtime.
+    pub fn plugin_config_generator(&self)
+            -> impl Fn(&str) -> PluginConfig + Send + Sync + 'static {
+        let plugin_config_directory = self.plugin_config_directory.clone();
+        let root_directory = self.root_directory.clone();
+        let allow_web_access = self.allow_web_access;
+
+        move |library| plugin_config_for_library(library,
+            &plugin_config_directory, &root_directory, allow_web_access)
     }
 
     /// Gets the [LevelFilter] to be applied to the logger.
     pub fn log_level_filter(&self) -> LevelFilter {
         self.log_level_filter
     }
+
+    /// Gets the directory in which recordings of the mixer output are stored.
+    pub fn recordings_directory(&self) -> &str {
+        &self.recordings_directory
+    }
+
+    /// Gets the maximum size of a single recording in bytes. Once a recording
+    /// reaches this size, it is stopped automatically.
+    pub fn max_recording_size(&self) -> u64 {
+        self.max_recording_size_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// Gets the configuration of the web API, or `None` if it is disabled.
+    pub fn web_api(&self) -> Option<&WebApiConfig> {
+        self.web_api.as_ref()
+    }
+
+    /// Gets the configuration of the OSC listener, or `None` if it is
+    /// disabled.
+    pub fn osc(&self) -> Option<&OscConfig> {
+        self.osc.as_ref()
+    }
+
+    /// Indicates whether the bot saves what is played in each guild and, once
+    /// it is started again, rejoins the voice channels and resumes playback.
+    /// Only the next 256 entries of playlists are saved, so a resumed playlist
+    /// ends after them, even if it was looped.
+    pub fn resume_playback(&self) -> bool {
+        self.resume_playback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn variables(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let variables = pairs.iter()
+            .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+            .collect::<HashMap<_, _>>();
+
+        move |name| variables.get(name).cloned()
+    }
+
+    fn problems(result: Result<Config, ConfigError>) -> Vec<String> {
+        match result {
+            Err(ConfigError::Invalid(problems)) => problems,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("config is valid")
+        }
+    }
+
+    #[test]
+    fn environment_overrides_config() {
+        let config = Config {
+            token: "file token".to_owned(),
+            ..Config::default()
+        };
+        let variables = variables(&[
+            (TOKEN_VARIABLE, "environment token"),
+            (OWNERS_VARIABLE, "123, 456"),
+            (PREFIX_VARIABLE, ""),
+            (ALLOW_SLASH_COMMANDS_VARIABLE, "false"),
+            (STATE_DIRECTORY_VARIABLE, "/var/lib/rambot"),
+            (LOG_LEVEL_FILTER_VARIABLE, "debug"),
+            (WEB_API_TOKEN_VARIABLE, "secret")
+        ]);
+
+        let config = config.finish(variables, true).unwrap();
+
+        assert_eq!("environment token", config.token());
+        assert_eq!(&[UserId::new(123), UserId::new(456)], config.owners());
+        assert_eq!(None, config.prefix());
+        assert!(!config.allow_slash_commands());
+        assert_eq!("/var/lib/rambot", config.state_directory());
+        assert_eq!(LevelFilter::Debug, config.log_level_filter());
+        assert_eq!("secret", config.web_api().unwrap().token());
+        assert_eq!(DEFAULT_WEB_API_ADDRESS, config.web_api().unwrap().address());
+    }
+
+    #[test]
+    fn invalid_environment_variables_are_reported() {
+        let result = Config::default()
+            .finish(variables(&[(OWNERS_VARIABLE, "123,abc")]), false);
+
+        assert!(matches!(result,
+            Err(ConfigError::InvalidEnvironmentVariable(OWNERS_VARIABLE, _))));
+
+        let result = Config::default()
+            .finish(variables(&[(ALLOW_WEB_ACCESS_VARIABLE, "maybe")]), false);
+
+        assert!(matches!(result,
+            Err(ConfigError::InvalidEnvironmentVariable(
+                ALLOW_WEB_ACCESS_VARIABLE, _))));
+    }
+
+    #[test]
+    fn missing_token_is_only_required_for_discord() {
+        assert!(Config::default().finish(variables(&[]), false).is_ok());
+
+        let problems = problems(Config::default().finish(variables(&[]), true));
+
+        assert_eq!(1, problems.len());
+        assert!(problems[0].contains(TOKEN_VARIABLE));
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        let variables = variables(&[
+            (TOKEN_VARIABLE, "token"),
+            (ROOT_DIRECTORY_VARIABLE, "/this/directory/does/not/exist"),
+            (WEB_API_ADDRESS_VARIABLE, "localhost"),
+            (OSC_ADDRESS_VARIABLE, "0.0.0.0:9000")
+        ]);
+
+        let problems = problems(Config::default().finish(variables, true));
+
+        assert_eq!(3, problems.len());
+        assert!(problems[0].contains("root directory"));
+        assert!(problems[1].contains("web API requires a token"));
+        assert!(problems[2].contains("web API address"));
+    }
+
+    #[test]
+    fn partial_toml_config_uses_defaults() {
+        let config: Config = toml::from_str("
+            token = \"token\"
+            owners = [123]
+            state_directory = \"state/guilds\"
+
+            [osc]
+        ").unwrap();
+
+        assert_eq!("token", config.token());
+        assert_eq!(&[UserId::new(123)], config.owners());
+        assert_eq!("state/guilds", config.state_directory());
+        assert_eq!(None, config.prefix());
+        assert_eq!(DEFAULT_PLUGIN_DIRECTORY, config.plugin_directory());
+        assert_eq!(DEFAULT_OSC_ADDRESS, config.osc().unwrap().address());
+        assert!(config.web_api().is_none());
+    }
+
+    #[test]
+    fn disabled_prefix_survives_round_trip() {
+        let config = Config {
+            prefix: None,
+            ..Config::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        let config: Config = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(None, config.prefix());
+    }
+
+    #[test]
+    fn toml_files_are_recognized() {
+        assert!(is_toml(Path::new("config.toml")));
+        assert!(is_toml(Path::new("/etc/rambot/Config.TOML")));
+        assert!(!is_toml(Path::new("config.json")));
+        assert!(!is_toml(Path::new("toml")));
+    }
 }
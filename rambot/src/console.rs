@@ -0,0 +1,375 @@
+use crate::audio::Mixer;
+use crate::command::{self, CommandResponse, Invoker};
+use crate::plugin::PluginManager;
+use crate::recording::{self, WavWriter};
+use crate::state::State;
+
+use rambot_api::{AudioSource, Sample, SAMPLES_PER_SECOND};
+
+use serenity::model::id::GuildId;
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The ID of the guild whose state is used by the console if no other guild
+/// is specified.
+pub const LOCAL_GUILD_ID: u64 = 1;
+
+/// The number of samples read from the mixer at once by an [Output] (20 ms).
+const CHUNK_SIZE: usize = 960;
+
+/// A destination for the audio produced by the mixer while running the bot
+/// on the console.
+pub enum OutputSink {
+
+    /// Writes the audio to a WAV file. Times in which no audio is playing are
+    /// skipped.
+    Wav(WavWriter),
+
+    /// Writes the audio as raw interleaved stereo 16-bit little-endian PCM at
+    /// 48 kHz, for example into a named pipe read by an audio player. Times in
+    /// which no audio is playing are filled with silence, so the stream is
+    /// continuous.
+    Pcm(BufWriter<File>)
+}
+
+impl OutputSink {
+
+    /// Creates a sink which writes into a new WAV file at the given path. If
+    /// the file already exists, it is overwritten.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while creating the file.
+    pub fn wav(path: impl AsRef<Path>) -> io::Result<OutputSink> {
+        Ok(OutputSink::Wav(WavWriter::new(File::create(path)?, u64::MAX)?))
+    }
+
+    /// Creates a sink which writes raw PCM into the file at the given path,
+    /// which may also be a named pipe.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while opening the file.
+    pub fn pcm(path: impl AsRef<Path>) -> io::Result<OutputSink> {
+        let file = OpenOptions::new().write(true).create(true).truncate(true)
+            .open(path)?;
+
+        Ok(OutputSink::Pcm(BufWriter::new(file)))
+    }
+
+    fn write(&mut self, samples: &[Sample]) -> io::Result<()> {
+        match self {
+            OutputSink::Wav(writer) => {
+                if !writer.write(samples)? {
+                    return Err(io::Error::other(
+                        "maximum WAV file size reached"));
+                }
+
+                Ok(())
+            },
+            OutputSink::Pcm(writer) => {
+                recording::write_pcm(writer, samples)?;
+                writer.flush()
+            }
+        }
+    }
+
+    fn write_silence(&mut self, count: usize) -> io::Result<()> {
+        match self {
+            OutputSink::Wav(_) => Ok(()),
+            OutputSink::Pcm(_) => self.write(&vec![Sample::ZERO; count])
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            OutputSink::Wav(writer) => writer.finish().map(|_| ()),
+            OutputSink::Pcm(mut writer) => writer.flush()
+        }
+    }
+}
+
+fn run_output(mixer: Arc<RwLock<Mixer>>, mut sink: OutputSink,
+        stop: Arc<AtomicBool>) -> io::Result<()> {
+    let mut buf = vec![Sample::ZERO; CHUNK_SIZE];
+    let start = Instant::now();
+    let mut chunks = 0u64;
+
+    while !stop.load(Ordering::Acquire) {
+        let read_res = mixer.write().unwrap().read(&mut buf);
+
+        match read_res {
+            Ok(0) => sink.write_silence(CHUNK_SIZE)?,
+            Ok(count) => sink.write(&buf[..count])?,
+            Err(e) => {
+                log::warn!("Error reading mixer output: {}", e);
+                sink.write_silence(CHUNK_SIZE)?
+            }
+        }
+
+        chunks += 1;
+
+        // Keep pace with real time, as a voice connection would.
+
+        let target = start + Duration::from_secs_f64(
+            (chunks * CHUNK_SIZE as u64) as f64 / SAMPLES_PER_SECOND as f64);
+        let now = Instant::now();
+
+        if target > now {
+            thread::sleep(target - now);
+        }
+    }
+
+    sink.finish()
+}
+
+/// Pulls audio from a mixer in real time on a separate thread and writes it
+/// to an [OutputSink]. This takes the role of the voice connection when
+/// running the bot on the console.
+pub struct Output {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<io::Result<()>>
+}
+
+impl Output {
+
+    /// Starts writing the output of the given mixer to the given sink.
+    pub fn start(mixer: Arc<RwLock<Mixer>>, sink: OutputSink) -> Output {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let handle = thread::spawn(move || run_output(mixer, sink, stop_clone));
+
+        Output {
+            stop,
+            handle
+        }
+    }
+
+    /// Stops writing audio and finalizes the sink.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while writing to the sink.
+    pub fn stop(self) -> io::Result<()> {
+        self.stop.store(true, Ordering::Release);
+        self.handle.join()
+            .map_err(|_| io::Error::other("output thread panicked"))?
+    }
+}
+
+/// The result of executing a line on the [Console].
+#[derive(Debug, Eq, PartialEq)]
+pub enum ConsoleReply {
+
+    /// Indicates that the command was executed successfully and has nothing
+    /// further to report.
+    Confirm,
+
+    /// A message to be displayed to the user. For commands which change
+    /// something, this is an error message.
+    Message(String),
+
+    /// Indicates that the user wants to leave the console.
+    Quit
+}
+
+impl From<String> for ConsoleReply {
+    fn from(message: String) -> ConsoleReply {
+        ConsoleReply::Message(message)
+    }
+}
+
+impl From<&str> for ConsoleReply {
+    fn from(message: &str) -> ConsoleReply {
+        ConsoleReply::Message(message.to_owned())
+    }
+}
+
+impl From<CommandResponse> for ConsoleReply {
+    fn from(response: CommandResponse) -> ConsoleReply {
+        match response {
+            CommandResponse::Confirm => ConsoleReply::Confirm,
+            CommandResponse::Reply(message) => ConsoleReply::Message(message)
+        }
+    }
+}
+
+/// A front-end which executes commands typed into a terminal against the
+/// state of a single guild, without any connection to Discord. The commands
+/// mirror those available on Discord that do not depend on Discord itself.
+/// The mixer output is not sent anywhere by the console, use an [Output] for
+/// that.
+pub struct Console {
+    state: State,
+    plugin_manager: Arc<PluginManager>,
+    guild_id: GuildId
+}
+
+impl Console {
+
+    /// Creates a new console operating on the state of the guild with the
+    /// given ID. If there is no state for that guild yet, it is created.
+    ///
+    /// # Arguments
+    ///
+    /// * `state`: The [State] containing the guild's state. Changes to the
+    ///   configuration are saved just as they are by the bot.
+    /// * `plugin_manager`: The [PluginManager] used to resolve audio.
+    /// * `guild_id`: The ID of the guild whose state to use.
+    pub fn new(mut state: State, plugin_manager: Arc<PluginManager>,
+            guild_id: GuildId) -> Console {
+        state.ensure_guild_state_exists(guild_id, &plugin_manager);
+
+        Console {
+            state,
+            plugin_manager,
+            guild_id
+        }
+    }
+
+    /// Gets a handle to the mixer of the guild, which can be used to start an
+    /// [Output].
+    pub fn mixer_arc(&self) -> Arc<RwLock<Mixer>> {
+        self.state.guild_state(self.guild_id).unwrap().mixer_arc()
+    }
+
+    /// Executes one line of input and returns the reply.
+    ///
+    /// # Arguments
+    ///
+    /// * `line`: The command to execute, in the same format as a prefix
+    ///   command on Discord, but without the prefix.
+    pub fn execute(&mut self, line: &str) -> ConsoleReply {
+        // Commands are resolved from the same definitions as on Discord, so
+        // every command which does not depend on Discord itself is available.
+
+        match line.trim() {
+            "" => ConsoleReply::Message(String::new()),
+            "quit" | "exit" => ConsoleReply::Quit,
+            _ => command::execute_local(&mut self.state, &self.plugin_manager,
+                self.guild_id, Invoker::Owner, line).into()
+        }
+    }
+
+    /// Reads lines from the given input and executes them until the input
+    /// ends or the user quits. Replies are written to standard output.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while reading input or writing replies.
+    pub fn run<R: BufRead>(&mut self, input: R) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        let mut lines = input.lines();
+
+        loop {
+            write!(stdout, "> ")?;
+            stdout.flush()?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(())
+            };
+
+            match self.execute(&line) {
+                ConsoleReply::Confirm => writeln!(stdout, "OK.")?,
+                ConsoleReply::Message(message) if message.is_empty() => { },
+                ConsoleReply::Message(message) => writeln!(stdout, "{}", message)?,
+                ConsoleReply::Quit => return Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::env;
+    use std::fs;
+
+    fn test_console(name: &str) -> (Console, String) {
+        let directory = env::temp_dir()
+            .join(format!("rambot-console-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_dir_all(&directory);
+        let plugin_manager = Arc::new(PluginManager::empty());
+        let state = State::load(&directory, Arc::clone(&plugin_manager))
+            .unwrap_or_else(|e| panic!("{}", e));
+        let console = Console::new(state, plugin_manager, GuildId::new(LOCAL_GUILD_ID));
+
+        (console, directory)
+    }
+
+    fn message(text: &str) -> ConsoleReply {
+        ConsoleReply::Message(text.to_owned())
+    }
+
+    #[test]
+    fn commands_are_resolved_from_definitions() {
+        let (mut console, directory) = test_console("definitions");
+
+        assert_eq!(ConsoleReply::Confirm, console.execute("LAYER ADD music"));
+        assert_eq!(ConsoleReply::Confirm, console.execute("scene save intro"));
+        assert_eq!(message("Scenes:\n- intro"), console.execute("scene list"));
+        assert_eq!(message("The command `connect` is only available on Discord."),
+            console.execute("connect"));
+        assert!(matches!(console.execute("help"),
+            ConsoleReply::Message(m)
+                if m.contains("- connect") && m.contains("- scene")));
+        assert_eq!(message("Missing argument. Usage: `layer add <name>`"),
+            console.execute("layer add"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn layers_can_be_managed() {
+        let (mut console, directory) = test_console("layers");
+
+        assert_eq!(ConsoleReply::Confirm, console.execute("layer add music"));
+        assert_eq!(message("A layer with the same name already exists."),
+            console.execute("layer add music"));
+        assert_eq!(ConsoleReply::Confirm, console.execute("layer gain music 0.5"));
+        assert_eq!(ConsoleReply::Confirm, console.execute("layer mute music"));
+        assert_eq!(message("Layer list:\n- music (gain 0.5, muted)"),
+            console.execute("layer list"));
+        assert_eq!(message("Layer not found."), console.execute("layer remove sfx"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn configuration_is_saved() {
+        let (mut console, directory) = test_console("saved");
+
+        console.execute("layer add music");
+
+        let saved = fs::read_to_string(
+            Path::new(&directory).join(format!("{}.json", LOCAL_GUILD_ID))).unwrap();
+        fs::remove_dir_all(directory).unwrap();
+
+        assert!(saved.contains("\"music\""));
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let (mut console, directory) = test_console("errors");
+
+        assert_eq!(message("No layer of name music."), console.execute("play music song.wav"));
+        assert_eq!(message("No audio to stop."), console.execute("stop"));
+        assert_eq!(message("Layer not found."), console.execute("effect list music"));
+        assert!(matches!(console.execute("frobnicate"),
+            ConsoleReply::Message(m) if m.starts_with("Unknown command")));
+        assert_eq!(ConsoleReply::Quit, console.execute("quit"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,498 @@
+use crate::audio;
+use crate::command::{self, to_input, CommandResponse, Invoker};
+use crate::idle::{self, IdleTracker};
+use crate::playback::{GuildPlayback, PlaybackStore};
+use crate::plugin::PluginManager;
+use crate::settings::StatusText;
+use crate::state::State;
+
+use serenity::model::id::{ChannelId, GuildId};
+
+use songbird::Songbird;
+
+use tokio::sync::RwLock as TokioRwLock;
+use tokio::task;
+
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// An enumeration of the different errors that can occur when controlling
+/// the bot from outside Discord.
+#[derive(Debug, Eq, PartialEq)]
+pub enum ControlError {
+
+    /// Indicates that the addressed layer does not exist.
+    LayerNotFound,
+
+    /// Indicates that the addressed sound board button does not exist.
+    ButtonNotFound,
+
+    /// Indicates that a gain which is not a number between 0 and
+    /// [MAX_LAYER_GAIN](audio::MAX_LAYER_GAIN) was requested.
+    InvalidGain,
+
+    /// Indicates that audio was started while the bot is not connected to a
+    /// voice channel in the guild. The audio is stopped in that case.
+    NotConnected,
+
+    /// Indicates that a command failed. The message explains the reason.
+    Command(String)
+}
+
+impl Display for ControlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlError::LayerNotFound => write!(f, "Layer not found."),
+            ControlError::ButtonNotFound => write!(f, "Button not found."),
+            ControlError::InvalidGain => write!(f,
+                "The gain must be a number between 0 and {}.",
+                audio::MAX_LAYER_GAIN),
+            ControlError::NotConnected =>
+                write!(f, "I am not connected to a voice channel."),
+            ControlError::Command(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+/// Indicates whether the given name, e.g. of a layer, can be passed to a
+/// command as a single argument, that is, it is not empty and contains no
+/// whitespace or double quotes.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() &&
+        !name.contains(|c: char| c.is_whitespace() || c == '"')
+}
+
+/// Executes actions on the shared [State] on behalf of remote control
+/// interfaces such as the web API or the OSC listener. Cloning a controller
+/// is cheap and yields a handle to the same state.
+#[derive(Clone)]
+pub struct Controller {
+    state: Arc<TokioRwLock<State>>,
+    plugin_manager: Arc<PluginManager>,
+    songbird: Option<Arc<Songbird>>,
+    prefix: Option<String>
+}
+
+impl Controller {
+
+    /// Creates a new controller.
+    ///
+    /// # Arguments
+    ///
+    /// * `state`: The [State] shared with the commands.
+    /// * `plugin_manager`: The [PluginManager] used to resolve audio.
+    /// * `songbird`: The [Songbird] instance used to send audio to voice
+    ///   channels. If `None`, audio is only played on the mixer, which is
+    ///   useful for tests.
+    /// * `prefix`: The command prefix, which is removed from sound board
+    ///   button commands before they are executed. The prefix of a guild is
+    ///   removed as well, if one is set in its settings.
+    pub fn new(state: Arc<TokioRwLock<State>>,
+            plugin_manager: Arc<PluginManager>,
+            songbird: Option<Arc<Songbird>>, prefix: Option<&str>)
+            -> Controller {
+        Controller {
+            state,
+            plugin_manager,
+            songbird,
+            prefix: prefix.map(str::to_owned)
+        }
+    }
+
+    /// Gets the [State] controlled by this controller.
+    pub fn state(&self) -> &Arc<TokioRwLock<State>> {
+        &self.state
+    }
+
+    /// Runs the given function with exclusive access to the [State] on a
+    /// blocking thread, since resolving audio and saving guild states block.
+    /// The state is locked only while the function runs.
+    async fn with_state_blocking<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut State, &Arc<PluginManager>) -> T + Send + 'static,
+        T: Send + 'static
+    {
+        let state = Arc::clone(&self.state);
+        let plugin_manager = Arc::clone(&self.plugin_manager);
+
+        task::spawn_blocking(move || {
+            f(&mut state.blocking_write(), &plugin_manager)
+        }).await.unwrap()
+    }
+
+    /// Executes the given commands in order as they would be executed on
+    /// Discord by a member without any roles, stopping at the first one that
+    /// fails. Commands reserved to owners, commands which require permissions,
+    /// and commands restricted in the guild are rejected. If audio was
+    /// started, it is sent to the voice channel the bot is connected to. The
+    /// state is locked separately for each command.
+    ///
+    /// # Errors
+    ///
+    /// * [ControlError::Command] if any command fails or may not be used.
+    /// * [ControlError::NotConnected] if audio was started, but the bot is not
+    ///   connected to a voice channel.
+    pub async fn execute(&self, guild_id: GuildId, commands: &[String])
+            -> Result<(), ControlError> {
+        let mixer = self.with_state_blocking(move |state, plugin_manager| {
+            state.ensure_guild_state_exists(guild_id, plugin_manager);
+            state.guild_state(guild_id).unwrap().mixer_arc()
+        }).await;
+        let active_before = mixer.read().unwrap().active();
+
+        for command in commands {
+            let command = command.clone();
+            let response =
+                self.with_state_blocking(move |state, plugin_manager| {
+                    command::execute_local(state, plugin_manager, guild_id,
+                        Invoker::Remote, &command)
+                }).await;
+
+            if let CommandResponse::Reply(message) = response {
+                return Err(ControlError::Command(message));
+            }
+        }
+
+        let active = mixer.read().unwrap().active();
+
+        if active && !active_before {
+            if let Some(songbird) = &self.songbird {
+                let call = songbird.get(guild_id);
+                let connected = match &call {
+                    Some(call) => {
+                        let mut call = call.lock().await;
+
+                        if call.current_channel().is_some() {
+                            call.play_input(to_input(Arc::clone(&mixer)));
+                            true
+                        }
+                        else {
+                            false
+                        }
+                    },
+                    None => false
+                };
+
+                if !connected {
+                    mixer.write().unwrap().stop_all();
+                    return Err(ControlError::NotConnected);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes the gain, mute, or solo state of a layer. Properties given as
+    /// `None` are left unchanged. Each property may only be changed if the
+    /// corresponding `layer` command may be used, as for [Controller::execute].
+    ///
+    /// # Errors
+    ///
+    /// * [ControlError::InvalidGain] if the gain is not a number between 0 and
+    ///   [MAX_LAYER_GAIN](audio::MAX_LAYER_GAIN).
+    /// * [ControlError::LayerNotFound] if the layer does not exist.
+    /// * [ControlError::Command] if a property may not be changed.
+    pub async fn update_layer(&self, guild_id: GuildId, layer: &str,
+            gain: Option<f32>, muted: Option<bool>, solo: Option<bool>)
+            -> Result<(), ControlError> {
+        if let Some(gain) = gain {
+            if !audio::is_valid_layer_gain(gain) {
+                return Err(ControlError::InvalidGain);
+            }
+        }
+
+        let layer = layer.to_owned();
+
+        self.with_state_blocking(move |state, plugin_manager| {
+            update_layer(state, plugin_manager, guild_id, &layer, gain, muted,
+                solo)
+        }).await
+    }
+
+    /// Presses the button with the given label on the sound board with the
+    /// given name and executes the associated command.
+    ///
+    /// # Errors
+    ///
+    /// * [ControlError::ButtonNotFound] if the button does not exist.
+    /// * Any error of [Controller::execute] for the button's command.
+    pub async fn press_button(&self, guild_id: GuildId, board: &str,
+            label: &str) -> Result<(), ControlError> {
+        let board = board.to_owned();
+        let label = label.to_owned();
+        let (command, guild_prefix) =
+            self.with_state_blocking(move |state, plugin_manager| {
+                if state.guild_state(guild_id).is_none() {
+                    return Err(ControlError::ButtonNotFound);
+                }
+
+                let mut guild_state =
+                    state.guild_state_mut(guild_id, plugin_manager);
+                let guild_prefix =
+                    guild_state.settings().prefix().map(str::to_owned);
+                let command = guild_state.board_manager_mut()
+                    .press_button(&board, &label)
+                    .ok_or(ControlError::ButtonNotFound)?;
+
+                Ok((command, guild_prefix))
+            }).await?;
+        let command = guild_prefix.as_deref()
+            .and_then(|prefix| command.strip_prefix(prefix))
+            .or_else(|| self.prefix.as_deref()
+                .and_then(|prefix| command.strip_prefix(prefix)))
+            .unwrap_or(&command)
+            .to_owned();
+
+        self.execute(guild_id, &[command]).await
+    }
+
+    /// Saves what is currently played in every guild to the given
+    /// [PlaybackStore]. Guilds in which the bot is not connected to a voice
+    /// channel or nothing is playing have their saved playback removed. The
+    /// files are written on a blocking thread, and only if the playback of a
+    /// guild changed. Errors are logged.
+    pub async fn save_playback(&self, store: &PlaybackStore) {
+        let songbird = match &self.songbird {
+            Some(songbird) => songbird,
+            None => return
+        };
+        let mixers = {
+            let state = self.state.read().await;
+
+            state.guild_ids()
+                .map(|guild_id| (guild_id,
+                    state.guild_state(guild_id).unwrap().mixer_arc()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut playbacks = Vec::new();
+
+        for (guild_id, mixer) in mixers {
+            let channel = match songbird.get(guild_id) {
+                Some(call) => call.lock().await.current_channel(),
+                None => None
+            };
+            let layers = mixer.write().unwrap().playback();
+            let playback = match channel {
+                Some(channel) if !layers.is_empty() => {
+                    let channel = ChannelId::new(channel.0.get());
+
+                    Some(GuildPlayback::new(channel, layers))
+                },
+                _ => None
+            };
+
+            playbacks.push((guild_id, playback));
+        }
+
+        let store = store.clone();
+
+        task::spawn_blocking(move || {
+            for (guild_id, playback) in playbacks {
+                let result = match playback {
+                    Some(playback) => store.save(guild_id, &playback),
+                    None => store.remove(guild_id)
+                };
+
+                if let Err(e) = result {
+                    log::warn!("Could not save playback of guild {}: {}",
+                        guild_id, e);
+                }
+            }
+        }).await.unwrap();
+    }
+
+    /// Leaves the voice channel in every guild whose settings ask for it,
+    /// because no audio was playing for longer than the idle timeout or no
+    /// one is listening. Audio that is still playing is stopped. Errors are
+    /// logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `tracker`: The [IdleTracker] which remembers since when no audio has
+    ///   been playing in each guild. The same tracker must be given on every
+    ///   call.
+    /// * `listeners`: Counts the users other than bots in the voice channel
+    ///   with the given ID in the guild with the given ID. Returns `None` if
+    ///   they are unknown.
+    pub async fn leave_idle_channels<L>(&self, tracker: &mut IdleTracker,
+        listeners: L)
+    where
+        L: Fn(GuildId, ChannelId) -> Option<usize>
+    {
+        let songbird = match &self.songbird {
+            Some(songbird) => songbird,
+            None => return
+        };
+        let guilds = {
+            let state = self.state.read().await;
+
+            state.guild_ids()
+                .filter_map(|guild_id| {
+                    let guild_state = state.guild_state(guild_id).unwrap();
+                    let settings = guild_state.settings();
+
+                    if settings.idle_timeout().is_some() ||
+                            settings.leave_when_alone() {
+                        Some((guild_id, guild_state.mixer_arc(),
+                            settings.clone()))
+                    }
+                    else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let now = Instant::now();
+
+        for (guild_id, mixer, settings) in guilds {
+            let channel = match songbird.get(guild_id) {
+                Some(call) => call.lock().await.current_channel(),
+                None => None
+            };
+            let channel = match channel {
+                Some(channel) => ChannelId::new(channel.0.get()),
+                None => {
+                    tracker.forget(guild_id);
+                    continue;
+                }
+            };
+            let active = mixer.read().unwrap().active();
+            let idle = tracker.update(guild_id, active, now);
+            let reason = idle::leave_reason(&settings, idle,
+                listeners(guild_id, channel));
+
+            if let Some(reason) = reason {
+                if let Err(e) = songbird.remove(guild_id).await {
+                    log::warn!("Could not leave voice channel in guild {}: {}",
+                        guild_id, e);
+                    continue;
+                }
+
+                mixer.write().unwrap().stop_all();
+                tracker.forget(guild_id);
+                log::info!("Left voice channel in guild {} because {}.",
+                    guild_id, reason);
+            }
+        }
+    }
+
+    /// Resumes the playback saved in the given [PlaybackStore] for every
+    /// guild, rejoining the voice channel to which the bot was connected.
+    /// Layers which no longer exist or whose audio cannot be resolved are
+    /// skipped. Errors are logged.
+    pub async fn restore_playback(&self, store: &PlaybackStore) {
+        let playbacks = match store.load_all() {
+            Ok(playbacks) => playbacks,
+            Err(e) => {
+                log::error!("Could not load saved playback: {}", e);
+                return;
+            }
+        };
+
+        for (guild_id, playback) in playbacks {
+            match self.restore_guild_playback(guild_id, &playback).await {
+                Ok(()) => log::info!("Resumed playback in guild {}.", guild_id),
+                Err(e) => log::warn!(
+                    "Could not resume playback in guild {}: {}", guild_id, e)
+            }
+        }
+    }
+
+    async fn restore_guild_playback(&self, guild_id: GuildId,
+            playback: &GuildPlayback) -> Result<(), ControlError> {
+        let songbird = self.songbird.as_ref()
+            .ok_or(ControlError::NotConnected)?;
+        let mixer = {
+            let state = self.state.read().await;
+            let guild_state = state.guild_state(guild_id)
+                .ok_or_else(|| ControlError::Command(
+                    "The guild has no state.".to_owned()))?;
+            let plugin_guild_config = guild_state.build_plugin_guild_config();
+            let mut mixer = guild_state.mixer_mut();
+
+            for layer in playback.layers() {
+                if !mixer.contains_layer(layer.layer()) {
+                    log::warn!("Cannot resume playback on layer {}, which no \
+                        longer exists.", layer.layer());
+                    continue;
+                }
+
+                let restore_res = mixer.restore_playback(layer,
+                    plugin_guild_config.clone(), |layer, e|
+                        log::warn!("Error on layer {}: {}", layer, e));
+
+                if let Err(e) = restore_res {
+                    log::warn!("Cannot resume playback on layer {}: {}",
+                        layer.layer(), e);
+                }
+            }
+
+            if !mixer.active() {
+                return Ok(());
+            }
+
+            guild_state.mixer_arc()
+        };
+
+        match songbird.join(guild_id, playback.channel()).await {
+            Ok(call) => {
+                call.lock().await.play_input(to_input(mixer));
+                Ok(())
+            },
+            Err(e) => {
+                mixer.write().unwrap().stop_all();
+                Err(ControlError::Command(
+                    format!("Could not join voice channel: {}", e)))
+            }
+        }
+    }
+}
+
+fn update_layer(state: &mut State, plugin_manager: &Arc<PluginManager>,
+        guild_id: GuildId, layer: &str, gain: Option<f32>,
+        muted: Option<bool>, solo: Option<bool>) -> Result<(), ControlError> {
+    let guild_state = state.guild_state(guild_id)
+        .ok_or(ControlError::LayerNotFound)?;
+    let mute_command =
+        |muted| if muted { "layer mute" } else { "layer unmute" };
+    let solo_command =
+        |solo| if solo { "layer solo" } else { "layer unsolo" };
+    let commands = [
+        gain.map(|_| "layer gain"),
+        muted.map(mute_command),
+        solo.map(solo_command)
+    ];
+
+    for command in commands.into_iter().flatten() {
+        if !command::may_use(Invoker::Remote, guild_state, command) {
+            let message = guild_state.settings().status_language()
+                .status_text(StatusText::NotAllowed);
+
+            return Err(ControlError::Command(message.to_owned()));
+        }
+    }
+
+    let guild_state = state.guild_state_mut(guild_id, plugin_manager);
+    let mut mixer = guild_state.mixer_mut();
+
+    if !mixer.contains_layer(layer) {
+        return Err(ControlError::LayerNotFound);
+    }
+
+    if let Some(gain) = gain {
+        mixer.set_layer_gain(layer, gain);
+    }
+
+    if let Some(muted) = muted {
+        mixer.set_layer_muted(layer, muted);
+    }
+
+    if let Some(solo) = solo {
+        mixer.set_layer_solo(layer, solo);
+    }
+
+    Ok(())
+}
@@ -6,8 +6,10 @@ use serenity::client::Context;
 use crate::command::{CommandError, CommandResult};
 use crate::command_data::CommandData;
 
+use std::future::Future;
+
 /// A trait for structs which can handle any Discord events.
-pub(crate) trait FrameworkEventHandler {
+pub trait FrameworkEventHandler {
 
     /// Called whenever a Discord event occurs.
     ///
@@ -19,7 +21,8 @@ pub(crate) trait FrameworkEventHandler {
     ///
     /// # Returns
     ///
-    /// A [CommandResult].
-    async fn handle_event(&self, serenity_ctx: &Context, event: &FullEvent,
-        framework_ctx: FrameworkContext<'_, CommandData, CommandError>) -> CommandResult;
+    /// A future resolving to a [CommandResult].
+    fn handle_event(&self, serenity_ctx: &Context, event: &FullEvent,
+        framework_ctx: FrameworkContext<'_, CommandData, CommandError>)
+        -> impl Future<Output = CommandResult> + Send;
 }
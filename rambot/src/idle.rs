@@ -0,0 +1,162 @@
+use crate::settings::GuildSettings;
+
+use serenity::model::id::GuildId;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// The reason for which the bot leaves a voice channel on its own.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LeaveReason {
+
+    /// Indicates that no audio was playing for longer than the idle timeout
+    /// of the guild.
+    Idle,
+
+    /// Indicates that no users other than bots remain in the voice channel.
+    Alone
+}
+
+impl Display for LeaveReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaveReason::Idle => write!(f, "no audio was playing"),
+            LeaveReason::Alone => write!(f, "no one was listening")
+        }
+    }
+}
+
+/// Keeps track of how long no audio has been playing in every guild in which
+/// the bot is connected to a voice channel.
+#[derive(Default)]
+pub struct IdleTracker {
+    idle_since: HashMap<GuildId, Instant>
+}
+
+impl IdleTracker {
+
+    /// Creates a new idle tracker which does not know any guild yet.
+    pub fn new() -> IdleTracker {
+        IdleTracker::default()
+    }
+
+    /// Records whether audio is playing in the guild with the given ID at the
+    /// given time.
+    ///
+    /// # Returns
+    ///
+    /// For how long no audio has been playing, according to all records since
+    /// the guild was last forgotten. This is zero if audio is playing.
+    pub fn update(&mut self, guild_id: GuildId, active: bool, now: Instant)
+            -> Duration {
+        if active {
+            self.idle_since.remove(&guild_id);
+            Duration::ZERO
+        }
+        else {
+            let idle_since = *self.idle_since.entry(guild_id).or_insert(now);
+
+            now.saturating_duration_since(idle_since)
+        }
+    }
+
+    /// Forgets the guild with the given ID, which should be done when the bot
+    /// leaves its voice channel.
+    pub fn forget(&mut self, guild_id: GuildId) {
+        self.idle_since.remove(&guild_id);
+    }
+}
+
+/// Decides whether the bot should leave the voice channel in a guild.
+///
+/// # Arguments
+///
+/// * `settings`: The [GuildSettings] of the guild.
+/// * `idle`: For how long no audio has been playing, as reported by
+///   [IdleTracker::update].
+/// * `listeners`: The number of users other than bots in the voice channel,
+///   or `None` if it is unknown, in which case the bot does not leave for
+///   being alone.
+///
+/// # Returns
+///
+/// The reason for leaving, or `None` if the bot should stay.
+pub fn leave_reason(settings: &GuildSettings, idle: Duration,
+        listeners: Option<usize>) -> Option<LeaveReason> {
+    if settings.leave_when_alone() && listeners == Some(0) {
+        return Some(LeaveReason::Alone);
+    }
+
+    let idle_timeout = settings.idle_timeout()
+        .map(|minutes| Duration::from_secs(minutes as u64 * 60));
+
+    match idle_timeout {
+        Some(idle_timeout) if idle >= idle_timeout => Some(LeaveReason::Idle),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn settings(idle_timeout: Option<u32>, leave_when_alone: bool)
+            -> GuildSettings {
+        let mut settings = GuildSettings::default();
+        settings.set_idle_timeout(idle_timeout).unwrap();
+        settings.set_leave_when_alone(leave_when_alone);
+        settings
+    }
+
+    #[test]
+    fn idle_time_is_reset_by_activity() {
+        let guild_id = GuildId::new(1);
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new();
+
+        assert_eq!(Duration::ZERO, tracker.update(guild_id, false, start));
+        assert_eq!(MINUTE, tracker.update(guild_id, false, start + MINUTE));
+        assert_eq!(Duration::ZERO,
+            tracker.update(guild_id, true, start + 2 * MINUTE));
+        assert_eq!(Duration::ZERO,
+            tracker.update(guild_id, false, start + 3 * MINUTE));
+        assert_eq!(MINUTE,
+            tracker.update(guild_id, false, start + 4 * MINUTE));
+    }
+
+    #[test]
+    fn forgotten_guild_starts_idle_time_again() {
+        let guild_id = GuildId::new(1);
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new();
+
+        tracker.update(guild_id, false, start);
+        tracker.forget(guild_id);
+
+        assert_eq!(Duration::ZERO,
+            tracker.update(guild_id, false, start + MINUTE));
+    }
+
+    #[test]
+    fn bot_leaves_after_idle_timeout() {
+        let settings = settings(Some(5), false);
+
+        assert_eq!(None, leave_reason(&settings, 4 * MINUTE, Some(1)));
+        assert_eq!(Some(LeaveReason::Idle),
+            leave_reason(&settings, 5 * MINUTE, Some(1)));
+    }
+
+    #[test]
+    fn bot_leaves_when_alone_only_if_enabled() {
+        assert_eq!(None,
+            leave_reason(&settings(None, false), MINUTE, Some(0)));
+        assert_eq!(Some(LeaveReason::Alone),
+            leave_reason(&settings(None, true), Duration::ZERO, Some(0)));
+        assert_eq!(None, leave_reason(&settings(None, true), MINUTE, Some(2)));
+        assert_eq!(None, leave_reason(&settings(None, true), MINUTE, None));
+    }
+}
@@ -0,0 +1,26 @@
+pub mod alias;
+pub mod audio;
+pub mod command_data;
+pub mod command;
+pub mod config;
+pub mod console;
+pub mod control;
+pub mod event;
+pub mod idle;
+pub mod key_value;
+pub mod limiter;
+pub mod logging;
+pub mod loudness;
+pub mod meter;
+pub mod migration;
+pub mod osc;
+pub mod permission;
+pub mod playback;
+pub mod plugin;
+pub mod recording;
+pub mod render;
+pub mod scene;
+pub mod schedule;
+pub mod settings;
+pub mod state;
+pub mod web;
@@ -0,0 +1,340 @@
+use rambot_api::Sample;
+
+use std::collections::VecDeque;
+
+/// The default ceiling of a [Limiter], i.e. the maximum absolute amplitude it
+/// lets through.
+pub const DEFAULT_CEILING: f32 = 0.95;
+
+/// The number of samples it takes for the gain reduction of a [Limiter] to
+/// decay to roughly a third after the signal fell below the ceiling, i.e.
+/// 100 ms.
+const RELEASE_SAMPLES: f32 = 4800.0;
+
+/// The number of samples by which a [Limiter] looks ahead, i.e. 5 ms. The
+/// output of an enabled limiter is delayed by this many samples.
+pub const LOOKAHEAD_SAMPLES: usize = 240;
+
+/// Gain reductions smaller than this are not counted as limiting in the
+/// [LimiterStatistics].
+const LIMITING_THRESHOLD: f32 = 0.001;
+
+/// Statistics about how often a [Limiter] had to reduce the gain of the audio
+/// passing through it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LimiterStatistics {
+
+    /// The total number of samples processed by the limiter.
+    pub processed_samples: u64,
+
+    /// The number of samples where the limiter reduced the gain.
+    pub limited_samples: u64,
+
+    /// The number of times the limiter started reducing the gain after it had
+    /// been idle before.
+    pub activations: u64
+}
+
+impl LimiterStatistics {
+
+    /// Gets the fraction of processed samples on which the gain was reduced,
+    /// as a number between 0 and 1. If no samples were processed yet, this
+    /// is 0.
+    pub fn limited_fraction(&self) -> f64 {
+        if self.processed_samples == 0 {
+            0.0
+        }
+        else {
+            self.limited_samples as f64 / self.processed_samples as f64
+        }
+    }
+}
+
+/// A look-ahead peak limiter which protects the output of a
+/// [Mixer](crate::audio::Mixer) from clipping. The audio is delayed by
+/// [LOOKAHEAD_SAMPLES], so the gain can already be reduced smoothly before a
+/// sample which would exceed the configured ceiling reaches the output, where
+/// it is then kept at the ceiling. Once the signal becomes quieter again, the
+/// gain smoothly returns to 1. Limiters are enabled by default.
+pub struct Limiter {
+    enabled: bool,
+    ceiling: f32,
+    gain: f32,
+    release_coefficient: f32,
+    delay_line: VecDeque<(Sample, f32)>,
+    limited_in_delay_line: usize,
+    pending: usize,
+    limiting: bool,
+    statistics: LimiterStatistics
+}
+
+impl Limiter {
+
+    /// Creates a new, enabled limiter with the [DEFAULT_CEILING].
+    pub fn new() -> Limiter {
+        Limiter {
+            enabled: true,
+            ceiling: DEFAULT_CEILING,
+            gain: 1.0,
+            release_coefficient: (-1.0 / RELEASE_SAMPLES).exp(),
+            delay_line: silent_delay_line(),
+            limited_in_delay_line: 0,
+            pending: 0,
+            limiting: false,
+            statistics: LimiterStatistics::default()
+        }
+    }
+
+    /// Indicates whether this limiter is enabled, i.e. processes audio.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables or disables this limiter. This resets the statistics and drops
+    /// any audio which is still delayed.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.gain = 1.0;
+        self.delay_line = silent_delay_line();
+        self.limited_in_delay_line = 0;
+        self.pending = 0;
+        self.limiting = false;
+        self.reset_statistics();
+    }
+
+    /// Gets the ceiling of this limiter, i.e. the maximum absolute amplitude
+    /// of samples in its output.
+    pub fn ceiling(&self) -> f32 {
+        self.ceiling
+    }
+
+    /// Sets the ceiling of this limiter, i.e. the maximum absolute amplitude
+    /// of samples in its output. This resets the statistics. Panics if the
+    /// ceiling is not positive and finite.
+    pub fn set_ceiling(&mut self, ceiling: f32) {
+        assert!(ceiling > 0.0 && ceiling.is_finite(),
+            "limiter ceiling must be positive and finite");
+
+        self.ceiling = ceiling;
+        self.reset_statistics();
+    }
+
+    /// Gets [LimiterStatistics] about how often this limiter reduced the gain
+    /// since it was created or its configuration was last changed.
+    pub fn statistics(&self) -> LimiterStatistics {
+        self.statistics
+    }
+
+    /// Resets the [LimiterStatistics] of this limiter.
+    pub fn reset_statistics(&mut self) {
+        self.statistics = LimiterStatistics::default();
+    }
+
+    /// Processes the given audio in-place, if this limiter is enabled. The
+    /// given audio is replaced by the processed audio which was delayed by
+    /// [LOOKAHEAD_SAMPLES].
+    pub fn process(&mut self, buf: &mut [Sample]) {
+        if !self.enabled {
+            return;
+        }
+
+        self.process_delayed(buf);
+        self.pending = (self.pending + buf.len()).min(LOOKAHEAD_SAMPLES);
+    }
+
+    /// Writes the processed audio which is still delayed into the given
+    /// buffer, as if the input was followed by silence. This should be called
+    /// once the input ended, so its last samples are not lost.
+    ///
+    /// # Returns
+    ///
+    /// The number of samples written to the start of the buffer. This is 0 if
+    /// no more audio is delayed or the limiter is disabled.
+    pub fn flush(&mut self, buf: &mut [Sample]) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+
+        let count = self.pending.min(buf.len());
+
+        buf[..count].fill(Sample::ZERO);
+        self.process_delayed(&mut buf[..count]);
+        self.pending -= count;
+        count
+    }
+
+    /// Gets the largest gain which may be applied to the sample which just
+    /// left the delay line and requires the given gain itself. The gain
+    /// reduction required by a sample in the delay line is faded in linearly
+    /// over the look-ahead, so it is reached exactly when that sample is
+    /// output.
+    fn envelope(&self, required_gain: f32) -> f32 {
+        if self.limited_in_delay_line == 0 {
+            return required_gain;
+        }
+
+        let length = (LOOKAHEAD_SAMPLES + 1) as f32;
+
+        self.delay_line.iter()
+            .enumerate()
+            .filter(|(_, &(_, required_gain))| required_gain < 1.0)
+            .map(|(index, &(_, required_gain))| {
+                let weight = (LOOKAHEAD_SAMPLES - index) as f32 / length;
+
+                1.0 - (1.0 - required_gain) * weight
+            })
+            .fold(required_gain, f32::min)
+    }
+
+    fn process_delayed(&mut self, buf: &mut [Sample]) {
+        for sample in buf {
+            let peak = sample.abs_amplitude();
+            let required_gain = if peak > self.ceiling {
+                self.limited_in_delay_line += 1;
+                self.ceiling / peak
+            }
+            else {
+                1.0
+            };
+
+            self.delay_line.push_back((*sample, required_gain));
+
+            let (delayed, required_gain) =
+                self.delay_line.pop_front().unwrap();
+
+            if required_gain < 1.0 {
+                self.limited_in_delay_line -= 1;
+            }
+
+            self.gain = 1.0 - (1.0 - self.gain) * self.release_coefficient;
+            self.gain = self.gain.min(self.envelope(required_gain));
+
+            let limiting = self.gain < 1.0 - LIMITING_THRESHOLD;
+
+            if limiting {
+                self.statistics.limited_samples += 1;
+
+                if !self.limiting {
+                    self.statistics.activations += 1;
+                }
+            }
+
+            self.limiting = limiting;
+            self.statistics.processed_samples += 1;
+            *sample = delayed * self.gain;
+        }
+    }
+}
+
+fn silent_delay_line() -> VecDeque<(Sample, f32)> {
+    let mut delay_line = VecDeque::with_capacity(LOOKAHEAD_SAMPLES + 1);
+    delay_line.extend((0..LOOKAHEAD_SAMPLES).map(|_| (Sample::ZERO, 1.0)));
+    delay_line
+}
+
+impl Default for Limiter {
+    fn default() -> Limiter {
+        Limiter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn enabled_limiter() -> Limiter {
+        let mut limiter = Limiter::new();
+        limiter.set_enabled(true);
+        limiter
+    }
+
+    fn process_to_end(limiter: &mut Limiter, audio: &[Sample]) -> Vec<Sample> {
+        let mut processed = audio.to_vec();
+        let mut tail = vec![Sample::ZERO; LOOKAHEAD_SAMPLES * 2];
+
+        limiter.process(&mut processed);
+
+        let count = limiter.flush(&mut tail);
+
+        processed.extend_from_slice(&tail[..count]);
+        processed
+    }
+
+    #[test]
+    fn quiet_audio_is_only_delayed() {
+        let audio = rambot_test_util::test_data(4800, 60.0, 120.0).into_iter()
+            .map(|s| s * 0.5)
+            .collect::<Vec<_>>();
+        let mut limiter = enabled_limiter();
+        let processed = process_to_end(&mut limiter, &audio);
+
+        assert!(processed[..LOOKAHEAD_SAMPLES].iter()
+            .all(|&s| s == Sample::ZERO));
+        rambot_test_util::assert_approximately_equal(audio,
+            &processed[LOOKAHEAD_SAMPLES..]);
+        assert_eq!(0, limiter.statistics().limited_samples);
+    }
+
+    #[test]
+    fn loud_audio_does_not_exceed_ceiling() {
+        let audio = rambot_test_util::test_data(4800, 60.0, 120.0)
+            .into_iter()
+            .map(|s| s * 3.0)
+            .collect::<Vec<_>>();
+        let mut limiter = enabled_limiter();
+        limiter.set_ceiling(0.8);
+
+        let processed = process_to_end(&mut limiter, &audio);
+
+        assert_eq!(audio.len() + LOOKAHEAD_SAMPLES, processed.len());
+        assert!(processed.iter().all(|s| s.abs_amplitude() <= 0.8 + 0.0001));
+        assert!(limiter.statistics().limited_samples > 0);
+        assert!(limiter.statistics().activations > 0);
+    }
+
+    #[test]
+    fn disabled_limiter_does_nothing() {
+        let audio = vec![Sample::mono(2.0); 100];
+        let mut processed = audio.clone();
+        let mut limiter = Limiter::new();
+
+        assert!(limiter.enabled());
+
+        limiter.set_enabled(false);
+        limiter.process(&mut processed);
+
+        assert_eq!(audio, processed);
+        assert_eq!(0, limiter.flush(&mut processed));
+        assert_eq!(LimiterStatistics::default(), limiter.statistics());
+    }
+
+    #[test]
+    fn gain_is_reduced_before_peak() {
+        let mut audio = vec![Sample::mono(0.5); 1000];
+        audio.push(Sample::mono(2.0));
+        audio.append(&mut vec![Sample::mono(0.5); 1000]);
+        let mut limiter = enabled_limiter();
+
+        let processed = process_to_end(&mut limiter, &audio);
+        let peak = 1000 + LOOKAHEAD_SAMPLES;
+
+        assert_eq!(0.5, processed[peak - LOOKAHEAD_SAMPLES - 1].left);
+        assert!(processed[peak - LOOKAHEAD_SAMPLES / 2].left < 0.5);
+        assert!(processed[peak - 1].left < processed[peak - 2].left);
+        assert!((processed[peak].left - DEFAULT_CEILING).abs() < 0.0001);
+    }
+
+    #[test]
+    fn gain_recovers_after_peak() {
+        let mut audio = vec![Sample::mono(2.0)];
+        audio.append(&mut vec![Sample::mono(0.5); 48000]);
+        let mut limiter = enabled_limiter();
+
+        let processed = process_to_end(&mut limiter, &audio);
+
+        assert!(processed[LOOKAHEAD_SAMPLES + 1].left < 0.5);
+        assert!((processed.last().unwrap().left - 0.5).abs() < 0.0001);
+        assert_eq!(1, limiter.statistics().activations);
+    }
+}
@@ -0,0 +1,641 @@
+use rambot_api::{AudioMetadata, Sample};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The loudness in LUFS to which tracks are normalized. This is the
+/// ReplayGain reference level, so gains read from ReplayGain tags can be
+/// applied directly.
+pub const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// The maximum absolute gain in dB that is applied based on a loudness
+/// measurement, to avoid extreme amplification of very quiet passages at the
+/// start of a track. Positive gains read from tags are limited to this as
+/// well.
+const MAX_GAIN: f64 = 12.0;
+
+/// The number of samples in one step of the loudness measurement (100 ms).
+const STEP_SAMPLES: usize = 4800;
+
+/// The number of steps that constitute one gating block (400 ms).
+const STEPS_PER_BLOCK: usize = 4;
+
+/// The number of gating blocks that have to be measured before the measured
+/// loudness is used for normalization (3 s).
+const MIN_MEASURED_BLOCKS: usize = 27;
+
+/// Blocks quieter than this absolute loudness in LUFS are ignored.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Blocks more than this many LU below the ungated loudness are ignored.
+const RELATIVE_GATE: f64 = 10.0;
+
+/// The width in LU of a bin of the histogram of block loudnesses, which
+/// determines the precision of the relative gate.
+const HISTOGRAM_BIN_WIDTH: f64 = 0.1;
+
+/// The number of bins of the histogram of block loudnesses, which covers the
+/// loudnesses from the [ABSOLUTE_GATE] up to 10 LUFS. Louder blocks are
+/// counted in the last bin.
+const HISTOGRAM_BINS: usize = 800;
+
+/// The maximum change of the normalization factor per sample, such that
+/// changes in the normalization gain do not cause audible clicks.
+const FACTOR_RAMP_STEP: f32 = 1.0 / STEP_SAMPLES as f32;
+
+/// Converts a gain in dB to a linear factor.
+pub fn db_to_factor(db: f64) -> f32 {
+    10f64.powf(db / 20.0) as f32
+}
+
+/// A biquad filter in transposed direct form II, used for the K-weighting of
+/// the [LoudnessMeter].
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [[f64; 2]; 2]
+}
+
+impl Biquad {
+    const fn new(b: [f64; 3], a: [f64; 2]) -> Biquad {
+        Biquad {
+            b,
+            a,
+            state: [[0.0; 2]; 2]
+        }
+    }
+
+    fn process(&mut self, channel: usize, x: f64) -> f64 {
+        let state = &mut self.state[channel];
+        let y = self.b[0] * x + state[0];
+        state[0] = self.b[1] * x - self.a[0] * y + state[1];
+        state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+// Coefficients of the K-weighting filter at 48 kHz as given in ITU-R BS.1770.
+
+const PRE_FILTER: Biquad = Biquad::new(
+    [1.53512485958697, -2.69169618940638, 1.19839281085285],
+    [-1.69065929318241, 0.73248077421585]);
+
+const RLB_FILTER: Biquad = Biquad::new(
+    [1.0, -2.0, 1.0],
+    [-1.99004745483398, 0.99007225036621]);
+
+fn block_loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+fn histogram_bin(loudness: f64) -> usize {
+    let bin = ((loudness - ABSOLUTE_GATE) / HISTOGRAM_BIN_WIDTH).floor();
+
+    (bin.max(0.0) as usize).min(HISTOGRAM_BINS - 1)
+}
+
+/// The gating blocks counted in one bin of the histogram of a
+/// [LoudnessMeter].
+#[derive(Clone, Copy, Default)]
+struct HistogramBin {
+    count: usize,
+    mean_square_sum: f64
+}
+
+/// Measures the integrated loudness of audio according to EBU R128 (ITU-R
+/// BS.1770) while it is being played. Gating blocks are counted in a
+/// histogram, so the memory does not grow with the length of the audio.
+#[derive(Clone)]
+pub struct LoudnessMeter {
+    pre_filter: Biquad,
+    rlb_filter: Biquad,
+    step_energy: f64,
+    step_len: usize,
+    recent_steps: [f64; STEPS_PER_BLOCK],
+    completed_steps: usize,
+    histogram: Vec<HistogramBin>,
+    block_count: usize,
+    mean_square_sum: f64,
+    integrated: Option<f64>
+}
+
+impl LoudnessMeter {
+
+    /// Creates a new loudness meter which has not measured any audio yet.
+    pub fn new() -> LoudnessMeter {
+        LoudnessMeter {
+            pre_filter: PRE_FILTER,
+            rlb_filter: RLB_FILTER,
+            step_energy: 0.0,
+            step_len: 0,
+            recent_steps: [0.0; STEPS_PER_BLOCK],
+            completed_steps: 0,
+            histogram: vec![HistogramBin::default(); HISTOGRAM_BINS],
+            block_count: 0,
+            mean_square_sum: 0.0,
+            integrated: None
+        }
+    }
+
+    fn complete_step(&mut self) {
+        self.recent_steps[self.completed_steps % STEPS_PER_BLOCK] =
+            self.step_energy;
+        self.completed_steps += 1;
+        self.step_energy = 0.0;
+        self.step_len = 0;
+
+        if self.completed_steps < STEPS_PER_BLOCK {
+            return;
+        }
+
+        let block_samples = (STEP_SAMPLES * STEPS_PER_BLOCK) as f64;
+        let mean_square = self.recent_steps.iter().sum::<f64>() / block_samples;
+        let loudness = block_loudness(mean_square);
+
+        if loudness > ABSOLUTE_GATE {
+            let bin = &mut self.histogram[histogram_bin(loudness)];
+
+            bin.count += 1;
+            bin.mean_square_sum += mean_square;
+            self.block_count += 1;
+            self.mean_square_sum += mean_square;
+            self.integrated = None;
+        }
+    }
+
+    /// Feeds the given audio into this meter.
+    pub fn process(&mut self, buf: &[Sample]) {
+        for sample in buf {
+            let mut energy = 0.0;
+
+            for (channel, value) in [sample.left, sample.right].into_iter()
+                    .enumerate() {
+                let value = self.pre_filter.process(channel, value as f64);
+                let value = self.rlb_filter.process(channel, value);
+                energy += value * value;
+            }
+
+            self.step_energy += energy;
+            self.step_len += 1;
+
+            if self.step_len == STEP_SAMPLES {
+                self.complete_step();
+            }
+        }
+    }
+
+    /// Gets the number of gating blocks of 400 ms which were loud enough to be
+    /// considered in the measurement so far.
+    pub fn measured_blocks(&self) -> usize {
+        self.block_count
+    }
+
+    /// Gets the integrated loudness in LUFS of all audio processed so far, or
+    /// `None` if no sufficiently loud audio was processed yet. The relative
+    /// gate is applied with the precision of the histogram bins.
+    pub fn integrated_loudness(&mut self) -> Option<f64> {
+        if self.integrated.is_none() && self.block_count > 0 {
+            let ungated = self.mean_square_sum / self.block_count as f64;
+            let threshold = block_loudness(ungated) - RELATIVE_GATE;
+            let (sum, count) = self.histogram[histogram_bin(threshold)..].iter()
+                .fold((0.0, 0), |(sum, count), bin|
+                    (sum + bin.mean_square_sum, count + bin.count));
+
+            self.integrated = Some(block_loudness(sum / count as f64));
+        }
+
+        self.integrated
+    }
+}
+
+impl Default for LoudnessMeter {
+    fn default() -> LoudnessMeter {
+        LoudnessMeter::new()
+    }
+}
+
+/// An enumeration of the modes in which loudness normalization can operate.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NormalizationMode {
+
+    /// No loudness normalization is applied.
+    Off,
+
+    /// Every track is normalized individually, using the track gain.
+    Track,
+
+    /// Tracks are normalized using the album gain, which preserves the
+    /// relative loudness of tracks on the same album. If a track has no album
+    /// gain, its track gain is used instead.
+    Album
+}
+
+impl Display for NormalizationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NormalizationMode::Off => write!(f, "off"),
+            NormalizationMode::Track => write!(f, "track"),
+            NormalizationMode::Album => write!(f, "album")
+        }
+    }
+}
+
+/// The error returned when parsing an invalid [NormalizationMode].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseNormalizationModeError(String);
+
+impl Display for ParseNormalizationModeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f,
+            "Unknown normalization mode `{}`. Expected `off`, `track`, or \
+                `album`.", self.0)
+    }
+}
+
+impl std::error::Error for ParseNormalizationModeError { }
+
+impl FromStr for NormalizationMode {
+    type Err = ParseNormalizationModeError;
+
+    fn from_str(s: &str) -> Result<NormalizationMode, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(NormalizationMode::Off),
+            "track" => Ok(NormalizationMode::Track),
+            "album" => Ok(NormalizationMode::Album),
+            _ => Err(ParseNormalizationModeError(s.to_owned()))
+        }
+    }
+}
+
+/// The per-guild configuration of loudness normalization, which is off by
+/// default.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NormalizationSettings {
+
+    /// The [NormalizationMode] which determines which gain is applied.
+    pub mode: NormalizationMode,
+
+    /// Whether the loudness of tracks without loudness tags shall be measured
+    /// while they are played and used for normalization.
+    pub analyze: bool
+}
+
+impl Default for NormalizationSettings {
+    fn default() -> NormalizationSettings {
+        NormalizationSettings {
+            mode: NormalizationMode::Off,
+            analyze: false
+        }
+    }
+}
+
+/// The source of the gain which a [Normalizer] currently applies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalizationGain {
+
+    /// No gain is applied, either because normalization is disabled or
+    /// because no information about the loudness of the track is available
+    /// (yet).
+    None,
+
+    /// The gain in dB was read from the track gain tag.
+    TrackTag(f32),
+
+    /// The gain in dB was read from the album gain tag.
+    AlbumTag(f32),
+
+    /// The gain in dB was derived from measuring the loudness of the track.
+    Measured(f32)
+}
+
+impl NormalizationGain {
+
+    /// Gets the gain in dB, which is 0 if no gain is applied.
+    pub fn db(&self) -> f32 {
+        match self {
+            NormalizationGain::None => 0.0,
+            NormalizationGain::TrackTag(db) |
+            NormalizationGain::AlbumTag(db) |
+            NormalizationGain::Measured(db) => *db
+        }
+    }
+}
+
+impl Display for NormalizationGain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NormalizationGain::None => write!(f, "none"),
+            NormalizationGain::TrackTag(db) =>
+                write!(f, "{:+.2} dB (track tag)", db),
+            NormalizationGain::AlbumTag(db) =>
+                write!(f, "{:+.2} dB (album tag)", db),
+            NormalizationGain::Measured(db) =>
+                write!(f, "{:+.2} dB (measured)", db)
+        }
+    }
+}
+
+/// Applies loudness normalization to the audio of a single layer. The gain is
+/// taken from the loudness tags of the currently played track or, if enabled
+/// and no tags are available, from a [LoudnessMeter].
+pub struct Normalizer {
+    track_gain: Option<f32>,
+    album_gain: Option<f32>,
+    peak: Option<f32>,
+    meter: LoudnessMeter,
+    gain: NormalizationGain,
+    factor: f32
+}
+
+impl Normalizer {
+
+    /// Creates a new normalizer for a track without loudness tags.
+    pub fn new() -> Normalizer {
+        Normalizer {
+            track_gain: None,
+            album_gain: None,
+            peak: None,
+            meter: LoudnessMeter::new(),
+            gain: NormalizationGain::None,
+            factor: 1.0
+        }
+    }
+
+    /// Prepares this normalizer for a new track with the given metadata. The
+    /// current factor is retained and ramped towards the gain of the new
+    /// track to avoid clicks.
+    pub fn start_track(&mut self, metadata: &AudioMetadata) {
+        self.track_gain = metadata.track_gain();
+        self.album_gain = metadata.album_gain();
+
+        // The album peak is an upper bound of the track peak, so it is only
+        // used as a fallback.
+
+        self.peak = metadata.track_peak().or(metadata.album_peak());
+        self.meter = LoudnessMeter::new();
+    }
+
+    /// Gets the [NormalizationGain] which is currently applied.
+    pub fn gain(&self) -> NormalizationGain {
+        self.gain
+    }
+
+    /// Limits a positive gain read from a tag such that the track does not
+    /// clip, according to its tagged peak, and the gain does not exceed
+    /// [MAX_GAIN].
+    fn limit_tag_gain(&self, db: f32) -> f32 {
+        if db <= 0.0 {
+            return db;
+        }
+
+        let headroom = self.peak
+            .map(|peak| (-20.0 * (peak as f64).log10()).max(0.0))
+            .unwrap_or(MAX_GAIN);
+
+        db.min(headroom.min(MAX_GAIN) as f32)
+    }
+
+    fn compute_gain(&mut self, settings: NormalizationSettings)
+            -> NormalizationGain {
+        let track_gain = self.track_gain.map(|db| self.limit_tag_gain(db));
+        let album_gain = self.album_gain.map(|db| self.limit_tag_gain(db));
+        let tag_gain = match settings.mode {
+            NormalizationMode::Off => return NormalizationGain::None,
+            NormalizationMode::Track =>
+                track_gain.map(NormalizationGain::TrackTag),
+            NormalizationMode::Album =>
+                album_gain.map(NormalizationGain::AlbumTag)
+                    .or(track_gain.map(NormalizationGain::TrackTag))
+        };
+
+        if let Some(tag_gain) = tag_gain {
+            return tag_gain;
+        }
+
+        if !settings.analyze ||
+                self.meter.measured_blocks() < MIN_MEASURED_BLOCKS {
+            return NormalizationGain::None;
+        }
+
+        match self.meter.integrated_loudness() {
+            Some(loudness) => {
+                let db = (REFERENCE_LOUDNESS - loudness)
+                    .clamp(-MAX_GAIN, MAX_GAIN);
+
+                NormalizationGain::Measured(db as f32)
+            },
+            None => NormalizationGain::None
+        }
+    }
+
+    /// Processes the given audio of the current track in-place.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf`: The audio to normalize.
+    /// * `settings`: The [NormalizationSettings] of the guild.
+    pub fn process(&mut self, buf: &mut [Sample],
+            settings: NormalizationSettings) {
+        let has_tags = self.track_gain.is_some() || self.album_gain.is_some();
+
+        if settings.analyze && settings.mode != NormalizationMode::Off &&
+                !has_tags {
+            self.meter.process(buf);
+        }
+
+        self.gain = self.compute_gain(settings);
+
+        let target = db_to_factor(self.gain.db() as f64);
+
+        if self.factor == target {
+            if target != 1.0 {
+                for sample in buf.iter_mut() {
+                    *sample *= target;
+                }
+            }
+
+            return;
+        }
+
+        for sample in buf.iter_mut() {
+            if self.factor < target {
+                self.factor = (self.factor + FACTOR_RAMP_STEP).min(target);
+            }
+            else {
+                self.factor = (self.factor - FACTOR_RAMP_STEP).max(target);
+            }
+
+            *sample *= self.factor;
+        }
+    }
+}
+
+impl Default for Normalizer {
+    fn default() -> Normalizer {
+        Normalizer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use rambot_api::AudioMetadataBuilder;
+
+    fn sine(len: usize, frequency: f64, amplitude: f32) -> Vec<Sample> {
+        rambot_test_util::test_data(len, frequency, frequency).into_iter()
+            .map(|s| s * amplitude)
+            .collect()
+    }
+
+    fn settings(mode: NormalizationMode, analyze: bool)
+            -> NormalizationSettings {
+        NormalizationSettings {
+            mode,
+            analyze
+        }
+    }
+
+    #[test]
+    fn full_scale_sine_measures_about_zero_lufs() {
+        let mut meter = LoudnessMeter::new();
+
+        meter.process(&sine(48000 * 5, 997.0, 1.0));
+
+        let loudness = meter.integrated_loudness().unwrap();
+
+        assert!(loudness.abs() < 0.1, "measured {} LUFS", loudness);
+    }
+
+    #[test]
+    fn silence_is_gated() {
+        let mut meter = LoudnessMeter::new();
+
+        meter.process(&vec![Sample::ZERO; 48000 * 5]);
+
+        assert_eq!(0, meter.measured_blocks());
+        assert_eq!(None, meter.integrated_loudness());
+    }
+
+    #[test]
+    fn relative_gate_ignores_quiet_blocks() {
+        let mut meter = LoudnessMeter::new();
+        let mut audio = sine(48000 * 5, 997.0, 0.5);
+        audio.append(&mut sine(48000 * 5, 997.0, 0.005));
+
+        meter.process(&audio);
+
+        let loudness = meter.integrated_loudness().unwrap();
+
+        assert!((loudness + 6.02).abs() < 0.5, "measured {} LUFS", loudness);
+    }
+
+    #[test]
+    fn positive_tag_gain_is_limited_by_peak() {
+        let mut normalizer = Normalizer::new();
+        normalizer.start_track(&AudioMetadataBuilder::new()
+            .with_track_gain(10.0)
+            .with_track_peak(0.5)
+            .build());
+        let gain =
+            normalizer.compute_gain(settings(NormalizationMode::Track, false));
+
+        match gain {
+            NormalizationGain::TrackTag(db) =>
+                assert!((db - 6.02).abs() < 0.01, "gain was {} dB", db),
+            gain => panic!("unexpected gain {:?}", gain)
+        }
+    }
+
+    #[test]
+    fn positive_tag_gain_without_peak_is_limited_to_maximum() {
+        let mut normalizer = Normalizer::new();
+        normalizer.start_track(&AudioMetadataBuilder::new()
+            .with_track_gain(30.0)
+            .with_album_gain(-3.0)
+            .build());
+
+        assert_eq!(NormalizationGain::TrackTag(MAX_GAIN as f32),
+            normalizer.compute_gain(settings(NormalizationMode::Track, false)));
+        assert_eq!(NormalizationGain::AlbumTag(-3.0),
+            normalizer.compute_gain(settings(NormalizationMode::Album, false)));
+    }
+
+    #[test]
+    fn normalization_is_off_by_default() {
+        assert_eq!(NormalizationMode::Off,
+            NormalizationSettings::default().mode);
+    }
+
+    #[test]
+    fn album_mode_prefers_album_gain_and_falls_back_to_track_gain() {
+        let mut normalizer = Normalizer::new();
+        let metadata = AudioMetadataBuilder::new()
+            .with_track_gain(-6.0)
+            .with_album_gain(-3.0)
+            .build();
+        normalizer.start_track(&metadata);
+
+        assert_eq!(NormalizationGain::AlbumTag(-3.0),
+            normalizer.compute_gain(settings(NormalizationMode::Album, false)));
+        assert_eq!(NormalizationGain::TrackTag(-6.0),
+            normalizer.compute_gain(settings(NormalizationMode::Track, false)));
+
+        normalizer.start_track(&AudioMetadataBuilder::new()
+            .with_track_gain(-6.0)
+            .build());
+
+        assert_eq!(NormalizationGain::TrackTag(-6.0),
+            normalizer.compute_gain(settings(NormalizationMode::Album, false)));
+    }
+
+    #[test]
+    fn tag_gain_is_applied_after_ramp() {
+        let mut normalizer = Normalizer::new();
+        normalizer.start_track(&AudioMetadataBuilder::new()
+            .with_track_gain(-20.0)
+            .build());
+        let mut audio = vec![Sample::mono(1.0); STEP_SAMPLES * 2];
+
+        normalizer.process(&mut audio, settings(NormalizationMode::Track, false));
+
+        assert!(audio[0].left > 0.9);
+        assert!((audio.last().unwrap().left - 0.1).abs() < 0.0001);
+    }
+
+    #[test]
+    fn measured_loudness_is_normalized_to_reference() {
+        let mut normalizer = Normalizer::new();
+        let mut audio = sine(48000 * 10, 997.0, 1.0);
+
+        normalizer.process(&mut audio,
+            settings(NormalizationMode::Track, true));
+
+        match normalizer.gain() {
+            NormalizationGain::Measured(db) =>
+                assert!((db + 12.0).abs() < 0.1, "gain was {} dB", db),
+            gain => panic!("unexpected gain {:?}", gain)
+        }
+    }
+
+    #[test]
+    fn off_mode_does_not_change_audio() {
+        let mut normalizer = Normalizer::new();
+        normalizer.start_track(&AudioMetadataBuilder::new()
+            .with_track_gain(-6.0)
+            .build());
+        let audio = sine(4800, 440.0, 0.5);
+        let mut processed = audio.clone();
+
+        normalizer.process(&mut processed,
+            settings(NormalizationMode::Off, true));
+
+        assert_eq!(audio, processed);
+        assert_eq!(NormalizationGain::None, normalizer.gain());
+    }
+}
@@ -1,33 +1,48 @@
-use crate::command::{BoardButtonEventHandler, CommandError, CommandResult};
-use crate::command_data::CommandData;
-use crate::config::Config;
-use crate::event::FrameworkEventHandler;
-use crate::logging::LoggingEventHandler;
-use crate::plugin::PluginManager;
-use crate::state::State;
+use rambot::command::{
+    self,
+    check_permissions,
+    guild_prefix,
+    BoardButtonEventHandler,
+    CommandError,
+    CommandResult,
+    FollowEventHandler
+};
+use rambot::command_data::CommandData;
+use rambot::config::{Config, CONFIG_PATH_VARIABLE};
+use rambot::console::{self, Console, Output, OutputSink};
+use rambot::control::Controller;
+use rambot::event::FrameworkEventHandler;
+use rambot::idle::IdleTracker;
+use rambot::logging::{self, LoggingEventHandler};
+use rambot::osc;
+use rambot::playback::PlaybackStore;
+use rambot::plugin::PluginManager;
+use rambot::state::State;
+use rambot::web;
 
+use serenity::cache::Cache;
 use serenity::client::{Client, Context};
+use serenity::model::id::{ChannelId, GuildId};
 use serenity::prelude::GatewayIntents;
 
 use simplelog::LevelFilter;
 
-use songbird::SerenityInit;
+use songbird::{SerenityInit, Songbird};
 
+use std::env;
+use std::io;
 use std::sync::Arc;
+use std::time::Duration;
 use poise::{Command, FrameworkContext, FrameworkError, FrameworkOptions, PrefixFrameworkOptions};
 use serenity::all::{FullEvent, UserId};
 
-pub mod audio;
-pub mod command_data;
-pub mod command;
-pub mod config;
-pub mod event;
-pub mod key_value;
-pub mod logging;
-pub mod plugin;
-pub mod state;
-
 async fn handle_error(err: FrameworkError<'_, CommandData, CommandError>) {
+    if let FrameworkError::CommandCheckFailed { error: None, .. } = err {
+        // The permission check already told the author why they may not use the command.
+
+        return;
+    }
+
     match err.ctx() {
         Some(ctx) => {
             if let Err(reply_err) = ctx.reply(format!("{}", err)).await {
@@ -43,6 +58,7 @@ async fn handle_error(err: FrameworkError<'_, CommandData, CommandError>) {
 async fn handle_event(serenity_ctx: &Context, event: &FullEvent,
         framework_ctx: FrameworkContext<'_, CommandData, CommandError>) -> CommandResult {
     BoardButtonEventHandler.handle_event(serenity_ctx, event, framework_ctx).await?;
+    FollowEventHandler.handle_event(serenity_ctx, event, framework_ctx).await?;
     LoggingEventHandler.handle_event(serenity_ctx, event, framework_ctx).await
 }
 
@@ -58,6 +74,7 @@ fn get_framework_options<'uid>(
             ..Default::default()
         },
         owners: owners.into_iter().cloned().collect(),
+        command_check: Some(|ctx| Box::pin(check_permissions(ctx))),
         on_error: |err| Box::pin(handle_error(err)),
         event_handler: |serenity_ctx, event, framework_ctx, _|
             Box::pin(handle_event(serenity_ctx, event, framework_ctx)),
@@ -73,12 +90,177 @@ fn get_framework_options_for_configured_modes(
         commands.iter_mut().for_each(|command| command.slash_action = None);
     }
 
-    get_framework_options(config.prefix(), config.owners(), commands)
+    // The prefix is resolved for every message, since guilds may override the global prefix.
+
+    let mut framework_options = get_framework_options(None, config.owners(), commands);
+    framework_options.prefix_options.dynamic_prefix = Some(|ctx| Box::pin(guild_prefix(ctx)));
+    framework_options
+}
+
+/// The interval in which the playback of all guilds is saved if it is resumed after a restart.
+const PLAYBACK_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Resumes the playback saved before the bot was stopped and then keeps saving the playback, so it
+/// can be resumed after the next restart or crash.
+async fn resume_and_save_playback(controller: Controller, store: PlaybackStore) {
+    controller.restore_playback(&store).await;
+
+    let mut interval = tokio::time::interval(PLAYBACK_SAVE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        controller.save_playback(&store).await;
+    }
+}
+
+/// The interval in which the bot checks whether it should leave voice channels in which it is idle
+/// or alone.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Counts the users other than bots in the given voice channel according to the cache, or returns
+/// `None` if the guild is not cached.
+fn count_listeners(cache: &Cache, guild_id: GuildId, channel_id: ChannelId) -> Option<usize> {
+    let bot_id = cache.current_user().id;
+    let guild = cache.guild(guild_id)?;
+    let listeners = guild.voice_states.values()
+        .filter(|voice_state| voice_state.channel_id == Some(channel_id))
+        .filter(|voice_state| voice_state.user_id != bot_id)
+        .filter(|voice_state| {
+            let bot = voice_state.member.as_ref()
+                .map(|member| member.user.bot)
+                .or_else(|| cache.user(voice_state.user_id).map(|user| user.bot));
+
+            !bot.unwrap_or(false)
+        })
+        .count();
+
+    Some(listeners)
+}
+
+/// Keeps leaving the voice channels of guilds in which no audio was playing for too long or no one
+/// is listening, as configured in their settings.
+async fn leave_idle_channels(controller: Controller, cache: Arc<Cache>) {
+    let mut tracker = IdleTracker::new();
+    let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        controller.leave_idle_channels(&mut tracker,
+            |guild_id, channel_id| count_listeners(&cache, guild_id, channel_id)).await;
+    }
+}
+
+/// Where to write the mixer output when running on the console.
+#[derive(Debug, PartialEq)]
+enum ConsoleOutput {
+    Wav(String),
+    Pcm(String)
+}
+
+/// Options for running the bot on the console instead of connecting to Discord.
+#[derive(Debug, PartialEq)]
+struct ConsoleOptions {
+    guild_id: u64,
+    output: Option<ConsoleOutput>
+}
+
+/// Options given on the command line.
+#[derive(Debug, PartialEq)]
+struct Options {
+    config_path: Option<String>,
+    console: Option<ConsoleOptions>
+}
+
+/// Parses the command line arguments (without the program name). The console options are `None` if
+/// the bot should connect to Discord as usual, i.e. if `--console` is not given.
+fn parse_options(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut args = args.into_iter();
+    let mut config_path = None;
+    let mut console = false;
+    let mut options = ConsoleOptions {
+        guild_id: console::LOCAL_GUILD_ID,
+        output: None
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for `{}`.", arg));
+
+        match arg.as_str() {
+            "--config" => config_path = Some(value()?),
+            "--console" => console = true,
+            "--guild" => {
+                let guild_id = value()?;
+
+                options.guild_id = guild_id.parse().ok()
+                    .filter(|&id| id != 0)
+                    .ok_or_else(|| format!("Invalid guild ID: {}", guild_id))?;
+            },
+            "--wav" => options.output = Some(ConsoleOutput::Wav(value()?)),
+            "--pcm" => options.output = Some(ConsoleOutput::Pcm(value()?)),
+            _ => return Err(format!("Unknown argument: {}", arg))
+        }
+    }
+
+    let console = if console {
+        Some(options)
+    }
+    else if options != (ConsoleOptions { guild_id: console::LOCAL_GUILD_ID, output: None }) {
+        return Err("The options `--guild`, `--wav`, and `--pcm` require `--console`.".to_owned());
+    }
+    else {
+        None
+    };
+
+    Ok(Options {
+        config_path,
+        console
+    })
+}
+
+fn run_console(state: State, plugin_mgr: Arc<PluginManager>, options: ConsoleOptions)
+        -> io::Result<()> {
+    let mut console = Console::new(state, plugin_mgr, GuildId::new(options.guild_id));
+    let sink = match options.output {
+        Some(ConsoleOutput::Wav(path)) => Some(OutputSink::wav(path)?),
+        Some(ConsoleOutput::Pcm(path)) => Some(OutputSink::pcm(path)?),
+        None => {
+            log::warn!("No output specified, audio will not be played. Use `--wav` or `--pcm` to \
+                write the mixer output to a file.");
+            None
+        }
+    };
+    let output = sink.map(|sink| Output::start(console.mixer_arc(), sink));
+
+    println!("Running on the console. Type `help` for a list of commands.");
+    console.run(io::stdin().lock())?;
+
+    if let Some(output) = output {
+        output.stop()?;
+    }
+
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() {
-    let config = match Config::load() {
+    let options = match parse_options(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage: rambot [--config <file>] \
+                [--console [--guild <id>] [--wav <file> | --pcm <file>]]");
+            return;
+        }
+    };
+    let config_path = options.config_path.or_else(|| env::var(CONFIG_PATH_VARIABLE).ok());
+    let console_options = options.console;
+    let config_res = if console_options.is_some() {
+        Config::load_local(config_path.as_deref())
+    }
+    else {
+        Config::load(config_path.as_deref())
+    };
+    let config = match config_res {
         Ok(c) => c,
         Err(e) => {
             if let Err(e_log) = logging::init(LevelFilter::Error) {
@@ -121,6 +303,19 @@ async fn main() {
 
     log::info!("Successfully loaded state for {} guilds.", state.guild_count());
 
+    if let Some(options) = console_options {
+        let console_res =
+            tokio::task::spawn_blocking(move || run_console(state, plugin_mgr, options)).await;
+
+        match console_res {
+            Ok(Ok(())) => { },
+            Ok(Err(e)) => log::error!("Error running console: {}", e),
+            Err(e) => log::error!("Console panicked: {}", e)
+        }
+
+        return;
+    }
+
     let token = config.token().to_owned();
     let framework_options =
         get_framework_options_for_configured_modes(&config, command::commands());
@@ -128,11 +323,48 @@ async fn main() {
         get_framework_options(config.prefix().or(Some("")), config.owners(), command::commands());
     let command_data =
         CommandData::new(config, plugin_mgr, state, programmatic_command_framework_options);
+    let songbird = Songbird::serenity();
+
+    let controller = Controller::new(command_data.state_arc(), command_data.plugin_manager_arc(),
+        Some(Arc::clone(&songbird)), command_data.config().prefix());
+    let idle_controller = controller.clone();
+    let playback_persistence = if command_data.config().resume_playback() {
+        let store = PlaybackStore::new(command_data.config().state_directory());
+
+        Some((controller.clone(), store))
+    }
+    else {
+        None
+    };
+
+    if let Some(web_api_config) = command_data.config().web_api() {
+        if let Err(e) = web::start(web_api_config, controller.clone()) {
+            log::error!("{}", e);
+            return;
+        }
+    }
+
+    if let Some(osc_config) = command_data.config().osc() {
+        if let Err(e) = osc::start(osc_config, controller).await {
+            log::error!("{}", e);
+            return;
+        }
+    }
+
     let framework = poise::Framework::builder()
         .options(framework_options)
-        .setup(|ctx, _ready, framework| {
+        .setup(|ctx, ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+
+                if let Some((controller, store)) = playback_persistence {
+                    tokio::spawn(resume_and_save_playback(controller, store));
+                }
+
+                tokio::spawn(leave_idle_channels(idle_controller, Arc::clone(&ctx.cache)));
+                tokio::spawn(command::run_scheduler(ctx.clone(), command_data.clone(),
+                    ready.user.id, Arc::clone(framework.shard_manager())));
+
                 Ok(command_data)
             })
         })
@@ -141,7 +373,7 @@ async fn main() {
         GatewayIntents::MESSAGE_CONTENT;
     let client_res = Client::builder(token, intents)
         .framework(framework)
-        .register_songbird()
+        .register_songbird_with(songbird)
         .await;
     let mut client = match client_res {
         Ok(c) => c,
@@ -195,10 +427,50 @@ mod tests {
         let expected_user_id = UserId::from_str("123").unwrap();
         assert_that!(&framework_options.commands).has_length(1);
         assert_that!(&framework_options.commands[0].slash_action).is_some();
-        assert_that!(&framework_options.prefix_options.prefix).contains("!".to_owned());
+        assert_that!(&framework_options.prefix_options.prefix).is_none();
+        assert!(framework_options.prefix_options.dynamic_prefix.is_some());
         assert_that!(&framework_options.owners).contains_exactly_in_any_order(&[expected_user_id]);
     }
     
+    #[test]
+    fn parse_options_without_console() {
+        let options = parse_options(Vec::new()).unwrap();
+
+        assert_that!(options.config_path).is_none();
+        assert_that!(options.console).is_none();
+    }
+
+    #[test]
+    fn parse_options_with_console() {
+        let args = ["--console", "--guild", "123", "--pcm", "out.pcm"].map(str::to_owned);
+
+        let options = parse_options(args).unwrap();
+
+        assert_that!(options.console).contains(ConsoleOptions {
+            guild_id: 123,
+            output: Some(ConsoleOutput::Pcm("out.pcm".to_owned()))
+        });
+    }
+
+    #[test]
+    fn parse_options_with_config_path() {
+        let args = ["--config", "/etc/rambot/config.toml"].map(str::to_owned);
+
+        let options = parse_options(args).unwrap();
+
+        assert_that!(options.config_path).contains("/etc/rambot/config.toml".to_owned());
+        assert_that!(options.console).is_none();
+    }
+
+    #[test]
+    fn parse_options_rejects_invalid_arguments() {
+        assert!(parse_options(["--wav", "a.wav"].map(str::to_owned)).is_err());
+        assert!(parse_options(["--console", "--guild"].map(str::to_owned)).is_err());
+        assert!(parse_options(["--console", "--guild", "0"].map(str::to_owned)).is_err());
+        assert!(parse_options(["--console", "--frobnicate"].map(str::to_owned)).is_err());
+        assert!(parse_options(["--config"].map(str::to_owned)).is_err());
+    }
+
     #[test]
     fn get_framework_options_works_without_slash_commands() {
         let config: Config = get_config("!", false, &["123", "456"]);
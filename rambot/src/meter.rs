@@ -0,0 +1,170 @@
+use rambot_api::Sample;
+
+/// The number of samples over which levels are measured (300 ms).
+const WINDOW_SAMPLES: usize = 14400;
+
+/// The lowest level in dBFS that is reported. Quieter levels, including
+/// silence, are reported as this value.
+pub const MIN_DB: f32 = -96.0;
+
+fn to_db(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        MIN_DB
+    }
+    else {
+        (20.0 * amplitude.log10()).max(MIN_DB)
+    }
+}
+
+/// The levels of audio measured by a [LevelMeter], given as linear amplitudes
+/// relative to full scale.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Levels {
+
+    /// The maximum absolute amplitude of any sample in the measured window.
+    pub peak: f32,
+
+    /// The root mean square of all samples in the measured window, averaged
+    /// over both channels.
+    pub rms: f32
+}
+
+impl Levels {
+
+    /// Gets the peak level in dBFS.
+    pub fn peak_db(&self) -> f32 {
+        to_db(self.peak)
+    }
+
+    /// Gets the RMS level in dBFS.
+    pub fn rms_db(&self) -> f32 {
+        to_db(self.rms)
+    }
+}
+
+/// Measures the peak and RMS levels of audio passing through it over windows
+/// of 300 ms. The reported [Levels] are those of the last complete window.
+#[derive(Clone, Debug, Default)]
+pub struct LevelMeter {
+    window_peak: f32,
+    window_sum: f64,
+    window_len: usize,
+    levels: Levels
+}
+
+impl LevelMeter {
+
+    /// Creates a new level meter which reports silence.
+    pub fn new() -> LevelMeter {
+        LevelMeter::default()
+    }
+
+    fn complete_window(&mut self) {
+        self.levels = Levels {
+            peak: self.window_peak,
+            rms: (self.window_sum / self.window_len as f64).sqrt() as f32
+        };
+        self.window_peak = 0.0;
+        self.window_sum = 0.0;
+        self.window_len = 0;
+    }
+
+    /// Measures the given audio, scaled by the given factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf`: The audio to measure.
+    /// * `factor`: The factor by which every sample is multiplied before it is
+    ///   measured, such as the gain of a layer.
+    pub fn process(&mut self, buf: &[Sample], factor: f32) {
+        for sample in buf {
+            let sample = sample * factor;
+            let left = sample.left as f64;
+            let right = sample.right as f64;
+
+            self.window_peak = self.window_peak.max(sample.abs_amplitude());
+            self.window_sum += (left * left + right * right) * 0.5;
+            self.window_len += 1;
+
+            if self.window_len == WINDOW_SAMPLES {
+                self.complete_window();
+            }
+        }
+    }
+
+    /// Measures the given number of samples of silence.
+    pub fn process_silence(&mut self, count: usize) {
+        let mut remaining = count;
+
+        while remaining > 0 {
+            let step = remaining.min(WINDOW_SAMPLES - self.window_len);
+
+            self.window_len += step;
+            remaining -= step;
+
+            if self.window_len == WINDOW_SAMPLES {
+                self.complete_window();
+            }
+        }
+    }
+
+    /// Resets this meter such that it reports silence.
+    pub fn reset(&mut self) {
+        *self = LevelMeter::new();
+    }
+
+    /// Gets the [Levels] of the last complete measurement window.
+    pub fn levels(&self) -> Levels {
+        self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn new_meter_reports_silence() {
+        let meter = LevelMeter::new();
+
+        assert_eq!(Levels::default(), meter.levels());
+        assert_eq!(MIN_DB, meter.levels().peak_db());
+    }
+
+    #[test]
+    fn full_scale_sine_levels() {
+        let audio = rambot_test_util::test_data(WINDOW_SAMPLES, 100.0, 100.0);
+        let mut meter = LevelMeter::new();
+
+        meter.process(&audio, 1.0);
+
+        let levels = meter.levels();
+
+        assert!((levels.peak - 1.0).abs() < 0.001);
+        assert!((levels.rms - 0.5f32.sqrt()).abs() < 0.001);
+        assert!((levels.rms_db() + 3.01).abs() < 0.01);
+    }
+
+    #[test]
+    fn factor_is_applied_before_measuring() {
+        let mut meter = LevelMeter::new();
+
+        meter.process(&vec![Sample::mono(1.0); WINDOW_SAMPLES], 0.5);
+
+        assert_eq!(Levels {
+            peak: 0.5,
+            rms: 0.5
+        }, meter.levels());
+    }
+
+    #[test]
+    fn levels_fall_back_to_silence() {
+        let mut meter = LevelMeter::new();
+
+        meter.process(&vec![Sample::mono(1.0); WINDOW_SAMPLES], 1.0);
+        meter.process_silence(WINDOW_SAMPLES * 2 + 5);
+
+        assert_eq!(Levels::default(), meter.levels());
+    }
+}
@@ -0,0 +1,193 @@
+use serde_json::{Map, Value};
+
+use std::fmt::{self, Display, Formatter};
+
+/// The key of the field in a guild state file which holds its version.
+const VERSION_KEY: &str = "version";
+
+/// A migration which converts the JSON object of a guild state file from one
+/// version to the next one. The version field itself is updated afterwards.
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// The chain of migrations, where the migration at index `i` converts a guild
+/// state file of version `i` to version `i + 1`. To change the layout of the
+/// files, append a migration to this list.
+const MIGRATIONS: &[Migration] = &[
+    from_unversioned
+];
+
+/// The version of guild state files written by this version of the bot.
+pub const CURRENT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Files written before the version field was introduced have version 0.
+/// Their layout is identical to that of version 1, since all fields added
+/// until then have default values.
+fn from_unversioned(_: &mut Map<String, Value>) -> Result<(), String> {
+    Ok(())
+}
+
+/// An enumeration of the errors that may occur while migrating a guild state
+/// file.
+#[derive(Debug, Eq, PartialEq)]
+pub enum MigrationError {
+
+    /// Indicates that the file does not contain a JSON object.
+    NotAnObject,
+
+    /// Indicates that the version field is not a non-negative integer.
+    InvalidVersion,
+
+    /// Indicates that the file was written by a newer version of the bot,
+    /// whose layout is unknown. Contains the version of the file.
+    UnsupportedVersion(u32),
+
+    /// Indicates that a migration failed. Contains the version from which
+    /// the file should have been migrated and a message describing the
+    /// problem.
+    Failed(u32, String)
+}
+
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::NotAnObject =>
+                write!(f, "The state file does not contain a JSON object."),
+            MigrationError::InvalidVersion =>
+                write!(f, "The version of the state file is invalid."),
+            MigrationError::UnsupportedVersion(version) =>
+                write!(f,
+                    "The state file has version {}, but only versions up to \
+                    {} are supported. It was probably written by a newer \
+                    version of the bot.", version, CURRENT_VERSION),
+            MigrationError::Failed(version, message) =>
+                write!(f,
+                    "Could not migrate the state file from version {}: {}",
+                    version, message)
+        }
+    }
+}
+
+/// Gets the version of the guild state file with the given JSON content. Files
+/// without a version field have version 0.
+///
+/// # Errors
+///
+/// [MigrationError::NotAnObject] or [MigrationError::InvalidVersion] if the
+/// content is not a JSON object with a valid version.
+pub fn version(value: &Value) -> Result<u32, MigrationError> {
+    let object = value.as_object().ok_or(MigrationError::NotAnObject)?;
+
+    match object.get(VERSION_KEY) {
+        Some(version) => version.as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(MigrationError::InvalidVersion),
+        None => Ok(0)
+    }
+}
+
+fn migrate_with(value: &mut Value, migrations: &[Migration])
+        -> Result<u32, MigrationError> {
+    let version = version(value)?;
+    let object = value.as_object_mut().unwrap();
+
+    if version as usize > migrations.len() {
+        return Err(MigrationError::UnsupportedVersion(version));
+    }
+
+    let pending = migrations.iter().enumerate().skip(version as usize);
+
+    for (from, migration) in pending {
+        let from = from as u32;
+
+        migration(object).map_err(|e| MigrationError::Failed(from, e))?;
+        object.insert(VERSION_KEY.to_owned(), Value::from(from + 1));
+    }
+
+    Ok(version)
+}
+
+/// Migrates the given content of a guild state file to the
+/// [CURRENT_VERSION] by applying all migrations from its version onwards in
+/// order.
+///
+/// # Returns
+///
+/// The version of the file before migration.
+///
+/// # Errors
+///
+/// Any [MigrationError] according to their respective documentations. In
+/// that case, the content may be partially migrated.
+pub fn migrate(value: &mut Value) -> Result<u32, MigrationError> {
+    migrate_with(value, MIGRATIONS)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use serde_json::json;
+
+    fn rename_layers(object: &mut Map<String, Value>) -> Result<(), String> {
+        let layers = object.remove("layers").ok_or("no layers")?;
+        object.insert("mixer".to_owned(), json!({ "layers": layers }));
+        Ok(())
+    }
+
+    fn add_boards(object: &mut Map<String, Value>) -> Result<(), String> {
+        object.insert("boards".to_owned(), json!([]));
+        Ok(())
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[rename_layers, add_boards];
+
+    #[test]
+    fn unversioned_files_are_migrated_to_current_version() {
+        let mut value = json!({ "mixer": { "layers": [] }, "boards": [] });
+
+        assert_eq!(Ok(0), migrate(&mut value));
+        assert_eq!(Ok(CURRENT_VERSION), version(&value));
+    }
+
+    #[test]
+    fn migrations_are_applied_in_order() {
+        let mut value = json!({ "layers": [ "music" ] });
+
+        assert_eq!(Ok(0), migrate_with(&mut value, TEST_MIGRATIONS));
+        assert_eq!(json!({
+            "version": 2,
+            "mixer": { "layers": [ "music" ] },
+            "boards": []
+        }), value);
+    }
+
+    #[test]
+    fn only_newer_migrations_are_applied() {
+        let mut value = json!({ "version": 1, "mixer": { "layers": [] } });
+
+        assert_eq!(Ok(1), migrate_with(&mut value, TEST_MIGRATIONS));
+        assert_eq!(json!({
+            "version": 2,
+            "mixer": { "layers": [] },
+            "boards": []
+        }), value);
+    }
+
+    #[test]
+    fn failing_migration_is_reported() {
+        let mut value = json!({ "mixer": { "layers": [] } });
+
+        assert_eq!(Err(MigrationError::Failed(0, "no layers".to_owned())),
+            migrate_with(&mut value, TEST_MIGRATIONS));
+    }
+
+    #[test]
+    fn invalid_versions_are_rejected() {
+        assert_eq!(Err(MigrationError::UnsupportedVersion(3)),
+            migrate_with(&mut json!({ "version": 3 }), TEST_MIGRATIONS));
+        assert_eq!(Err(MigrationError::InvalidVersion),
+            migrate(&mut json!({ "version": "one" })));
+        assert_eq!(Err(MigrationError::NotAnObject), migrate(&mut json!([])));
+    }
+}
@@ -0,0 +1,754 @@
+//! A listener for OSC (Open Sound Control) messages received via UDP, which
+//! allows hardware and software controllers to operate layers and sound
+//! boards. The following addresses are supported, where `<id>` is the ID of a
+//! guild.
+//!
+//! * `/guild/<id>/layer/<layer>/play` with a string argument: plays the given
+//!   audio on the layer.
+//! * `/guild/<id>/layer/<layer>/stop`: stops the layer.
+//! * `/guild/<id>/layer/<layer>/gain` with a numeric argument: sets the gain
+//!   of the layer.
+//! * `/guild/<id>/layer/<layer>/mute` and `/guild/<id>/layer/<layer>/solo`
+//!   with an optional numeric or boolean argument: mutes or solos the layer if
+//!   the argument is absent, `true`, or non-zero, and unmutes or unsolos it
+//!   otherwise.
+//! * `/guild/<id>/board/<board>/<label>` with an optional argument: presses
+//!   the button with the given label on the given sound board. Messages with a
+//!   `false` or zero argument are ignored, so controllers that send a message
+//!   both when a button is pressed and released only press it once.
+//!
+//! Actions are executed like commands of a guild member without any roles, so
+//! they are rejected if the corresponding command is restricted in the guild.
+//!
+//! Layer names in addresses must not contain whitespace or double quotes.
+//! Address patterns are not supported, that is, every part of the address is
+//! matched literally. Bundles are executed immediately regardless of their
+//! time tag and may be nested at most 8 levels deep.
+
+use crate::config::OscConfig;
+use crate::control::{self, Controller};
+
+use serenity::model::id::GuildId;
+
+use tokio::net::UdpSocket;
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::net::{AddrParseError, SocketAddr};
+
+/// The maximum size of a UDP datagram, which limits the size of an OSC
+/// packet.
+const MAX_PACKET_SIZE: usize = 65536;
+
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+
+/// The maximum number of bundles that may be nested inside each other, which
+/// prevents malicious packets from exhausting the stack.
+const MAX_BUNDLE_DEPTH: usize = 8;
+
+/// An argument of an [OscMessage].
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArgument {
+
+    /// A 32-bit integer (type tag `i`).
+    Int(i32),
+
+    /// A 64-bit integer (type tag `h`).
+    Long(i64),
+
+    /// A 32-bit floating point number (type tag `f`).
+    Float(f32),
+
+    /// A 64-bit floating point number (type tag `d`).
+    Double(f64),
+
+    /// A string (type tags `s` and `S`).
+    String(String),
+
+    /// A blob of binary data (type tag `b`).
+    Blob(Vec<u8>),
+
+    /// A boolean (type tags `T` and `F`).
+    Bool(bool),
+
+    /// The absence of a value (type tags `N` and `I`).
+    Nil
+}
+
+impl OscArgument {
+
+    /// Converts this argument to a number, if it is numeric or boolean.
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArgument::Int(i) => Some(*i as f32),
+            OscArgument::Long(l) => Some(*l as f32),
+            OscArgument::Float(f) => Some(*f),
+            OscArgument::Double(d) => Some(*d as f32),
+            OscArgument::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            _ => None
+        }
+    }
+
+    /// Gets the content of this argument if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OscArgument::String(s) => Some(s),
+            _ => None
+        }
+    }
+}
+
+/// A single OSC message, consisting of an address and a list of arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMessage {
+
+    /// The address to which the message is sent, e.g.
+    /// `/guild/42/layer/music/gain`.
+    pub address: String,
+
+    /// The arguments of the message.
+    pub arguments: Vec<OscArgument>
+}
+
+/// An enumeration of the different errors that can occur when parsing an OSC
+/// packet.
+#[derive(Debug, Eq, PartialEq)]
+pub enum OscParseError {
+
+    /// Indicates that the packet ended in the middle of an element.
+    UnexpectedEnd,
+
+    /// Indicates that a string was not valid UTF-8.
+    InvalidString,
+
+    /// Indicates that an address did not start with `/`.
+    InvalidAddress,
+
+    /// Indicates that an argument had a type tag that is not supported.
+    UnsupportedType(char),
+
+    /// Indicates that bundles were nested more than 8 levels deep.
+    NestingTooDeep
+}
+
+impl Display for OscParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OscParseError::UnexpectedEnd =>
+                write!(f, "packet ended unexpectedly"),
+            OscParseError::InvalidString =>
+                write!(f, "string is not valid UTF-8"),
+            OscParseError::InvalidAddress =>
+                write!(f, "address does not start with `/`"),
+            OscParseError::UnsupportedType(tag) =>
+                write!(f, "unsupported argument type `{}`", tag),
+            OscParseError::NestingTooDeep =>
+                write!(f, "bundles are nested too deeply")
+        }
+    }
+}
+
+struct PacketReader<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> PacketReader<'a> {
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], OscParseError> {
+        if self.data.len() < len {
+            return Err(OscParseError::UnexpectedEnd);
+        }
+
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self)
+            -> Result<[u8; N], OscParseError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    /// Reads a null-terminated string padded to a multiple of 4 bytes.
+    fn read_string(&mut self) -> Result<String, OscParseError> {
+        let len = self.data.iter()
+            .position(|&b| b == 0)
+            .ok_or(OscParseError::UnexpectedEnd)?;
+        let padded_len = (len / 4 + 1) * 4;
+        let bytes = self.take(padded_len.min(self.data.len()))?;
+
+        String::from_utf8(bytes[..len].to_vec())
+            .map_err(|_| OscParseError::InvalidString)
+    }
+
+    fn read_blob(&mut self) -> Result<Vec<u8>, OscParseError> {
+        let len = i32::from_be_bytes(self.take_array()?).max(0) as usize;
+        let padded_len = len.div_ceil(4) * 4;
+
+        Ok(self.take(padded_len)?[..len].to_vec())
+    }
+
+    fn read_argument(&mut self, tag: char)
+            -> Result<OscArgument, OscParseError> {
+        Ok(match tag {
+            'i' => OscArgument::Int(i32::from_be_bytes(self.take_array()?)),
+            'h' => OscArgument::Long(i64::from_be_bytes(self.take_array()?)),
+            'f' => OscArgument::Float(f32::from_be_bytes(self.take_array()?)),
+            'd' => OscArgument::Double(f64::from_be_bytes(self.take_array()?)),
+            's' | 'S' => OscArgument::String(self.read_string()?),
+            'b' => OscArgument::Blob(self.read_blob()?),
+            'T' => OscArgument::Bool(true),
+            'F' => OscArgument::Bool(false),
+            'N' | 'I' => OscArgument::Nil,
+            _ => return Err(OscParseError::UnsupportedType(tag))
+        })
+    }
+}
+
+fn parse_message(data: &[u8]) -> Result<OscMessage, OscParseError> {
+    let mut reader = PacketReader { data };
+    let address = reader.read_string()?;
+
+    if !address.starts_with('/') {
+        return Err(OscParseError::InvalidAddress);
+    }
+
+    let mut arguments = Vec::new();
+
+    // Very old implementations omit the type tag string, in which case the
+    // arguments cannot be interpreted and are ignored.
+
+    if reader.data.first() == Some(&b',') {
+        let type_tags = reader.read_string()?;
+
+        for tag in type_tags.chars().skip(1) {
+            arguments.push(reader.read_argument(tag)?);
+        }
+    }
+
+    Ok(OscMessage {
+        address,
+        arguments
+    })
+}
+
+fn parse_packet_into(data: &[u8], depth: usize,
+        messages: &mut Vec<OscMessage>) -> Result<(), OscParseError> {
+    if !data.starts_with(BUNDLE_TAG) {
+        messages.push(parse_message(data)?);
+        return Ok(());
+    }
+
+    if depth >= MAX_BUNDLE_DEPTH {
+        return Err(OscParseError::NestingTooDeep);
+    }
+
+    let mut reader = PacketReader { data };
+
+    // Skip the bundle tag and time tag.
+
+    reader.take(BUNDLE_TAG.len() + 8)?;
+
+    while !reader.data.is_empty() {
+        let len = i32::from_be_bytes(reader.take_array()?).max(0) as usize;
+        parse_packet_into(reader.take(len)?, depth + 1, messages)?;
+    }
+
+    Ok(())
+}
+
+/// Parses a single OSC packet, which is either a message or a bundle that may
+/// contain further messages and bundles.
+///
+/// # Returns
+///
+/// All messages contained in the packet in the order in which they appear.
+///
+/// # Errors
+///
+/// Any [OscParseError] according to their respective documentation.
+pub fn parse_packet(data: &[u8]) -> Result<Vec<OscMessage>, OscParseError> {
+    let mut messages = Vec::new();
+    parse_packet_into(data, 0, &mut messages)?;
+    Ok(messages)
+}
+
+#[derive(Debug, PartialEq)]
+enum OscAction {
+    Play {
+        layer: String,
+        audio: String
+    },
+    Stop {
+        layer: String
+    },
+    Gain {
+        layer: String,
+        gain: f32
+    },
+    Mute {
+        layer: String,
+        muted: bool
+    },
+    Solo {
+        layer: String,
+        solo: bool
+    },
+    Press {
+        board: String,
+        label: String
+    }
+}
+
+fn switch_argument(message: &OscMessage) -> Result<bool, String> {
+    match message.arguments.first() {
+        Some(argument) => argument.as_f32()
+            .map(|value| value != 0.0)
+            .ok_or_else(|| format!("{} expects a number or boolean.",
+                message.address)),
+        None => Ok(true)
+    }
+}
+
+/// Determines the action requested by the given message.
+///
+/// # Returns
+///
+/// The ID of the addressed guild and the action to execute, or `None` if the
+/// message does not require an action, such as the release of a button.
+///
+/// # Errors
+///
+/// A message explaining why the message is invalid.
+fn parse_action(message: &OscMessage)
+        -> Result<Option<(GuildId, OscAction)>, String> {
+    let unknown = || format!("Unknown OSC address: {}", message.address);
+    let parts = message.address.strip_prefix('/')
+        .ok_or_else(unknown)?
+        .split('/')
+        .collect::<Vec<_>>();
+
+    if parts.len() != 5 || parts[0] != "guild" ||
+            parts.iter().any(|part| part.is_empty()) {
+        return Err(unknown());
+    }
+
+    let guild_id = parts[1].parse::<u64>().ok()
+        .filter(|&id| id != 0)
+        .map(GuildId::new)
+        .ok_or_else(unknown)?;
+
+    // Layer names are passed on as command arguments, so they must not split
+    // into several arguments.
+
+    if parts[2] == "layer" && !control::is_valid_name(parts[3]) {
+        return Err(format!("Invalid layer name in OSC address: {}",
+            message.address));
+    }
+    let action = match (parts[2], parts[4]) {
+        ("layer", "play") => {
+            let audio = message.arguments.first()
+                .and_then(OscArgument::as_str)
+                .ok_or_else(|| format!("{} expects the audio as a string.",
+                    message.address))?;
+
+            OscAction::Play {
+                layer: parts[3].to_owned(),
+                audio: audio.to_owned()
+            }
+        },
+        ("layer", "stop") => OscAction::Stop {
+            layer: parts[3].to_owned()
+        },
+        ("layer", "gain") => {
+            let gain = message.arguments.first()
+                .and_then(OscArgument::as_f32)
+                .ok_or_else(|| format!("{} expects the gain as a number.",
+                    message.address))?;
+
+            OscAction::Gain {
+                layer: parts[3].to_owned(),
+                gain
+            }
+        },
+        ("layer", "mute") => OscAction::Mute {
+            layer: parts[3].to_owned(),
+            muted: switch_argument(message)?
+        },
+        ("layer", "solo") => OscAction::Solo {
+            layer: parts[3].to_owned(),
+            solo: switch_argument(message)?
+        },
+        ("board", _) => {
+            if !switch_argument(message)? {
+                return Ok(None);
+            }
+
+            OscAction::Press {
+                board: parts[3].to_owned(),
+                label: parts[4].to_owned()
+            }
+        },
+        _ => return Err(unknown())
+    };
+
+    Ok(Some((guild_id, action)))
+}
+
+async fn handle_message(controller: &Controller, message: &OscMessage) {
+    let (guild_id, action) = match parse_action(message) {
+        Ok(Some(action)) => action,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("{}", e);
+            return;
+        }
+    };
+    let result = match action {
+        OscAction::Play { layer, audio } => controller.execute(guild_id,
+            &[format!("play {} {}", layer, audio)]).await,
+        OscAction::Stop { layer } =>
+            controller.execute(guild_id, &[format!("stop {}", layer)]).await,
+        OscAction::Gain { layer, gain } => controller.update_layer(guild_id,
+            &layer, Some(gain), None, None).await,
+        OscAction::Mute { layer, muted } => controller.update_layer(guild_id,
+            &layer, None, Some(muted), None).await,
+        OscAction::Solo { layer, solo } => controller.update_layer(guild_id,
+            &layer, None, None, Some(solo)).await,
+        OscAction::Press { board, label } =>
+            controller.press_button(guild_id, &board, &label).await
+    };
+
+    if let Err(e) = result {
+        log::warn!("Error executing OSC message {}: {}", message.address, e);
+    }
+}
+
+async fn run(socket: UdpSocket, controller: Controller) {
+    let mut buf = vec![0; MAX_PACKET_SIZE];
+
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                log::warn!("Error receiving OSC packet: {}", e);
+                continue;
+            }
+        };
+        let messages = match parse_packet(&buf[..len]) {
+            Ok(messages) => messages,
+            Err(e) => {
+                log::warn!("Invalid OSC packet from {}: {}", peer, e);
+                continue;
+            }
+        };
+
+        for message in messages {
+            handle_message(&controller, &message).await;
+        }
+    }
+}
+
+/// An enumeration of the different errors that can occur when starting the
+/// OSC listener.
+#[derive(Debug)]
+pub enum OscError {
+
+    /// Indicates that the configured address is not a valid socket address.
+    InvalidAddress(AddrParseError),
+
+    /// Wraps an [io::Error] raised while binding the socket.
+    BindError(io::Error)
+}
+
+impl Display for OscError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OscError::InvalidAddress(e) =>
+                write!(f, "Invalid OSC address: {}", e),
+            OscError::BindError(e) =>
+                write!(f, "Error binding OSC listener: {}", e)
+        }
+    }
+}
+
+/// Starts listening for OSC messages in the background.
+///
+/// # Arguments
+///
+/// * `config`: The [OscConfig] specifying the address to listen on.
+/// * `controller`: The [Controller] which executes the requested actions.
+///
+/// # Returns
+///
+/// The address to which the listener was bound.
+///
+/// # Errors
+///
+/// Any [OscError] according to their respective documentation.
+pub async fn start(config: &OscConfig, controller: Controller)
+        -> Result<SocketAddr, OscError> {
+    let address = config.address().parse::<SocketAddr>()
+        .map_err(OscError::InvalidAddress)?;
+    let socket = UdpSocket::bind(address).await
+        .map_err(OscError::BindError)?;
+    let address = socket.local_addr().map_err(OscError::BindError)?;
+
+    tokio::spawn(run(socket, controller));
+    log::info!("OSC listener listening on {}.", address);
+
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::plugin::PluginManager;
+    use crate::state::State;
+
+    use std::env;
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::RwLock as TokioRwLock;
+
+    fn write_string(bytes: &mut Vec<u8>, s: &str) {
+        bytes.extend_from_slice(s.as_bytes());
+        bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+    }
+
+    /// Encodes a message like an OSC client would.
+    fn encode(address: &str, arguments: &[OscArgument]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut type_tags = ",".to_owned();
+        let mut data = Vec::new();
+
+        write_string(&mut bytes, address);
+
+        for argument in arguments {
+            match argument {
+                OscArgument::Int(i) => {
+                    type_tags.push('i');
+                    data.extend_from_slice(&i.to_be_bytes());
+                },
+                OscArgument::Float(f) => {
+                    type_tags.push('f');
+                    data.extend_from_slice(&f.to_be_bytes());
+                },
+                OscArgument::String(s) => {
+                    type_tags.push('s');
+                    write_string(&mut data, s);
+                },
+                OscArgument::Bool(b) => type_tags.push(if *b { 'T' } else { 'F' }),
+                _ => panic!("unsupported argument in test")
+            }
+        }
+
+        write_string(&mut bytes, &type_tags);
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    fn bundle(elements: &[&[u8]]) -> Vec<u8> {
+        let mut data = BUNDLE_TAG.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+
+        for element in elements {
+            data.extend_from_slice(&(element.len() as i32).to_be_bytes());
+            data.extend_from_slice(element);
+        }
+
+        data
+    }
+
+    fn message(address: &str, arguments: Vec<OscArgument>) -> OscMessage {
+        OscMessage {
+            address: address.to_owned(),
+            arguments
+        }
+    }
+
+    #[test]
+    fn messages_are_parsed() {
+        let data = encode("/guild/1/layer/music/play", &[
+            OscArgument::String("song.wav".to_owned()),
+            OscArgument::Int(-3),
+            OscArgument::Bool(true),
+            OscArgument::Float(0.5)
+        ]);
+
+        let messages = parse_packet(&data).unwrap();
+
+        assert_eq!(vec![message("/guild/1/layer/music/play", vec![
+            OscArgument::String("song.wav".to_owned()),
+            OscArgument::Int(-3),
+            OscArgument::Bool(true),
+            OscArgument::Float(0.5)
+        ])], messages);
+    }
+
+    #[test]
+    fn bundles_are_parsed() {
+        let first = encode("/a", &[OscArgument::Int(1)]);
+        let second = encode("/b", &[]);
+        let data = bundle(&[&first, &bundle(&[&second])]);
+
+        let messages = parse_packet(&data).unwrap();
+
+        assert_eq!(vec![
+            message("/a", vec![OscArgument::Int(1)]),
+            message("/b", Vec::new())
+        ], messages);
+    }
+
+    #[test]
+    fn deeply_nested_bundles_are_rejected() {
+        let mut data = encode("/a", &[]);
+
+        for _ in 0..MAX_BUNDLE_DEPTH {
+            data = bundle(&[&data]);
+        }
+
+        assert_eq!(1, parse_packet(&data).unwrap().len());
+
+        data = bundle(&[&data]);
+
+        assert_eq!(Err(OscParseError::NestingTooDeep), parse_packet(&data));
+    }
+
+    #[test]
+    fn invalid_packets_are_rejected() {
+        let mut truncated = encode("/a", &[OscArgument::Int(1)]);
+        truncated.truncate(truncated.len() - 2);
+        let mut unsupported = Vec::new();
+        write_string(&mut unsupported, "/a");
+        write_string(&mut unsupported, ",x");
+
+        assert_eq!(Err(OscParseError::UnexpectedEnd), parse_packet(&truncated));
+        assert_eq!(Err(OscParseError::UnsupportedType('x')),
+            parse_packet(&unsupported));
+        assert_eq!(Err(OscParseError::InvalidAddress),
+            parse_packet(&encode("a", &[])));
+    }
+
+    #[test]
+    fn actions_are_determined_from_addresses() {
+        let guild_id = GuildId::new(42);
+
+        assert_eq!(Ok(Some((guild_id, OscAction::Gain {
+            layer: "music".to_owned(),
+            gain: 0.25
+        }))), parse_action(&message("/guild/42/layer/music/gain",
+            vec![OscArgument::Float(0.25)])));
+        assert_eq!(Ok(Some((guild_id, OscAction::Mute {
+            layer: "music".to_owned(),
+            muted: false
+        }))), parse_action(&message("/guild/42/layer/music/mute",
+            vec![OscArgument::Int(0)])));
+        assert_eq!(Ok(Some((guild_id, OscAction::Press {
+            board: "sfx".to_owned(),
+            label: "horn".to_owned()
+        }))), parse_action(&message("/guild/42/board/sfx/horn", Vec::new())));
+        assert_eq!(Ok(None), parse_action(&message("/guild/42/board/sfx/horn",
+            vec![OscArgument::Float(0.0)])));
+        assert!(parse_action(&message("/guild/42/layer/music/gain",
+            Vec::new())).is_err());
+        assert!(parse_action(&message("/guild/0/layer/music/stop",
+            Vec::new())).is_err());
+        assert!(parse_action(&message("/guild/42/layer/music/fly",
+            Vec::new())).is_err());
+        assert!(parse_action(&message("/guild/42/layer/a b/stop",
+            Vec::new())).is_err());
+        assert!(parse_action(&message("/guild/42/layer/\"a/stop",
+            Vec::new())).is_err());
+    }
+
+    #[tokio::test]
+    async fn messages_from_client_control_layers() {
+        let directory = env::temp_dir()
+            .join(format!("rambot-osc-{}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_dir_all(&directory);
+        let plugin_manager = Arc::new(PluginManager::empty());
+        let state = State::load(&directory, Arc::clone(&plugin_manager))
+            .unwrap_or_else(|e| panic!("{}", e));
+        let state = Arc::new(TokioRwLock::new(state));
+        let controller = Controller::new(Arc::clone(&state),
+            plugin_manager, None, None);
+        let guild_id = GuildId::new(42);
+
+        controller.execute(guild_id, &["layer add music".to_owned()]).await
+            .unwrap();
+
+        let config: OscConfig =
+            serde_json::from_str("{ \"address\": \"127.0.0.1:0\" }").unwrap();
+        let address = start(&config, controller).await.unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        client.send_to(&encode("/guild/42/layer/music/gain",
+            &[OscArgument::Float(0.5)]), address).await.unwrap();
+        client.send_to(&encode("/guild/42/layer/music/mute", &[]), address)
+            .await.unwrap();
+
+        let mut layer_state = (1.0, false);
+
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            let state = state.read().await;
+            let mixer = state.guild_state(guild_id).unwrap().mixer_blocking();
+            let layer = mixer.layer("music");
+            layer_state = (layer.gain(), layer.is_muted());
+
+            if layer_state == (0.5, true) {
+                break;
+            }
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!((0.5, true), layer_state);
+    }
+
+    #[tokio::test]
+    async fn restricted_actions_are_rejected() {
+        let directory = env::temp_dir()
+            .join(format!("rambot-osc-restricted-{}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_dir_all(&directory);
+        let plugin_manager = Arc::new(PluginManager::empty());
+        let state = State::load(&directory, Arc::clone(&plugin_manager))
+            .unwrap_or_else(|e| panic!("{}", e));
+        let state = Arc::new(TokioRwLock::new(state));
+        let controller = Controller::new(Arc::clone(&state),
+            Arc::clone(&plugin_manager), None, None);
+        let guild_id = GuildId::new(42);
+
+        controller.execute(guild_id, &["layer add music".to_owned()]).await
+            .unwrap();
+        state.write().await.guild_state_mut(guild_id, &plugin_manager)
+            .permissions_mut().restrict("layer gain", None);
+
+        handle_message(&controller, &message("/guild/42/layer/music/gain",
+            vec![OscArgument::Float(0.5)])).await;
+        handle_message(&controller, &message("/guild/42/layer/music/mute",
+            Vec::new())).await;
+
+        let layer_state = {
+            let state = state.read().await;
+            let mixer = state.guild_state(guild_id).unwrap().mixer_blocking();
+            let layer = mixer.layer("music");
+
+            (layer.gain(), layer.is_muted())
+        };
+
+        fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!((1.0, true), layer_state);
+    }
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+
+use serenity::model::id::RoleId;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The permissions configured for one guild, which restrict which members may
+/// use which commands. Commands are identified by their qualified name, such
+/// as `play` or `board button add`. A restriction on a command also applies to
+/// all of its subcommands, unless a subcommand has a restriction of its own.
+///
+/// Members with a role that is allowed to use a restricted command as well as
+/// members with the DJ role may use it. Commands without a restriction can be
+/// used by everyone.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Permissions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dj_role: Option<RoleId>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    restrictions: BTreeMap<String, BTreeSet<RoleId>>
+}
+
+impl Permissions {
+
+    /// Indicates whether no DJ role and no restrictions are configured, i.e.
+    /// everybody may use every command.
+    pub fn is_empty(&self) -> bool {
+        self.dj_role.is_none() && self.restrictions.is_empty()
+    }
+
+    /// Gets the DJ role, whose members may use all restricted commands, if
+    /// one is set.
+    pub fn dj_role(&self) -> Option<RoleId> {
+        self.dj_role
+    }
+
+    /// Sets the DJ role, whose members may use all restricted commands. If
+    /// `None` is given, the DJ role is removed.
+    pub fn set_dj_role(&mut self, dj_role: Option<RoleId>) {
+        self.dj_role = dj_role;
+    }
+
+    /// Gets an iterator over all restricted commands together with the roles
+    /// that may use them in addition to the DJ role, in alphabetical order of
+    /// the command names.
+    pub fn restrictions(&self)
+            -> impl Iterator<Item = (&str, &BTreeSet<RoleId>)> {
+        self.restrictions.iter()
+            .map(|(command, roles)| (command.as_str(), roles))
+    }
+
+    /// Restricts the command with the given qualified name, so only members
+    /// with the DJ role or one of the allowed roles may use it.
+    ///
+    /// # Arguments
+    ///
+    /// * `command`: The qualified name of the command to restrict.
+    /// * `role`: A role to allow for the command in addition to all roles
+    ///   which were allowed before. If `None`, the command is restricted
+    ///   without allowing any additional role, so only DJs may use it unless
+    ///   it was already restricted.
+    pub fn restrict(&mut self, command: &str, role: Option<RoleId>) {
+        let roles = self.restrictions.entry(command.to_owned()).or_default();
+
+        if let Some(role) = role {
+            roles.insert(role);
+        }
+    }
+
+    /// Lifts a restriction of the command with the given qualified name.
+    ///
+    /// # Arguments
+    ///
+    /// * `command`: The qualified name of the command whose restriction to
+    ///   lift.
+    /// * `role`: The role to disallow for the command. The command remains
+    ///   restricted even if no allowed roles are left. If `None`, the entire
+    ///   restriction is removed, so everybody may use the command again.
+    ///
+    /// # Returns
+    ///
+    /// `true` if and only if anything changed.
+    pub fn unrestrict(&mut self, command: &str, role: Option<RoleId>) -> bool {
+        match role {
+            Some(role) => self.restrictions.get_mut(command)
+                .map(|roles| roles.remove(&role))
+                .unwrap_or(false),
+            None => self.restrictions.remove(command).is_some()
+        }
+    }
+
+    /// Gets the restriction which applies to the command with the given
+    /// qualified name, that is, the allowed roles for the command itself or
+    /// otherwise its closest restricted parent command.
+    fn restriction(&self, command: &str) -> Option<&BTreeSet<RoleId>> {
+        let mut name = command;
+
+        loop {
+            if let Some(roles) = self.restrictions.get(name) {
+                return Some(roles);
+            }
+
+            match name.rfind(' ') {
+                Some(index) => name = &name[..index],
+                None => return None
+            }
+        }
+    }
+
+    /// Indicates whether a member with the given roles may use the command
+    /// with the given qualified name.
+    ///
+    /// # Arguments
+    ///
+    /// * `command`: The qualified name of the command to check.
+    /// * `roles`: The roles of the member who wants to use the command.
+    pub fn is_allowed(&self, command: &str, roles: &[RoleId]) -> bool {
+        match self.restriction(command) {
+            Some(allowed_roles) => roles.iter().any(|role|
+                Some(*role) == self.dj_role || allowed_roles.contains(role)),
+            None => true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const DJ: RoleId = RoleId::new(1);
+    const MODERATOR: RoleId = RoleId::new(2);
+    const MEMBER: RoleId = RoleId::new(3);
+
+    fn permissions() -> Permissions {
+        let mut permissions = Permissions::default();
+        permissions.set_dj_role(Some(DJ));
+        permissions.restrict("stop", None);
+        permissions.restrict("board", Some(MODERATOR));
+        permissions.restrict("board button add", Some(MEMBER));
+        permissions
+    }
+
+    #[test]
+    fn unrestricted_commands_are_allowed() {
+        let permissions = permissions();
+
+        assert!(permissions.is_allowed("play", &[]));
+        assert!(permissions.is_allowed("layer add", &[MEMBER]));
+    }
+
+    #[test]
+    fn restricted_commands_require_role() {
+        let permissions = permissions();
+
+        assert!(!permissions.is_allowed("stop", &[]));
+        assert!(!permissions.is_allowed("stop", &[MODERATOR, MEMBER]));
+        assert!(permissions.is_allowed("stop", &[MEMBER, DJ]));
+        assert!(permissions.is_allowed("board", &[MODERATOR]));
+    }
+
+    #[test]
+    fn restrictions_apply_to_subcommands() {
+        let permissions = permissions();
+
+        assert!(permissions.is_allowed("board button remove", &[MODERATOR]));
+        assert!(!permissions.is_allowed("board button remove", &[MEMBER]));
+        assert!(permissions.is_allowed("board button add", &[MEMBER]));
+        assert!(!permissions.is_allowed("board button add", &[MODERATOR]));
+        assert!(permissions.is_allowed("board button add", &[DJ]));
+    }
+
+    #[test]
+    fn unrestricting_role_keeps_restriction() {
+        let mut permissions = permissions();
+
+        assert!(permissions.unrestrict("board", Some(MODERATOR)));
+        assert!(!permissions.unrestrict("board", Some(MODERATOR)));
+        assert!(!permissions.is_allowed("board", &[MODERATOR]));
+        assert!(permissions.unrestrict("board", None));
+        assert!(permissions.is_allowed("board", &[]));
+        assert!(!permissions.unrestrict("play", None));
+    }
+
+    #[test]
+    fn permissions_are_serialized() {
+        let permissions = permissions();
+        let json = serde_json::to_string(&permissions).unwrap();
+        let deserialized: Permissions = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(permissions, deserialized);
+        assert!(Permissions::default().is_empty());
+        assert_eq!("{}",
+            serde_json::to_string(&Permissions::default()).unwrap());
+    }
+}
@@ -0,0 +1,240 @@
+use crate::audio::LayerPlayback;
+use crate::state::StateError;
+
+use serde::{Deserialize, Serialize};
+
+use serenity::model::id::{ChannelId, GuildId};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The name of the sub-directory of the state directory in which the playback
+/// of every guild is saved.
+const PLAYBACK_DIRECTORY_NAME: &str = "playback";
+
+/// A snapshot of the playback in one guild, that is, the voice channel to
+/// which the bot was connected and what each of its layers was playing.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GuildPlayback {
+    channel: ChannelId,
+    layers: Vec<LayerPlayback>
+}
+
+impl GuildPlayback {
+
+    /// Creates a new guild playback.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel`: The ID of the voice channel to which the bot was
+    ///   connected.
+    /// * `layers`: The [LayerPlayback]s of all layers which were playing.
+    pub fn new(channel: ChannelId, layers: Vec<LayerPlayback>)
+            -> GuildPlayback {
+        GuildPlayback {
+            channel,
+            layers
+        }
+    }
+
+    /// Gets the ID of the voice channel to which the bot was connected.
+    pub fn channel(&self) -> ChannelId {
+        self.channel
+    }
+
+    /// Gets the [LayerPlayback]s of all layers which were playing.
+    pub fn layers(&self) -> &[LayerPlayback] {
+        &self.layers
+    }
+}
+
+/// Saves the [GuildPlayback] of every guild in a file of its own, so playback
+/// can be resumed after the bot was restarted or crashed. The files are
+/// placed in a sub-directory of the state directory.
+///
+/// The store remembers what it last saved for every guild and does not touch
+/// the file again if nothing changed. Clones of a store share this record.
+#[derive(Clone)]
+pub struct PlaybackStore {
+    directory: PathBuf,
+    saved: Arc<Mutex<HashMap<GuildId, Option<GuildPlayback>>>>
+}
+
+impl PlaybackStore {
+
+    /// Creates a new playback store which places its files in the given state
+    /// directory.
+    pub fn new(state_directory: impl AsRef<Path>) -> PlaybackStore {
+        PlaybackStore {
+            directory: state_directory.as_ref().join(PLAYBACK_DIRECTORY_NAME),
+            saved: Arc::new(Mutex::new(HashMap::new()))
+        }
+    }
+
+    fn path(&self, guild_id: GuildId) -> PathBuf {
+        self.directory.join(format!("{}.json", guild_id))
+    }
+
+    fn is_saved(&self, guild_id: GuildId, playback: Option<&GuildPlayback>)
+            -> bool {
+        self.saved.lock().unwrap().get(&guild_id)
+            .is_some_and(|saved| saved.as_ref() == playback)
+    }
+
+    fn set_saved(&self, guild_id: GuildId, playback: Option<GuildPlayback>) {
+        self.saved.lock().unwrap().insert(guild_id, playback);
+    }
+
+    /// Saves the given [GuildPlayback] for the guild with the given ID,
+    /// replacing any playback saved before. The file is replaced atomically,
+    /// so a crash while saving does not leave a corrupt file. If the same
+    /// playback was saved last time, nothing is written.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be written.
+    pub fn save(&self, guild_id: GuildId, playback: &GuildPlayback)
+            -> Result<(), StateError> {
+        if self.is_saved(guild_id, Some(playback)) {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.directory)?;
+
+        let path = self.path(guild_id);
+        let temporary_path = path.with_extension("json.tmp");
+
+        serde_json::to_writer(File::create(&temporary_path)?, playback)?;
+        fs::rename(temporary_path, path)?;
+        self.set_saved(guild_id, Some(playback.clone()));
+        Ok(())
+    }
+
+    /// Removes the playback saved for the guild with the given ID, if any. If
+    /// it was already removed by this store, nothing is done.
+    ///
+    /// # Errors
+    ///
+    /// If the file exists, but cannot be removed.
+    pub fn remove(&self, guild_id: GuildId) -> Result<(), StateError> {
+        if self.is_saved(guild_id, None) {
+            return Ok(());
+        }
+
+        match fs::remove_file(self.path(guild_id)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => {
+                self.set_saved(guild_id, None);
+                Ok(())
+            }
+        }
+    }
+
+    /// Loads the playback saved for all guilds.
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be read, a file name is not a guild ID, or a
+    /// file does not contain a valid [GuildPlayback].
+    pub fn load_all(&self)
+            -> Result<Vec<(GuildId, GuildPlayback)>, StateError> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound =>
+                return Ok(Vec::new()),
+            Err(e) => return Err(e.into())
+        };
+        let mut playbacks = Vec::new();
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            let guild_id = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData,
+                    "playback file name is not valid UTF-8"))?
+                .parse::<u64>()?;
+            let playback = serde_json::from_reader(File::open(&path)?)?;
+
+            playbacks.push((GuildId::new(guild_id), playback));
+        }
+
+        Ok(playbacks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::env;
+
+    fn store(name: &str) -> PlaybackStore {
+        let directory = env::temp_dir()
+            .join(format!("rambot-playback-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+
+        PlaybackStore::new(directory)
+    }
+
+    fn playback() -> GuildPlayback {
+        let layer = serde_json::from_str(r#"{
+            "layer": "music",
+            "descriptor": "song.mp3",
+            "upcoming": [ "next.mp3" ],
+            "position": 48000
+        }"#).unwrap();
+
+        GuildPlayback::new(ChannelId::new(42), vec![layer])
+    }
+
+    fn load_all(store: &PlaybackStore) -> Vec<(GuildId, GuildPlayback)> {
+        store.load_all().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn saved_playback_is_loaded() {
+        let store = store("load");
+        let guild_id = GuildId::new(1);
+
+        assert!(store.save(guild_id, &playback()).is_ok());
+        assert_eq!(vec![(guild_id, playback())], load_all(&store));
+    }
+
+    #[test]
+    fn removed_playback_is_not_loaded() {
+        let store = store("remove");
+        let guild_id = GuildId::new(2);
+
+        assert!(load_all(&store).is_empty());
+        assert!(store.remove(guild_id).is_ok());
+        assert!(store.save(guild_id, &playback()).is_ok());
+        assert!(store.remove(guild_id).is_ok());
+        assert!(load_all(&store).is_empty());
+    }
+
+    #[test]
+    fn unchanged_playback_is_not_written_again() {
+        let store = store("unchanged");
+        let guild_id = GuildId::new(3);
+        let mut changed = playback();
+        changed.channel = ChannelId::new(43);
+
+        assert!(store.save(guild_id, &playback()).is_ok());
+
+        fs::remove_file(store.path(guild_id)).unwrap();
+
+        assert!(store.clone().save(guild_id, &playback()).is_ok());
+        assert!(load_all(&store).is_empty());
+        assert!(store.save(guild_id, &changed).is_ok());
+        assert_eq!(vec![(guild_id, changed)], load_all(&store));
+    }
+}
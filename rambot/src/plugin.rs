@@ -3,6 +3,8 @@ use libloading::{Library, Symbol};
 use rambot_api::{
     AdapterResolver,
     AudioDocumentation,
+    AudioMetadata,
+    AudioMetadataBuilder,
     AudioSource,
     AudioSourceList,
     AudioSourceListResolver,
@@ -12,15 +14,23 @@ use rambot_api::{
     Plugin,
     PluginConfig,
     PluginGuildConfig,
-    ResolverRegistry
+    ResolverRegistry,
+    Sample,
+    SampleDuration,
+    SeekError,
+    API_VERSION
 };
 
 use std::collections::HashMap;
-use std::collections::hash_map::Keys;
+use std::env;
 use std::fmt::{self, Display, Formatter};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::config::Config;
 
@@ -36,7 +46,25 @@ pub enum LoadPluginsError {
 
     /// An initialization error raised by the plugin itself. A message is
     /// provided.
-    InitError(String)
+    InitError(String),
+
+    /// The plugin was built against a different [API_VERSION] than the bot.
+    /// The version of the plugin is provided, which is `None` if it was built
+    /// against an API which did not export its version yet.
+    IncompatibleApi(Option<u32>),
+
+    /// There is no plugin file with the given name in the plugin directory.
+    PluginNotFound(String),
+
+    /// A plugin from the file with the given name is already loaded.
+    PluginAlreadyLoaded(String),
+
+    /// No plugin from the file with the given name is loaded.
+    PluginNotLoaded(String),
+
+    /// The plugin manager was not created from a plugin directory, so it
+    /// cannot load plugins at runtime.
+    NoPluginDirectory
 }
 
 impl From<io::Error> for LoadPluginsError {
@@ -59,7 +87,21 @@ impl Display for LoadPluginsError {
             LoadPluginsError::LoadError(e) =>
                 write!(f, "error loading plugin library: {}", e),
             LoadPluginsError::InitError(msg) =>
-                write!(f, "plugin reported initialization error: {}", msg)
+                write!(f, "plugin reported initialization error: {}", msg),
+            LoadPluginsError::IncompatibleApi(Some(version)) =>
+                write!(f, "plugin was built against API version {}, but the \
+                    bot requires version {}", version, API_VERSION),
+            LoadPluginsError::IncompatibleApi(None) =>
+                write!(f, "plugin was built against an outdated API, the bot \
+                    requires version {}", API_VERSION),
+            LoadPluginsError::PluginNotFound(name) =>
+                write!(f, "no plugin file named {} found", name),
+            LoadPluginsError::PluginAlreadyLoaded(name) =>
+                write!(f, "plugin {} is already loaded", name),
+            LoadPluginsError::PluginNotLoaded(name) =>
+                write!(f, "plugin {} is not loaded", name),
+            LoadPluginsError::NoPluginDirectory =>
+                write!(f, "no plugin directory configured")
         }
     }
 }
@@ -103,6 +145,297 @@ pub enum AudioDescriptorList {
     List(Box<dyn AudioSourceList + Send + Sync>)
 }
 
+/// Information about a resolver that was registered by a plugin.
+pub struct ResolverInfo {
+    name: String,
+    overridden_by: Option<String>
+}
+
+impl ResolverInfo {
+
+    /// Gets the name of the resolver. For audio sources and lists, this is
+    /// the name from their documentation.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the file name of the plugin whose resolver of the same name takes
+    /// precedence over this one, if there is any. For effects and adapters,
+    /// this is the last plugin that registered the name. For audio sources
+    /// and lists, this is the first one, since audio is resolved by the first
+    /// plugin which can resolve it.
+    pub fn overridden_by(&self) -> Option<&str> {
+        self.overridden_by.as_deref()
+    }
+}
+
+/// Information about a loaded plugin and all resolvers it registered.
+pub struct PluginInfo {
+    file_name: String,
+    version: Option<String>,
+    audio_sources: Vec<ResolverInfo>,
+    audio_source_lists: Vec<ResolverInfo>,
+    effects: Vec<ResolverInfo>,
+    adapters: Vec<ResolverInfo>
+}
+
+impl PluginInfo {
+
+    /// Gets the file name of the plugin's library in the plugin directory.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Gets the version of the plugin's crate, if the plugin exports it.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Gets information about the audio source resolvers of the plugin.
+    pub fn audio_sources(&self) -> &[ResolverInfo] {
+        &self.audio_sources
+    }
+
+    /// Gets information about the audio source list resolvers of the plugin.
+    pub fn audio_source_lists(&self) -> &[ResolverInfo] {
+        &self.audio_source_lists
+    }
+
+    /// Gets information about the effect resolvers of the plugin.
+    pub fn effects(&self) -> &[ResolverInfo] {
+        &self.effects
+    }
+
+    /// Gets information about the adapter resolvers of the plugin.
+    pub fn adapters(&self) -> &[ResolverInfo] {
+        &self.adapters
+    }
+}
+
+/// A handle to the dynamic library of a plugin. Every resolver, audio source,
+/// list, and effect created by the plugin holds a reference to it, so the
+/// library is only unloaded once none of them is in use anymore.
+struct LibraryHandle {
+    library: Option<Library>,
+    shadow_path: Option<PathBuf>
+}
+
+impl Drop for LibraryHandle {
+    fn drop(&mut self) {
+        drop(self.library.take());
+
+        if let Some(shadow_path) = &self.shadow_path {
+            if let Err(e) = fs::remove_file(shadow_path) {
+                log::warn!("Could not remove plugin copy {}: {}",
+                    shadow_path.display(), e);
+            }
+        }
+    }
+}
+
+/// An [AudioSource] created by a plugin, which keeps the plugin's library
+/// loaded while it exists.
+struct PluginAudioSource {
+    source: Box<dyn AudioSource + Send + Sync>,
+    _library: Arc<LibraryHandle>
+}
+
+impl AudioSource for PluginAudioSource {
+    fn read(&mut self, buf: &mut [Sample]) -> Result<usize, io::Error> {
+        self.source.read(buf)
+    }
+
+    fn seek(&mut self, delta: SampleDuration) -> Result<(), SeekError> {
+        self.source.seek(delta)
+    }
+
+    fn has_child(&self) -> bool {
+        self.source.has_child()
+    }
+
+    fn take_child(&mut self) -> Box<dyn AudioSource + Send + Sync> {
+        self.source.take_child()
+    }
+
+    fn metadata(&self) -> AudioMetadata {
+        self.source.metadata()
+    }
+}
+
+/// An [AudioSourceList] created by a plugin, which keeps the plugin's library
+/// loaded while it exists.
+struct PluginAudioSourceList {
+    list: Box<dyn AudioSourceList + Send + Sync>,
+    _library: Arc<LibraryHandle>
+}
+
+impl AudioSourceList for PluginAudioSourceList {
+    fn next(&mut self) -> Result<Option<String>, io::Error> {
+        self.list.next()
+    }
+}
+
+/// An [AudioSource] without any audio, to which effects are applied in order
+/// to check whether they can be resolved.
+struct Silence;
+
+impl AudioSource for Silence {
+    fn read(&mut self, _: &mut [Sample]) -> Result<usize, io::Error> {
+        Ok(0)
+    }
+
+    fn has_child(&self) -> bool {
+        false
+    }
+
+    fn take_child(&mut self) -> Box<dyn AudioSource + Send + Sync> {
+        panic!("silence has no child")
+    }
+
+    fn metadata(&self) -> AudioMetadata {
+        AudioMetadataBuilder::new().build()
+    }
+}
+
+/// An [AudioSourceList] without any entries, to which adapters are applied in
+/// order to check whether they can be resolved.
+struct EmptyList;
+
+impl AudioSourceList for EmptyList {
+    fn next(&mut self) -> Result<Option<String>, io::Error> {
+        Ok(None)
+    }
+}
+
+fn retain_library_source(source: Box<dyn AudioSource + Send + Sync>,
+        library: &Option<Arc<LibraryHandle>>)
+        -> Box<dyn AudioSource + Send + Sync> {
+    match library {
+        Some(library) => Box::new(PluginAudioSource {
+            source,
+            _library: Arc::clone(library)
+        }),
+        None => source
+    }
+}
+
+fn retain_library_list(list: Box<dyn AudioSourceList + Send + Sync>,
+        library: &Option<Arc<LibraryHandle>>)
+        -> Box<dyn AudioSourceList + Send + Sync> {
+    match library {
+        Some(library) => Box::new(PluginAudioSourceList {
+            list,
+            _library: Arc::clone(library)
+        }),
+        None => list
+    }
+}
+
+/// A plugin together with the resolvers it registered. When it is dropped,
+/// the plugin is notified, and its resolvers and library are released in that
+/// order.
+struct LoadedPlugin {
+    file_name: String,
+    version: Option<String>,
+    audio_source_resolvers: Vec<Box<dyn AudioSourceResolver>>,
+    audio_source_list_resolvers: Vec<Box<dyn AudioSourceListResolver>>,
+    effect_resolvers: HashMap<String, Box<dyn EffectResolver>>,
+    adapter_resolvers: HashMap<String, Box<dyn AdapterResolver>>,
+    plugin: Option<Box<dyn Plugin>>,
+    library: Option<Arc<LibraryHandle>>
+}
+
+impl LoadedPlugin {
+
+    fn new(file_name: impl Into<String>) -> LoadedPlugin {
+        LoadedPlugin {
+            file_name: file_name.into(),
+            version: None,
+            audio_source_resolvers: Vec::new(),
+            audio_source_list_resolvers: Vec::new(),
+            effect_resolvers: HashMap::new(),
+            adapter_resolvers: HashMap::new(),
+            plugin: None,
+            library: None
+        }
+    }
+
+    fn audio_source_names(&self) -> Vec<String> {
+        self.audio_source_resolvers.iter()
+            .map(|r| r.documentation().name().to_owned())
+            .collect()
+    }
+
+    fn audio_source_list_names(&self) -> Vec<String> {
+        self.audio_source_list_resolvers.iter()
+            .map(|r| r.documentation().name().to_owned())
+            .collect()
+    }
+
+    fn effect_names(&self) -> Vec<String> {
+        sorted_keys(&self.effect_resolvers)
+    }
+
+    fn adapter_names(&self) -> Vec<String> {
+        sorted_keys(&self.adapter_resolvers)
+    }
+
+    fn registry(&mut self) -> ResolverRegistry<'_> {
+        ResolverRegistry::new(
+            |r| self.audio_source_resolvers.push(r),
+            |r| self.audio_source_list_resolvers.push(r),
+            |r| {
+                let name = r.name().to_owned();
+                self.effect_resolvers.insert(name, r);
+            },
+            |r| {
+                let name = r.name().to_owned();
+                self.adapter_resolvers.insert(name, r);
+            }
+        )
+    }
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        if let Some(plugin) = &self.plugin {
+            plugin.unload_plugin();
+        }
+    }
+}
+
+fn sorted_keys<V>(map: &HashMap<String, V>) -> Vec<String> {
+    let mut keys = map.keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+/// Collects the [ResolverInfo]s for the resolvers of the plugin at the given
+/// `index`. If `last_wins` is true, resolvers are overridden by plugins loaded
+/// later, otherwise by ones loaded earlier.
+fn resolver_infos<F>(plugins: &[LoadedPlugin], index: usize, last_wins: bool,
+    get_names: F) -> Vec<ResolverInfo>
+where
+    F: Fn(&LoadedPlugin) -> Vec<String>
+{
+    let names = plugins.iter().map(&get_names).collect::<Vec<_>>();
+    let candidates = if last_wins {
+        (index + 1..plugins.len()).rev().collect::<Vec<_>>()
+    }
+    else {
+        (0..index).collect::<Vec<_>>()
+    };
+
+    names[index].iter()
+        .map(|name| ResolverInfo {
+            name: name.clone(),
+            overridden_by: candidates.iter()
+                .find(|&&i| names[i].contains(name))
+                .map(|&i| plugins[i].file_name.clone())
+        })
+        .collect()
+}
+
 trait AudioResolver {
     type Value;
 
@@ -111,6 +444,9 @@ trait AudioResolver {
 
     fn resolve(&self, descriptor: &str, plugin_guild_config: PluginGuildConfig)
         -> Result<Self::Value, String>;
+
+    fn retain_library(value: Self::Value,
+        library: &Option<Arc<LibraryHandle>>) -> Self::Value;
 }
 
 impl AudioResolver for Box<dyn AudioSourceResolver> {
@@ -125,6 +461,11 @@ impl AudioResolver for Box<dyn AudioSourceResolver> {
             -> Result<Self::Value, String> {
         self.as_ref().resolve(descriptor, plugin_guild_config)
     }
+
+    fn retain_library(value: Self::Value,
+            library: &Option<Arc<LibraryHandle>>) -> Self::Value {
+        retain_library_source(value, library)
+    }
 }
 
 impl AudioResolver for Box<dyn AudioSourceListResolver> {
@@ -139,6 +480,11 @@ impl AudioResolver for Box<dyn AudioSourceListResolver> {
             -> Result<Self::Value, String> {
         self.as_ref().resolve(descriptor, plugin_guild_config)
     }
+
+    fn retain_library(value: Self::Value,
+            library: &Option<Arc<LibraryHandle>>) -> Self::Value {
+        retain_library_list(value, library)
+    }
 }
 
 trait ModifierResolver {
@@ -180,106 +526,266 @@ impl ModifierResolver for Box<dyn AdapterResolver> {
     }
 }
 
-fn resolve_audio<V, R>(descriptor: &str,
-    plugin_guild_config: &PluginGuildConfig, resolvers: &[R])
-    -> Result<V, ResolveError>
+fn resolve_audio<V, R, F>(descriptor: &str,
+    plugin_guild_config: &PluginGuildConfig, plugins: &[LoadedPlugin],
+    get_resolvers: F) -> Result<V, ResolveError>
 where
-    R: AudioResolver<Value = V>
+    R: AudioResolver<Value = V>,
+    F: Fn(&LoadedPlugin) -> &[R]
 {
-    for resolver in resolvers.iter() {
-        if resolver.can_resolve(descriptor, plugin_guild_config.clone()) {
-            return resolver.resolve(descriptor, plugin_guild_config.clone())
-                .map_err(ResolveError::PluginResolveError);
+    let enabled_plugins = plugins.iter()
+        .filter(|p| !plugin_guild_config.is_plugin_disabled(&p.file_name));
+
+    for plugin in enabled_plugins {
+        for resolver in get_resolvers(plugin) {
+            if resolver.can_resolve(descriptor, plugin_guild_config.clone()) {
+                return resolver.resolve(descriptor, plugin_guild_config.clone())
+                    .map(|value| R::retain_library(value, &plugin.library))
+                    .map_err(ResolveError::PluginResolveError);
+            }
         }
     }
 
     Err(ResolveError::NoPluginFound)
 }
 
-fn is_modifier_unique<R>(name: &str, resolvers: &HashMap<String, R>) -> bool
+/// Finds the resolver for the modifier with the given name. If multiple
+/// plugins provide a modifier of the same name, the one loaded last is used.
+fn find_modifier<'plugins, R, F>(name: &str, plugins: &'plugins [LoadedPlugin],
+    get_resolvers: F) -> Option<(&'plugins R, &'plugins LoadedPlugin)>
+where
+    F: Fn(&LoadedPlugin) -> &HashMap<String, R>
+{
+    plugins.iter().rev()
+        .find_map(|plugin| get_resolvers(plugin).get(name)
+            .map(|resolver| (resolver, plugin)))
+}
+
+/// Finds the resolver for the modifier with the given name like
+/// [find_modifier], but ignores plugins that are disabled in the guild. If
+/// the modifier itself or all plugins which provide it are disabled, `None`
+/// is returned. If no plugin provides the modifier at all,
+/// [ResolveError::NoPluginFound] is raised.
+fn find_enabled_modifier<'plugins, R, F>(name: &str, disabled: bool,
+    plugins: &'plugins [LoadedPlugin],
+    plugin_guild_config: &PluginGuildConfig, get_resolvers: F)
+    -> Result<Option<(&'plugins R, &'plugins LoadedPlugin)>, ResolveError>
+where
+    F: Fn(&LoadedPlugin) -> &HashMap<String, R>
+{
+    if find_modifier(name, plugins, &get_resolvers).is_none() {
+        return Err(ResolveError::NoPluginFound);
+    }
+
+    if disabled {
+        return Ok(None);
+    }
+
+    Ok(plugins.iter().rev()
+        .filter(|p| !plugin_guild_config.is_plugin_disabled(&p.file_name))
+        .find_map(|plugin| get_resolvers(plugin).get(name)
+            .map(|resolver| (resolver, plugin))))
+}
+
+fn modifier_names<R, F>(plugins: &[LoadedPlugin], get_resolvers: F)
+    -> Vec<String>
+where
+    F: Fn(&LoadedPlugin) -> &HashMap<String, R>
+{
+    let mut names = plugins.iter()
+        .flat_map(|plugin| get_resolvers(plugin).keys().cloned())
+        .collect::<Vec<_>>();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn is_modifier_unique<R, F>(name: &str, plugins: &[LoadedPlugin],
+    get_resolvers: F) -> bool
 where
-    R: ModifierResolver
+    R: ModifierResolver,
+    F: Fn(&LoadedPlugin) -> &HashMap<String, R>
 {
-    resolvers.get(name)
-        .map(|r| r.unique())
+    find_modifier(name, plugins, get_resolvers)
+        .map(|(r, _)| r.unique())
         .unwrap_or(false)
 }
 
-fn get_modifier_documentation<R>(name: &str, resolvers: &HashMap<String, R>)
-    -> Option<ModifierDocumentation>
+fn get_modifier_documentation<R, F>(name: &str, plugins: &[LoadedPlugin],
+    get_resolvers: F) -> Option<ModifierDocumentation>
 where
-    R: ModifierResolver
+    R: ModifierResolver,
+    F: Fn(&LoadedPlugin) -> &HashMap<String, R>
 {
-    resolvers.get(name)
-        .map(|r| r.documentation())
+    find_modifier(name, plugins, get_resolvers)
+        .map(|(r, _)| r.documentation())
+}
+
+/// Copies the library at the given path to a unique file in the temporary
+/// directory. Loading the copy instead of the original ensures that a new
+/// version of the library is loaded even if an old version is still in use,
+/// as the dynamic loader would otherwise return the already loaded library.
+fn shadow_copy(path: &Path, file_name: &str) -> io::Result<PathBuf> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let directory = env::temp_dir()
+        .join(format!("rambot-plugins-{}", process::id()));
+    let shadow_path = directory.join(format!("{}-{}",
+        COUNTER.fetch_add(1, Ordering::Relaxed), file_name));
+
+    fs::create_dir_all(&directory)?;
+    fs::copy(path, &shadow_path)?;
+
+    Ok(shadow_path)
 }
 
-unsafe fn load_plugin(path: PathBuf, config: PluginConfig,
-        registry: &mut ResolverRegistry, plugins: &mut Vec<Box<dyn Plugin>>,
-        loaded_libraries: &mut Vec<Library>) -> Result<(), LoadPluginsError> {
+unsafe fn load_plugin(path: &Path, file_name: &str, config: PluginConfig,
+        shadow: bool) -> Result<LoadedPlugin, LoadPluginsError> {
     type CreatePlugin = unsafe fn() -> *mut Box<dyn Plugin>;
+    type PluginVersion = unsafe fn() -> &'static str;
+    type ApiVersion = unsafe fn() -> u32;
+
+    let mut library = LibraryHandle {
+        library: None,
+        shadow_path: None
+    };
+
+    if shadow {
+        library.shadow_path = Some(shadow_copy(path, file_name)?);
+    }
+
+    library.library = Some(Library::new(
+        library.shadow_path.as_deref().unwrap_or(path))?);
+
+    let library = Arc::new(library);
+    let api_version: Option<Symbol<ApiVersion>> =
+        library.library.as_ref().unwrap().get(b"_api_version").ok();
+    let api_version = api_version.map(|api_version| api_version());
 
-    let lib = Library::new(path)?;
-    loaded_libraries.push(lib);
-    let lib = loaded_libraries.last().unwrap();
-    let create_plugin: Symbol<CreatePlugin> = lib.get(b"_create_plugin")?;
-    let raw = create_plugin();
-    plugins.push(*Box::from_raw(raw));
-    let plugin = plugins.last().unwrap();
+    // The plugin must not be created if it uses a different layout for the
+    // types shared with the bot.
 
-    if let Err(msg) = plugin.load_plugin(config, registry) {
+    if api_version != Some(API_VERSION) {
+        return Err(LoadPluginsError::IncompatibleApi(api_version));
+    }
+
+    let create_plugin: Symbol<CreatePlugin> =
+        library.library.as_ref().unwrap().get(b"_create_plugin")?;
+    let version: Option<Symbol<PluginVersion>> =
+        library.library.as_ref().unwrap().get(b"_plugin_version").ok();
+    let plugin = *Box::from_raw(create_plugin());
+    let mut loaded_plugin = LoadedPlugin::new(file_name);
+
+    // Plugins built against older versions of the API do not export a
+    // version, which is not an error.
+
+    loaded_plugin.version = version.map(|version| version().to_owned());
+    let load_result = plugin.load_plugin(config, &mut loaded_plugin.registry());
+
+    loaded_plugin.library = Some(Arc::clone(&library));
+    loaded_plugin.plugin = Some(plugin);
+
+    if let Err(msg) = load_result {
         return Err(LoadPluginsError::InitError(msg));
     }
 
-    Ok(())
+    Ok(loaded_plugin)
+}
+
+fn is_valid_file_name(file_name: &str) -> bool {
+    !file_name.is_empty() && !file_name.starts_with('.') &&
+        !file_name.contains(['/', '\\', ':'])
+}
+
+type PluginConfigGenerator = Box<dyn Fn(&str) -> PluginConfig + Send + Sync>;
+
+/// Where the plugins of a [PluginManager] come from, which is required to
+/// load and reload plugins at runtime.
+struct PluginSource {
+    plugin_directory: String,
+    generate_plugin_config: PluginConfigGenerator
+}
+
+impl PluginSource {
+
+    fn path(&self, file_name: &str) -> Result<PathBuf, LoadPluginsError> {
+        let path = Path::new(&self.plugin_directory).join(file_name);
+
+        if is_valid_file_name(file_name) && path.is_file() {
+            Ok(path)
+        }
+        else {
+            Err(LoadPluginsError::PluginNotFound(file_name.to_owned()))
+        }
+    }
+
+    fn load(&self, file_name: &str, shadow: bool)
+            -> Result<LoadedPlugin, LoadPluginsError> {
+        let path = self.path(file_name)?;
+        let plugin_config = (self.generate_plugin_config)(file_name);
+
+        // TODO this is probably truly unsafe -- how to contain?
+
+        unsafe { load_plugin(&path, file_name, plugin_config, shadow) }
+    }
+
+    fn load_all(&self, shadow: bool)
+            -> Result<Vec<LoadedPlugin>, LoadPluginsError> {
+        let mut file_names = Vec::new();
+
+        for dir_entry in fs::read_dir(&self.plugin_directory)? {
+            let dir_entry = dir_entry?;
+            let file_type = dir_entry.file_type()?;
+
+            if file_type.is_file() {
+                file_names.push(
+                    dir_entry.file_name().to_str().unwrap().to_owned());
+            }
+        }
+
+        file_names.sort();
+        file_names.iter()
+            .map(|file_name| self.load(file_name, shadow))
+            .collect()
+    }
 }
 
 /// Manages loading of plugins and resolution of functionality offered by those
-/// plugins (i.e. audio sources, lists, effects, and adapters).
+/// plugins (i.e. audio sources, lists, effects, and adapters). Plugins can be
+/// loaded, unloaded, and reloaded while the manager is in use. Audio created
+/// by a plugin keeps its library loaded until it is dropped, even if the
+/// plugin itself was unloaded.
 pub struct PluginManager {
-    audio_source_resolvers: Vec<Box<dyn AudioSourceResolver>>,
-    audio_source_list_resolvers: Vec<Box<dyn AudioSourceListResolver>>,
-    effect_resolvers: HashMap<String, Box<dyn EffectResolver>>,
-    adapter_resolvers: HashMap<String, Box<dyn AdapterResolver>>,
-    plugins: Vec<Box<dyn Plugin>>,
-    loaded_libraries: Vec<Library>
+    plugins: RwLock<Vec<LoadedPlugin>>,
+    source: Option<PluginSource>
 }
 
 impl PluginManager {
 
+    #[cfg(test)]
     pub(crate) fn empty() -> PluginManager {
         PluginManager {
-            audio_source_resolvers: Vec::new(),
-            audio_source_list_resolvers: Vec::new(),
-            effect_resolvers: HashMap::new(),
-            adapter_resolvers: HashMap::new(),
-            plugins: Vec::new(),
-            loaded_libraries: Vec::new()
+            plugins: RwLock::new(Vec::new()),
+            source: None
         }
     }
 
-    fn registration_parts(&mut self)
-            -> (ResolverRegistry<'_>, &mut Vec<Box<dyn Plugin>>,
-                &mut Vec<Library>) {
-        let registry = ResolverRegistry::new(
-            |r| self.audio_source_resolvers.push(r),
-            |r| self.audio_source_list_resolvers.push(r),
-            |r| {
-                let name = r.name().to_owned();
-                self.effect_resolvers.insert(name, r);
-            },
-            |r| {
-                let name = r.name().to_owned();
-                self.adapter_resolvers.insert(name, r);
-            }
-        );
+    #[cfg(test)]
+    pub(crate) fn mock_registry(&mut self, file_name: &str)
+            -> ResolverRegistry<'_> {
+        let plugins = self.plugins.get_mut().unwrap();
 
-        (registry, &mut self.plugins, &mut self.loaded_libraries)
+        plugins.push(LoadedPlugin::new(file_name));
+        plugins.last_mut().unwrap().registry()
     }
 
-    #[cfg(test)]
-    pub(crate) fn mock_registry(&mut self) -> ResolverRegistry<'_> {
-        self.registration_parts().0
+    fn plugins(&self) -> RwLockReadGuard<'_, Vec<LoadedPlugin>> {
+        self.plugins.read().unwrap()
+    }
+
+    fn source(&self) -> Result<&PluginSource, LoadPluginsError> {
+        self.source.as_ref().ok_or(LoadPluginsError::NoPluginDirectory)
     }
 
     /// Loads plugins from the plugin directory specified in the given config
@@ -295,44 +801,196 @@ impl PluginManager {
     ///
     /// Any [LoadPluginsError] according to their respective documentation.
     pub fn new(config: &Config) -> Result<PluginManager, LoadPluginsError> {
-        let mut plugin_manager = PluginManager::empty();
-        let (mut resolver_registry, plugins, loaded_libraries) =
-            plugin_manager.registration_parts();
+        PluginManager::load(config.plugin_directory(),
+            config.plugin_config_directory(),
+            config.plugin_config_generator())
+    }
 
-        fs::create_dir_all(config.plugin_directory())?;
-        fs::create_dir_all(config.plugin_config_directory())?;
+    /// Loads plugins from the given plugin directory and returns a manager
+    /// for them. This does not require a complete [Config], which allows
+    /// tools other than the bot itself to load plugins.
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin_directory`: The directory from which to load the plugins.
+    /// * `plugin_config_directory`: The directory in which plugins put their
+    ///   specific config files.
+    /// * `generate_plugin_config`: A function which generates the
+    ///   [PluginConfig] for a plugin given the file name of its library. It
+    ///   is retained to load plugins at runtime.
+    ///
+    /// # Errors
+    ///
+    /// Any [LoadPluginsError] according to their respective documentation.
+    pub fn load<F>(plugin_directory: &str, plugin_config_directory: &str,
+        generate_plugin_config: F) -> Result<PluginManager, LoadPluginsError>
+    where
+        F: Fn(&str) -> PluginConfig + Send + Sync + 'static
+    {
+        fs::create_dir_all(plugin_directory)?;
+        fs::create_dir_all(plugin_config_directory)?;
+
+        let source = PluginSource {
+            plugin_directory: plugin_directory.to_owned(),
+            generate_plugin_config: Box::new(generate_plugin_config)
+        };
+        let plugins = source.load_all(false)?;
+        let plugin_manager = PluginManager {
+            plugins: RwLock::new(plugins),
+            source: Some(source)
+        };
+
+        plugin_manager.log_summary("Loaded");
+        Ok(plugin_manager)
+    }
 
-        for dir_entry in fs::read_dir(config.plugin_directory())? {
-            let dir_entry = dir_entry?;
-            let file_type = dir_entry.file_type()?;
+    fn log_summary(&self, action: &str) {
+        let plugins = self.plugins();
+        let count = |get_count: fn(&LoadedPlugin) -> usize|
+            plugins.iter().map(get_count).sum::<usize>();
+
+        log::info!("{} {} plugins with {} audio sources, {} lists, {} \
+            effects, and {} adapters.", action, plugins.len(),
+            count(|p| p.audio_source_resolvers.len()),
+            count(|p| p.audio_source_list_resolvers.len()),
+            count(|p| p.effect_resolvers.len()),
+            count(|p| p.adapter_resolvers.len()));
+    }
 
-            if file_type.is_file() {
-                let plugin_config = config.generate_plugin_config(
-                    dir_entry.file_name().to_str().unwrap());
+    /// Gets a [PluginInfo] for every currently loaded plugin in the order in
+    /// which they were loaded, which describes the resolvers each plugin
+    /// registered and which of them are overridden by other plugins.
+    pub fn plugin_infos(&self) -> Vec<PluginInfo> {
+        let plugins = self.plugins();
+
+        (0..plugins.len())
+            .map(|index| PluginInfo {
+                file_name: plugins[index].file_name.clone(),
+                version: plugins[index].version.clone(),
+                audio_sources: resolver_infos(&plugins, index, false,
+                    LoadedPlugin::audio_source_names),
+                audio_source_lists: resolver_infos(&plugins, index, false,
+                    LoadedPlugin::audio_source_list_names),
+                effects: resolver_infos(&plugins, index, true,
+                    LoadedPlugin::effect_names),
+                adapters: resolver_infos(&plugins, index, true,
+                    LoadedPlugin::adapter_names)
+            })
+            .collect()
+    }
+
+    /// Loads the plugin from the file with the given name in the plugin
+    /// directory, which must not be loaded already.
+    ///
+    /// # Errors
+    ///
+    /// * [LoadPluginsError::PluginAlreadyLoaded] if a plugin from the given
+    ///   file is already loaded.
+    /// * Any other [LoadPluginsError] raised while loading the plugin.
+    pub fn load_plugin(&self, file_name: &str) -> Result<(), LoadPluginsError> {
+        let mut plugins = self.plugins.write().unwrap();
+
+        if plugins.iter().any(|p| p.file_name == file_name) {
+            return Err(
+                LoadPluginsError::PluginAlreadyLoaded(file_name.to_owned()));
+        }
+
+        plugins.push(self.source()?.load(file_name, true)?);
+        drop(plugins);
 
-                // TODO this is probably truly unsafe -- how to contain?
+        log::info!("Loaded plugin {}.", file_name);
+        Ok(())
+    }
+
+    /// Unloads the plugin from the file with the given name, such that its
+    /// functionality is no longer available. Audio that was created by the
+    /// plugin continues playing, and the library of the plugin is released
+    /// once all such audio is dropped.
+    ///
+    /// # Errors
+    ///
+    /// [LoadPluginsError::PluginNotLoaded] if no plugin from the given file is
+    /// loaded.
+    pub fn unload_plugin(&self, file_name: &str)
+            -> Result<(), LoadPluginsError> {
+        let removed = {
+            let mut plugins = self.plugins.write().unwrap();
+            let index = plugins.iter()
+                .position(|p| p.file_name == file_name)
+                .ok_or_else(|| LoadPluginsError::PluginNotLoaded(
+                    file_name.to_owned()))?;
+
+            plugins.remove(index)
+        };
+
+        // The plugin is notified outside the lock, so it may take its time.
+
+        drop(removed);
+        log::info!("Unloaded plugin {}.", file_name);
+        Ok(())
+    }
 
-                unsafe {
-                    load_plugin(dir_entry.path(), plugin_config,
-                        &mut resolver_registry, plugins, loaded_libraries)?;
+    /// Reloads the plugin from the file with the given name or, if `None` is
+    /// given, replaces all plugins by the ones currently in the plugin
+    /// directory. New versions are loaded before the old ones are unloaded,
+    /// so if loading fails, the old plugins remain in use. Audio that was
+    /// created by the old versions continues playing with the old code, but
+    /// effects must be re-resolved using [Mixer::reapply_effects] to use the
+    /// new versions.
+    ///
+    /// # Returns
+    ///
+    /// The number of reloaded plugins.
+    ///
+    /// # Errors
+    ///
+    /// * [LoadPluginsError::PluginNotLoaded] if a name is given but no plugin
+    ///   from that file is loaded.
+    /// * Any other [LoadPluginsError] raised while loading the new versions.
+    ///
+    /// [Mixer::reapply_effects]: crate::audio::Mixer::reapply_effects
+    pub fn reload(&self, file_name: Option<&str>)
+            -> Result<usize, LoadPluginsError> {
+        let source = self.source()?;
+        let (old_plugins, count) = match file_name {
+            Some(file_name) => {
+                if !self.plugins().iter().any(|p| p.file_name == file_name) {
+                    return Err(LoadPluginsError::PluginNotLoaded(
+                        file_name.to_owned()));
                 }
-            }
-        }
 
-        drop(resolver_registry);
+                let new_plugin = source.load(file_name, true)?;
+                let mut plugins = self.plugins.write().unwrap();
+                let old_plugin = match plugins.iter()
+                        .position(|p| p.file_name == file_name) {
+                    Some(index) => mem::replace(&mut plugins[index], new_plugin),
+                    None => {
+                        // Unloaded concurrently, so we do not revive it.
 
-        log::info!("Loaded {} plugins with {} audio sources, {} lists, {} \
-            effects, and {} adapters.", plugin_manager.loaded_libraries.len(),
-            plugin_manager.audio_source_resolvers.len(),
-            plugin_manager.audio_source_list_resolvers.len(),
-            plugin_manager.effect_resolvers.len(),
-            plugin_manager.adapter_resolvers.len());
+                        return Err(LoadPluginsError::PluginNotLoaded(
+                            file_name.to_owned()));
+                    }
+                };
 
-        Ok(plugin_manager)
+                (vec![old_plugin], 1)
+            },
+            None => {
+                let new_plugins = source.load_all(true)?;
+                let count = new_plugins.len();
+                let mut plugins = self.plugins.write().unwrap();
+
+                (mem::replace(&mut *plugins, new_plugins), count)
+            }
+        };
+
+        drop(old_plugins);
+        self.log_summary("Reloaded");
+        Ok(count)
     }
 
     /// Resolves an [AudioSource] given a textual descriptor by searching for a
-    /// plugin-provided resolver that can process the descriptor.
+    /// plugin-provided resolver that can process the descriptor. Plugins which
+    /// are disabled in the guild are ignored.
     ///
     /// # Arguments
     ///
@@ -352,12 +1010,13 @@ impl PluginManager {
     pub fn resolve_audio_source(&self, descriptor: &str,
             plugin_guild_config: &PluginGuildConfig)
             -> Result<Box<dyn AudioSource + Send + Sync>, ResolveError> {
-        resolve_audio(descriptor, plugin_guild_config,
-            &self.audio_source_resolvers)
+        resolve_audio(descriptor, plugin_guild_config, &self.plugins(),
+            |p| &p.audio_source_resolvers)
     }
 
     /// Resolves an [AudioSourceList] given a textual descriptor by searching
-    /// for a plugin-provided resolver that can process the descriptor.
+    /// for a plugin-provided resolver that can process the descriptor. Plugins
+    /// which are disabled in the guild are ignored.
     ///
     /// # Arguments
     ///
@@ -377,8 +1036,8 @@ impl PluginManager {
     pub fn resolve_audio_source_list(&self, descriptor: &str,
             plugin_guild_config: &PluginGuildConfig)
             -> Result<Box<dyn AudioSourceList + Send + Sync>, ResolveError> {
-        resolve_audio(descriptor, plugin_guild_config,
-            &self.audio_source_list_resolvers)
+        resolve_audio(descriptor, plugin_guild_config, &self.plugins(),
+            |p| &p.audio_source_list_resolvers)
     }
 
     /// Resolves an [AudioDescriptorList] given a textual descriptor. This is
@@ -409,27 +1068,43 @@ impl PluginManager {
         }
     }
 
-    /// Gets an iterator over the [AudioDocumentation]s for all audio sources
-    /// and audio source lists provided by plugins.
-    pub fn get_audio_documentations(&self)
-            -> impl Iterator<Item = AudioDocumentation> + '_ {
-        self.audio_source_resolvers.iter()
+    /// Gets the [AudioDocumentation]s for all audio sources and audio source
+    /// lists provided by plugins.
+    pub fn get_audio_documentations(&self) -> Vec<AudioDocumentation> {
+        let plugins = self.plugins();
+
+        plugins.iter()
+            .flat_map(|p| p.audio_source_resolvers.iter())
             .map(|r| r.documentation())
-            .chain(self.audio_source_list_resolvers.iter()
+            .chain(plugins.iter()
+                .flat_map(|p| p.audio_source_list_resolvers.iter())
                 .map(|r| r.documentation()))
+            .collect()
     }
 
-    /// Gets an iterator over the names of all effects that have been
-    /// registered by plugins.
-    pub fn effect_names(&self) -> Keys<'_, String, Box<dyn EffectResolver>> {
-        self.effect_resolvers.keys()
+    /// Gets the names of all effects that have been registered by plugins in
+    /// alphabetical order.
+    pub fn effect_names(&self) -> Vec<String> {
+        modifier_names(&self.plugins(), |p| &p.effect_resolvers)
     }
 
     /// Indicates whether the effect with the given name is unique, that is,
     /// only one can exist per layer. If no effect with the given name exists,
     /// `false` is returned.
     pub fn is_effect_unique(&self, name: &str) -> bool {
-        is_modifier_unique(name, &self.effect_resolvers)
+        is_modifier_unique(name, &self.plugins(), |p| &p.effect_resolvers)
+    }
+
+    /// Indicates whether the effect with the given name is disabled in the
+    /// guild with the given [PluginGuildConfig], either by itself or because
+    /// all plugins which provide it are disabled. Disabled effects leave the
+    /// audio unchanged.
+    pub fn is_effect_disabled(&self, name: &str,
+            plugin_guild_config: &PluginGuildConfig) -> bool {
+        let disabled = plugin_guild_config.is_effect_disabled(name);
+
+        matches!(find_enabled_modifier(name, disabled, &self.plugins(),
+            plugin_guild_config, |p| &p.effect_resolvers), Ok(None))
     }
 
     /// Resolves an effect given the name and parameters as key-values by
@@ -448,7 +1123,8 @@ impl PluginManager {
     /// # Returns
     ///
     /// A new [AudioSource] trait object that represents the resolved effect
-    /// applied to the child.
+    /// applied to the child. If the effect or all plugins which provide it
+    /// are disabled in the guild, the child is returned unchanged.
     ///
     /// # Errors
     ///
@@ -459,18 +1135,49 @@ impl PluginManager {
             plugin_guild_config: &PluginGuildConfig)
             -> Result<Box<dyn AudioSource + Send + Sync>,
                 (ResolveError, Box<dyn AudioSource + Send + Sync>)> {
-        if let Some(resolver) = self.effect_resolvers.get(name) {
-            resolver.resolve(key_values, child, plugin_guild_config.clone())
+        let plugins = self.plugins();
+        let disabled = plugin_guild_config.is_effect_disabled(name);
+        let modifier = find_enabled_modifier(name, disabled, &plugins,
+            plugin_guild_config, |p| &p.effect_resolvers);
+
+        match modifier {
+            Ok(Some((resolver, plugin))) => resolver
+                .resolve(key_values, child, plugin_guild_config.clone())
+                .map(|effect| retain_library_source(effect, &plugin.library))
                 .map_err(|e| {
                     let (msg, child) = e.into_parts();
                     (ResolveError::PluginResolveError(msg), child)
-                })
-        }
-        else {
-            Err((ResolveError::NoPluginFound, child))
+                }),
+            Ok(None) => Ok(child),
+            Err(e) => Err((e, child))
         }
     }
 
+    /// Checks whether the effect with the given name and parameters can be
+    /// resolved, without applying it to any audio. This is used to validate
+    /// configurations before they are applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the effect type to check.
+    /// * `key_values`: A [HashMap] that stores key-value pairs provided as
+    ///   arguments for the effect.
+    /// * `plugin_guild_config`: A reference to the [PluginGuildConfig] in
+    ///   which carries guild-specific information for the plugin(s).
+    ///
+    /// # Errors
+    ///
+    /// Any [ResolveError] which [PluginManager::resolve_effect] would return.
+    pub fn validate_effect(&self, name: &str,
+            key_values: &HashMap<String, String>,
+            plugin_guild_config: &PluginGuildConfig)
+            -> Result<(), ResolveError> {
+        self.resolve_effect(name, key_values, Box::new(Silence),
+            plugin_guild_config)
+            .map(|_| ())
+            .map_err(|(e, _)| e)
+    }
+
     /// Gets the documentation for the effect with the given name. The
     /// documentation is provided by plugins themselves.
     ///
@@ -484,20 +1191,33 @@ impl PluginManager {
     /// given name and `None` if no such effect exists.
     pub fn get_effect_documentation(&self, name: &str)
             -> Option<ModifierDocumentation> {
-        get_modifier_documentation(name, &self.effect_resolvers)
+        get_modifier_documentation(name, &self.plugins(),
+            |p| &p.effect_resolvers)
     }
 
-    /// Gets an iterator over the names of all adapters that have been
-    /// registered by plugins.
-    pub fn adapter_names(&self) -> Keys<'_, String, Box<dyn AdapterResolver>> {
-        self.adapter_resolvers.keys()
+    /// Gets the names of all adapters that have been registered by plugins in
+    /// alphabetical order.
+    pub fn adapter_names(&self) -> Vec<String> {
+        modifier_names(&self.plugins(), |p| &p.adapter_resolvers)
     }
 
     /// Indicates whether the adapter with the given name is unique, that is,
     /// only one can exist per layer. If no adapter with the given name exists,
     /// `false` is returned.
     pub fn is_adapter_unique(&self, name: &str) -> bool {
-        is_modifier_unique(name, &self.adapter_resolvers)
+        is_modifier_unique(name, &self.plugins(), |p| &p.adapter_resolvers)
+    }
+
+    /// Indicates whether the adapter with the given name is disabled in the
+    /// guild with the given [PluginGuildConfig], either by itself or because
+    /// all plugins which provide it are disabled. Disabled adapters leave the
+    /// playlist unchanged.
+    pub fn is_adapter_disabled(&self, name: &str,
+            plugin_guild_config: &PluginGuildConfig) -> bool {
+        let disabled = plugin_guild_config.is_adapter_disabled(name);
+
+        matches!(find_enabled_modifier(name, disabled, &self.plugins(),
+            plugin_guild_config, |p| &p.adapter_resolvers), Ok(None))
     }
 
     /// Resolves an adapter given the name and parameters as key-values by
@@ -516,7 +1236,8 @@ impl PluginManager {
     /// # Returns
     ///
     /// A new [AudioSource] trait object that represents the resolved adapter
-    /// applied to the child.
+    /// applied to the child. If the adapter or all plugins which provide it
+    /// are disabled in the guild, the child is returned unchanged.
     ///
     /// # Errors
     ///
@@ -526,15 +1247,45 @@ impl PluginManager {
             child: Box<dyn AudioSourceList + Send + Sync>,
             plugin_guild_config: &PluginGuildConfig)
             -> Result<Box<dyn AudioSourceList + Send + Sync>, ResolveError> {
-        if let Some(resolver) = self.adapter_resolvers.get(name) {
-            resolver.resolve(key_values, child, plugin_guild_config.clone())
-                .map_err(ResolveError::PluginResolveError)
-        }
-        else {
-            Err(ResolveError::NoPluginFound)
+        let plugins = self.plugins();
+        let disabled = plugin_guild_config.is_adapter_disabled(name);
+        let modifier = find_enabled_modifier(name, disabled, &plugins,
+            plugin_guild_config, |p| &p.adapter_resolvers)?;
+
+        match modifier {
+            Some((resolver, plugin)) => resolver
+                .resolve(key_values, child, plugin_guild_config.clone())
+                .map(|adapter| retain_library_list(adapter, &plugin.library))
+                .map_err(ResolveError::PluginResolveError),
+            None => Ok(child)
         }
     }
 
+    /// Checks whether the adapter with the given name and parameters can be
+    /// resolved, without applying it to any audio source list. This is used
+    /// to validate configurations before they are applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the adapter type to check.
+    /// * `key_values`: A [HashMap] that stores key-value pairs provided as
+    ///   arguments for the adapter.
+    /// * `plugin_guild_config`: A reference to the [PluginGuildConfig] in
+    ///   which carries guild-specific information for the plugin(s).
+    ///
+    /// # Errors
+    ///
+    /// Any [ResolveError] which [PluginManager::resolve_adapter] would
+    /// return.
+    pub fn validate_adapter(&self, name: &str,
+            key_values: &HashMap<String, String>,
+            plugin_guild_config: &PluginGuildConfig)
+            -> Result<(), ResolveError> {
+        self.resolve_adapter(name, key_values, Box::new(EmptyList),
+            plugin_guild_config)
+            .map(|_| ())
+    }
+
     /// Gets the documentation for the adapter with the given name. The
     /// documentation is provided by plugins themselves.
     ///
@@ -548,28 +1299,119 @@ impl PluginManager {
     /// given name and `None` if no such adapter exists.
     pub fn get_adapter_documentation(&self, name: &str)
             -> Option<ModifierDocumentation> {
-        get_modifier_documentation(name, &self.adapter_resolvers)
+        get_modifier_documentation(name, &self.plugins(),
+            |p| &p.adapter_resolvers)
     }
+}
 
-    fn unload(&mut self) {
-        let count = self.plugins.len();
+impl Drop for PluginManager {
+    fn drop(&mut self) {
+        let plugins = self.plugins.get_mut().unwrap();
+        let count = plugins.len();
 
-        for plugin in self.plugins.drain(..) {
-            plugin.unload_plugin();
+        if count > 0 {
+            plugins.clear();
+            log::debug!("Unloaded {} plugins.", count);
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use rambot_api::ResolveEffectError;
 
-        for library in self.loaded_libraries.drain(..) {
-            drop(library);
+    struct MockEffectResolver(&'static str);
+
+    impl EffectResolver for MockEffectResolver {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn unique(&self) -> bool {
+            false
+        }
+
+        fn documentation(&self) -> ModifierDocumentation {
+            panic!("mock effect resolver asked for documentation")
         }
 
-        log::debug!("Unloaded {} plugins.", count);
+        fn resolve(&self, _: &HashMap<String, String>,
+                child: Box<dyn AudioSource + Send + Sync>, _: PluginGuildConfig)
+                -> Result<Box<dyn AudioSource + Send + Sync>, ResolveEffectError> {
+            Ok(child)
+        }
     }
-}
 
-impl Drop for PluginManager {
-    fn drop(&mut self) {
-        if !self.plugins.is_empty() || !self.loaded_libraries.is_empty() {
-            self.unload()
+    fn register_effects(plugin_manager: &mut PluginManager, file_name: &str,
+            effects: &[&'static str]) {
+        let mut registry = plugin_manager.mock_registry(file_name);
+
+        for &effect in effects {
+            registry.register_effect_resolver(MockEffectResolver(effect));
         }
     }
+
+    #[test]
+    fn plugin_infos_list_registered_effects() {
+        let mut plugin_manager = PluginManager::empty();
+        register_effects(&mut plugin_manager, "first", &["b", "a"]);
+        register_effects(&mut plugin_manager, "second", &["c"]);
+
+        let infos = plugin_manager.plugin_infos();
+
+        assert_eq!(2, infos.len());
+        assert_eq!("first", infos[0].file_name());
+        assert_eq!(None, infos[0].version());
+        assert!(infos[0].audio_sources().is_empty());
+
+        let effect_names = infos[0].effects().iter()
+            .map(|e| e.name())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["a", "b"], effect_names);
+        assert!(infos[0].effects().iter().all(|e| e.overridden_by().is_none()));
+        assert_eq!("c", infos[1].effects()[0].name());
+    }
+
+    #[test]
+    fn plugin_infos_report_overridden_effects() {
+        let mut plugin_manager = PluginManager::empty();
+        register_effects(&mut plugin_manager, "first", &["a", "b"]);
+        register_effects(&mut plugin_manager, "second", &["a"]);
+        register_effects(&mut plugin_manager, "third", &["a"]);
+
+        let infos = plugin_manager.plugin_infos();
+
+        assert_eq!(Some("third"), infos[0].effects()[0].overridden_by());
+        assert_eq!(None, infos[0].effects()[1].overridden_by());
+        assert_eq!(Some("third"), infos[1].effects()[0].overridden_by());
+        assert_eq!(None, infos[2].effects()[0].overridden_by());
+    }
+
+    #[test]
+    fn disabled_effects_are_reported() {
+        let mut plugin_manager = PluginManager::empty();
+        register_effects(&mut plugin_manager, "first", &["a"]);
+        register_effects(&mut plugin_manager, "second", &["a", "b"]);
+
+        let config = PluginGuildConfig::default();
+
+        assert!(!plugin_manager.is_effect_disabled("a", &config));
+        assert!(!plugin_manager.is_effect_disabled("c", &config));
+
+        let config = PluginGuildConfig::default()
+            .with_disabled_plugins(["second"]);
+
+        assert!(!plugin_manager.is_effect_disabled("a", &config));
+        assert!(plugin_manager.is_effect_disabled("b", &config));
+
+        let config = PluginGuildConfig::default()
+            .with_disabled_effects(["a"]);
+
+        assert!(plugin_manager.is_effect_disabled("a", &config));
+        assert!(!plugin_manager.is_effect_disabled("b", &config));
+    }
 }
@@ -0,0 +1,481 @@
+use rambot_api::{Sample, SAMPLES_PER_SECOND};
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// The number of chunks of audio that can be queued for the writer thread
+/// before further audio is dropped. With the usual chunk size of 20 ms, this
+/// corresponds to roughly 5 seconds.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// The number of samples by which the recorded audio may lag behind the time
+/// since the recording started (200 ms). Larger gaps, which occur while the
+/// mixer is not played, are filled with silence.
+const MAX_LAG_SAMPLES: u64 = 9600;
+
+/// The number of samples of silence which are written at once.
+const SILENCE_CHUNK_SAMPLES: usize = 4800;
+
+const CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+const BLOCK_ALIGN: u16 = CHANNELS * BITS_PER_SAMPLE / 8;
+const HEADER_SIZE: u64 = 44;
+
+/// The largest amount of audio data a WAV file can hold, as its size fields
+/// are 32 bits wide.
+const MAX_DATA_SIZE: u64 = u32::MAX as u64 - HEADER_SIZE;
+
+/// Information about a finished recording.
+#[derive(Clone, Debug)]
+pub struct RecordingSummary {
+
+    /// The path of the file to which the recording was written.
+    pub path: PathBuf,
+
+    /// The number of samples written to the file, including silence which was
+    /// written while the mixer was not played.
+    pub samples: u64,
+
+    /// Indicates whether the recording was stopped because the size limit was
+    /// reached.
+    pub limit_reached: bool,
+
+    /// The number of samples which could not be recorded because the writer
+    /// did not keep up with the audio.
+    pub dropped_samples: u64
+}
+
+impl RecordingSummary {
+
+    /// Gets the duration of the recording in seconds.
+    pub fn seconds(&self) -> f64 {
+        self.samples as f64 / SAMPLES_PER_SECOND as f64
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W, data_size: u32) -> io::Result<()> {
+    let byte_rate = SAMPLES_PER_SECOND as u32 * BLOCK_ALIGN as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(data_size + HEADER_SIZE as u32 - 8).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&(SAMPLES_PER_SECOND as u32).to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&BLOCK_ALIGN.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())
+}
+
+fn to_i16(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+/// Writes the given audio as raw interleaved stereo 16-bit little-endian PCM,
+/// which is also the format of the data in WAV files written by [WavWriter].
+/// Samples outside the range from -1 to 1 are clipped.
+///
+/// # Errors
+///
+/// Any [io::Error] raised by the writer.
+pub fn write_pcm<W: Write>(writer: &mut W, samples: &[Sample])
+        -> io::Result<()> {
+    for sample in samples {
+        writer.write_all(&to_i16(sample.left).to_le_bytes())?;
+        writer.write_all(&to_i16(sample.right).to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Writes audio synchronously to a 48 kHz stereo 16-bit WAV file. Samples
+/// outside the range from -1 to 1 are clipped.
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    data_size: u64,
+    max_data_size: u64
+}
+
+impl WavWriter {
+
+    /// Creates a new WAV writer which writes into the given file, which must
+    /// be empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `file`: The file to write into.
+    /// * `max_size`: The maximum size of the file in bytes, which is further
+    ///   limited by the maximum size supported by the WAV format.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while writing the header.
+    pub fn new(file: File, max_size: u64) -> io::Result<WavWriter> {
+        let mut writer = BufWriter::new(file);
+
+        write_header(&mut writer, 0)?;
+
+        let max_data_size = max_size.saturating_sub(HEADER_SIZE)
+            .min(MAX_DATA_SIZE) / BLOCK_ALIGN as u64 * BLOCK_ALIGN as u64;
+
+        Ok(WavWriter {
+            writer,
+            data_size: 0,
+            max_data_size
+        })
+    }
+
+    /// Writes as much of the given audio as fits into the size limit. Returns
+    /// `true` if and only if all of it fit.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while writing.
+    pub fn write(&mut self, samples: &[Sample]) -> io::Result<bool> {
+        let remaining =
+            (self.max_data_size - self.data_size) / BLOCK_ALIGN as u64;
+        let count = samples.len().min(remaining as usize);
+
+        write_pcm(&mut self.writer, &samples[..count])?;
+        self.data_size += count as u64 * BLOCK_ALIGN as u64;
+        Ok(count == samples.len())
+    }
+
+    fn write_silence(&mut self, count: u64) -> io::Result<bool> {
+        let silence = [Sample::ZERO; SILENCE_CHUNK_SAMPLES];
+        let mut remaining = count;
+
+        while remaining > 0 {
+            let chunk = remaining.min(SILENCE_CHUNK_SAMPLES as u64) as usize;
+
+            if !self.write(&silence[..chunk])? {
+                return Ok(false);
+            }
+
+            remaining -= chunk as u64;
+        }
+
+        Ok(true)
+    }
+
+    /// Completes the header of the file and flushes all written audio.
+    ///
+    /// # Returns
+    ///
+    /// The number of samples that were written.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while writing.
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        write_header(&mut self.writer, self.data_size as u32)?;
+        self.writer.flush()?;
+        Ok(self.data_size / BLOCK_ALIGN as u64)
+    }
+}
+
+/// A piece of the recording which is sent to the writer thread.
+enum Chunk {
+    Audio(Vec<Sample>),
+    Silence(u64)
+}
+
+fn run_writer(mut writer: WavWriter, receiver: Receiver<Chunk>,
+        finished: Arc<AtomicBool>) -> io::Result<(u64, bool)> {
+    let mut limit_reached = false;
+    let mut result = Ok(());
+
+    for chunk in receiver {
+        let written = match chunk {
+            Chunk::Audio(samples) => writer.write(&samples),
+            Chunk::Silence(count) => writer.write_silence(count)
+        };
+
+        match written {
+            Ok(true) => { },
+            Ok(false) => {
+                limit_reached = true;
+                break;
+            },
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+
+    finished.store(true, Ordering::Release);
+    let samples = writer.finish()?;
+    result.map(|_| (samples, limit_reached))
+}
+
+/// Records audio to a 48 kHz stereo 16-bit WAV file. Audio handed to the
+/// recorder with [Recorder::push] is written on a separate thread, so
+/// recording never blocks the audio path. If the writer falls behind, audio is
+/// dropped instead. Whenever the recorded audio lags behind the time since the
+/// recording started, for example because nothing was played in between, the
+/// gap is filled with silence.
+pub struct Recorder {
+    path: PathBuf,
+    sender: Option<SyncSender<Chunk>>,
+    finished: Arc<AtomicBool>,
+    handle: JoinHandle<io::Result<(u64, bool)>>,
+    started: Instant,
+    queued_samples: u64,
+    dropped_samples: u64
+}
+
+impl Recorder {
+
+    /// Creates a new file at the given path and starts recording into it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file to create. If a file already exists at
+    ///   that path, an error is returned.
+    /// * `max_size`: The maximum size of the file in bytes. Once it is
+    ///   reached, the recording stops automatically.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while creating the file or writing its header.
+    pub fn start(path: impl AsRef<Path>, max_size: u64)
+            -> io::Result<Recorder> {
+        let path = path.as_ref().to_owned();
+        let file = OpenOptions::new().write(true).create_new(true)
+            .open(&path)?;
+        let writer = WavWriter::new(file, max_size)?;
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let finished = Arc::new(AtomicBool::new(false));
+        let finished_clone = Arc::clone(&finished);
+        let handle = thread::spawn(move ||
+            run_writer(writer, receiver, finished_clone));
+
+        Ok(Recorder {
+            path,
+            sender: Some(sender),
+            finished,
+            handle,
+            started: Instant::now(),
+            queued_samples: 0,
+            dropped_samples: 0
+        })
+    }
+
+    /// Gets the path of the file into which this recorder writes.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Indicates whether this recorder stopped writing on its own, i.e.
+    /// because the size limit was reached or an error occurred.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    fn send(&mut self, chunk: Chunk, samples: u64) {
+        if let Some(sender) = &self.sender {
+            match sender.try_send(chunk) {
+                Ok(()) => self.queued_samples += samples,
+                Err(TrySendError::Disconnected(_)) => { },
+                Err(TrySendError::Full(_)) => self.dropped_samples += samples
+            }
+        }
+    }
+
+    /// Queues silence for the gap between the recorded audio and the given
+    /// time, if it is larger than [MAX_LAG_SAMPLES]. Audio which was dropped
+    /// is part of the gap, so it is replaced by silence as well.
+    fn fill_gap(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.started)
+            .as_nanos() * SAMPLES_PER_SECOND as u128 / 1_000_000_000;
+        let gap = (elapsed as u64).saturating_sub(self.queued_samples);
+
+        if gap > MAX_LAG_SAMPLES {
+            self.send(Chunk::Silence(gap), gap);
+        }
+    }
+
+    fn push_at(&mut self, samples: &[Sample], now: Instant) {
+        if samples.is_empty() || self.is_finished() {
+            return;
+        }
+
+        self.fill_gap(now);
+        self.send(Chunk::Audio(samples.to_vec()), samples.len() as u64);
+    }
+
+    /// Queues the given audio to be written to the file. This never blocks.
+    pub fn push(&mut self, samples: &[Sample]) {
+        self.push_at(samples, Instant::now());
+    }
+
+    fn stop_at(mut self, now: Instant) -> io::Result<RecordingSummary> {
+        if !self.is_finished() {
+            self.fill_gap(now);
+        }
+
+        self.sender = None;
+
+        let (samples, limit_reached) = self.handle.join()
+            .map_err(|_|
+                io::Error::other("recording writer thread panicked"))??;
+
+        Ok(RecordingSummary {
+            path: self.path,
+            samples,
+            limit_reached,
+            dropped_samples: self.dropped_samples
+        })
+    }
+
+    /// Stops recording, waits until all queued audio is written, and
+    /// finalizes the file. Silence is recorded for the time since audio was
+    /// last pushed. This may block for a while, so it should not be called on
+    /// the audio path or in async code.
+    ///
+    /// # Errors
+    ///
+    /// Any [io::Error] raised while writing the file.
+    pub fn stop(self) -> io::Result<RecordingSummary> {
+        self.stop_at(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::time::Duration;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = env::temp_dir()
+            .join(format!("rambot-recording-{}-{}.wav", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn start(path: &Path, max_size: u64) -> Recorder {
+        Recorder::start(path, max_size).unwrap()
+    }
+
+    fn push(recorder: &mut Recorder, samples: &[Sample], seconds: f64) {
+        let now = recorder.started + Duration::from_secs_f64(seconds);
+
+        recorder.push_at(samples, now);
+    }
+
+    fn stop(recorder: Recorder, seconds: f64) -> RecordingSummary {
+        let now = recorder.started + Duration::from_secs_f64(seconds);
+
+        recorder.stop_at(now).unwrap()
+    }
+
+    #[test]
+    fn recording_writes_valid_wav() {
+        let path = temp_path("valid");
+        let mut recorder = start(&path, 1 << 20);
+
+        push(&mut recorder, &vec![Sample { left: 0.5, right: -1.0 }; 100], 0.0);
+        push(&mut recorder, &vec![Sample::ZERO; 50], 0.0);
+
+        let summary = stop(recorder, 0.0);
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(150, summary.samples);
+        assert!(!summary.limit_reached);
+        assert_eq!(HEADER_SIZE as usize + 600, bytes.len());
+        assert_eq!(b"RIFF", &bytes[0..4]);
+        assert_eq!(bytes.len() as u32 - 8, read_u32(&bytes, 4));
+        assert_eq!(48000, read_u32(&bytes, 24));
+        assert_eq!(600, read_u32(&bytes, 40));
+        assert_eq!(to_i16(0.5).to_le_bytes(), bytes[44..46]);
+        assert_eq!(i16::MIN + 1, i16::from_le_bytes([bytes[46], bytes[47]]));
+    }
+
+    #[test]
+    fn recording_stops_at_size_limit() {
+        let path = temp_path("limit");
+        let mut recorder = start(&path, HEADER_SIZE + 40);
+
+        push(&mut recorder, &vec![Sample::mono(0.25); 100], 0.0);
+
+        let summary = stop(recorder, 0.0);
+        let size = fs::metadata(&path).unwrap().len();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(10, summary.samples);
+        assert!(summary.limit_reached);
+        assert_eq!(HEADER_SIZE + 40, size);
+    }
+
+    #[test]
+    fn existing_file_is_not_overwritten() {
+        let path = temp_path("existing");
+        fs::write(&path, b"important").unwrap();
+
+        let result = Recorder::start(&path, 1 << 20);
+        let content = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(b"important".to_vec(), content);
+    }
+
+    #[test]
+    fn gaps_are_filled_with_silence() {
+        let path = temp_path("gaps");
+        let mut recorder = start(&path, 1 << 20);
+
+        push(&mut recorder, &vec![Sample::mono(0.5); 100], 0.0);
+        push(&mut recorder, &vec![Sample::mono(0.5); 100], 1.0);
+
+        let summary = stop(recorder, 2.0);
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let sample_at = |index: usize| {
+            let offset = HEADER_SIZE as usize + index * 4;
+
+            i16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+        };
+
+        assert_eq!(96000, summary.samples);
+        assert_eq!(to_i16(0.5), sample_at(99));
+        assert_eq!(0, sample_at(100));
+        assert_eq!(to_i16(0.5), sample_at(48000));
+        assert_eq!(0, sample_at(48100));
+    }
+
+    #[test]
+    fn small_lag_is_not_filled() {
+        let path = temp_path("lag");
+        let mut recorder = start(&path, 1 << 20);
+
+        push(&mut recorder, &vec![Sample::mono(0.5); 100], 0.0);
+        push(&mut recorder, &vec![Sample::mono(0.5); 100], 0.1);
+
+        let summary = stop(recorder, 0.1);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(200, summary.samples);
+    }
+}
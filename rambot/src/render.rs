@@ -0,0 +1,181 @@
+//! Offline rendering of the mixer output into WAV files, as done by the
+//! `rambot-render` binary.
+
+use crate::audio::Mixer;
+use crate::recording::WavWriter;
+
+use rambot_api::{AudioSource, Sample};
+
+use std::io;
+
+const BUFFER_SIZE: usize = 4800;
+
+/// Reads audio from the given mixer and writes it into the given WAV writer
+/// until the mixer has nothing more to play, the maximum number of samples
+/// is reached, or the writer reaches its size limit. The writer is not
+/// finished, so that callers can decide what to do with a partial result.
+///
+/// # Arguments
+///
+/// * `mixer`: The [Mixer] from which to read the audio.
+/// * `writer`: The [WavWriter] into which to write the audio.
+/// * `max_samples`: The maximum number of samples to render.
+///
+/// # Returns
+///
+/// The number of samples that were written.
+///
+/// # Errors
+///
+/// Any [io::Error] raised while reading from the mixer or writing.
+pub fn render(mixer: &mut Mixer, writer: &mut WavWriter, max_samples: u64)
+        -> io::Result<u64> {
+    let mut buf = vec![Sample::ZERO; BUFFER_SIZE];
+    let mut samples = 0;
+
+    while samples < max_samples {
+        let len = (max_samples - samples).min(BUFFER_SIZE as u64) as usize;
+        let count = mixer.read(&mut buf[..len])?;
+
+        if count == 0 {
+            break;
+        }
+
+        let complete = writer.write(&buf[..count])?;
+
+        samples += count as u64;
+
+        if !complete {
+            log::warn!("Maximum WAV file size reached, output is truncated.");
+            break;
+        }
+    }
+
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::limiter::LOOKAHEAD_SAMPLES;
+    use crate::plugin::PluginManager;
+    use crate::recording;
+
+    use rambot_api::{
+        AudioDocumentation,
+        AudioSourceResolver,
+        PluginGuildConfig
+    };
+
+    use rambot_test_util::MockAudioSource;
+
+    use std::env;
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    const FIXTURE_LEN: usize = 12000;
+    const LAYER: &str = "render";
+
+    /// The header of a 48 kHz stereo 16-bit WAV file with [FIXTURE_LEN]
+    /// samples, written out by hand so it does not depend on the writer.
+    const GOLDEN_HEADER: [u8; 44] = [
+        b'R', b'I', b'F', b'F', 0xa4, 0xbb, 0x00, 0x00,
+        b'W', b'A', b'V', b'E', b'f', b'm', b't', b' ',
+        0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00,
+        0x80, 0xbb, 0x00, 0x00, 0x00, 0xee, 0x02, 0x00,
+        0x04, 0x00, 0x10, 0x00, b'd', b'a', b't', b'a',
+        0x80, 0xbb, 0x00, 0x00
+    ];
+
+    fn fixture() -> Vec<Sample> {
+        rambot_test_util::test_data(FIXTURE_LEN, 440.0, 660.0)
+    }
+
+    struct FixtureResolver;
+
+    impl AudioSourceResolver for FixtureResolver {
+        fn documentation(&self) -> AudioDocumentation {
+            panic!("fixture resolver asked for documentation")
+        }
+
+        fn can_resolve(&self, descriptor: &str, _: PluginGuildConfig)
+                -> bool {
+            descriptor == "fixture"
+        }
+
+        fn resolve(&self, _: &str, _: PluginGuildConfig)
+                -> Result<Box<dyn AudioSource + Send + Sync>, String> {
+            Ok(Box::new(MockAudioSource::with_segment_size(fixture(), 1000)))
+        }
+    }
+
+    fn fixture_mixer() -> Mixer {
+        let mut plugin_manager = PluginManager::empty();
+        plugin_manager.mock_registry("fixture")
+            .register_audio_source_resolver(FixtureResolver);
+
+        let mut mixer = Mixer::new(Arc::new(plugin_manager));
+        mixer.limiter_mut().set_enabled(false);
+        mixer.add_layer(LAYER);
+        mixer.play_on_layer(LAYER, "fixture", PluginGuildConfig::default(),
+            |_, e| panic!("error while playing fixture: {}", e)).unwrap();
+        mixer
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = env::temp_dir()
+            .join(format!("rambot-render-{}-{}.wav", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn render_to_bytes(mixer: &mut Mixer, name: &str, max_samples: u64)
+            -> (u64, Vec<u8>) {
+        let path = temp_path(name);
+        let file = File::create(&path).unwrap();
+        let mut writer = WavWriter::new(file, u64::MAX).unwrap();
+        let samples = render(mixer, &mut writer, max_samples).unwrap();
+
+        assert_eq!(samples, writer.finish().unwrap());
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        (samples, bytes)
+    }
+
+    #[test]
+    fn fixture_renders_to_golden_wav() {
+        let mut mixer = fixture_mixer();
+        let (samples, bytes) = render_to_bytes(&mut mixer, "golden", u64::MAX);
+        let mut golden = GOLDEN_HEADER.to_vec();
+        recording::write_pcm(&mut golden, &fixture()).unwrap();
+
+        assert_eq!(FIXTURE_LEN as u64, samples);
+        assert_eq!(44 + FIXTURE_LEN * 4, bytes.len());
+        assert_eq!(&GOLDEN_HEADER[..], &bytes[..44]);
+        assert!(golden == bytes, "rendered WAV differs from golden WAV");
+    }
+
+    #[test]
+    fn rendering_stops_at_max_samples() {
+        let mut mixer = fixture_mixer();
+        let (samples, bytes) = render_to_bytes(&mut mixer, "max", 5000);
+
+        assert_eq!(5000, samples);
+        assert_eq!(44 + 5000 * 4, bytes.len());
+        assert_eq!(&(5000u32 * 4).to_le_bytes(), &bytes[40..44]);
+    }
+
+    #[test]
+    fn limiter_delay_is_rendered() {
+        let mut mixer = fixture_mixer();
+        mixer.limiter_mut().set_enabled(true);
+        let (samples, bytes) = render_to_bytes(&mut mixer, "limiter", u64::MAX);
+
+        assert_eq!((FIXTURE_LEN + LOOKAHEAD_SAMPLES) as u64, samples);
+        assert_eq!(44 + samples as usize * 4, bytes.len());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::audio::{
+    is_valid_layer_gain,
+    Layer,
+    LayerPlayback,
+    Mixer,
+    MAX_LAYER_GAIN
+};
+use crate::key_value::KeyValueDescriptor;
+use crate::plugin::ResolveError;
+
+use rambot_api::PluginGuildConfig;
+
+use serde::{Deserialize, Serialize};
+
+use std::io;
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+/// The configuration of one layer of a [Mixer], that is, its effects,
+/// adapters, gain, and whether it is muted or soloed.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LayerConfig {
+    name: String,
+    effects: Vec<KeyValueDescriptor>,
+    adapters: Vec<KeyValueDescriptor>,
+
+    #[serde(default = "default_gain")]
+    gain: f32,
+
+    #[serde(default)]
+    muted: bool,
+
+    #[serde(default)]
+    solo: bool
+}
+
+impl LayerConfig {
+
+    /// Takes the current configuration of the given layer.
+    pub fn of(layer: &Layer) -> LayerConfig {
+        LayerConfig {
+            name: layer.name().to_owned(),
+            effects: layer.effects().to_vec(),
+            adapters: layer.adapters().to_vec(),
+            gain: layer.gain(),
+            muted: layer.is_muted(),
+            solo: layer.is_solo()
+        }
+    }
+
+    /// Gets the name of the layer.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the [KeyValueDescriptor]s of the effects on the layer.
+    pub fn effects(&self) -> &[KeyValueDescriptor] {
+        &self.effects
+    }
+
+    /// Gets the [KeyValueDescriptor]s of the adapters on the layer.
+    pub fn adapters(&self) -> &[KeyValueDescriptor] {
+        &self.adapters
+    }
+
+    /// Gets the gain of the layer.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Gets the gain of the layer, replacing an invalid gain, which may have
+    /// been read from a file edited by hand, by the closest valid one.
+    /// Negative gains become 0 and gains which are not numbers become 1.
+    fn valid_gain(&self) -> f32 {
+        if is_valid_layer_gain(self.gain) {
+            return self.gain;
+        }
+
+        let gain = if self.gain.is_nan() {
+            default_gain()
+        }
+        else {
+            self.gain.clamp(0.0, MAX_LAYER_GAIN)
+        };
+
+        log::warn!("Invalid gain {} of layer {}, using {}.", self.gain,
+            self.name, gain);
+        gain
+    }
+
+    /// Applies this configuration to the layer of the same name in the given
+    /// mixer, which is added if it does not exist yet. Effects and adapters
+    /// of an existing layer are replaced, audio playing on it continues.
+    ///
+    /// # Errors
+    ///
+    /// If audio is playing on the layer and an effect cannot be resolved. In
+    /// that case, the remaining configuration is applied nevertheless.
+    pub fn apply(&self, mixer: &mut Mixer) -> Result<(), ResolveError> {
+        let mut result = Ok(());
+
+        if !mixer.add_layer(&self.name) {
+            mixer.clear_effects(&self.name);
+            mixer.clear_adapters(&self.name);
+        }
+
+        for effect in &self.effects {
+            let effect_result = mixer.add_effect(&self.name, effect.clone());
+
+            if result.is_ok() {
+                result = effect_result;
+            }
+        }
+
+        for adapter in &self.adapters {
+            mixer.add_adapter(&self.name, adapter.clone());
+        }
+
+        mixer.set_layer_gain(&self.name, self.valid_gain());
+        mixer.set_layer_muted(&self.name, self.muted);
+        mixer.set_layer_solo(&self.name, self.solo);
+        result
+    }
+}
+
+/// A saved configuration of either one layer or all layers of a [Mixer],
+/// which can be recalled later. Optionally, it also contains what was playing
+/// on the layers when it was saved.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Scene {
+    layers: Vec<LayerConfig>,
+
+    #[serde(default)]
+    partial: bool,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    playback: Vec<LayerPlayback>
+}
+
+impl Scene {
+
+    /// Saves the configuration of all layers of the given mixer as a scene.
+    ///
+    /// # Arguments
+    ///
+    /// * `mixer`: The [Mixer] whose configuration to save.
+    /// * `include_playback`: Whether to save what is playing on the layers,
+    ///   so it is played again when the scene is recalled.
+    pub fn of_mixer(mixer: &mut Mixer, include_playback: bool) -> Scene {
+        let layers = mixer.layers().iter().map(LayerConfig::of).collect();
+        let playback = if include_playback {
+            mixer.playback()
+        }
+        else {
+            Vec::new()
+        };
+
+        Scene {
+            layers,
+            partial: false,
+            playback
+        }
+    }
+
+    /// Saves the configuration of a single layer of the given mixer as a
+    /// scene. Recalling it leaves all other layers unchanged. Panics if the
+    /// layer does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `mixer`: The [Mixer] which contains the layer.
+    /// * `layer`: The name of the layer whose configuration to save.
+    /// * `include_playback`: Whether to save what is playing on the layer,
+    ///   so it is played again when the scene is recalled.
+    pub fn of_layer(mixer: &mut Mixer, layer: &str, include_playback: bool)
+            -> Scene {
+        let layers = vec![LayerConfig::of(mixer.layer(layer))];
+        let playback = if include_playback {
+            mixer.playback().into_iter()
+                .filter(|playback| playback.layer() == layer)
+                .collect()
+        }
+        else {
+            Vec::new()
+        };
+
+        Scene {
+            layers,
+            partial: true,
+            playback
+        }
+    }
+
+    /// Gets the [LayerConfig]s of all layers in this scene.
+    pub fn layers(&self) -> &[LayerConfig] {
+        &self.layers
+    }
+
+    /// Indicates whether this scene only contains some layers of the mixer,
+    /// in which case all other layers are left unchanged when it is recalled.
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Indicates whether this scene contains audio which is played when it is
+    /// recalled.
+    pub fn has_playback(&self) -> bool {
+        !self.playback.is_empty()
+    }
+
+    /// Recalls this scene on the given mixer. Layers of the scene are
+    /// configured as saved. If the scene is not partial, all other layers are
+    /// removed. If the scene contains audio for a layer, the audio playing on
+    /// it fades out while the saved audio fades in. Audio on other layers of
+    /// the scene continues playing.
+    ///
+    /// # Arguments
+    ///
+    /// * `mixer`: The [Mixer] on which to recall this scene.
+    /// * `plugin_guild_config`: The [PluginGuildConfig] with which the saved
+    ///   audio is resolved.
+    /// * `crossfade`: The number of samples over which removed and replaced
+    ///   audio fades out and saved audio fades in.
+    /// * `error_callback`: Called if an error occurs while playing a later
+    ///   entry of a saved playlist.
+    ///
+    /// # Returns
+    ///
+    /// A description of every error which occurred. The remaining layers are
+    /// recalled nevertheless.
+    pub fn recall<E>(&self, mixer: &mut Mixer,
+        plugin_guild_config: &PluginGuildConfig, crossfade: usize,
+        error_callback: E) -> Vec<String>
+    where
+        E: Fn(String, io::Error) + Clone + Send + Sync + 'static
+    {
+        let mut errors = Vec::new();
+
+        if !self.partial {
+            let removed_layers = mixer.layers().iter()
+                .map(|layer| layer.name().to_owned())
+                .filter(|name| self.layers.iter().all(|l| &l.name != name))
+                .collect::<Vec<_>>();
+
+            for layer in removed_layers {
+                mixer.fade_out_layer(&layer, crossfade);
+            }
+        }
+
+        for layer in &self.layers {
+            let playback = self.playback.iter()
+                .find(|playback| playback.layer() == layer.name);
+
+            if playback.is_some() {
+                mixer.fade_out_layer(&layer.name, crossfade);
+            }
+
+            if let Err(e) = layer.apply(mixer) {
+                errors.push(format!("Could not apply the effects of layer \
+                    {}: {}", layer.name, e));
+            }
+
+            if let Some(playback) = playback {
+                let result = mixer.restore_playback(playback,
+                    plugin_guild_config.clone(), error_callback.clone());
+
+                match result {
+                    Ok(()) => mixer.fade_in_layer(&layer.name, crossfade),
+                    Err(e) => errors.push(format!("Could not play {} on \
+                        layer {}: {}", playback.descriptor(), layer.name, e))
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::plugin::PluginManager;
+
+    use std::sync::Arc;
+
+    fn mixer() -> Mixer {
+        Mixer::new(Arc::new(PluginManager::empty()))
+    }
+
+    fn echo() -> KeyValueDescriptor {
+        "echo(delay=0.5)".parse().unwrap()
+    }
+
+    fn recall(scene: &Scene, mixer: &mut Mixer) -> Vec<String> {
+        scene.recall(mixer, &PluginGuildConfig::default(), 0, |_, _| { })
+    }
+
+    #[test]
+    fn invalid_gains_are_clamped() {
+        let mut mixer = mixer();
+        let layers: Vec<LayerConfig> = serde_json::from_str(r#"[
+            { "name": "quiet", "effects": [], "adapters": [], "gain": -1.0 },
+            { "name": "loud", "effects": [], "adapters": [], "gain": 1e30 }
+        ]"#).unwrap();
+
+        for layer in &layers {
+            layer.apply(&mut mixer).unwrap();
+        }
+
+        assert_eq!(0.0, mixer.layer("quiet").gain());
+        assert_eq!(MAX_LAYER_GAIN, mixer.layer("loud").gain());
+    }
+
+    #[test]
+    fn mixer_scene_replaces_all_layers() {
+        let mut mixer = mixer();
+        mixer.add_layer("music");
+        mixer.add_effect("music", echo()).unwrap();
+        mixer.set_layer_gain("music", 0.5);
+
+        let scene = Scene::of_mixer(&mut mixer, false);
+
+        mixer.clear_effects("music");
+        mixer.set_layer_gain("music", 1.0);
+        mixer.add_layer("ambience");
+
+        assert!(recall(&scene, &mut mixer).is_empty());
+        assert!(!scene.is_partial());
+        assert!(!scene.has_playback());
+        assert!(!mixer.contains_layer("ambience"));
+        assert_eq!(&[echo()], mixer.layer("music").effects());
+        assert_eq!(0.5, mixer.layer("music").gain());
+    }
+
+    #[test]
+    fn layer_scene_keeps_other_layers() {
+        let mut mixer = mixer();
+        mixer.add_layer("music");
+        mixer.add_layer("ambience");
+        mixer.add_effect("music", echo()).unwrap();
+
+        let scene = Scene::of_layer(&mut mixer, "music", false);
+
+        mixer.remove_layer("music");
+
+        assert!(recall(&scene, &mut mixer).is_empty());
+        assert!(scene.is_partial());
+        assert!(mixer.contains_layer("ambience"));
+        assert_eq!(&[echo()], mixer.layer("music").effects());
+    }
+}
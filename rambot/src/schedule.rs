@@ -0,0 +1,289 @@
+use chrono::{DateTime, Duration as ChronoDuration, NaiveTime, TimeZone};
+
+use serde::{Deserialize, Serialize};
+
+use serenity::model::id::{ChannelId, UserId};
+
+use std::time::Duration;
+
+/// A command which is executed once at a given time on behalf of the user who
+/// scheduled it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScheduledCommand {
+    id: u64,
+    time: i64,
+    command: String,
+    channel: ChannelId,
+    author: UserId
+}
+
+impl ScheduledCommand {
+
+    /// Gets the ID of this command, which is unique within its [Schedule].
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Gets the time at which this command is executed as a Unix timestamp in
+    /// seconds.
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+
+    /// Gets the command to execute, as it would be written in a message.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Gets the ID of the text channel in which the command was scheduled and
+    /// in which it is executed.
+    pub fn channel(&self) -> ChannelId {
+        self.channel
+    }
+
+    /// Gets the ID of the user who scheduled the command and as whom it is
+    /// executed.
+    pub fn author(&self) -> UserId {
+        self.author
+    }
+}
+
+/// The commands which are scheduled for execution in one guild, ordered by
+/// the time at which they are executed.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Schedule {
+    next_id: u64,
+    commands: Vec<ScheduledCommand>
+}
+
+impl Schedule {
+
+    /// Indicates whether no commands are scheduled.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Gets all scheduled commands, ordered by the time at which they are
+    /// executed.
+    pub fn commands(&self) -> &[ScheduledCommand] {
+        &self.commands
+    }
+
+    /// Schedules a new command.
+    ///
+    /// # Arguments
+    ///
+    /// * `time`: The time at which to execute the command as a Unix timestamp
+    ///   in seconds.
+    /// * `command`: The command to execute, as it would be written in a
+    ///   message.
+    /// * `channel`: The ID of the text channel in which to execute the
+    ///   command.
+    /// * `author`: The ID of the user as whom to execute the command.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the new command, which can be given to [Schedule::cancel].
+    pub fn add(&mut self, time: i64, command: impl Into<String>,
+            channel: ChannelId, author: UserId) -> u64 {
+        self.next_id += 1;
+
+        let id = self.next_id;
+        let index = self.commands.partition_point(|c| c.time <= time);
+
+        self.commands.insert(index, ScheduledCommand {
+            id,
+            time,
+            command: command.into(),
+            channel,
+            author
+        });
+
+        id
+    }
+
+    /// Removes the command with the given ID, so it is not executed.
+    ///
+    /// # Returns
+    ///
+    /// The removed command, or `None` if there is no command with that ID.
+    pub fn cancel(&mut self, id: u64) -> Option<ScheduledCommand> {
+        let index = self.commands.iter().position(|c| c.id == id)?;
+
+        Some(self.commands.remove(index))
+    }
+
+    /// Indicates whether any command is due at the given time, that is,
+    /// scheduled for that time or earlier.
+    pub fn has_due(&self, now: i64) -> bool {
+        self.commands.first().map(|c| c.time <= now).unwrap_or(false)
+    }
+
+    /// Removes and returns all commands which are due at the given time, that
+    /// is, scheduled for that time or earlier, in the order in which they
+    /// should be executed.
+    pub fn take_due(&mut self, now: i64) -> Vec<ScheduledCommand> {
+        let count = self.commands.partition_point(|c| c.time <= now);
+
+        self.commands.drain(..count).collect()
+    }
+}
+
+/// Parses a delay such as `90s`, `10m`, or `1h30m`. A delay consists of one
+/// or more numbers, each followed by a unit, which is `s` for seconds, `m`
+/// for minutes, `h` for hours, or `d` for days.
+///
+/// # Returns
+///
+/// The parsed delay, or `None` if the text is not a valid delay.
+pub fn parse_delay(text: &str) -> Option<Duration> {
+    let mut seconds = 0u64;
+    let mut number: Option<u64> = None;
+
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            number = Some(number.unwrap_or(0)
+                .checked_mul(10)?
+                .checked_add(digit as u64)?);
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None
+        };
+
+        seconds = seconds.checked_add(number.take()?.checked_mul(unit)?)?;
+    }
+
+    if number.is_some() || text.is_empty() {
+        return None;
+    }
+
+    Some(Duration::from_secs(seconds))
+}
+
+/// Finds the next time at which a clock shows the given time of day, such as
+/// `20:00`, in the time zone of `now`. If that time has already passed today
+/// or is right now, the time on the next day is returned.
+///
+/// # Returns
+///
+/// The next time at the given time of day, or `None` if the text is not a
+/// valid time of the form `HH:MM` or the time does not exist on the next two
+/// days due to a change of the offset of the time zone.
+pub fn next_time_of_day<Tz: TimeZone>(text: &str, now: &DateTime<Tz>)
+        -> Option<DateTime<Tz>> {
+    let time = NaiveTime::parse_from_str(text, "%H:%M").ok()?;
+    let timezone = now.timezone();
+
+    [0, 1].into_iter()
+        .filter_map(|days| now.date_naive()
+            .checked_add_signed(ChronoDuration::days(days)))
+        .filter_map(|date| timezone
+            .from_local_datetime(&date.and_time(time))
+            .earliest())
+        .find(|next| next > now)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use chrono::{FixedOffset, Utc};
+
+    fn schedule(times: &[i64]) -> Schedule {
+        let mut schedule = Schedule::default();
+
+        for &time in times {
+            schedule.add(time, format!("stop {}", time), ChannelId::new(1),
+                UserId::new(2));
+        }
+
+        schedule
+    }
+
+    fn times(commands: &[ScheduledCommand]) -> Vec<i64> {
+        commands.iter().map(ScheduledCommand::time).collect()
+    }
+
+    #[test]
+    fn due_commands_are_taken_in_order() {
+        let mut schedule = schedule(&[30, 10, 20]);
+
+        assert!(!schedule.has_due(9));
+        assert!(schedule.has_due(20));
+        assert_eq!(vec![10, 20], times(&schedule.take_due(20)));
+        assert_eq!(vec![30], times(schedule.commands()));
+    }
+
+    #[test]
+    fn cancelled_command_is_removed() {
+        let mut schedule = schedule(&[10, 20]);
+        let id = schedule.commands()[1].id();
+
+        assert_eq!(Some(20), schedule.cancel(id).map(|c| c.time()));
+        assert_eq!(None, schedule.cancel(id));
+        assert_eq!(vec![10], times(schedule.commands()));
+    }
+
+    #[test]
+    fn ids_are_not_reused() {
+        let mut schedule = schedule(&[10]);
+        let first = schedule.commands()[0].id();
+
+        schedule.take_due(10);
+
+        let second = schedule.add(20, "stop", ChannelId::new(1),
+            UserId::new(2));
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn delays_are_parsed() {
+        assert_eq!(Some(Duration::from_secs(90)), parse_delay("90s"));
+        assert_eq!(Some(Duration::from_secs(600)), parse_delay("10m"));
+        assert_eq!(Some(Duration::from_secs(5400)), parse_delay("1h30m"));
+        assert_eq!(Some(Duration::from_secs(86400)), parse_delay("1D"));
+    }
+
+    #[test]
+    fn invalid_delays_are_rejected() {
+        assert_eq!(None, parse_delay(""));
+        assert_eq!(None, parse_delay("10"));
+        assert_eq!(None, parse_delay("m"));
+        assert_eq!(None, parse_delay("10x"));
+        assert_eq!(None, parse_delay("99999999999999999999s"));
+    }
+
+    #[test]
+    fn time_of_day_later_today_is_today() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 18, 30, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 5, 1, 20, 0, 0).unwrap();
+
+        assert_eq!(Some(expected), next_time_of_day("20:00", &now));
+    }
+
+    #[test]
+    fn time_of_day_passed_today_is_tomorrow() {
+        let timezone = FixedOffset::east_opt(2 * 3600).unwrap();
+        let now = timezone.with_ymd_and_hms(2024, 5, 1, 20, 0, 0).unwrap();
+        let expected =
+            timezone.with_ymd_and_hms(2024, 5, 2, 20, 0, 0).unwrap();
+
+        assert_eq!(Some(expected), next_time_of_day("20:00", &now));
+    }
+
+    #[test]
+    fn invalid_time_of_day_is_rejected() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 18, 30, 0).unwrap();
+
+        assert_eq!(None, next_time_of_day("25:00", &now));
+        assert_eq!(None, next_time_of_day("evening", &now));
+    }
+}
@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A fixed status message of the bot which is translated to the status
+/// [Language] of a guild.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StatusText {
+
+    /// Sent to confirm a successful command if the [ConfirmationStyle] is
+    /// [ConfirmationStyle::Message].
+    Confirmation,
+
+    /// Sent if the author of a command is not allowed to use it.
+    NotAllowed,
+
+    /// Sent if the bot cannot determine the voice channel of the author.
+    AuthorNotConnected,
+
+    /// Sent if the bot is already connected to the author's voice channel.
+    AlreadyConnected,
+
+    /// Sent if the bot should leave a voice channel, but is not connected.
+    NotConnected
+}
+
+/// The language of the status messages of the bot in a guild. Only the fixed
+/// status messages represented by [StatusText] are translated, all other
+/// responses, such as the replies of individual commands, are in English.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+
+    /// English, which is the default.
+    #[default]
+    English,
+
+    /// German.
+    German
+}
+
+impl Language {
+
+    /// Translates the given [StatusText] to this language.
+    pub fn status_text(self, text: StatusText) -> &'static str {
+        match (self, text) {
+            (Language::English, StatusText::Confirmation) => "Done.",
+            (Language::English, StatusText::NotAllowed) =>
+                "You are not allowed to use this command.",
+            (Language::English, StatusText::AuthorNotConnected) =>
+                "I cannot see your voice channel. Are you connected?",
+            (Language::English, StatusText::AlreadyConnected) =>
+                "I am already connected to your voice channel.",
+            (Language::English, StatusText::NotConnected) =>
+                "I am not connected to a voice channel",
+            (Language::German, StatusText::Confirmation) => "Erledigt.",
+            (Language::German, StatusText::NotAllowed) =>
+                "Du darfst diesen Befehl nicht verwenden.",
+            (Language::German, StatusText::AuthorNotConnected) =>
+                "Ich kann deinen Sprachkanal nicht sehen. Bist du verbunden?",
+            (Language::German, StatusText::AlreadyConnected) =>
+                "Ich bin bereits mit deinem Sprachkanal verbunden.",
+            (Language::German, StatusText::NotConnected) =>
+                "Ich bin mit keinem Sprachkanal verbunden"
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::English => write!(f, "english"),
+            Language::German => write!(f, "german")
+        }
+    }
+}
+
+/// The error returned when parsing an invalid [Language].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseLanguageError(String);
+
+impl Display for ParseLanguageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown language `{}`. Expected `english` or `german`.",
+            self.0)
+    }
+}
+
+impl std::error::Error for ParseLanguageError { }
+
+impl FromStr for Language {
+    type Err = ParseLanguageError;
+
+    fn from_str(s: &str) -> Result<Language, ParseLanguageError> {
+        match s.to_ascii_lowercase().as_str() {
+            "english" | "en" => Ok(Language::English),
+            "german" | "de" => Ok(Language::German),
+            _ => Err(ParseLanguageError(s.to_owned()))
+        }
+    }
+}
+
+/// The way in which the bot confirms commands which were successful and have
+/// no other response.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmationStyle {
+
+    /// Reacts to the command message with an emoji, which is the default.
+    #[default]
+    Reaction,
+
+    /// Replies to the command message with a short text in the status
+    /// language of the guild.
+    Message,
+
+    /// Does not confirm commands at all. Slash commands still receive a
+    /// response which is only visible to their author, since Discord requires
+    /// one.
+    Silent
+}
+
+impl Display for ConfirmationStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfirmationStyle::Reaction => write!(f, "reaction"),
+            ConfirmationStyle::Message => write!(f, "message"),
+            ConfirmationStyle::Silent => write!(f, "silent")
+        }
+    }
+}
+
+/// The error returned when parsing an invalid [ConfirmationStyle].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseConfirmationStyleError(String);
+
+impl Display for ParseConfirmationStyleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f,
+            "Unknown confirmation style `{}`. Expected `reaction`, `message`, \
+                or `silent`.", self.0)
+    }
+}
+
+impl std::error::Error for ParseConfirmationStyleError { }
+
+impl FromStr for ConfirmationStyle {
+    type Err = ParseConfirmationStyleError;
+
+    fn from_str(s: &str)
+            -> Result<ConfirmationStyle, ParseConfirmationStyleError> {
+        match s.to_ascii_lowercase().as_str() {
+            "reaction" => Ok(ConfirmationStyle::Reaction),
+            "message" => Ok(ConfirmationStyle::Message),
+            "silent" => Ok(ConfirmationStyle::Silent),
+            _ => Err(ParseConfirmationStyleError(s.to_owned()))
+        }
+    }
+}
+
+/// The settings of one guild which customize how the bot behaves there.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GuildSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prefix: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_layer: Option<String>,
+
+    #[serde(default, alias = "language")]
+    status_language: Language,
+
+    #[serde(default)]
+    confirmation_style: ConfirmationStyle,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_timeout: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "is_false")]
+    leave_when_alone: bool
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl GuildSettings {
+
+    /// Indicates whether all settings have their default value.
+    pub fn is_empty(&self) -> bool {
+        self == &GuildSettings::default()
+    }
+
+    /// Gets the prefix which overrides the globally configured one in this
+    /// guild, if one is set.
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    /// Sets the prefix which overrides the globally configured one in this
+    /// guild. If `None` is given, the global prefix is used again.
+    ///
+    /// # Errors
+    ///
+    /// If the prefix is empty or contains whitespace. In that case, the
+    /// prefix is not changed.
+    pub fn set_prefix(&mut self, prefix: Option<String>)
+            -> Result<(), String> {
+        if let Some(prefix) = &prefix {
+            if prefix.is_empty() || prefix.contains(char::is_whitespace) {
+                let message = "The prefix must not be empty or contain spaces.";
+
+                return Err(message.to_owned());
+            }
+        }
+
+        self.prefix = prefix;
+        Ok(())
+    }
+
+    /// Gets the prefix which applies in this guild, that is, the override if
+    /// one is set and otherwise the given global prefix.
+    pub fn effective_prefix<'a>(&'a self, global_prefix: Option<&'a str>)
+            -> Option<&'a str> {
+        self.prefix().or(global_prefix)
+    }
+
+    /// Gets the layer on which `play` plays audio if no layer is given, if
+    /// one is set.
+    pub fn default_layer(&self) -> Option<&str> {
+        self.default_layer.as_deref()
+    }
+
+    /// Sets the layer on which `play` plays audio if no layer is given. If
+    /// `None` is given, a layer always has to be provided.
+    pub fn set_default_layer(&mut self, default_layer: Option<String>) {
+        self.default_layer = default_layer;
+    }
+
+    /// Gets the [Language] of the status messages represented by
+    /// [StatusText].
+    pub fn status_language(&self) -> Language {
+        self.status_language
+    }
+
+    /// Sets the [Language] of the status messages represented by
+    /// [StatusText].
+    pub fn set_status_language(&mut self, language: Language) {
+        self.status_language = language;
+    }
+
+    /// Gets the [ConfirmationStyle] with which successful commands are
+    /// confirmed.
+    pub fn confirmation_style(&self) -> ConfirmationStyle {
+        self.confirmation_style
+    }
+
+    /// Sets the [ConfirmationStyle] with which successful commands are
+    /// confirmed.
+    pub fn set_confirmation_style(&mut self,
+            confirmation_style: ConfirmationStyle) {
+        self.confirmation_style = confirmation_style;
+    }
+
+    /// Gets the number of minutes after which the bot leaves the voice channel
+    /// if no audio is playing, if it should do so.
+    pub fn idle_timeout(&self) -> Option<u32> {
+        self.idle_timeout
+    }
+
+    /// Sets the number of minutes after which the bot leaves the voice channel
+    /// if no audio is playing. If `None` is given, the bot stays until it is
+    /// disconnected.
+    ///
+    /// # Errors
+    ///
+    /// If the timeout is zero. In that case, the timeout is not changed.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<u32>)
+            -> Result<(), String> {
+        if idle_timeout == Some(0) {
+            return Err("The idle timeout must be at least one minute."
+                .to_owned());
+        }
+
+        self.idle_timeout = idle_timeout;
+        Ok(())
+    }
+
+    /// Indicates whether the bot leaves the voice channel once no users other
+    /// than bots remain in it.
+    pub fn leave_when_alone(&self) -> bool {
+        self.leave_when_alone
+    }
+
+    /// Sets whether the bot leaves the voice channel once no users other than
+    /// bots remain in it.
+    pub fn set_leave_when_alone(&mut self, leave_when_alone: bool) {
+        self.leave_when_alone = leave_when_alone;
+    }
+
+    /// Splits the arguments of a `play` command into the layer and the audio
+    /// to play, taking the default layer into account. If only one argument
+    /// is given or the first one is not the name of an existing layer, all
+    /// arguments together are the audio, which is played on the default
+    /// layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer`: The first argument, which is usually the layer.
+    /// * `audio`: The remaining arguments, if any.
+    /// * `contains_layer`: Indicates whether a layer with the given name
+    ///   exists.
+    ///
+    /// # Returns
+    ///
+    /// The layer and the audio to play, or `None` if no audio was given and
+    /// no default layer is set.
+    pub fn play_arguments<C>(&self, layer: String, audio: Option<String>,
+        contains_layer: C) -> Option<(String, String)>
+    where
+        C: FnOnce(&str) -> bool
+    {
+        match (audio, self.default_layer()) {
+            (None, Some(default_layer)) =>
+                Some((default_layer.to_owned(), layer)),
+            (None, None) => None,
+            (Some(audio), Some(default_layer)) if !contains_layer(&layer) => {
+                let audio = format!("{} {}", layer, audio);
+
+                Some((default_layer.to_owned(), audio))
+            },
+            (Some(audio), _) => Some((layer, audio))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn settings_with_default_layer() -> GuildSettings {
+        let mut settings = GuildSettings::default();
+        settings.set_default_layer(Some("music".to_owned()));
+        settings
+    }
+
+    #[test]
+    fn prefix_override_takes_precedence() {
+        let mut settings = GuildSettings::default();
+
+        assert_eq!(Some("!"), settings.effective_prefix(Some("!")));
+
+        settings.set_prefix(Some("?".to_owned())).unwrap();
+
+        assert_eq!(Some("?"), settings.effective_prefix(Some("!")));
+        assert_eq!(Some("?"), settings.effective_prefix(None));
+    }
+
+    #[test]
+    fn invalid_prefixes_are_rejected() {
+        let mut settings = GuildSettings::default();
+
+        assert!(settings.set_prefix(Some("".to_owned())).is_err());
+        assert!(settings.set_prefix(Some("a b".to_owned())).is_err());
+        assert_eq!(None, settings.prefix());
+    }
+
+    #[test]
+    fn play_arguments_use_given_layer() {
+        let settings = settings_with_default_layer();
+        let arguments = settings.play_arguments(
+            "effects".to_owned(), Some("horn.wav".to_owned()), |_| true);
+
+        assert_eq!(Some(("effects".to_owned(), "horn.wav".to_owned())),
+            arguments);
+    }
+
+    #[test]
+    fn play_arguments_fall_back_to_default_layer() {
+        let settings = settings_with_default_layer();
+        let single = settings.play_arguments(
+            "song.mp3".to_owned(), None, |_| true);
+        let multiple = settings.play_arguments(
+            "my".to_owned(), Some("song.mp3".to_owned()), |_| false);
+
+        assert_eq!(Some(("music".to_owned(), "song.mp3".to_owned())), single);
+        assert_eq!(Some(("music".to_owned(), "my song.mp3".to_owned())),
+            multiple);
+    }
+
+    #[test]
+    fn play_arguments_require_audio_without_default_layer() {
+        let settings = GuildSettings::default();
+
+        assert_eq!(None,
+            settings.play_arguments("song.mp3".to_owned(), None, |_| false));
+        assert_eq!(Some(("my".to_owned(), "song.mp3".to_owned())),
+            settings.play_arguments(
+                "my".to_owned(), Some("song.mp3".to_owned()), |_| false));
+    }
+
+    #[test]
+    fn zero_idle_timeout_is_rejected() {
+        let mut settings = GuildSettings::default();
+
+        assert!(settings.set_idle_timeout(Some(0)).is_err());
+        assert_eq!(None, settings.idle_timeout());
+    }
+
+    #[test]
+    fn settings_are_serialized() {
+        let mut settings = settings_with_default_layer();
+        settings.set_status_language(Language::German);
+        settings.set_confirmation_style(ConfirmationStyle::Silent);
+        settings.set_idle_timeout(Some(10)).unwrap();
+        settings.set_leave_when_alone(true);
+        let json = serde_json::to_string(&settings).unwrap();
+        let deserialized: GuildSettings = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(settings, deserialized);
+        assert!(GuildSettings::default().is_empty());
+        assert!(!settings.is_empty());
+        assert_eq!(Ok(Language::German), "German".parse());
+        assert_eq!(Language::German, serde_json::from_str::<GuildSettings>(
+            "{ \"language\": \"german\" }").unwrap().status_language());
+        assert!("klingon".parse::<Language>().is_err());
+    }
+}
@@ -1,15 +1,24 @@
-use crate::audio::Mixer;
+use crate::audio::{self, Mixer};
 use crate::command::board::{BoardManager, Board};
 use crate::key_value::KeyValueDescriptor;
+use crate::limiter;
+use crate::loudness::NormalizationSettings;
+use crate::migration::{self, MigrationError};
+use crate::permission::Permissions;
 use crate::plugin::PluginManager;
+use crate::scene::{LayerConfig, Scene};
+use crate::schedule::Schedule;
+use crate::settings::GuildSettings;
 
 use rambot_api::PluginGuildConfig;
 
 use serde::{Deserialize, Serialize, Serializer};
 
-use serenity::model::id::GuildId;
+use serde_json::Value;
 
-use std::collections::HashMap;
+use serenity::model::id::{GuildId, UserId};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{self, Display, Formatter};
 use std::fs::{self, File, OpenOptions};
 use std::io;
@@ -18,11 +27,79 @@ use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+/// The kinds of plugin functionality which can be disabled in a guild.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PluginFeature {
+
+    /// An entire plugin, identified by the file name of its library.
+    Plugin,
+
+    /// An effect, identified by its name.
+    Effect,
+
+    /// An adapter, identified by its name.
+    Adapter
+}
+
+impl Display for PluginFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginFeature::Plugin => write!(f, "plugin"),
+            PluginFeature::Effect => write!(f, "effect"),
+            PluginFeature::Adapter => write!(f, "adapter")
+        }
+    }
+}
+
+/// Adds the layers, effects, and adapters described by the given
+/// [SerdeMixer] to the given mixer and applies its settings. Effects on new
+/// layers are only resolved once audio is played, so adding them cannot fail.
+fn load_mixer(mixer: &mut Mixer, serde: SerdeMixer,
+        plugin_guild_config: &PluginGuildConfig) {
+    for layer in serde.layers {
+        if let Err(e) = layer.apply(mixer) {
+            log::warn!("Could not restore effects of layer {}: {}",
+                layer.name(), e);
+        }
+    }
+
+    for effect in serde.master_effects {
+        let result = mixer.add_master_effect(effect, plugin_guild_config);
+
+        if let Err(e) = result {
+            log::warn!("Could not restore master effect: {}", e);
+        }
+    }
+
+    let limiter = mixer.limiter_mut();
+    limiter.set_enabled(serde.limiter.enabled);
+
+    let ceiling = serde.limiter.ceiling;
+
+    if ceiling > 0.0 && ceiling.is_finite() {
+        limiter.set_ceiling(ceiling);
+    }
+    else {
+        log::warn!("Invalid limiter ceiling {}, using default.", ceiling);
+    }
+
+    mixer.set_normalization(serde.normalization);
+}
+
 /// The bot's state for one specific guild.
 pub struct GuildState {
     mixer: Arc<RwLock<Mixer>>,
     board_manager: BoardManager,
-    root_directory: Option<String>
+    root_directory: Option<String>,
+    disabled_plugins: BTreeSet<String>,
+    disabled_effects: BTreeSet<String>,
+    disabled_adapters: BTreeSet<String>,
+    permissions: Permissions,
+    settings: GuildSettings,
+    scenes: BTreeMap<String, Scene>,
+    aliases: BTreeMap<String, String>,
+    schedule: Schedule,
+    followed_user: Option<UserId>
 }
 
 impl GuildState {
@@ -32,25 +109,33 @@ impl GuildState {
         GuildState {
             mixer: Arc::new(RwLock::new(Mixer::new(plugin_manager))),
             board_manager: BoardManager::new(),
-            root_directory: None
+            root_directory: None,
+            disabled_plugins: BTreeSet::new(),
+            disabled_effects: BTreeSet::new(),
+            disabled_adapters: BTreeSet::new(),
+            permissions: Permissions::default(),
+            settings: GuildSettings::default(),
+            scenes: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            schedule: Schedule::default(),
+            followed_user: None
         }
     }
 
     fn from_serde(plugin_manager: Arc<PluginManager>,
             serde: SerdeGuildState) -> GuildState {
+        let plugin_guild_config =
+            PluginGuildConfig::new(serde.directory.as_ref())
+                .with_disabled_plugins(&serde.disabled_plugins)
+                .with_disabled_effects(&serde.disabled_effects)
+                .with_disabled_adapters(&serde.disabled_adapters);
         let mut mixer = Mixer::new(plugin_manager);
-        
-        for layer in serde.mixer.layers {
-            mixer.add_layer(&layer.name);
 
-            for effect in layer.effects {
-                mixer.add_effect(&layer.name, effect).unwrap();
-            }
+        // There are no effects yet, so this cannot fail.
 
-            for adapter in layer.adapters {
-                mixer.add_adapter(&layer.name, adapter);
-            }
-        }
+        mixer.set_plugin_guild_config(plugin_guild_config.clone()).unwrap();
+        
+        load_mixer(&mut mixer, serde.mixer, &plugin_guild_config);
 
         let mut board_manager = BoardManager::new();
 
@@ -61,12 +146,21 @@ impl GuildState {
         GuildState {
             mixer: Arc::new(RwLock::new(mixer)),
             board_manager,
-            root_directory: serde.directory
+            root_directory: serde.directory,
+            disabled_plugins: serde.disabled_plugins,
+            disabled_effects: serde.disabled_effects,
+            disabled_adapters: serde.disabled_adapters,
+            permissions: serde.permissions,
+            settings: serde.settings,
+            scenes: serde.scenes,
+            aliases: serde.aliases,
+            schedule: serde.schedule,
+            followed_user: serde.followed_user
         }
     }
 
     /// Read-locks the [Mixer] for this guild and returns an appropriate guard.
-    pub fn mixer_blocking(&self) -> RwLockReadGuard<Mixer> {
+    pub fn mixer_blocking(&self) -> RwLockReadGuard<'_, Mixer> {
         self.mixer.read().unwrap()
     }
 
@@ -76,7 +170,7 @@ impl GuildState {
     /// while the guild state is behind a [GuildStateGuard]. This ensures that
     /// any changes in the configuration are propagated to the associated file
     /// on the hard drive.
-    pub fn mixer_mut(&self) -> RwLockWriteGuard<Mixer> {
+    pub fn mixer_mut(&self) -> RwLockWriteGuard<'_, Mixer> {
         self.mixer.write().unwrap()
     }
 
@@ -96,10 +190,140 @@ impl GuildState {
         &mut self.board_manager
     }
 
+    /// Gets the [Permissions] which restrict who may use which commands in
+    /// this guild.
+    pub fn permissions(&self) -> &Permissions {
+        &self.permissions
+    }
+
+    /// Gets a mutable reference to the [Permissions] which restrict who may
+    /// use which commands in this guild.
+    pub fn permissions_mut(&mut self) -> &mut Permissions {
+        &mut self.permissions
+    }
+
+    /// Gets the [GuildSettings] which customize the behavior of the bot in
+    /// this guild.
+    pub fn settings(&self) -> &GuildSettings {
+        &self.settings
+    }
+
+    /// Gets a mutable reference to the [GuildSettings] which customize the
+    /// behavior of the bot in this guild.
+    pub fn settings_mut(&mut self) -> &mut GuildSettings {
+        &mut self.settings
+    }
+
+    /// Gets the [Scene]s saved in this guild by their names.
+    pub fn scenes(&self) -> &BTreeMap<String, Scene> {
+        &self.scenes
+    }
+
+    /// Gets a mutable reference to the [Scene]s saved in this guild by their
+    /// names.
+    pub fn scenes_mut(&mut self) -> &mut BTreeMap<String, Scene> {
+        &mut self.scenes
+    }
+
+    /// Gets the commands of the aliases defined in this guild by the names of
+    /// the aliases.
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// Gets a mutable reference to the commands of the aliases defined in
+    /// this guild by the names of the aliases.
+    pub fn aliases_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.aliases
+    }
+
+    /// Gets the [Schedule] of commands to be executed in this guild.
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Gets a mutable reference to the [Schedule] of commands to be executed
+    /// in this guild.
+    pub fn schedule_mut(&mut self) -> &mut Schedule {
+        &mut self.schedule
+    }
+
+    /// Gets the ID of the user whom the bot follows when they switch voice
+    /// channels, if there is one.
+    pub fn followed_user(&self) -> Option<UserId> {
+        self.followed_user
+    }
+
+    /// Sets the ID of the user whom the bot follows when they switch voice
+    /// channels. If `None` is given, the bot stays in its voice channel.
+    pub fn set_followed_user(&mut self, followed_user: Option<UserId>) {
+        self.followed_user = followed_user;
+    }
+
     /// Constructs a [PluginGuildConfig] from the information stored in this
     /// guild state.
     pub fn build_plugin_guild_config(&self) -> PluginGuildConfig {
         PluginGuildConfig::new(self.root_directory.as_ref())
+            .with_disabled_plugins(&self.disabled_plugins)
+            .with_disabled_effects(&self.disabled_effects)
+            .with_disabled_adapters(&self.disabled_adapters)
+    }
+
+    fn disabled_mut(&mut self, feature: PluginFeature)
+            -> &mut BTreeSet<String> {
+        match feature {
+            PluginFeature::Plugin => &mut self.disabled_plugins,
+            PluginFeature::Effect => &mut self.disabled_effects,
+            PluginFeature::Adapter => &mut self.disabled_adapters
+        }
+    }
+
+    /// Gets the names of all plugins, effects, or adapters, depending on the
+    /// given [PluginFeature], which are disabled in this guild.
+    pub fn disabled(&self, feature: PluginFeature) -> &BTreeSet<String> {
+        match feature {
+            PluginFeature::Plugin => &self.disabled_plugins,
+            PluginFeature::Effect => &self.disabled_effects,
+            PluginFeature::Adapter => &self.disabled_adapters
+        }
+    }
+
+    /// Disables or enables a plugin, effect, or adapter in this guild. Effects
+    /// on the mixer are re-applied, so disabled effects stop immediately.
+    /// They remain configured and return once they are enabled again.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature`: The [PluginFeature] which determines whether `name`
+    ///   refers to a plugin, effect, or adapter.
+    /// * `name`: The file name of the plugin or the name of the effect or
+    ///   adapter.
+    /// * `disabled`: Whether to disable (`true`) or enable (`false`) it.
+    ///
+    /// # Returns
+    ///
+    /// `true` if and only if anything changed, i.e. it was not already
+    /// disabled or enabled respectively.
+    pub fn set_disabled(&mut self, feature: PluginFeature, name: &str,
+            disabled: bool) -> bool {
+        let set = self.disabled_mut(feature);
+        let changed = if disabled {
+            set.insert(name.to_owned())
+        }
+        else {
+            set.remove(name)
+        };
+
+        if changed {
+            // Effects which are disabled now are expected to fail, they are
+            // skipped by the mixer.
+
+            let plugin_guild_config = self.build_plugin_guild_config();
+            let _ = self.mixer_mut()
+                .set_plugin_guild_config(plugin_guild_config);
+        }
+
+        changed
     }
 
     /// Sets a guild-specific root directory.
@@ -113,25 +337,86 @@ impl GuildState {
         self.root_directory = None;
     }
 
-    fn serde(&self) -> SerdeGuildState {
-        let mut layers = Vec::new();
+    fn serde_mixer(&self) -> SerdeMixer {
+        let mixer = self.mixer.read().unwrap();
 
-        for layer in self.mixer.read().unwrap().layers() {
-            layers.push(SerdeLayer {
-                name: layer.name().to_owned(),
-                effects: layer.effects().to_vec(),
-                adapters: layer.adapters().to_vec()
-            });
+        SerdeMixer {
+            layers: mixer.layers().iter().map(LayerConfig::of).collect(),
+            master_effects: mixer.master_effects().to_vec(),
+            limiter: SerdeLimiter {
+                enabled: mixer.limiter().enabled(),
+                ceiling: mixer.limiter().ceiling()
+            },
+            normalization: mixer.normalization()
         }
+    }
 
+    fn serde(&self) -> SerdeGuildState {
         SerdeGuildState {
-            mixer: SerdeMixer {
-                layers
-            },
+            version: migration::CURRENT_VERSION,
+            mixer: self.serde_mixer(),
+            boards: self.board_manager.boards().cloned().collect(),
+            directory: self.root_directory.clone(),
+            disabled_plugins: self.disabled_plugins.clone(),
+            disabled_effects: self.disabled_effects.clone(),
+            disabled_adapters: self.disabled_adapters.clone(),
+            permissions: self.permissions.clone(),
+            settings: self.settings.clone(),
+            scenes: self.scenes.clone(),
+            aliases: self.aliases.clone(),
+            schedule: self.schedule.clone(),
+            followed_user: self.followed_user
+        }
+    }
+
+    /// Exports the portable part of the configuration of this guild, which
+    /// can be imported into another guild with [GuildState::import].
+    pub fn export(&self) -> GuildExport {
+        GuildExport {
+            version: migration::CURRENT_VERSION,
+            mixer: self.serde_mixer(),
             boards: self.board_manager.boards().cloned().collect(),
-            directory: self.root_directory.clone()
+            settings: self.settings.clone(),
+            scenes: self.scenes.clone(),
+            aliases: self.aliases.clone()
         }
     }
+
+    /// Replaces the layers, master effects, limiter and normalization
+    /// settings, boards, settings, scenes, and aliases of this guild by those
+    /// of the given [GuildExport]. Audio playing on the old layers is stopped
+    /// and boards which are currently displayed no longer respond.
+    /// Permissions, disabled plugins, the root directory, scheduled commands,
+    /// and the followed user are kept. The export should be checked with
+    /// [GuildExport::validate] beforehand.
+    pub fn import(&mut self, export: GuildExport) {
+        let plugin_guild_config = self.build_plugin_guild_config();
+
+        {
+            let mut mixer = self.mixer_mut();
+            let layers = mixer.layers().iter()
+                .map(|layer| layer.name().to_owned())
+                .collect::<Vec<_>>();
+
+            for layer in layers {
+                mixer.remove_layer(&layer);
+            }
+
+            mixer.clear_master_effects();
+            load_mixer(&mut mixer, export.mixer, &plugin_guild_config);
+        }
+
+        let mut board_manager = BoardManager::new();
+
+        for board in export.boards {
+            board_manager.add_board(board);
+        }
+
+        self.board_manager = board_manager;
+        self.settings = export.settings;
+        self.scenes = export.scenes;
+        self.aliases = export.aliases;
+    }
 }
 
 impl Serialize for GuildState {
@@ -143,25 +428,246 @@ impl Serialize for GuildState {
     }
 }
 
+fn default_limiter_enabled() -> bool {
+    true
+}
+
+fn default_limiter_ceiling() -> f32 {
+    limiter::DEFAULT_CEILING
+}
+
 #[derive(Deserialize, Serialize)]
-struct SerdeLayer {
-    name: String,
-    effects: Vec<KeyValueDescriptor>,
-    adapters: Vec<KeyValueDescriptor>
+struct SerdeLimiter {
+    #[serde(default = "default_limiter_enabled")]
+    enabled: bool,
+
+    #[serde(default = "default_limiter_ceiling")]
+    ceiling: f32
+}
+
+impl Default for SerdeLimiter {
+    fn default() -> SerdeLimiter {
+        SerdeLimiter {
+            enabled: default_limiter_enabled(),
+            ceiling: default_limiter_ceiling()
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 struct SerdeMixer {
-    layers: Vec<SerdeLayer>
+    layers: Vec<LayerConfig>,
+
+    #[serde(default)]
+    master_effects: Vec<KeyValueDescriptor>,
+
+    #[serde(default)]
+    limiter: SerdeLimiter,
+
+    #[serde(default)]
+    normalization: NormalizationSettings
 }
 
 #[derive(Deserialize, Serialize)]
 struct SerdeGuildState {
+    #[serde(default)]
+    version: u32,
     mixer: SerdeMixer,
     boards: Vec<Board>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    directory: Option<String>
+    directory: Option<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    disabled_plugins: BTreeSet<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    disabled_effects: BTreeSet<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    disabled_adapters: BTreeSet<String>,
+
+    #[serde(default, skip_serializing_if = "Permissions::is_empty")]
+    permissions: Permissions,
+
+    #[serde(default, skip_serializing_if = "GuildSettings::is_empty")]
+    settings: GuildSettings,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    scenes: BTreeMap<String, Scene>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "Schedule::is_empty")]
+    schedule: Schedule,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    followed_user: Option<UserId>
+}
+
+/// Checks the given layer configurations for duplicate names, invalid gains,
+/// and effects or adapters which cannot be resolved. Every problem is added
+/// to `problems`, prefixed with `context`. Returns the names of all layers.
+fn validate_layers<'a>(layers: &'a [LayerConfig], context: &str,
+        plugin_manager: &PluginManager,
+        plugin_guild_config: &PluginGuildConfig, problems: &mut Vec<String>)
+        -> BTreeSet<&'a str> {
+    let mut names = BTreeSet::new();
+
+    for layer in layers {
+        if !names.insert(layer.name()) {
+            problems.push(format!("{}Layer `{}` exists more than once.",
+                context, layer.name()));
+        }
+
+        if !audio::is_valid_layer_gain(layer.gain()) {
+            problems.push(format!("{}Layer `{}` has invalid gain {}.",
+                context, layer.name(), layer.gain()));
+        }
+
+        for effect in layer.effects() {
+            let result = plugin_manager.validate_effect(&effect.name,
+                &effect.key_values, plugin_guild_config);
+
+            if let Err(e) = result {
+                problems.push(format!("{}Effect `{}` on layer `{}`: {}",
+                    context, effect.name, layer.name(), e));
+            }
+        }
+
+        for adapter in layer.adapters() {
+            let result = plugin_manager.validate_adapter(&adapter.name,
+                &adapter.key_values, plugin_guild_config);
+
+            if let Err(e) = result {
+                problems.push(format!("{}Adapter `{}` on layer `{}`: {}",
+                    context, adapter.name, layer.name(), e));
+            }
+        }
+    }
+
+    names
+}
+
+/// The portable part of the configuration of a guild, that is, its layers
+/// with their effects and adapters, the master effects, the limiter and
+/// normalization settings, the sound boards, the [GuildSettings], the saved
+/// [Scene]s, and the aliases. It can be exported from one guild and imported
+/// into another one. Anything which only makes sense in the original guild,
+/// such as permissions or scheduled commands, is left out.
+///
+/// Exports use the same layout and version as guild state files, so older
+/// exports are migrated when they are read.
+#[derive(Deserialize, Serialize)]
+pub struct GuildExport {
+    version: u32,
+    mixer: SerdeMixer,
+
+    #[serde(default)]
+    boards: Vec<Board>,
+
+    #[serde(default, skip_serializing_if = "GuildSettings::is_empty")]
+    settings: GuildSettings,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    scenes: BTreeMap<String, Scene>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, String>
+}
+
+impl GuildExport {
+
+    /// Reads an export from the given JSON data, migrating it to the current
+    /// version if necessary.
+    ///
+    /// # Errors
+    ///
+    /// If the data is not valid JSON, cannot be migrated, or does not
+    /// describe a guild configuration.
+    pub fn from_json(data: &[u8]) -> Result<GuildExport, StateError> {
+        let mut value: Value = serde_json::from_slice(data)?;
+
+        migration::migrate(&mut value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Converts this export into pretty-printed JSON data.
+    pub fn to_json(&self) -> Vec<u8> {
+        // Serializing this structure cannot fail, all keys are strings.
+
+        serde_json::to_vec_pretty(self).unwrap()
+    }
+
+    /// Checks whether this export can be imported into a guild, that is,
+    /// whether all names are unique, the default layer exists, and every
+    /// effect and adapter can be resolved by the installed plugins.
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin_manager`: The [PluginManager] with the installed plugins.
+    /// * `plugin_guild_config`: The [PluginGuildConfig] of the guild into
+    ///   which the export shall be imported.
+    ///
+    /// # Returns
+    ///
+    /// A description of every problem which was found. If it is empty, the
+    /// export can be imported.
+    pub fn validate(&self, plugin_manager: &PluginManager,
+            plugin_guild_config: &PluginGuildConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+        let layer_names = validate_layers(&self.mixer.layers, "",
+            plugin_manager, plugin_guild_config, &mut problems);
+
+        for (name, scene) in &self.scenes {
+            validate_layers(scene.layers(), &format!("Scene `{}`: ", name),
+                plugin_manager, plugin_guild_config, &mut problems);
+        }
+
+        for effect in &self.mixer.master_effects {
+            let result = plugin_manager.validate_effect(&effect.name,
+                &effect.key_values, plugin_guild_config);
+
+            if let Err(e) = result {
+                problems.push(format!("Master effect `{}`: {}", effect.name,
+                    e));
+            }
+        }
+
+        let mut board_names = BTreeSet::new();
+
+        for board in &self.boards {
+            if !board_names.insert(board.name()) {
+                problems.push(format!("Board `{}` exists more than once.",
+                    board.name()));
+            }
+        }
+
+        if let Some(prefix) = self.settings.prefix() {
+            let mut settings = GuildSettings::default();
+
+            if let Err(e) = settings.set_prefix(Some(prefix.to_owned())) {
+                problems.push(e);
+            }
+        }
+
+        let idle_timeout = self.settings.idle_timeout();
+        let mut settings = GuildSettings::default();
+
+        if let Err(e) = settings.set_idle_timeout(idle_timeout) {
+            problems.push(e);
+        }
+
+        if let Some(default_layer) = self.settings.default_layer() {
+            if !layer_names.contains(default_layer) {
+                problems.push(format!(
+                    "The default layer `{}` does not exist.", default_layer));
+            }
+        }
+
+        problems
+    }
 }
 
 /// An enumeration of the errors that may occur while loading or saving the
@@ -182,7 +688,11 @@ pub enum StateError {
 
     /// Indicates that something went wrong while deerializing or serializing
     /// the JSON files.
-    JsonError(serde_json::Error)
+    JsonError(serde_json::Error),
+
+    /// Indicates that the state file for some guild could not be migrated to
+    /// the current version. The file is left unchanged in that case.
+    MigrationError(MigrationError)
 }
 
 impl From<ParseIntError> for StateError {
@@ -203,6 +713,12 @@ impl From<serde_json::Error> for StateError {
     }
 }
 
+impl From<MigrationError> for StateError {
+    fn from(e: MigrationError) -> StateError {
+        StateError::MigrationError(e)
+    }
+}
+
 impl Display for StateError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -219,7 +735,9 @@ impl Display for StateError {
             StateError::JsonError(e) =>
                 write!(f,
                     "Error while deserializing or serializing state files: {}",
-                    e)
+                    e),
+            StateError::MigrationError(e) =>
+                write!(f, "Error while migrating state files: {}", e)
         }
     }
 }
@@ -271,6 +789,45 @@ impl Drop for GuildStateGuard<'_> {
     }
 }
 
+/// The name of the sub-directory of the state directory in which the original
+/// state files are kept before they are migrated to a newer version.
+const BACKUP_DIRECTORY_NAME: &str = "backup";
+
+/// Reads the state file at the given path and migrates it to the current
+/// version if necessary. Before migrating, the original file is copied to the
+/// backup directory, with its version appended to the name.
+///
+/// # Returns
+///
+/// The content of the file and a flag which indicates whether it was
+/// migrated, in which case it should be saved again.
+fn read_guild_state(path: &Path)
+        -> Result<(SerdeGuildState, bool), StateError> {
+    let mut value: Value = serde_json::from_reader(File::open(path)?)?;
+    let version = migration::version(&value)?;
+
+    if version > migration::CURRENT_VERSION {
+        return Err(MigrationError::UnsupportedVersion(version).into());
+    }
+
+    let migrated = version < migration::CURRENT_VERSION;
+
+    if migrated {
+        let directory = path.parent().unwrap().join(BACKUP_DIRECTORY_NAME);
+        let file_name = format!("{}.v{}.json",
+            path.file_stem().unwrap().to_string_lossy(), version);
+
+        fs::create_dir_all(&directory)?;
+        fs::copy(path, directory.join(file_name))?;
+        migration::migrate(&mut value)?;
+
+        log::info!("Migrated state file {} from version {} to {}.",
+            path.display(), version, migration::CURRENT_VERSION);
+    }
+
+    Ok((serde_json::from_value(value)?, migrated))
+}
+
 /// The global state of the bot.
 pub struct State {
     guild_states: HashMap<GuildId, GuildState>,
@@ -330,10 +887,15 @@ impl State {
 
                 if let Some(guild_id_str) = guild_id_str_opt {
                     let guild_id = GuildId::from(guild_id_str.parse::<u64>()?);
-                    let topology =
-                        serde_json::from_reader(File::open(json_path)?)?;
+                    let (topology, migrated) = read_guild_state(&json_path)?;
                     let guild_state =
                         GuildState::from_serde(Arc::clone(&plugin_manager), topology);
+
+                    if migrated {
+                        serde_json::to_writer(
+                            File::create(&json_path)?, &guild_state)?;
+                    }
+
                     state.guild_states.insert(guild_id, guild_state);
                 }
             }
@@ -395,8 +957,150 @@ impl State {
         self.ensure_guild_state_exists_do(id, plugin_manager);
     }
 
+    /// Gets an iterator over the IDs of all guilds for which a state is
+    /// registered.
+    pub fn guild_ids(&self) -> impl Iterator<Item = GuildId> + '_ {
+        self.guild_states.keys().cloned()
+    }
+
     /// Gets the number of guilds for which a state is registered.
     pub fn guild_count(&self) -> usize {
         self.guild_states.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::env;
+
+    fn state_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir()
+            .join(format!("rambot-state-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn load(directory: &Path) -> Result<State, StateError> {
+        let plugin_manager = Arc::new(PluginManager::empty());
+
+        State::load(directory.to_str().unwrap(), plugin_manager)
+    }
+
+    const UNVERSIONED: &str = r#"{
+        "mixer": {
+            "layers": [ { "name": "music", "effects": [], "adapters": [] } ]
+        },
+        "boards": []
+    }"#;
+
+    #[test]
+    fn unversioned_state_is_backed_up_and_migrated() {
+        let directory = state_directory("migrate");
+        let path = directory.join("123.json");
+        fs::write(&path, UNVERSIONED).unwrap();
+
+        let state = load(&directory).unwrap_or_else(|e| panic!("{}", e));
+        let guild_state = state.guild_state(GuildId::new(123)).unwrap();
+        let backup = directory.join(BACKUP_DIRECTORY_NAME).join("123.v0.json");
+        let migrated: Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert!(guild_state.mixer_blocking().contains_layer("music"));
+        assert_eq!(UNVERSIONED, fs::read_to_string(backup).unwrap());
+        assert_eq!(Ok(migration::CURRENT_VERSION),
+            migration::version(&migrated));
+    }
+
+    #[test]
+    fn current_state_is_not_backed_up() {
+        let directory = state_directory("current");
+        let content = format!(r#"{{ "version": {}, "mixer": {{ "layers": [] }},
+            "boards": [] }}"#, migration::CURRENT_VERSION);
+        fs::write(directory.join("123.json"), &content).unwrap();
+
+        assert!(load(&directory).is_ok());
+        assert!(!directory.join(BACKUP_DIRECTORY_NAME).exists());
+        assert_eq!(content,
+            fs::read_to_string(directory.join("123.json")).unwrap());
+    }
+
+    #[test]
+    fn newer_state_is_rejected() {
+        let directory = state_directory("newer");
+        let content = format!(r#"{{ "version": {}, "mixer": {{ "layers": [] }},
+            "boards": [] }}"#, migration::CURRENT_VERSION + 1);
+        fs::write(directory.join("123.json"), content).unwrap();
+
+        assert!(matches!(load(&directory),
+            Err(StateError::MigrationError(
+                MigrationError::UnsupportedVersion(_)))));
+        assert!(!directory.join(BACKUP_DIRECTORY_NAME).exists());
+    }
+
+    fn guild_state() -> GuildState {
+        GuildState::new(Arc::new(PluginManager::empty()))
+    }
+
+    fn read_export(json: &[u8]) -> GuildExport {
+        GuildExport::from_json(json).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn exported_configuration_is_imported() {
+        let mut source = guild_state();
+
+        {
+            let mut mixer = source.mixer_mut();
+            mixer.add_layer("music");
+            mixer.set_layer_gain("music", 0.5);
+            mixer.set_layer_muted("music", true);
+        }
+
+        source.settings_mut().set_default_layer(Some("music".to_owned()));
+
+        let export = read_export(&source.export().to_json());
+        let plugin_guild_config = source.build_plugin_guild_config();
+        let mut target = guild_state();
+        target.mixer_mut().add_layer("old");
+
+        assert!(export.validate(&PluginManager::empty(), &plugin_guild_config)
+            .is_empty());
+
+        target.import(export);
+
+        let mixer = target.mixer_blocking();
+
+        assert!(!mixer.contains_layer("old"));
+        assert_eq!(0.5, mixer.layer("music").gain());
+        assert!(mixer.layer("music").is_muted());
+        assert_eq!(source.settings(), target.settings());
+    }
+
+    #[test]
+    fn invalid_export_is_reported() {
+        let export = read_export(br#"{
+            "mixer": {
+                "layers": [
+                    {
+                        "name": "music",
+                        "effects": [ { "name": "echo", "key_values": {} } ],
+                        "adapters": []
+                    },
+                    { "name": "music", "effects": [], "adapters": [] }
+                ]
+            },
+            "settings": { "default_layer": "effects" }
+        }"#);
+        let problems = export.validate(&PluginManager::empty(),
+            &PluginGuildConfig::new(None::<String>));
+
+        assert_eq!(3, problems.len());
+        assert!(problems[0].contains("echo"));
+        assert!(problems[1].contains("more than once"));
+        assert!(problems[2].contains("effects"));
+    }
+}
@@ -0,0 +1,678 @@
+use crate::config::WebApiConfig;
+use crate::control::{self, ControlError, Controller};
+use crate::state::GuildState;
+
+use futures_util::{SinkExt, StreamExt};
+
+use serde::{Deserialize, Serialize};
+
+use serenity::model::id::GuildId;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::{self, Display, Formatter};
+use std::net::{AddrParseError, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use warp::{Filter, Rejection, Reply};
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::ws::{Message, WebSocket, Ws};
+
+/// The interval in which the state of a guild is checked for changes to push
+/// to WebSocket clients.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// An enumeration of the different errors that can occur when starting the
+/// web API.
+#[derive(Debug)]
+pub enum WebApiError {
+
+    /// Indicates that the configured address is not a valid socket address.
+    InvalidAddress(AddrParseError),
+
+    /// Indicates that the configured token is empty, which would allow anyone
+    /// to control the bot.
+    EmptyToken,
+
+    /// Wraps a [warp::Error] raised while binding the server.
+    BindError(warp::Error)
+}
+
+impl Display for WebApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WebApiError::InvalidAddress(e) =>
+                write!(f, "Invalid web API address: {}", e),
+            WebApiError::EmptyToken =>
+                write!(f, "The web API token must not be empty."),
+            WebApiError::BindError(e) =>
+                write!(f, "Error binding web API: {}", e)
+        }
+    }
+}
+
+/// Information about a single layer as returned by the web API.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LayerInfo {
+    name: String,
+    gain: f32,
+    muted: bool,
+    solo: bool,
+    paused: bool,
+    playing: bool,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    effects: Vec<String>,
+    adapters: Vec<String>
+}
+
+/// Information about a single sound board button as returned by the web API.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ButtonInfo {
+    label: String,
+    toggle: bool,
+    active: bool
+}
+
+/// Information about a single sound board as returned by the web API.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BoardInfo {
+    name: String,
+    buttons: Vec<ButtonInfo>
+}
+
+/// Information about the state of a guild as returned by the web API and
+/// pushed to WebSocket clients whenever it changes.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GuildInfo {
+    id: String,
+    layers: Vec<LayerInfo>,
+    master_effects: Vec<String>,
+    boards: Vec<BoardInfo>
+}
+
+impl GuildInfo {
+    fn new(guild_id: GuildId, guild_state: &GuildState) -> GuildInfo {
+        let mixer = guild_state.mixer_blocking();
+        let layers = mixer.layers().iter()
+            .map(|layer| {
+                let metadata = mixer.layer_metadata(layer.name()).ok();
+
+                LayerInfo {
+                    name: layer.name().to_owned(),
+                    gain: layer.gain(),
+                    muted: layer.is_muted(),
+                    solo: layer.is_solo(),
+                    paused: layer.is_paused(),
+                    playing: metadata.is_some(),
+                    title: metadata.as_ref()
+                        .and_then(|m| m.title().map(str::to_owned)),
+                    artist: metadata.as_ref()
+                        .and_then(|m| m.artist().map(str::to_owned)),
+                    album: metadata.as_ref()
+                        .and_then(|m| m.album().map(str::to_owned)),
+                    effects: layer.effects().iter()
+                        .map(|e| format!("{}", e))
+                        .collect(),
+                    adapters: layer.adapters().iter()
+                        .map(|a| format!("{}", a))
+                        .collect()
+                }
+            })
+            .collect();
+        let master_effects = mixer.master_effects().iter()
+            .map(|e| format!("{}", e))
+            .collect();
+        let mut boards = guild_state.board_manager().boards()
+            .map(|board| BoardInfo {
+                name: board.name().to_owned(),
+                buttons: board.buttons().iter()
+                    .map(|button| ButtonInfo {
+                        label: button.label().to_owned(),
+                        toggle: button.is_toggle(),
+                        active: button.is_active()
+                    })
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+
+        boards.sort_by(|b1, b2| b1.name.cmp(&b2.name));
+
+        GuildInfo {
+            id: guild_id.to_string(),
+            layers,
+            master_effects,
+            boards
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    event: &'a str,
+    guild: &'a GuildInfo
+}
+
+#[derive(Deserialize)]
+struct NewLayer {
+    name: String
+}
+
+#[derive(Deserialize)]
+struct LayerUpdate {
+    gain: Option<f32>,
+    muted: Option<bool>,
+    solo: Option<bool>
+}
+
+#[derive(Deserialize)]
+struct PlayRequest {
+    audio: String
+}
+
+#[derive(Deserialize)]
+struct SeekRequest {
+    delta: String
+}
+
+#[derive(Deserialize)]
+struct EffectRequest {
+    effect: String
+}
+
+#[derive(Deserialize)]
+struct EffectQuery {
+    name: Option<String>
+}
+
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized { }
+
+fn error(status: StatusCode, message: &str) -> Response {
+    warp::reply::with_status(
+        warp::reply::json(&ErrorBody { error: message }), status)
+        .into_response()
+}
+
+fn ok() -> Response {
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Compares the given tokens in a time that does not depend on the position
+/// of the first difference.
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len() &&
+        provided.bytes().zip(expected.bytes())
+            .fold(0, |acc, (p, e)| acc | (p ^ e)) == 0
+}
+
+fn control_response(result: Result<(), ControlError>) -> Response {
+    match result {
+        Ok(()) => ok(),
+        Err(e) => {
+            let status = match e {
+                ControlError::LayerNotFound | ControlError::ButtonNotFound =>
+                    StatusCode::NOT_FOUND,
+                ControlError::NotConnected => StatusCode::CONFLICT,
+                ControlError::InvalidGain | ControlError::Command(_) =>
+                    StatusCode::BAD_REQUEST
+            };
+
+            error(status, &e.to_string())
+        }
+    }
+}
+
+#[derive(Clone)]
+struct WebApi {
+    controller: Controller
+}
+
+impl WebApi {
+
+    async fn guild_info(&self, guild_id: GuildId) -> Option<GuildInfo> {
+        let state = self.controller.state().read().await;
+
+        state.guild_state(guild_id)
+            .map(|guild_state| GuildInfo::new(guild_id, guild_state))
+    }
+
+    async fn execute(&self, guild_id: GuildId, command: String) -> Response {
+        control_response(self.controller.execute(guild_id, &[command]).await)
+    }
+
+    async fn stream_events(self, socket: WebSocket, guild_id: GuildId) {
+        let (mut sender, mut receiver) = socket.split();
+        let mut interval = tokio::time::interval(EVENT_POLL_INTERVAL);
+        let mut last_info = None;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let info = self.guild_info(guild_id).await;
+
+                    if info == last_info {
+                        continue;
+                    }
+
+                    if let Some(info) = &info {
+                        let event = Event {
+                            event: "state",
+                            guild: info
+                        };
+                        let json = serde_json::to_string(&event).unwrap();
+
+                        if sender.send(Message::text(json)).await.is_err() {
+                            break;
+                        }
+                    }
+
+                    last_info = info;
+                },
+                message = receiver.next() => match message {
+                    Some(Ok(message)) if !message.is_close() => { },
+                    _ => break
+                }
+            }
+        }
+    }
+}
+
+/// Requires the token in the `Authorization` header. If `allow_query` is true,
+/// the token may also be given as a `token` query parameter, which is meant
+/// for WebSocket clients that cannot set headers. Query parameters tend to end
+/// up in logs, so all other routes only accept the header.
+fn authorize(token: Arc<String>, allow_query: bool)
+        -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(move |header: Option<String>, query: HashMap<String, String>| {
+            let token = Arc::clone(&token);
+
+            async move {
+                let provided = header.as_deref()
+                    .and_then(|h| h.strip_prefix("Bearer "))
+                    .or_else(|| query.get("token")
+                        .filter(|_| allow_query)
+                        .map(String::as_str));
+
+                match provided {
+                    Some(provided) if tokens_match(provided, &token) => Ok(()),
+                    _ => Err(warp::reject::custom(Unauthorized))
+                }
+            }
+        })
+        .untuple_one()
+}
+
+fn guild_id() -> impl Filter<Extract = (GuildId,), Error = Rejection> + Clone {
+    warp::path::param::<u64>()
+        .and_then(|id: u64| async move {
+            if id == 0 {
+                Err(warp::reject::not_found())
+            }
+            else {
+                Ok(GuildId::new(id))
+            }
+        })
+}
+
+async fn handle_rejection(rejection: Rejection) -> Result<Response, Infallible> {
+    let response = if rejection.find::<Unauthorized>().is_some() {
+        error(StatusCode::UNAUTHORIZED, "Missing or invalid token.")
+    }
+    else if rejection.is_not_found() {
+        error(StatusCode::NOT_FOUND, "Not found.")
+    }
+    else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed.")
+    }
+    else {
+        error(StatusCode::BAD_REQUEST, "Invalid request.")
+    };
+
+    Ok(response)
+}
+
+fn routes(api: WebApi, token: String)
+        -> impl Filter<Extract = (Response,), Error = Infallible> + Clone {
+    let token = Arc::new(token);
+    let with_api = warp::any().map(move || api.clone());
+    let guild = warp::path("guilds").and(guild_id());
+    let layer = guild.clone().and(warp::path("layers")).and(warp::path::param::<String>());
+
+    let list_guilds = warp::path!("guilds")
+        .and(warp::get())
+        .and(with_api.clone())
+        .then(|api: WebApi| async move {
+            let state = api.controller.state().read().await;
+            let ids = state.guild_ids()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>();
+
+            warp::reply::json(&ids).into_response()
+        });
+    let get_guild = guild.clone().and(warp::path::end())
+        .and(warp::get())
+        .and(with_api.clone())
+        .then(|guild_id, api: WebApi| async move {
+            match api.guild_info(guild_id).await {
+                Some(info) => warp::reply::json(&info).into_response(),
+                None => error(StatusCode::NOT_FOUND, "Guild not found.")
+            }
+        });
+    let add_layer = guild.clone().and(warp::path!("layers"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_api.clone())
+        .then(|guild_id, new_layer: NewLayer, api: WebApi| async move {
+            if !control::is_valid_name(&new_layer.name) {
+                return error(StatusCode::BAD_REQUEST,
+                    "Layer names must not be empty or contain whitespace or \
+                    double quotes.");
+            }
+
+            api.execute(guild_id, format!("layer add {}", new_layer.name))
+                .await
+        });
+    let remove_layer = layer.clone().and(warp::path::end())
+        .and(warp::delete())
+        .and(with_api.clone())
+        .then(|guild_id, layer: String, api: WebApi| async move {
+            api.execute(guild_id, format!("layer remove {}", layer)).await
+        });
+    let update_layer = layer.clone().and(warp::path::end())
+        .and(warp::patch())
+        .and(warp::body::json())
+        .and(with_api.clone())
+        .then(|guild_id, layer: String, update: LayerUpdate, api: WebApi| async move {
+            control_response(api.controller.update_layer(guild_id, &layer,
+                update.gain, update.muted, update.solo).await)
+        });
+    let play = layer.clone().and(warp::path!("play"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_api.clone())
+        .then(|guild_id, layer: String, play: PlayRequest, api: WebApi| async move {
+            api.execute(guild_id, format!("play {} {}", layer, play.audio))
+                .await
+        });
+    let stop = layer.clone().and(warp::path!("stop"))
+        .and(warp::post())
+        .and(with_api.clone())
+        .then(|guild_id, layer: String, api: WebApi| async move {
+            api.execute(guild_id, format!("stop {}", layer)).await
+        });
+    let seek = layer.clone().and(warp::path!("seek"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_api.clone())
+        .then(|guild_id, layer: String, seek: SeekRequest, api: WebApi| async move {
+            api.execute(guild_id, format!("seek {} {}", layer, seek.delta))
+                .await
+        });
+    let add_effect = layer.clone().and(warp::path!("effects"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_api.clone())
+        .then(|guild_id, layer: String, effect: EffectRequest, api: WebApi| async move {
+            api.execute(guild_id,
+                format!("effect add {} {}", layer, effect.effect)).await
+        });
+    let clear_effects = layer.and(warp::path!("effects"))
+        .and(warp::delete())
+        .and(warp::query::<EffectQuery>())
+        .and(with_api.clone())
+        .then(|guild_id, layer: String, query: EffectQuery, api: WebApi| async move {
+            let command = match query.name {
+                Some(name) => format!("effect clear {} {}", layer, name),
+                None => format!("effect clear {}", layer)
+            };
+
+            api.execute(guild_id, command).await
+        });
+    let press_button = guild.clone().and(warp::path!("boards" / String / String))
+        .and(warp::post())
+        .and(with_api.clone())
+        .then(|guild_id, board: String, label: String, api: WebApi| async move {
+            control_response(
+                api.controller.press_button(guild_id, &board, &label).await)
+        });
+    let events = guild.and(warp::path!("events"))
+        .and(authorize(Arc::clone(&token), true))
+        .and(warp::ws())
+        .and(with_api)
+        .map(|guild_id, ws: Ws, api: WebApi| {
+            ws.on_upgrade(move |socket| api.stream_events(socket, guild_id))
+                .into_response()
+        });
+
+    authorize(token, false)
+        .and(list_guilds
+            .or(get_guild).unify()
+            .or(add_layer).unify()
+            .or(remove_layer).unify()
+            .or(update_layer).unify()
+            .or(play).unify()
+            .or(stop).unify()
+            .or(seek).unify()
+            .or(add_effect).unify()
+            .or(clear_effects).unify()
+            .or(press_button).unify())
+        .or(events).unify()
+        .recover(handle_rejection)
+        .unify()
+}
+
+/// Starts the HTTP/WebSocket control API in the background.
+///
+/// # Arguments
+///
+/// * `config`: The [WebApiConfig] specifying the address and token.
+/// * `controller`: The [Controller] which executes the requested actions.
+///
+/// # Returns
+///
+/// The address to which the web API was bound.
+///
+/// # Errors
+///
+/// Any [WebApiError] according to their respective documentation.
+pub fn start(config: &WebApiConfig, controller: Controller)
+        -> Result<SocketAddr, WebApiError> {
+    let address = config.address().parse::<SocketAddr>()
+        .map_err(WebApiError::InvalidAddress)?;
+
+    if config.token().is_empty() {
+        return Err(WebApiError::EmptyToken);
+    }
+
+    let api = WebApi {
+        controller
+    };
+    let (address, server) =
+        warp::serve(routes(api, config.token().to_owned()))
+            .try_bind_ephemeral(address)
+            .map_err(WebApiError::BindError)?;
+
+    tokio::spawn(server);
+    log::info!("Web API listening on {}.", address);
+
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::plugin::PluginManager;
+    use crate::state::State;
+
+    use std::env;
+
+    use tokio::sync::RwLock as TokioRwLock;
+    use std::fs;
+
+    const TOKEN: &str = "secret";
+
+    fn test_api(name: &str) -> (WebApi, String) {
+        let directory = env::temp_dir()
+            .join(format!("rambot-web-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned();
+        let _ = fs::remove_dir_all(&directory);
+        let plugin_manager = Arc::new(PluginManager::empty());
+        let state = State::load(&directory, Arc::clone(&plugin_manager))
+            .unwrap_or_else(|e| panic!("{}", e));
+        let api = WebApi {
+            controller: Controller::new(Arc::new(TokioRwLock::new(state)),
+                plugin_manager, None, Some("!"))
+        };
+
+        (api, directory)
+    }
+
+    fn request(method: &str, path: &str) -> warp::test::RequestBuilder {
+        warp::test::request()
+            .method(method)
+            .path(path)
+            .header("authorization", format!("Bearer {}", TOKEN))
+    }
+
+    #[tokio::test]
+    async fn requests_without_valid_token_are_rejected() {
+        let (api, directory) = test_api("unauthorized");
+        let filter = routes(api, TOKEN.to_owned());
+
+        let missing = warp::test::request().path("/guilds").reply(&filter).await;
+        let wrong = warp::test::request()
+            .path("/guilds")
+            .header("authorization", "Bearer wrong")
+            .reply(&filter).await;
+        let query = warp::test::request()
+            .path("/guilds?token=secret")
+            .reply(&filter).await;
+        let events = warp::test::ws()
+            .path("/guilds/42/events?token=secret")
+            .handshake(filter.clone()).await;
+
+        fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, missing.status());
+        assert_eq!(StatusCode::UNAUTHORIZED, wrong.status());
+        assert_eq!(StatusCode::UNAUTHORIZED, query.status());
+        assert!(events.is_ok());
+    }
+
+    #[tokio::test]
+    async fn layers_can_be_managed() {
+        let (api, directory) = test_api("layers");
+        let filter = routes(api, TOKEN.to_owned());
+
+        let added = request("POST", "/guilds/42/layers")
+            .json(&serde_json::json!({ "name": "music" }))
+            .reply(&filter).await;
+        let updated = request("PATCH", "/guilds/42/layers/music")
+            .json(&serde_json::json!({ "gain": 0.5, "muted": true }))
+            .reply(&filter).await;
+        let guilds = request("GET", "/guilds").reply(&filter).await;
+        let guild = request("GET", "/guilds/42").reply(&filter).await;
+        let guild: serde_json::Value =
+            serde_json::from_slice(guild.body()).unwrap();
+
+        fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!(StatusCode::NO_CONTENT, added.status());
+        assert_eq!(StatusCode::NO_CONTENT, updated.status());
+        assert_eq!(b"[\"42\"]", guilds.body().as_ref());
+        assert_eq!("music", guild["layers"][0]["name"]);
+        assert_eq!(0.5, guild["layers"][0]["gain"]);
+        assert_eq!(true, guild["layers"][0]["muted"]);
+    }
+
+    #[tokio::test]
+    async fn errors_are_reported() {
+        let (api, directory) = test_api("errors");
+        let filter = routes(api, TOKEN.to_owned());
+
+        let unknown_guild = request("GET", "/guilds/42").reply(&filter).await;
+        let play = request("POST", "/guilds/42/layers/music/play")
+            .json(&serde_json::json!({ "audio": "song.wav" }))
+            .reply(&filter).await;
+        let button = request("POST", "/guilds/42/boards/board/button")
+            .reply(&filter).await;
+        let update = request("PATCH", "/guilds/42/layers/music")
+            .json(&serde_json::json!({ "gain": 0.5 }))
+            .reply(&filter).await;
+
+        fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, unknown_guild.status());
+        assert_eq!(StatusCode::BAD_REQUEST, play.status());
+        assert_eq!(b"{\"error\":\"No layer of name music.\"}", play.body().as_ref());
+        assert_eq!(StatusCode::NOT_FOUND, button.status());
+        assert_eq!(StatusCode::NOT_FOUND, update.status());
+    }
+
+    #[tokio::test]
+    async fn commands_are_checked_for_permissions() {
+        let (api, directory) = test_api("permissions");
+        let controller = api.controller.clone();
+        let filter = routes(api, TOKEN.to_owned());
+        let guild_id = GuildId::new(42);
+
+        controller.execute(guild_id, &["layer add music".to_owned()]).await
+            .unwrap();
+
+        {
+            let plugin_manager = Arc::new(PluginManager::empty());
+            let mut state = controller.state().write().await;
+            let mut guild_state =
+                state.guild_state_mut(guild_id, &plugin_manager);
+
+            guild_state.permissions_mut().restrict("stop", None);
+            guild_state.permissions_mut().restrict("layer gain", None);
+        }
+
+        let restricted =
+            controller.execute(guild_id, &["stop music".to_owned()]).await;
+        let owners_only = controller
+            .execute(guild_id, &["directory music".to_owned()]).await;
+        let manage_guild = controller
+            .execute(guild_id, &["effect disable volume".to_owned()]).await;
+        let allowed = controller
+            .execute(guild_id, &["layer mute music".to_owned()]).await;
+        let gain = request("PATCH", "/guilds/42/layers/music")
+            .json(&serde_json::json!({ "gain": 0.5 }))
+            .reply(&filter).await;
+        let muted = request("PATCH", "/guilds/42/layers/music")
+            .json(&serde_json::json!({ "muted": false }))
+            .reply(&filter).await;
+
+        fs::remove_dir_all(directory).unwrap();
+
+        let not_allowed = || Err(ControlError::Command(
+            "You are not allowed to use this command.".to_owned()));
+
+        assert_eq!(not_allowed(), restricted);
+        assert_eq!(not_allowed(), owners_only);
+        assert_eq!(not_allowed(), manage_guild);
+        assert_eq!(Ok(()), allowed);
+        assert_eq!(StatusCode::BAD_REQUEST, gain.status());
+        assert_eq!(
+            b"{\"error\":\"You are not allowed to use this command.\"}",
+            gain.body().as_ref());
+        assert_eq!(StatusCode::NO_CONTENT, muted.status());
+    }
+}